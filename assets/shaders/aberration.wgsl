@@ -0,0 +1,38 @@
+struct Aberration {
+    aberration: f32;
+};
+
+[[group(0), binding(0)]]
+var screen_texture: texture_2d<f32>;
+[[group(0), binding(1)]]
+var screen_sampler: sampler;
+[[group(0), binding(2)]]
+var<uniform> material: Aberration;
+
+struct VertexOutput {
+    [[builtin(position)]] clip_position: vec4<f32>;
+    [[location(0)]] uv: vec2<f32>;
+};
+
+// One triangle twice the size of the screen, so every pixel is covered
+// without any vertex buffer
+[[stage(vertex)]]
+fn vertex([[builtin(vertex_index)]] index: u32) -> VertexOutput {
+    var out: VertexOutput;
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    out.clip_position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    out.uv = uv;
+    return out;
+}
+
+[[stage(fragment)]]
+fn fragment(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+    // Red and blue are split apart by nothing at the center of the screen
+    // and the aberration in pixels at the edges
+    let size = vec2<f32>(textureDimensions(screen_texture));
+    let shift = (in.uv * 2.0 - 1.0) * material.aberration / size;
+    var color: vec4<f32> = textureSample(screen_texture, screen_sampler, in.uv);
+    color.r = textureSample(screen_texture, screen_sampler, in.uv + shift).r;
+    color.b = textureSample(screen_texture, screen_sampler, in.uv - shift).b;
+    return color;
+}
@@ -0,0 +1,54 @@
+#import bevy_pbr::mesh_view_bind_group
+
+struct BackdropMaterial {
+    top: vec4<f32>;
+    bottom: vec4<f32>;
+    parallax: f32;
+    scale: f32;
+    flags: u32;
+};
+
+let BACKDROP_FLAGS_TEXTURE_BIT: u32 = 1u;
+
+[[group(1), binding(0)]]
+var base_texture: texture_2d<f32>;
+[[group(1), binding(1)]]
+var base_sampler: sampler;
+[[group(1), binding(2)]]
+var<uniform> material: BackdropMaterial;
+
+struct Vertex {
+    [[location(0)]] position: vec3<f32>;
+    [[location(1)]] normal: vec3<f32>;
+    [[location(2)]] uv: vec2<f32>;
+};
+
+struct VertexOutput {
+    [[builtin(position)]] clip_position: vec4<f32>;
+};
+
+// Like the screen overlay the quad is already in clip space, at the far
+// plane so everything else is drawn in front of it
+[[stage(vertex)]]
+fn vertex(vertex: Vertex) -> VertexOutput {
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(vertex.position.xy, 0.0, 1.0);
+    return out;
+}
+
+[[stage(fragment)]]
+fn fragment(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+    let height = in.clip_position.y / view.height;
+    var color: vec4<f32> = mix(material.top, material.bottom, height);
+
+    // Measured in screen heights so texels stay square, and moving with
+    // the camera across the ground plane
+    let screen = in.clip_position.xy / view.height;
+    let scroll = view.world_position.xz * material.parallax;
+    let uv = (screen + scroll) / material.scale;
+    let texel = textureSample(base_texture, base_sampler, uv);
+    if ((material.flags & BACKDROP_FLAGS_TEXTURE_BIT) != 0u) {
+        color = color * texel;
+    }
+    return color;
+}
@@ -0,0 +1,59 @@
+#import bevy_pbr::mesh_view_bind_group
+#import bevy_pbr::mesh_struct
+
+struct HologramMaterial {
+    uv_offset: vec2<f32>;
+    uv_size: vec2<f32>;
+    tint: vec4<f32>;
+    scanlines: f32;
+    time: f32;
+};
+
+[[group(1), binding(0)]]
+var base_texture: texture_2d<f32>;
+[[group(1), binding(1)]]
+var base_sampler: sampler;
+[[group(1), binding(2)]]
+var<uniform> material: HologramMaterial;
+
+[[group(2), binding(0)]]
+var<uniform> mesh: Mesh;
+
+struct Vertex {
+    [[location(0)]] position: vec3<f32>;
+    [[location(2)]] uv: vec2<f32>;
+};
+
+struct VertexOutput {
+    [[builtin(position)]] clip_position: vec4<f32>;
+    [[location(0)]] uv: vec2<f32>;
+};
+
+[[stage(vertex)]]
+fn vertex(vertex: Vertex) -> VertexOutput {
+    var out: VertexOutput;
+    out.clip_position = view.view_proj * mesh.model * vec4<f32>(vertex.position, 1.0);
+    out.uv = vertex.uv;
+    return out;
+}
+
+[[stage(fragment)]]
+fn fragment(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+    let t = material.time;
+    let base = textureSample(base_texture, base_sampler, material.uv_offset + in.uv * material.uv_size);
+
+    // Thin bright lines scrolling up the sprite
+    let scan = fract(in.uv.y * material.scanlines + t * 2.0);
+    let line = 1.0 - smoothStep(0.0, 0.3, scan);
+    // Glow around the edge of the quad, even where the sprite is empty
+    let edge = min(min(in.uv.x, 1.0 - in.uv.x), min(in.uv.y, 1.0 - in.uv.y));
+    let rim = 1.0 - smoothStep(0.0, 0.06, edge);
+    // Slow pulse with the odd quick flicker
+    let pulse = 0.85 + 0.1 * sin(t * 3.0) + 0.05 * sin(t * 23.0);
+
+    // The sprite is shown by brightness alone so the tint always reads
+    let luma = dot(base.rgb, vec3<f32>(0.299, 0.587, 0.114));
+    let glow = (0.5 + 0.5 * luma) * base.a + line * 0.25 * base.a + rim * 0.5;
+    let alpha = (base.a * (0.6 + 0.4 * line) + rim) * pulse;
+    return vec4<f32>(material.tint.rgb * glow, clamp(alpha, 0.0, 1.0) * material.tint.a);
+}
@@ -0,0 +1,248 @@
+#import bevy_pbr::mesh_view_bind_group
+#import bevy_pbr::mesh_struct
+
+struct LitSpriteMaterial {
+    uv_offset: vec2<f32>;
+    uv_size: vec2<f32>;
+    color: vec4<f32>;
+    flags: u32;
+    uv_flags: u32;
+    grade: vec4<f32>;
+    dither: f32;
+};
+
+let LIT_SPRITE_FLAGS_NORMAL_MAP_BIT: u32 = 1u;
+let LIT_SPRITE_FLAGS_EMISSIVE_BIT: u32 = 2u;
+
+[[group(1), binding(0)]]
+var base_texture: texture_2d<f32>;
+[[group(1), binding(1)]]
+var base_sampler: sampler;
+[[group(1), binding(2)]]
+var normal_texture: texture_2d<f32>;
+[[group(1), binding(3)]]
+var normal_sampler: sampler;
+[[group(1), binding(4)]]
+var<uniform> material: LitSpriteMaterial;
+[[group(1), binding(5)]]
+var emissive_texture: texture_2d<f32>;
+[[group(1), binding(6)]]
+var emissive_sampler: sampler;
+
+[[group(2), binding(0)]]
+var<uniform> mesh: Mesh;
+
+struct Vertex {
+    [[location(0)]] position: vec3<f32>;
+    [[location(1)]] normal: vec3<f32>;
+    [[location(2)]] uv: vec2<f32>;
+#ifdef VERTEX_COLORS
+    [[location(3)]] color: u32;
+#endif
+};
+
+struct VertexOutput {
+    [[builtin(position)]] clip_position: vec4<f32>;
+    [[location(0)]] world_position: vec4<f32>;
+    [[location(1)]] world_normal: vec3<f32>;
+    [[location(2)]] uv: vec2<f32>;
+    [[location(3)]] color: vec4<f32>;
+};
+
+// Kept in sync with SpriteFlip::uv
+fn flip_uv(uv_in: vec2<f32>) -> vec2<f32> {
+    var uv: vec2<f32> = uv_in;
+    let turns = (material.uv_flags >> 2u) & 3u;
+    for (var i: u32 = 0u; i < turns; i = i + 1u) {
+        uv = vec2<f32>(uv.y, 1.0 - uv.x);
+    }
+    if ((material.uv_flags & 1u) != 0u) {
+        uv.x = 1.0 - uv.x;
+    }
+    if ((material.uv_flags & 2u) != 0u) {
+        uv.y = 1.0 - uv.y;
+    }
+    return uv;
+}
+
+[[stage(vertex)]]
+fn vertex(vertex: Vertex) -> VertexOutput {
+    var out: VertexOutput;
+    out.world_position = mesh.model * vec4<f32>(vertex.position, 1.0);
+    out.clip_position = view.view_proj * out.world_position;
+    out.world_normal = mat3x3<f32>(
+        mesh.inverse_transpose_model[0].xyz,
+        mesh.inverse_transpose_model[1].xyz,
+        mesh.inverse_transpose_model[2].xyz
+    ) * vertex.normal;
+    out.uv = material.uv_offset + flip_uv(vertex.uv) * material.uv_size;
+#ifdef VERTEX_COLORS
+    out.color = unpack4x8unorm(vertex.color);
+#else
+    out.color = vec4<f32>(1.0);
+#endif
+    return out;
+}
+
+struct FragmentInput {
+    [[builtin(front_facing)]] is_front: bool;
+    [[builtin(position)]] frag_coord: vec4<f32>;
+    [[location(0)]] world_position: vec4<f32>;
+    [[location(1)]] world_normal: vec3<f32>;
+    [[location(2)]] uv: vec2<f32>;
+    [[location(3)]] color: vec4<f32>;
+};
+
+// Threshold of a pixel in a 4x4 Bayer matrix, in (0, 1), kept in sync with
+// unlit_material.wgsl
+fn bayer(frag_coord: vec2<f32>) -> f32 {
+    let x = u32(frag_coord.x) & 3u;
+    let y = u32(frag_coord.y) & 3u;
+    let a = x ^ y;
+    let index = ((a & 1u) << 3u) | ((y & 1u) << 2u) | (a & 2u) | ((y & 2u) >> 1u);
+    return (f32(index) + 0.5) / 16.0;
+}
+
+// Cluster lookups, kept in sync with bevy_pbr's pbr.wgsl
+
+fn view_z_to_z_slice(view_z: f32, is_orthographic: bool) -> u32 {
+    var z_slice: u32 = 0u;
+    if (is_orthographic) {
+        z_slice = u32(floor((view_z - lights.cluster_factors.z) * lights.cluster_factors.w));
+    } else {
+        z_slice = u32(log(-view_z) * lights.cluster_factors.z - lights.cluster_factors.w + 1.0);
+    }
+    return min(z_slice, lights.cluster_dimensions.z - 1u);
+}
+
+fn fragment_cluster_index(frag_coord: vec2<f32>, view_z: f32, is_orthographic: bool) -> u32 {
+    let xy = vec2<u32>(floor(frag_coord * lights.cluster_factors.xy));
+    let z_slice = view_z_to_z_slice(view_z, is_orthographic);
+    return min(
+        (xy.y * lights.cluster_dimensions.x + xy.x) * lights.cluster_dimensions.z + z_slice,
+        lights.cluster_dimensions.w - 1u
+    );
+}
+
+let CLUSTER_COUNT_SIZE = 13u;
+fn unpack_offset_and_count(cluster_index: u32) -> vec2<u32> {
+#ifdef NO_STORAGE_BUFFERS_SUPPORT
+    let offset_and_count = cluster_offsets_and_counts.data[cluster_index >> 2u][cluster_index & ((1u << 2u) - 1u)];
+    return vec2<u32>(
+        (offset_and_count >> CLUSTER_COUNT_SIZE) & ((1u << 32u - CLUSTER_COUNT_SIZE) - 1u),
+        offset_and_count & ((1u << CLUSTER_COUNT_SIZE) - 1u)
+    );
+#else
+    return cluster_offsets_and_counts.data[cluster_index];
+#endif
+}
+
+fn get_light_id(index: u32) -> u32 {
+#ifdef NO_STORAGE_BUFFERS_SUPPORT
+    let indices = cluster_light_index_lists.data[index >> 4u][(index >> 2u) & ((1u << 2u) - 1u)];
+    return (indices >> (8u * (index & ((1u << 2u) - 1u)))) & ((1u << 8u) - 1u);
+#else
+    return cluster_light_index_lists.data[index];
+#endif
+}
+
+fn distance_attenuation(distance_square: f32, inverse_range_squared: f32) -> f32 {
+    let factor = distance_square * inverse_range_squared;
+    let smooth_factor = clamp(1.0 - factor * factor, 0.0, 1.0);
+    return smooth_factor * smooth_factor / max(distance_square, 0.0001);
+}
+
+// Sprites have no tangents, so build the tangent frame from screen space
+// derivatives of the position and uvs
+fn perturb_normal(N: vec3<f32>, position: vec3<f32>, uv: vec2<f32>) -> vec3<f32> {
+    let dp1 = dpdx(position);
+    let dp2 = dpdy(position);
+    let duv1 = dpdx(uv);
+    let duv2 = dpdy(uv);
+
+    let dp2perp = cross(dp2, N);
+    let dp1perp = cross(N, dp1);
+    let T = dp2perp * duv1.x + dp1perp * duv2.x;
+    let B = dp2perp * duv1.y + dp1perp * duv2.y;
+    let invmax = inverseSqrt(max(dot(T, T), dot(B, B)));
+    let TBN = mat3x3<f32>(T * invmax, B * invmax, N);
+
+    let Nt = textureSample(normal_texture, normal_sampler, uv).rgb * 2.0 - 1.0;
+    // Sprite sheets store +y up while uvs grow downwards
+    return normalize(TBN * vec3<f32>(Nt.x, -Nt.y, Nt.z));
+}
+
+#ifdef DEBUG_UV_CHECKER
+// Checkerboard colored by the sprite's own uvs, magenta outside the sprite
+fn uv_checker(uv: vec2<f32>) -> vec4<f32> {
+    if (any(uv < vec2<f32>(0.0)) || any(uv > vec2<f32>(1.0))) {
+        return vec4<f32>(1.0, 0.0, 1.0, 1.0);
+    }
+    let cell = floor(uv * 8.0);
+    let shade = 0.6 + 0.8 * fract((cell.x + cell.y) * 0.5);
+    return vec4<f32>(vec3<f32>(uv.x, uv.y, 1.0 - uv.x) * shade, 1.0);
+}
+#endif
+
+#ifdef DEBUG_OVERDRAW
+// Heat each fragment adds when drawing overdraw
+let OVERDRAW_HEAT: vec4<f32> = vec4<f32>(0.12, 0.05, 0.02, 1.0);
+#endif
+
+[[stage(fragment)]]
+fn fragment(in: FragmentInput) -> [[location(0)]] vec4<f32> {
+    let base = textureSample(base_texture, base_sampler, in.uv).rgb;
+    let albedo = material.color.rgb * in.color.rgb * base;
+    var emissive: vec3<f32> = textureSample(emissive_texture, emissive_sampler, in.uv).rgb;
+    if ((material.flags & LIT_SPRITE_FLAGS_EMISSIVE_BIT) == 0u) {
+        emissive = vec3<f32>(0.0);
+    }
+    if (bayer(in.frag_coord.xy) < material.dither) {
+        discard;
+    }
+
+    var N: vec3<f32> = normalize(in.world_normal);
+    if (!in.is_front) {
+        N = -N;
+    }
+    if ((material.flags & LIT_SPRITE_FLAGS_NORMAL_MAP_BIT) != 0u) {
+        N = perturb_normal(N, in.world_position.xyz, in.uv);
+    }
+
+    var light: vec3<f32> = lights.ambient_color.rgb;
+
+    let is_orthographic = view.projection[3].w == 1.0;
+    let view_z = dot(vec4<f32>(
+        view.inverse_view[0].z,
+        view.inverse_view[1].z,
+        view.inverse_view[2].z,
+        view.inverse_view[3].z
+    ), in.world_position);
+    let cluster_index = fragment_cluster_index(in.frag_coord.xy, view_z, is_orthographic);
+    let offset_and_count = unpack_offset_and_count(cluster_index);
+    for (var i: u32 = offset_and_count[0]; i < offset_and_count[0] + offset_and_count[1]; i = i + 1u) {
+        let point = point_lights.data[get_light_id(i)];
+        let to_light = point.position_radius.xyz - in.world_position.xyz;
+        let attenuation = distance_attenuation(
+            dot(to_light, to_light),
+            point.color_inverse_square_range.w
+        );
+        let NoL = clamp(dot(N, normalize(to_light)), 0.0, 1.0);
+        light = light + point.color_inverse_square_range.rgb * attenuation * NoL;
+    }
+
+    for (var i: u32 = 0u; i < lights.n_directional_lights; i = i + 1u) {
+        let directional = lights.directional_lights[i];
+        let NoL = clamp(dot(N, directional.direction_to_light), 0.0, 1.0);
+        light = light + directional.color.rgb * NoL;
+    }
+
+    var color: vec4<f32> = vec4<f32>(albedo * light * material.grade.rgb + emissive, 1.0);
+#ifdef DEBUG_UV_CHECKER
+    color = uv_checker((in.uv - material.uv_offset) / material.uv_size);
+#endif
+#ifdef DEBUG_OVERDRAW
+    color = OVERDRAW_HEAT;
+#endif
+    return color;
+}
@@ -0,0 +1,47 @@
+struct ScreenMaterial {
+    flash: vec4<f32>;
+    vignette: f32;
+    fade: f32;
+};
+
+[[group(1), binding(0)]]
+var<uniform> material: ScreenMaterial;
+
+struct Vertex {
+    [[location(0)]] position: vec3<f32>;
+    [[location(1)]] normal: vec3<f32>;
+    [[location(2)]] uv: vec2<f32>;
+};
+
+struct VertexOutput {
+    [[builtin(position)]] clip_position: vec4<f32>;
+    [[location(0)]] uv: vec2<f32>;
+};
+
+// The quad is already in clip space, so it covers the screen wherever the
+// camera is
+[[stage(vertex)]]
+fn vertex(vertex: Vertex) -> VertexOutput {
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(vertex.position.xy, 0.0, 1.0);
+    out.uv = vertex.uv;
+    return out;
+}
+
+[[stage(fragment)]]
+fn fragment(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+    // Zero in the middle of the screen and one at the corners
+    let from_center = (in.uv - 0.5) * 2.0;
+    let edge = clamp(dot(from_center, from_center) * 0.5, 0.0, 1.0);
+    let vignette = smoothStep(0.2, 1.0, edge) * material.vignette;
+
+    // The flash is layered over the black vignette, so it washes over it
+    let flash = material.flash.a;
+    let alpha = flash + vignette * (1.0 - flash);
+
+    // Fading to black covers everything, the flash included
+    let fade = material.fade;
+    let total = fade + alpha * (1.0 - fade);
+    let color = material.flash.rgb * flash * (1.0 - fade);
+    return vec4<f32>(color / max(total, 0.0001), total);
+}
@@ -1,28 +0,0 @@
-#version 450
-
-layout(location = 0) in vec3 Vertex_Position;
-layout(location = 1) in vec3 Vertex_Normal;
-layout(location = 2) in vec2 Vertex_Uv;
-
-layout(location = 0) out vec2 o_uv;
-
-layout(set = 0, binding = 0) uniform CameraViewProj {
-    mat4 ViewProj;
-    mat4 View;
-    mat4 InverseView;
-    mat4 Projection;
-    vec3 WorldPosition;
-    float width;
-    float height;
-};
-
-layout(set = 2, binding = 0) uniform Mesh {
-    mat4 Model;
-    mat4 InverseTransposeModel;
-    uint flags;
-};
-
-void main() {
-    gl_Position = ViewProj * Model * vec4(Vertex_Position, 1.0);
-    o_uv = Vertex_Uv;
-}
@@ -0,0 +1,176 @@
+#import bevy_pbr::mesh_view_bind_group
+#import bevy_pbr::mesh_struct
+
+struct UnlitMaterial {
+    uv_offset: vec2<f32>;
+    uv_size: vec2<f32>;
+    color: vec4<f32>;
+    alpha_cutoff: f32;
+    uv_flags: u32;
+    dither: f32;
+    flags: u32;
+    grade: vec4<f32>;
+    flash: vec4<f32>;
+    grid: vec2<f32>;
+    fog_color: vec4<f32>;
+    fog_range: vec2<f32>;
+};
+
+let UNLIT_FLAGS_EMISSIVE_BIT: u32 = 1u;
+
+[[group(1), binding(0)]]
+var base_texture: texture_2d<f32>;
+[[group(1), binding(1)]]
+var base_sampler: sampler;
+[[group(1), binding(2)]]
+var<uniform> material: UnlitMaterial;
+[[group(1), binding(3)]]
+var emissive_texture: texture_2d<f32>;
+[[group(1), binding(4)]]
+var emissive_sampler: sampler;
+[[group(1), binding(5)]]
+var sprite_array: texture_2d_array<f32>;
+
+[[group(2), binding(0)]]
+var<uniform> mesh: Mesh;
+
+// Unshaded, so normals aren't needed
+struct Vertex {
+    [[location(0)]] position: vec3<f32>;
+    [[location(2)]] uv: vec2<f32>;
+#ifdef VERTEX_COLORS
+    [[location(3)]] color: u32;
+#endif
+#ifdef SPRITE_ARRAY
+    [[location(4)]] layer: u32;
+#endif
+};
+
+struct VertexOutput {
+    [[builtin(position)]] clip_position: vec4<f32>;
+    [[location(0)]] uv: vec2<f32>;
+    [[location(1)]] color: vec4<f32>;
+#ifdef SPRITE_ARRAY
+    [[location(2), interpolate(flat)]] layer: u32;
+#endif
+    [[location(3)]] world_position: vec3<f32>;
+};
+
+// Kept in sync with SpriteFlip::uv
+fn flip_uv(uv_in: vec2<f32>) -> vec2<f32> {
+    var uv: vec2<f32> = uv_in;
+    let turns = (material.uv_flags >> 2u) & 3u;
+    for (var i: u32 = 0u; i < turns; i = i + 1u) {
+        uv = vec2<f32>(uv.y, 1.0 - uv.x);
+    }
+    if ((material.uv_flags & 1u) != 0u) {
+        uv.x = 1.0 - uv.x;
+    }
+    if ((material.uv_flags & 2u) != 0u) {
+        uv.y = 1.0 - uv.y;
+    }
+    return uv;
+}
+
+// Threshold of a pixel in a 4x4 Bayer matrix, in (0, 1)
+fn bayer(frag_coord: vec2<f32>) -> f32 {
+    let x = u32(frag_coord.x) & 3u;
+    let y = u32(frag_coord.y) & 3u;
+    let a = x ^ y;
+    let index = ((a & 1u) << 3u) | ((y & 1u) << 2u) | (a & 2u) | ((y & 2u) >> 1u);
+    return (f32(index) + 0.5) / 16.0;
+}
+
+#ifdef DEBUG_UV_CHECKER
+// Checkerboard colored by the sprite's own uvs, magenta outside the sprite
+fn uv_checker(uv: vec2<f32>) -> vec4<f32> {
+    if (any(uv < vec2<f32>(0.0)) || any(uv > vec2<f32>(1.0))) {
+        return vec4<f32>(1.0, 0.0, 1.0, 1.0);
+    }
+    let cell = floor(uv * 8.0);
+    let shade = 0.6 + 0.8 * fract((cell.x + cell.y) * 0.5);
+    return vec4<f32>(vec3<f32>(uv.x, uv.y, 1.0 - uv.x) * shade, 1.0);
+}
+#endif
+
+#ifdef DEBUG_OVERDRAW
+// Heat each fragment adds when drawing overdraw
+let OVERDRAW_HEAT: vec4<f32> = vec4<f32>(0.12, 0.05, 0.02, 1.0);
+#endif
+
+// How much of the fog covers a point, by how far it is across the ground from
+// where the center of the view meets it
+fn fog_amount(world_position: vec3<f32>) -> f32 {
+    let eye = view.world_position;
+    let forward = -vec3<f32>(view.inverse_view[2].x, view.inverse_view[2].y, view.inverse_view[2].z);
+    // Looking along the ground there is no center, so fog from the eye
+    var center: vec3<f32> = eye;
+    if (abs(forward.y) > 0.0001) {
+        center = eye - forward * (eye.y / forward.y);
+    }
+    let distance = length(world_position.xz - center.xz);
+    let range = max(material.fog_range.y - material.fog_range.x, 0.0001);
+    return clamp((distance - material.fog_range.x) / range, 0.0, 1.0) * material.fog_color.a;
+}
+
+[[stage(vertex)]]
+fn vertex(vertex: Vertex) -> VertexOutput {
+    var out: VertexOutput;
+    let world_position = mesh.model * vec4<f32>(vertex.position, 1.0);
+    out.clip_position = view.view_proj * world_position;
+    out.world_position = world_position.xyz;
+    out.uv = material.uv_offset + flip_uv(vertex.uv) * material.uv_size;
+#ifdef VERTEX_COLORS
+    out.color = unpack4x8unorm(vertex.color);
+#else
+    out.color = vec4<f32>(1.0);
+#endif
+#ifdef SPRITE_ARRAY
+    out.layer = vertex.layer;
+#endif
+    return out;
+}
+
+[[stage(fragment)]]
+fn fragment(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+#ifdef SPRITE_ARRAY
+    // The mesh UVs still point into the sheet, find where they land inside
+    // the sprite, which fills its whole layer
+    let columns = u32(material.grid.x);
+    let cell = vec2<f32>(f32(in.layer % columns), f32(in.layer / columns));
+    let local = in.uv * material.grid - cell;
+    let base = textureSample(sprite_array, base_sampler, local, i32(in.layer));
+#else
+    let base = textureSample(base_texture, base_sampler, in.uv);
+#endif
+    var color: vec4<f32> = material.color * in.color * base;
+    // Sampled even when unused, texture samples must stay in uniform control flow
+    var emissive: vec3<f32> = textureSample(emissive_texture, emissive_sampler, in.uv).rgb;
+    if ((material.flags & UNLIT_FLAGS_EMISSIVE_BIT) == 0u) {
+        emissive = vec3<f32>(0.0);
+    }
+    if (bayer(in.clip_position.xy) < material.dither) {
+        discard;
+    }
+#ifdef ALPHA_MASK
+    if (color.a < material.alpha_cutoff) {
+        discard;
+    }
+#endif
+#ifndef ALPHA_BLEND
+    color.a = 1.0;
+#endif
+    // The flash covers the grade so a hit reads at any time of day, and glow
+    // is added last so neither tint nor grade can darken it
+    let graded = mix(color.rgb * material.grade.rgb, material.flash.rgb, material.flash.a);
+    color = vec4<f32>(graded + emissive, color.a);
+    // Distant sprites disappear into the atmosphere, glow and all
+    color = vec4<f32>(mix(color.rgb, material.fog_color.rgb, fog_amount(in.world_position)), color.a);
+#ifdef DEBUG_UV_CHECKER
+    color = uv_checker((in.uv - material.uv_offset) / material.uv_size);
+#endif
+#ifdef DEBUG_OVERDRAW
+    color = OVERDRAW_HEAT;
+#endif
+    return color;
+}
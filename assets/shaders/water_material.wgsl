@@ -0,0 +1,55 @@
+#import bevy_pbr::mesh_view_bind_group
+#import bevy_pbr::mesh_struct
+
+struct WaterMaterial {
+    uv_offset: vec2<f32>;
+    uv_size: vec2<f32>;
+    color: vec4<f32>;
+    scroll: vec2<f32>;
+    wave_amplitude: f32;
+    wave_frequency: f32;
+    time: f32;
+    grade: vec4<f32>;
+};
+
+[[group(1), binding(0)]]
+var base_texture: texture_2d<f32>;
+[[group(1), binding(1)]]
+var base_sampler: sampler;
+[[group(1), binding(2)]]
+var<uniform> material: WaterMaterial;
+
+[[group(2), binding(0)]]
+var<uniform> mesh: Mesh;
+
+struct Vertex {
+    [[location(0)]] position: vec3<f32>;
+    [[location(1)]] normal: vec3<f32>;
+    [[location(2)]] uv: vec2<f32>;
+};
+
+struct VertexOutput {
+    [[builtin(position)]] clip_position: vec4<f32>;
+    [[location(0)]] uv: vec2<f32>;
+};
+
+[[stage(vertex)]]
+fn vertex(vertex: Vertex) -> VertexOutput {
+    var out: VertexOutput;
+    out.clip_position = view.view_proj * mesh.model * vec4<f32>(vertex.position, 1.0);
+    out.uv = vertex.uv;
+    return out;
+}
+
+[[stage(fragment)]]
+fn fragment(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+    let t = material.time;
+    let phase = in.uv * material.wave_frequency + vec2<f32>(t, t) * 2.0;
+    let ripple = vec2<f32>(sin(phase.y), cos(phase.x)) * material.wave_amplitude;
+    // Wrap inside the sprite so neighbouring sprites never scroll in
+    let local = fract(in.uv + material.scroll * t + ripple);
+    let uv = material.uv_offset + local * material.uv_size;
+
+    let color = material.color * textureSample(base_texture, base_sampler, uv);
+    return vec4<f32>(color.rgb * material.grade.rgb, color.a);
+}
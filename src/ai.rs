@@ -0,0 +1,508 @@
+use crate::camera::Easing;
+use crate::checkpoint::{Checkpoints, Respawned};
+use crate::editor::GameMode;
+use crate::fov::{FieldOfView, VisionCone};
+use crate::health::{Corpse, Damage, Health};
+use crate::knockback::{Knockback, KnockbackOnHit};
+use crate::loot::DropsLoot;
+use crate::map::{
+    Direction, Location, Marker, MoveTween, NavGrid, Occupancy, OccupancySystem,
+};
+use crate::player::{spawn_body, Player, STEP_DURATION};
+use crate::rules::Rules;
+use crate::stats::{StatBlock, StatJitter, Stats};
+use crate::stealth::{Alert, AlertSystem};
+use crate::team::{Hostility, Team};
+use crate::turn::{enemies_act, Actor, EndTurn, TurnStarted};
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy_inspector_egui::Inspectable;
+use iyes_loopless::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub struct AiPlugin;
+
+impl Plugin for AiPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PatrolPaths>()
+            .add_enter_system(GameMode::Playing, spawn_enemies)
+            .add_exit_system(GameMode::Playing, despawn_enemies)
+            .add_system(reset_enemies.run_in_state(GameMode::Playing))
+            .add_system(
+                take_turns
+                    .after(AlertSystem)
+                    .after(OccupancySystem)
+                    .run_in_state(GameMode::Playing)
+                    .run_if(enemies_act),
+            );
+    }
+}
+
+/// Marker for the units the AI moves on the enemies' turns
+#[derive(Component, Debug, Clone, Copy, Default)]
+pub struct Enemy;
+
+/// Walk from waypoint to waypoint while nothing else is going on
+#[derive(Component, Debug, Clone, Default)]
+pub struct PatrolRoute {
+    /// Visited in order, going back to the first after the last
+    pub route: Vec<Location>,
+    next: usize,
+}
+
+impl PatrolRoute {
+    pub fn new(route: Vec<Location>) -> Self {
+        Self { route, next: 0 }
+    }
+}
+
+/// Route through the map for the enemy of the same `name`, drawn as a
+/// polyline in the map file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PatrolPath {
+    pub name: String,
+    /// Corners of the route, walked between in order and then back to the
+    /// first
+    pub path: Vec<Location>,
+}
+
+/// Patrol paths of the map being played or edited
+#[derive(Debug, Clone, Default)]
+pub struct PatrolPaths {
+    pub paths: Vec<PatrolPath>,
+}
+
+impl PatrolPaths {
+    /// Path bound to the enemy called `name`, if there is one
+    pub fn get(&self, name: &str) -> Option<&PatrolPath> {
+        self.paths.iter().find(|path| path.name == name)
+    }
+}
+
+/// Walk towards the player while it is in the [`FieldOfView`]
+#[derive(Component, Debug, Clone, Copy, Default)]
+pub struct Chase;
+
+/// Run from the player once [`Health`] falls to the `below` share of its
+/// maximum
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Flee {
+    pub below: f32,
+}
+
+impl Default for Flee {
+    fn default() -> Self {
+        Self { below: 0.3 }
+    }
+}
+
+/// What sort of enemy to spawn, each as tough and far sighted as its name
+#[derive(
+    Inspectable,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    Default,
+    Serialize,
+    Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum EnemyKind {
+    #[default]
+    Grunt,
+    /// Slow to die
+    Brute,
+    /// Sees further, but goes down quickly
+    Scout,
+}
+
+impl EnemyKind {
+    pub fn is_grunt(&self) -> bool {
+        *self == EnemyKind::Grunt
+    }
+
+    /// Hit points the enemy starts with
+    fn health(self) -> u32 {
+        match self {
+            EnemyKind::Grunt => 3,
+            EnemyKind::Brute => 6,
+            EnemyKind::Scout => 2,
+        }
+    }
+
+    /// Brutes shrug off hits but are slow to act, scouts act first
+    fn stats(self) -> StatBlock {
+        match self {
+            EnemyKind::Grunt => StatBlock::default(),
+            EnemyKind::Brute => StatBlock {
+                defense: 1,
+                speed: -1,
+                ..default()
+            },
+            EnemyKind::Scout => StatBlock {
+                speed: 1,
+                ..default()
+            },
+        }
+    }
+
+    /// Tiles the enemy sees, the rules' vision radius for most
+    fn vision(self, rules: &Rules) -> u32 {
+        match self {
+            EnemyKind::Scout => rules.vision_radius + 2,
+            _ => rules.vision_radius,
+        }
+    }
+
+    /// Tiles the enemy's attacks knock their target back
+    fn knockback(self) -> u32 {
+        match self {
+            EnemyKind::Brute => 2,
+            _ => 0,
+        }
+    }
+
+    /// Loot table rolled when the enemy dies
+    fn loot_table(self) -> &'static str {
+        match self {
+            EnemyKind::Grunt => "grunt",
+            EnemyKind::Brute => "brute",
+            EnemyKind::Scout => "scout",
+        }
+    }
+
+    fn color(self) -> Color {
+        match self {
+            EnemyKind::Grunt => Color::rgb(0.8, 0.2, 0.25),
+            EnemyKind::Brute => Color::rgb(0.5, 0.1, 0.15),
+            EnemyKind::Scout => Color::rgb(0.9, 0.45, 0.2),
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            EnemyKind::Grunt => "Grunt",
+            EnemyKind::Brute => "Brute",
+            EnemyKind::Scout => "Scout",
+        }
+    }
+}
+
+/// Damage dealt to whatever an enemy chasing it catches up with
+const ATTACK_DAMAGE: u32 = 1;
+
+/// Enemy marker an enemy was spawned on
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpawnedFrom(pub Entity);
+
+/// Everything needed to spawn the enemies of the map's markers
+#[derive(SystemParam)]
+struct EnemySpawning<'w, 's> {
+    commands: Commands<'w, 's>,
+    meshes: ResMut<'w, Assets<Mesh>>,
+    materials: ResMut<'w, Assets<StandardMaterial>>,
+    rules: Res<'w, Rules>,
+    paths: Res<'w, PatrolPaths>,
+    markers: Query<
+        'w,
+        's,
+        (
+            Entity,
+            &'static Location,
+            &'static Marker,
+            Option<&'static Name>,
+            Option<&'static StatJitter>,
+        ),
+    >,
+}
+
+impl<'w, 's> EnemySpawning<'w, 's> {
+    /// Spawn an enemy on every enemy marker for which `skip` is false,
+    /// patrolling the path named after the marker or else the waypoints of
+    /// its route
+    fn spawn(&mut self, skip: impl Fn(Entity) -> bool) {
+        let mut waypoints: Vec<(u32, u32, Location)> = self
+            .markers
+            .iter()
+            .filter_map(|(_, &location, marker, ..)| match *marker {
+                Marker::Waypoint { route, order } => {
+                    Some((route, order, location))
+                }
+                _ => None,
+            })
+            .collect();
+        waypoints.sort_by_key(|&(route, order, _)| (route, order));
+
+        for (entity, &location, marker, name, jitter) in self.markers.iter() {
+            let (route, team, kind) = match *marker {
+                Marker::Enemy { route, team, kind } => (route, team, kind),
+                _ => continue,
+            };
+            if skip(entity) {
+                continue;
+            }
+            let enemy = spawn_enemy(
+                &mut self.commands,
+                &mut self.meshes,
+                &mut self.materials,
+                &self.rules,
+                location,
+                kind,
+                team,
+            );
+            self.commands.entity(enemy).insert(SpawnedFrom(entity));
+            if let Some(jitter) = jitter {
+                let stats = kind.stats() + jitter.0;
+                self.commands.entity(enemy).insert(Stats::new(stats));
+            }
+
+            if let Some(name) = name {
+                self.commands.entity(enemy).insert(name.clone());
+            }
+
+            let path = name.and_then(|name| self.paths.get(name.as_str()));
+            let stops: Vec<Location> = match path {
+                Some(path) => path.path.clone(),
+                None => waypoints
+                    .iter()
+                    .filter(|&&(r, _, _)| r == route)
+                    .map(|&(_, _, location)| location)
+                    .collect(),
+            };
+            if !stops.is_empty() {
+                self.commands.entity(enemy).insert(PatrolRoute::new(stops));
+            }
+        }
+    }
+}
+
+fn spawn_enemies(mut spawning: EnemySpawning) {
+    spawning.spawn(|_| false);
+}
+
+/// Put the enemies back where the map starts them once the player respawns,
+/// leaving out any which were already dead at the checkpoint
+fn reset_enemies(
+    mut spawning: EnemySpawning,
+    checkpoints: Res<Checkpoints>,
+    mut respawned: EventReader<Respawned>,
+    enemies: Query<Entity, With<Enemy>>,
+) {
+    if respawned.iter().last().is_none() {
+        return;
+    }
+    for entity in enemies.iter() {
+        spawning.commands.entity(entity).despawn_recursive();
+    }
+    spawning.spawn(|marker| checkpoints.is_cleared(marker));
+}
+
+/// Spawn an enemy of `kind` at `location`, which chases the player once it
+/// sees it
+pub fn spawn_enemy(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<StandardMaterial>,
+    rules: &Rules,
+    location: Location,
+    kind: EnemyKind,
+    team: Team,
+) -> Entity {
+    let body = spawn_body(commands, meshes, materials, kind.color());
+    let enemy = commands
+        .spawn_bundle(TransformBundle::from_transform(
+            Transform::from_translation(location.into()),
+        ))
+        .insert(location)
+        .insert(MoveTween::new(STEP_DURATION, Easing::EaseOut))
+        .insert(Direction::NegativeY)
+        .insert(Enemy)
+        .insert(Actor { initiative: 0 })
+        .insert(team)
+        .insert(Health::new(kind.health()))
+        .insert(Stats::new(kind.stats()))
+        .insert(DropsLoot(kind.loot_table().to_string()))
+        .insert(FieldOfView::new(kind.vision(rules)))
+        .insert(VisionCone)
+        .insert(Alert::default())
+        .insert(Chase)
+        .insert(Flee::default())
+        .insert(Name::new(kind.name()))
+        .add_child(body)
+        .id();
+    if kind.knockback() > 0 {
+        commands
+            .entity(enemy)
+            .insert(KnockbackOnHit(kind.knockback()));
+    }
+    enemy
+}
+
+fn despawn_enemies(
+    mut commands: Commands,
+    enemies: Query<Entity, With<Enemy>>,
+) {
+    for entity in enemies.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn distance_squared(a: Location, b: Location) -> i32 {
+    let (dx, dy) = (a.x - b.x, a.y - b.y);
+    dx * dx + dy * dy
+}
+
+/// Way to face from `from` to look most directly at `to`
+fn facing_towards(from: Location, to: Location) -> Direction {
+    let (dx, dy) = (to.x - from.x, to.y - from.y);
+    match (dx.abs() >= dy.abs(), dx >= 0, dy >= 0) {
+        (true, true, _) => Direction::PositiveX,
+        (true, false, _) => Direction::NegativeX,
+        (false, _, true) => Direction::PositiveY,
+        (false, _, false) => Direction::NegativeY,
+    }
+}
+
+/// Move each enemy a single tile when its turn starts, and end the turn.
+///
+/// Fleeing comes first, then chasing and attacking once next to the nearest
+/// unit in sight on a hostile [`Team`], then patrolling, and an enemy with
+/// nothing to do waits. Enemies which aren't yet sure of what they saw only
+/// turn to look at it.
+#[allow(clippy::too_many_arguments)]
+fn take_turns(
+    nav: Res<NavGrid>,
+    mut occupancy: ResMut<Occupancy>,
+    mut started: EventReader<TurnStarted>,
+    mut end_turns: EventWriter<EndTurn>,
+    mut damage: EventWriter<Damage>,
+    mut knockbacks: EventWriter<Knockback>,
+    hostility: Res<Hostility>,
+    targets: Query<(Entity, &Team), (With<Health>, Without<Corpse>)>,
+    units: Query<(), Or<(With<Player>, With<Enemy>)>>,
+    mut enemies: Query<
+        (
+            &mut Location,
+            &mut Direction,
+            Option<&Health>,
+            Option<&FieldOfView>,
+            Option<&Alert>,
+            Option<&Chase>,
+            Option<&Flee>,
+            Option<&mut PatrolRoute>,
+            Option<&KnockbackOnHit>,
+        ),
+        (With<Enemy>, Without<Corpse>),
+    >,
+) {
+    for &TurnStarted { entity, .. } in started.iter() {
+        let (
+            mut location,
+            mut facing,
+            health,
+            fov,
+            alert,
+            chase,
+            flee,
+            patrol,
+            heavy,
+        ) = match enemies.get_mut(entity) {
+            Ok(enemy) => enemy,
+            Err(_) => continue,
+        };
+        let here = *location;
+        // Nobody walks into anyone else
+        let free = |to: Location| {
+            let entities = occupancy.entities_at(to);
+            !entities.iter().any(|&e| units.get(e).is_ok())
+        };
+
+        let team = targets.get(entity).map(|(_, &team)| team).ok();
+        let target = fov.zip(team).and_then(|(fov, team)| {
+            targets
+                .iter()
+                .filter(|&(_, &other)| hostility.is_hostile(team, other))
+                .filter_map(|(other, _)| {
+                    Some((other, occupancy.location_of(other)?))
+                })
+                .filter(|&(_, at)| fov.can_see(at))
+                .min_by_key(|&(_, at)| distance_squared(here, at))
+        });
+        let alerted = alert.map(Alert::is_alerted).unwrap_or(true);
+        if let (Some((_, seen)), false) = (target, alerted) {
+            let look = facing_towards(here, seen);
+            if *facing != look {
+                *facing = look;
+            }
+            end_turns.send(EndTurn(entity));
+            continue;
+        }
+        let threat = target.map(|(_, location)| location);
+        let fleeing = match (flee, health) {
+            (Some(flee), Some(health)) => health.fraction() <= flee.below,
+            _ => false,
+        };
+
+        let step = match (threat, patrol) {
+            (Some(threat), _) if fleeing => nav
+                .neighbors(here)
+                .filter(|&to| free(to))
+                .filter(|&to| {
+                    distance_squared(to, threat)
+                        > distance_squared(here, threat)
+                })
+                .max_by_key(|&to| distance_squared(to, threat)),
+            // Close in, but stop next to the player rather than on top
+            (Some(threat), _) if chase.is_some() => {
+                match nav.path(here, threat, |to| !free(to)) {
+                    Some(path) if path.len() > 1 => Some(path[0]),
+                    Some(path) if path.len() == 1 => {
+                        if let Some((victim, _)) = target {
+                            damage.send(Damage {
+                                target: victim,
+                                amount: ATTACK_DAMAGE,
+                                source: Some(entity),
+                            });
+                            if let Some(&KnockbackOnHit(distance)) = heavy {
+                                knockbacks.send(Knockback {
+                                    target: victim,
+                                    direction: facing_towards(here, threat),
+                                    distance,
+                                    source: Some(entity),
+                                });
+                            }
+                        }
+                        None
+                    }
+                    _ => None,
+                }
+            }
+            (_, Some(mut patrol)) if !patrol.route.is_empty() => {
+                if patrol.route[patrol.next] == here {
+                    patrol.next = (patrol.next + 1) % patrol.route.len();
+                }
+                let stop = patrol.route[patrol.next];
+                nav.path(here, stop, |to| !free(to))
+                    .and_then(|path| path.first().copied())
+                    .filter(|&to| free(to))
+            }
+            _ => None,
+        };
+
+        if let Some(step) = step {
+            if let Some(dir) = Direction::ALL
+                .into_iter()
+                .find(|&d| here.neighbor(d) == step)
+            {
+                *facing = dir;
+            }
+            *location = step;
+            // Enemies taking their turns after this one see it moved
+            occupancy.move_to(entity, step);
+        }
+        end_turns.send(EndTurn(entity));
+    }
+}
@@ -0,0 +1,461 @@
+use crate::camera::IsometricCamera;
+use crate::map::{
+    CornerShade, Direction, Location, Tile, TileMesh, WallGrid, TILE_SIZE,
+};
+use crate::material::{BackdropMaterial, DistanceFog, ViewLayer};
+use bevy::prelude::*;
+use bevy::render::view::NoFrustumCulling;
+use bevy::utils::{HashMap, HashSet};
+use bevy_inspector_egui::{Inspectable, RegisterInspectable};
+use iyes_loopless::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub struct AmbiencePlugin;
+
+impl Plugin for AmbiencePlugin {
+    fn build(&self, app: &mut App) {
+        app.register_inspectable::<MapAmbience>()
+            .init_resource::<MapAmbience>()
+            .add_startup_system(setup_void)
+            .add_system_set(
+                ConditionSet::new()
+                    .with_system(shade_edges)
+                    .with_system(shade_corners)
+                    .with_system(animate_void)
+                    .with_system(spawn_backdrop)
+                    .with_system(apply_fog)
+                    .into(),
+            );
+    }
+}
+
+/// Look of the map boundary and the void beyond it, configured by the
+/// `ambience` section of a map
+#[derive(Inspectable, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MapAmbience {
+    /// How dark the outermost ring of tiles gets [0, 1]
+    pub edge_darkness: f32,
+    /// Number of tiles the darkening fades across
+    pub edge_width: u8,
+    /// How dark the corners of tiles get where walls meet them [0, 1], zero
+    /// to leave them unshaded
+    pub corner_occlusion: f32,
+    /// Base color of the void beyond the map
+    pub void_color: Color,
+    /// Color the void pulses towards
+    pub void_pulse_color: Color,
+    /// Pulses per second, zero to disable the animation
+    pub void_pulse_rate: f32,
+    /// Hour of the day the map starts at [0, 24)
+    pub time_of_day: f32,
+    /// Lay soft shadows along walls and under props
+    pub shadows: bool,
+    /// How dark the middle of a shadow gets [0, 1]
+    pub shadow_darkness: f32,
+    /// Drawn beyond the edges of the map
+    pub backdrop: Backdrop,
+    /// Atmosphere the map fades into towards the edges of the view
+    pub fog: MapFog,
+    /// Sprites of the map which glow, in place of the theme's
+    pub emissive: MapEmissive,
+}
+
+impl Default for MapAmbience {
+    fn default() -> Self {
+        Self {
+            edge_darkness: 0.6,
+            edge_width: 2,
+            corner_occlusion: 0.0,
+            void_color: Color::rgb(0.02, 0.01, 0.04),
+            void_pulse_color: Color::rgb(0.06, 0.03, 0.1),
+            void_pulse_rate: 0.2,
+            time_of_day: 12.0,
+            shadows: false,
+            shadow_darkness: 0.4,
+            backdrop: Backdrop::Void,
+            fog: MapFog::default(),
+            emissive: MapEmissive::default(),
+        }
+    }
+}
+
+/// What is seen beyond the edges of the map
+#[derive(
+    Inspectable, Debug, Clone, PartialEq, Default, Serialize, Deserialize,
+)]
+#[serde(rename_all = "snake_case", tag = "kind")]
+pub enum Backdrop {
+    /// The pulsing void plane under the map
+    #[default]
+    Void,
+    /// Gradient from the top of the screen to the bottom
+    Gradient { top: Color, bottom: Color },
+    /// Images stacked from back to front, each drifting with the camera
+    Layers { layers: Vec<BackdropLayer> },
+}
+
+/// Image tiled across the screen behind the map
+#[derive(Inspectable, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BackdropLayer {
+    /// Asset path of the image
+    pub image: String,
+    /// Multiplied with the image
+    pub tint: Color,
+    /// Fraction of the camera's movement the layer follows, distant layers
+    /// should follow less
+    pub parallax: f32,
+    /// Screen heights covered by one repeat of the image
+    pub scale: f32,
+}
+
+impl Default for BackdropLayer {
+    fn default() -> Self {
+        Self {
+            image: String::new(),
+            tint: Color::WHITE,
+            parallax: 0.5,
+            scale: 1.0,
+        }
+    }
+}
+
+/// Distance fog over the unlit sprites of a map, measured from the center of
+/// the view
+#[derive(Inspectable, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MapFog {
+    pub enabled: bool,
+    /// Color faded into, by its alpha
+    pub color: Color,
+    /// Tiles from the center of the view the fade starts at
+    pub start: f32,
+    /// Tiles from the center of the view everything is lost in the fog
+    pub end: f32,
+}
+
+impl Default for MapFog {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            color: Color::rgb(0.02, 0.01, 0.04),
+            start: 8.0,
+            end: 16.0,
+        }
+    }
+}
+
+/// Textures laid out like the theme's sprite sheets whose bright pixels glow
+/// through tint, fog, and darkness, falling back to the theme's own for each
+/// sheet left out
+#[derive(
+    Inspectable, Debug, Clone, PartialEq, Default, Serialize, Deserialize,
+)]
+#[serde(default)]
+pub struct MapEmissive {
+    /// Asset path of the glow of the tile sheet
+    pub tiles: Option<String>,
+    /// Asset path of the glow of the wall sheet
+    pub walls: Option<String>,
+}
+
+/// Marker for the translucent overlays darkening tiles near the boundary
+#[derive(Component)]
+pub struct EdgeShade;
+
+/// Marker for the plane drawn under the whole map
+#[derive(Component)]
+pub struct Void;
+
+/// Marker for the layers of a [`Backdrop`], drawn by the camera behind
+/// everything else
+#[derive(Component)]
+pub struct BackdropQuad;
+
+/// How large the void plane is, in tiles
+const VOID_SIZE: f32 = 512.0;
+
+fn setup_void(
+    mut commands: Commands,
+    ambience: Res<MapAmbience>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.insert_resource(ClearColor(ambience.void_color));
+    commands
+        .spawn_bundle(PbrBundle {
+            mesh: meshes.add(Mesh::from(shape::Plane {
+                size: VOID_SIZE * TILE_SIZE,
+            })),
+            material: materials.add(StandardMaterial {
+                base_color: ambience.void_color,
+                unlit: true,
+                ..Default::default()
+            }),
+            // Sit just below the tiles so it never covers them
+            transform: Transform::from_xyz(0.0, -0.01, 0.0),
+            ..Default::default()
+        })
+        .insert(Void)
+        .insert(Name::new("Void"));
+}
+
+/// Slowly pulse the void between its two colors
+fn animate_void(
+    time: Res<Time>,
+    ambience: Res<MapAmbience>,
+    mut clear: ResMut<ClearColor>,
+    query: Query<&Handle<StandardMaterial>, With<Void>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let phase = time.seconds_since_startup() as f32
+        * ambience.void_pulse_rate
+        * std::f32::consts::TAU;
+    let t = 0.5 - 0.5 * phase.cos();
+    let [r0, g0, b0, _] = ambience.void_color.as_rgba_f32();
+    let [r1, g1, b1, _] = ambience.void_pulse_color.as_rgba_f32();
+    let color =
+        Color::rgb(r0 + (r1 - r0) * t, g0 + (g1 - g0) * t, b0 + (b1 - b0) * t);
+
+    clear.0 = color;
+    for handle in query.iter() {
+        if let Some(material) = materials.get_mut(handle) {
+            material.base_color = color;
+        }
+    }
+}
+
+/// Distance in front of the camera of the furthest backdrop layer, only used
+/// to draw the layers in order
+const BACKDROP_DEPTH: f32 = 1000.0;
+
+/// Replace the backdrop layers of every camera when the backdrop changes,
+/// hiding the void behind anything else
+#[allow(clippy::too_many_arguments)]
+fn spawn_backdrop(
+    mut commands: Commands,
+    ambience: Res<MapAmbience>,
+    asset_server: Res<AssetServer>,
+    cameras: Query<Entity, With<IsometricCamera>>,
+    added: Query<(), Added<IsometricCamera>>,
+    layers: Query<Entity, With<BackdropQuad>>,
+    mut void: Query<&mut Visibility, With<Void>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<BackdropMaterial>>,
+) {
+    if !ambience.is_changed() && added.is_empty() {
+        return;
+    }
+
+    for entity in layers.iter() {
+        commands.entity(entity).despawn();
+    }
+    let backdrop: Vec<BackdropMaterial> = match &ambience.backdrop {
+        Backdrop::Void => Vec::new(),
+        &Backdrop::Gradient { top, bottom } => vec![BackdropMaterial {
+            top,
+            bottom,
+            ..Default::default()
+        }],
+        Backdrop::Layers { layers } => layers
+            .iter()
+            .map(|layer| BackdropMaterial {
+                texture: Some(asset_server.load(layer.image.as_str())),
+                top: layer.tint,
+                bottom: layer.tint,
+                parallax: layer.parallax,
+                scale: layer.scale,
+            })
+            .collect(),
+    };
+    for mut visibility in void.iter_mut() {
+        visibility.is_visible = backdrop.is_empty();
+    }
+    if backdrop.is_empty() {
+        return;
+    }
+
+    // Spans clip space, like the screen overlay
+    let mesh = meshes.add(shape::Quad::new(Vec2::splat(2.0)).into());
+    let backdrop: Vec<Handle<BackdropMaterial>> = backdrop
+        .into_iter()
+        .map(|layer| materials.add(layer))
+        .collect();
+    for camera in cameras.iter() {
+        for (i, material) in backdrop.iter().enumerate() {
+            // Blended back to front, so the first layer is furthest away
+            let depth = BACKDROP_DEPTH - i as f32;
+            let layer = commands
+                .spawn_bundle(MaterialMeshBundle {
+                    mesh: mesh.clone(),
+                    material: material.clone(),
+                    transform: Transform::from_xyz(0.0, 0.0, -depth),
+                    ..Default::default()
+                })
+                .insert(NoFrustumCulling)
+                .insert(BackdropQuad)
+                .insert(ViewLayer::Screen.layers())
+                .insert(Name::new("Backdrop Layer"))
+                .id();
+            commands.entity(camera).add_child(layer);
+        }
+    }
+}
+
+/// Darken tiles by how close they are to the edge of the map, rebuilt
+/// whenever tiles or the ambience settings change
+#[allow(clippy::too_many_arguments)]
+fn shade_edges(
+    mut commands: Commands,
+    ambience: Res<MapAmbience>,
+    mesh: Res<TileMesh>,
+    tiles: Query<&Location, With<Tile>>,
+    changed: Query<(), (With<Tile>, Changed<Location>)>,
+    removed: RemovedComponents<Tile>,
+    shades: Query<Entity, With<EdgeShade>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let dirty = ambience.is_changed()
+        || !changed.is_empty()
+        || removed.iter().next().is_some();
+    if !dirty {
+        return;
+    }
+
+    for entity in shades.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    let width = ambience.edge_width;
+    if width == 0 || ambience.edge_darkness <= 0.0 {
+        return;
+    }
+
+    // Breadth first search inwards from every tile missing a neighbor
+    let floor: HashSet<Location> = tiles.iter().copied().collect();
+    let mut depth: HashMap<Location, u8> = HashMap::default();
+    let mut frontier: Vec<Location> = floor
+        .iter()
+        .copied()
+        .filter(|&l| {
+            Direction::ALL
+                .iter()
+                .any(|&d| !floor.contains(&l.neighbor(d)))
+        })
+        .collect();
+    for ring in 0..width {
+        let mut next = Vec::new();
+        for location in frontier {
+            if depth.contains_key(&location) {
+                continue;
+            }
+            depth.insert(location, ring);
+            next.extend(
+                Direction::ALL
+                    .iter()
+                    .map(|&d| location.neighbor(d))
+                    .filter(|n| floor.contains(n) && !depth.contains_key(n)),
+            );
+        }
+        frontier = next;
+    }
+
+    // One material per ring of the gradient
+    let rings: Vec<Handle<StandardMaterial>> = (0..width)
+        .map(|ring| {
+            let fade = 1.0 - ring as f32 / width as f32;
+            materials.add(StandardMaterial {
+                base_color: Color::rgba(
+                    0.0,
+                    0.0,
+                    0.0,
+                    ambience.edge_darkness * fade,
+                ),
+                alpha_mode: AlphaMode::Blend,
+                unlit: true,
+                ..Default::default()
+            })
+        })
+        .collect();
+
+    for (location, ring) in depth {
+        let translation = Vec3::from(location) + Vec3::Y * 0.0005;
+        commands
+            .spawn_bundle(PbrBundle {
+                mesh: mesh.handle.clone(),
+                material: rings[ring as usize].clone(),
+                transform: Transform::from_translation(translation),
+                ..Default::default()
+            })
+            .insert(EdgeShade);
+    }
+}
+
+/// Keep the distance fog of every unlit sprite in step with the ambience
+fn apply_fog(ambience: Res<MapAmbience>, mut fog: ResMut<DistanceFog>) {
+    if !ambience.is_changed() {
+        return;
+    }
+    let map = &ambience.fog;
+    *fog = if map.enabled {
+        DistanceFog {
+            color: map.color,
+            start: map.start * TILE_SIZE,
+            end: map.end * TILE_SIZE,
+        }
+    } else {
+        DistanceFog::default()
+    };
+}
+
+/// Sides of a tile meeting at each corner, in the order of [`CornerShade`]
+const CORNERS: [(Direction, Direction); 4] = [
+    (Direction::NegativeX, Direction::PositiveY),
+    (Direction::PositiveX, Direction::PositiveY),
+    (Direction::NegativeX, Direction::NegativeY),
+    (Direction::PositiveX, Direction::NegativeY),
+];
+
+/// Darken each corner of a tile by the walls meeting there, whether they run
+/// along the tile's own sides or end against the corner
+fn corner_shade(
+    location: Location,
+    walls: &WallGrid,
+    darkness: f32,
+) -> CornerShade {
+    let darkness = darkness.clamp(0.0, 1.0);
+    CornerShade(CORNERS.map(|(x, y)| {
+        let edges = [
+            walls.is_blocked(location, x),
+            walls.is_blocked(location, y),
+            walls.is_blocked(location.neighbor(x), y),
+            walls.is_blocked(location.neighbor(y), x),
+        ];
+        // A second wall closes the corner off, any more can't darken it
+        let count = edges.iter().filter(|&&blocked| blocked).count().min(2);
+        1.0 - darkness * count as f32 / 2.0
+    }))
+}
+
+/// Bake the shade of wall corners into the tiles whenever tiles, walls, or
+/// the ambience settings change, so it costs nothing while drawing
+fn shade_corners(
+    mut commands: Commands,
+    ambience: Res<MapAmbience>,
+    walls: Res<WallGrid>,
+    tiles: Query<(Entity, &Location, Option<&CornerShade>), With<Tile>>,
+    changed: Query<(), (With<Tile>, Changed<Location>)>,
+) {
+    if !ambience.is_changed() && !walls.is_changed() && changed.is_empty() {
+        return;
+    }
+
+    for (entity, &location, current) in tiles.iter() {
+        let shade = corner_shade(location, &walls, ambience.corner_occlusion);
+        // Only touch tiles whose shade moved, each one rebuilds a mesh
+        if current.copied().unwrap_or_default() != shade {
+            commands.entity(entity).insert(shade);
+        }
+    }
+}
@@ -0,0 +1,302 @@
+use crate::dialogue::not_talking;
+use crate::editor::GameMode;
+use crate::fov::line_of_sight;
+use crate::health::{Damage, Health};
+use crate::map::{Direction, Location, NavGrid, TileMesh, WallGrid};
+use crate::material::ViewLayer;
+use crate::player::Player;
+use crate::select::{HoveredTile, TileClicked};
+use crate::turn::{player_acts, EndTurn, TurnPhase, TurnQueue};
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy::utils::HashSet;
+use bevy_egui::EguiContext;
+use iyes_loopless::prelude::*;
+
+pub struct AreaPlugin;
+
+impl Plugin for AreaPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<AreaPreview>()
+            .init_resource::<Aiming>()
+            .init_resource::<AreaPreviewStyle>()
+            .add_exit_system(GameMode::Playing, stop_aiming)
+            .add_exit_system(TurnPhase::PlayerInput, stop_aiming)
+            .add_system(
+                aim_blast
+                    .label(AimSystem)
+                    .run_in_state(GameMode::Playing)
+                    .run_if(player_acts)
+                    .run_if(not_talking),
+            )
+            .add_system(AreaPreview::system.after(aim_blast));
+    }
+}
+
+/// Aiming and casting blasts, which takes over clicks on tiles while aiming
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, SystemLabel)]
+pub struct AimSystem;
+
+/// Key which starts and stops aiming a blast, cast by clicking a tile
+const BLAST: KeyCode = KeyCode::G;
+
+/// Tiles from the player a blast can be centered on
+const BLAST_RANGE: i32 = 5;
+
+/// Damage dealt to everything caught in a blast
+const BLAST_DAMAGE: u32 = 2;
+
+/// Footprint of an area of effect, stopped by walls and anything out of
+/// sight of where it starts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AreaShape {
+    /// Every tile within `radius`, the center included
+    Circle { radius: u32 },
+    /// Widening a tile on each side with every step `length` tiles out in
+    /// `direction`, not including the start
+    Cone { direction: Direction, length: u32 },
+    /// Straight out `length` tiles in `direction`, not including the start
+    Line { direction: Direction, length: u32 },
+    /// Straight out `length` tiles every way, the center included
+    Cross { length: u32 },
+}
+
+impl AreaShape {
+    /// Floor tiles covered when the area starts at `origin`
+    pub fn footprint(
+        self,
+        origin: Location,
+        nav: &NavGrid,
+        walls: &WallGrid,
+    ) -> HashSet<Location> {
+        let seen = |at: Location| {
+            nav.has_floor(at) && line_of_sight(origin, at, walls)
+        };
+        match self {
+            AreaShape::Circle { radius } => {
+                let r = radius as i32;
+                // Rounder than comparing to the radius squared alone
+                let reach = r * r + r;
+                square(origin, r)
+                    .filter(|at| {
+                        let (dx, dy) = (at.x - origin.x, at.y - origin.y);
+                        dx * dx + dy * dy <= reach
+                    })
+                    .filter(|&at| seen(at))
+                    .collect()
+            }
+            AreaShape::Cone { direction, length } => {
+                let (fx, fy) = direction.offset();
+                square(origin, length as i32)
+                    .filter(|at| {
+                        let (dx, dy) = (at.x - origin.x, at.y - origin.y);
+                        let ahead = dx * fx + dy * fy;
+                        let aside = (dx * fy - dy * fx).abs();
+                        ahead > 0 && ahead <= length as i32 && aside < ahead
+                    })
+                    .filter(|&at| seen(at))
+                    .collect()
+            }
+            AreaShape::Line { direction, length } => {
+                ray(origin, direction, length, nav).collect()
+            }
+            AreaShape::Cross { length } => Direction::ALL
+                .into_iter()
+                .flat_map(|direction| ray(origin, direction, length, nav))
+                .chain(Some(origin).filter(|&at| nav.has_floor(at)))
+                .collect(),
+        }
+    }
+}
+
+/// Every tile within `reach` of `origin` along both axes
+fn square(origin: Location, reach: i32) -> impl Iterator<Item = Location> {
+    (-reach..=reach).flat_map(move |dy| {
+        (-reach..=reach).map(move |dx| origin.offset(dx, dy))
+    })
+}
+
+/// Tiles out from `origin` until `length` or the first wall
+fn ray(
+    origin: Location,
+    direction: Direction,
+    length: u32,
+    nav: &NavGrid,
+) -> impl Iterator<Item = Location> + '_ {
+    (0..length)
+        .scan(origin, move |at, _| {
+            let next = at.neighbor(direction);
+            if nav.is_walled(*at, next) || !nav.has_floor(next) {
+                return None;
+            }
+            *at = next;
+            Some(next)
+        })
+        .fuse()
+}
+
+/// Finds what an area of effect covers on the map as it is now
+#[derive(SystemParam)]
+pub struct Areas<'w, 's> {
+    nav: Res<'w, NavGrid>,
+    walls: Res<'w, WallGrid>,
+    units: Query<'w, 's, (Entity, &'static Location), With<Health>>,
+}
+
+impl<'w, 's> Areas<'w, 's> {
+    pub fn locations(
+        &self,
+        shape: AreaShape,
+        origin: Location,
+    ) -> HashSet<Location> {
+        shape.footprint(origin, &self.nav, &self.walls)
+    }
+
+    /// Everything with [`Health`] standing in the area
+    pub fn entities(&self, shape: AreaShape, origin: Location) -> Vec<Entity> {
+        let covered = self.locations(shape, origin);
+        self.units
+            .iter()
+            .filter(|(_, location)| covered.contains(*location))
+            .map(|(entity, _)| entity)
+            .collect()
+    }
+}
+
+/// Area shown on the map while it is being aimed, `None` when not aiming
+#[derive(Default, Debug)]
+pub struct AreaPreview {
+    pub area: Option<(AreaShape, Location)>,
+}
+
+impl AreaPreview {
+    /// Rebuild the preview overlays whenever the area moves
+    fn system(
+        mut commands: Commands,
+        preview: Res<AreaPreview>,
+        style: Res<AreaPreviewStyle>,
+        mesh: Res<TileMesh>,
+        areas: Areas,
+        overlays: Query<Entity, With<AreaOverlay>>,
+    ) {
+        if !preview.is_changed() {
+            return;
+        }
+        for entity in overlays.iter() {
+            commands.entity(entity).despawn();
+        }
+
+        let (shape, origin) = match preview.area {
+            Some(area) => area,
+            None => return,
+        };
+        for location in areas.locations(shape, origin) {
+            // Lift slightly off the ground to avoid z-fighting with the tile
+            let translation = Vec3::from(location) + Vec3::Y * 0.001;
+            commands
+                .spawn_bundle(PbrBundle {
+                    mesh: mesh.handle.clone(),
+                    material: style.material.clone(),
+                    transform: Transform::from_translation(translation),
+                    ..Default::default()
+                })
+                .insert(AreaOverlay)
+                .insert(ViewLayer::Gameplay.layers());
+        }
+    }
+}
+
+/// Appearance of the tiles covered by the [`AreaPreview`]
+pub struct AreaPreviewStyle {
+    pub material: Handle<StandardMaterial>,
+}
+
+impl FromWorld for AreaPreviewStyle {
+    fn from_world(world: &mut World) -> Self {
+        Self {
+            material: world.resource_mut::<Assets<StandardMaterial>>().add(
+                StandardMaterial {
+                    base_color: Color::rgba(1.0, 0.35, 0.1, 0.4),
+                    alpha_mode: AlphaMode::Blend,
+                    unlit: true,
+                    ..Default::default()
+                },
+            ),
+        }
+    }
+}
+
+/// Marker for the overlay drawn on a tile in the [`AreaPreview`]
+#[derive(Component)]
+pub struct AreaOverlay;
+
+/// Whether the player is aiming a blast
+#[derive(Default)]
+pub struct Aiming(pub bool);
+
+fn stop_aiming(mut aiming: ResMut<Aiming>, mut preview: ResMut<AreaPreview>) {
+    aiming.0 = false;
+    if preview.area.is_some() {
+        preview.area = None;
+    }
+}
+
+/// Aim a blast at the tile under the cursor, previewing what it will catch,
+/// and cast it with a click, which ends the player's turn
+#[allow(clippy::too_many_arguments)]
+fn aim_blast(
+    keys: Res<Input<KeyCode>>,
+    hovered: Res<HoveredTile>,
+    turns: Res<TurnQueue>,
+    areas: Areas,
+    mut aiming: ResMut<Aiming>,
+    mut preview: ResMut<AreaPreview>,
+    mut egui: ResMut<EguiContext>,
+    mut clicked: EventReader<TileClicked>,
+    mut damage: EventWriter<Damage>,
+    mut end_turns: EventWriter<EndTurn>,
+    players: Query<(Entity, &Location), With<Player>>,
+) {
+    let clicks: Vec<TileClicked> = clicked.iter().copied().collect();
+    let (player, &from) = match players.iter().find(|&(e, _)| turns.is_turn(e))
+    {
+        Some(player) => player,
+        None => return,
+    };
+    if !egui.ctx_mut().wants_keyboard_input() && keys.just_pressed(BLAST) {
+        aiming.0 = !aiming.0;
+    }
+    if !aiming.0 {
+        if preview.area.is_some() {
+            preview.area = None;
+        }
+        return;
+    }
+
+    // Only centered on tiles in range the player can see
+    let shape = AreaShape::Circle { radius: 1 };
+    let target = hovered.0.filter(|&at| {
+        let distance = (at.x - from.x).abs() + (at.y - from.y).abs();
+        distance <= BLAST_RANGE && line_of_sight(from, at, &areas.walls)
+    });
+    let area = target.map(|at| (shape, at));
+    if preview.area != area {
+        preview.area = area;
+    }
+
+    let cast = clicks.iter().any(|click| {
+        click.button == MouseButton::Left && Some(click.location) == target
+    });
+    if let (true, Some(at)) = (cast, target) {
+        for target in areas.entities(shape, at) {
+            damage.send(Damage {
+                target,
+                amount: BLAST_DAMAGE,
+                source: Some(player),
+            });
+        }
+        aiming.0 = false;
+        preview.area = None;
+        end_turns.send(EndTurn(player));
+    }
+}
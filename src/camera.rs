@@ -1,7 +1,20 @@
+use crate::map::{Direction, Facing, Location, MapBounds, TILE_SIZE};
+use bevy::input::mouse::{MouseMotion, MouseScrollUnit, MouseWheel};
 use bevy::prelude::*;
 use bevy::render::camera::*;
+use bevy::render::primitives::Frustum;
+use bevy::render::view::{RenderLayers, VisibilitySystems};
+use bevy_egui::EguiContext;
 use bevy_inspector_egui::{Inspectable, RegisterInspectable};
 use iyes_loopless::prelude::*;
+use std::f32::consts::{FRAC_PI_2, FRAC_PI_4, PI, TAU};
+
+pub mod input;
+pub mod preset;
+pub mod track;
+
+pub use input::CameraInputConfig;
+pub use track::Easing;
 
 pub struct CameraPlugin;
 
@@ -9,6 +22,10 @@ pub struct CameraPlugin;
 #[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
 pub struct CameraSystem;
 
+/// Label applied to the systems moving the camera focus to its target
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
+pub struct CameraFollowSystem;
+
 impl Plugin for CameraPlugin {
     fn build(&self, app: &mut App) {
         app.register_inspectable::<ControllerBasis>()
@@ -17,12 +34,78 @@ impl Plugin for CameraPlugin {
             .register_type::<YawPitchControls>()
             .register_inspectable::<IsometricCamera>()
             .register_type::<IsometricCamera>()
+            .register_inspectable::<Zoom>()
+            .register_inspectable::<Momentum>()
+            .register_inspectable::<ProjectionBlend>()
+            .register_inspectable::<CameraSettings>()
+            .init_resource::<CameraSettings>()
+            .init_resource::<CameraControlMode>()
+            .add_event::<CameraRotated>()
+            .add_event::<CameraTransition>()
+            .add_plugin(input::CameraInputPlugin)
+            .add_plugin(preset::CameraPresetPlugin)
+            .add_plugin(track::TrackPlugin)
             .add_startup_system(setup_camera.label(CameraSystem))
+            .add_system_set(
+                ConditionSet::new()
+                    .label(CameraFollowSystem)
+                    .before(CameraSystem)
+                    .with_system(PannedAway::system)
+                    .with_system(Follow::retarget.after(PannedAway::system))
+                    .with_system(FocusTransition::system)
+                    .with_system(Follow::system.after(Follow::retarget))
+                    .with_system(
+                        CameraTransition::start
+                            .after(Follow::system)
+                            .after(FocusTransition::system),
+                    )
+                    .with_system(
+                        FocusPan::system.after(CameraTransition::start),
+                    )
+                    .into(),
+            )
             .add_system_set(
                 ConditionSet::new()
                     .label(CameraSystem)
-                    .with_system(YawPitchControls::system)
+                    .with_system(drag_pan)
+                    .with_system(edge_scroll)
+                    .with_system(Turn::input)
+                    .with_system(Turn::system.after(Turn::input))
+                    .with_system(Momentum::system.after(drag_pan))
+                    .with_system(
+                        clamp_to_map
+                            .after(Momentum::system)
+                            .after(edge_scroll)
+                            .after(Zoom::input),
+                    )
+                    .with_system(
+                        YawPitchControls::system
+                            .after(Turn::system)
+                            .after(Momentum::system)
+                            .after(clamp_to_map),
+                    )
+                    .with_system(Zoom::input)
+                    .with_system(Zoom::system.after(clamp_to_map))
                     .into(),
+            )
+            .add_system(ProjectionBlend::input)
+            .add_system(
+                ProjectionBlend::system
+                    .after(ProjectionBlend::input)
+                    .after(CameraSystem)
+                    .after(Zoom::system),
+            )
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                fit_visible_tiles
+                    .before(camera_system::<OrthographicProjection>),
+            )
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                ProjectionBlend::project
+                    .after(camera_system::<OrthographicProjection>)
+                    .after(VisibilitySystems::UpdateOrthographicFrusta)
+                    .before(VisibilitySystems::CheckVisibility),
             );
     }
 }
@@ -34,6 +117,12 @@ struct IsometricCameraBundle {
     controller_basis: ControllerBasis,
     controls: YawPitchControls,
     marker: IsometricCamera,
+    zoom: Zoom,
+    momentum: Momentum,
+    look_ahead: LookAhead,
+    blend: ProjectionBlend,
+    /// Sees the map, gameplay, and editor gizmos alike
+    layers: RenderLayers,
 }
 
 impl IsometricCameraBundle {
@@ -43,6 +132,11 @@ impl IsometricCameraBundle {
             controller_basis: ControllerBasis::default(),
             controls: YawPitchControls::default(),
             marker: IsometricCamera,
+            zoom: Zoom { target: 1.0 },
+            momentum: Momentum::default(),
+            look_ahead: LookAhead::default(),
+            blend: ProjectionBlend::default(),
+            layers: RenderLayers::all(),
         }
     }
 }
@@ -54,6 +148,703 @@ fn setup_camera(mut commands: Commands) {
 #[derive(Component, Inspectable, Reflect)]
 pub struct IsometricCamera;
 
+/// How the player can move the [`IsometricCamera`]
+#[derive(Inspectable, Debug, Clone)]
+pub struct CameraSettings {
+    /// Tiles the view is tall at the default zoom, whatever the size or
+    /// shape of the window
+    #[inspectable(min = 1.0)]
+    pub visible_tiles: f32,
+    /// Smallest orthographic scale, the closest the view can zoom in
+    #[inspectable(min = 0.01)]
+    pub min_zoom: f32,
+    /// Largest orthographic scale, the furthest the view can zoom out
+    #[inspectable(min = 0.01)]
+    pub max_zoom: f32,
+    /// Fraction the scale changes by per line scrolled or zoom key pressed
+    #[inspectable(min = 0.0, max = 0.9)]
+    pub zoom_step: f32,
+    /// How quickly the scale catches up with the zoom, higher is snappier
+    #[inspectable(min = 0.0)]
+    pub zoom_speed: f32,
+    /// Drag the ground around while holding the middle mouse button
+    pub drag_pan: bool,
+    /// Radians the view orbits by per pixel dragged
+    #[inspectable(min = 0.0)]
+    pub orbit_speed: f32,
+    /// Keep panning and orbiting for a moment after a drag is let go
+    pub momentum: bool,
+    /// How quickly panning after a drag comes to a stop, higher stops sooner
+    #[inspectable(min = 0.0)]
+    pub pan_damping: f32,
+    /// How quickly orbiting after a drag comes to a stop
+    #[inspectable(min = 0.0)]
+    pub orbit_damping: f32,
+    /// Scroll the view while the cursor is against the edge of the window,
+    /// turned on by the editor while it is open
+    pub edge_scroll: bool,
+    /// Pixels from the edge of the window the cursor starts scrolling at
+    #[inspectable(min = 1.0)]
+    pub edge_margin: f32,
+    /// World units scrolled per second at the default zoom
+    #[inspectable(min = 0.0)]
+    pub edge_scroll_speed: f32,
+    /// Keep the focus over the map, and zoom out no further than it takes
+    /// to see all of it
+    pub clamp_to_map: bool,
+    /// Tiles the focus may wander past the edges of the map
+    #[inspectable(min = 0.0)]
+    pub map_margin: f32,
+    /// Seconds of movement the focus leads a followed target by, so more of
+    /// where it is going is in view
+    #[inspectable(min = 0.0)]
+    pub look_ahead: f32,
+    /// Furthest the focus leads a followed target by, in tiles
+    #[inspectable(min = 0.0)]
+    pub look_ahead_max: f32,
+    /// How quickly the lead catches up with the target's movement, higher is
+    /// snappier
+    #[inspectable(min = 0.0)]
+    pub look_ahead_speed: f32,
+    /// Vertical field of view in degrees when seen in perspective
+    #[inspectable(min = 5.0, max = 120.0)]
+    pub perspective_fov: f32,
+    /// Seconds taken to blend between orthographic and perspective
+    #[inspectable(min = 0.0)]
+    pub projection_blend_time: f32,
+}
+
+impl Default for CameraSettings {
+    fn default() -> Self {
+        Self {
+            visible_tiles: 6.0,
+            min_zoom: 0.25,
+            max_zoom: 8.0,
+            zoom_step: 0.1,
+            zoom_speed: 12.0,
+            drag_pan: true,
+            orbit_speed: 0.01,
+            momentum: true,
+            pan_damping: 6.0,
+            orbit_damping: 8.0,
+            edge_scroll: false,
+            edge_margin: 8.0,
+            edge_scroll_speed: 1.5,
+            clamp_to_map: true,
+            map_margin: 2.0,
+            look_ahead: 0.35,
+            look_ahead_max: 2.0,
+            look_ahead_speed: 3.0,
+            perspective_fov: 35.0,
+            projection_blend_time: 0.4,
+        }
+    }
+}
+
+/// Size the orthographic view to show the same number of tiles from top to
+/// bottom at any resolution, stretching it sideways to the window's shape
+fn fit_visible_tiles(
+    settings: Res<CameraSettings>,
+    windows: Res<Windows>,
+    images: Res<Assets<Image>>,
+    mut cameras: Query<
+        (&Camera, &mut OrthographicProjection),
+        With<IsometricCamera>,
+    >,
+) {
+    for (camera, mut projection) in cameras.iter_mut() {
+        let size = match camera.target.get_logical_size(&windows, &images) {
+            Some(size) if size.y > 0.0 => size,
+            _ => continue,
+        };
+        let half_height = settings.visible_tiles.max(1.0) * TILE_SIZE * 0.5;
+        let half_width = half_height * size.x / size.y;
+        let fitted = matches!(projection.scaling_mode, ScalingMode::None)
+            && projection.top == half_height
+            && projection.right == half_width;
+        if fitted {
+            continue;
+        }
+        // Left alone by bevy, which would otherwise size it to the window
+        projection.scaling_mode = ScalingMode::None;
+        projection.left = -half_width;
+        projection.right = half_width;
+        projection.bottom = -half_height;
+        projection.top = half_height;
+    }
+}
+
+/// Whether the player moves the [`IsometricCamera`], or something else like
+/// a cutscene has taken it over
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CameraControlMode {
+    /// Panned, zoomed, and turned by the player
+    #[default]
+    Player,
+    /// Left alone by every player control
+    Scripted,
+}
+
+/// Picking through a camera, from points on its target to points on the
+/// y = 0 ground plane
+pub trait CameraProjectionExt {
+    /// Ray from the near plane through `screen`, in pixels from the bottom
+    /// left of a target `size` pixels across, as an origin and direction
+    fn screen_ray(
+        &self,
+        transform: &GlobalTransform,
+        size: Vec2,
+        screen: Vec2,
+    ) -> (Vec3, Vec3);
+
+    /// Where `screen` lands on the ground plane, if the ray ever reaches it
+    fn screen_to_ground(
+        &self,
+        transform: &GlobalTransform,
+        size: Vec2,
+        screen: Vec2,
+    ) -> Option<Vec3> {
+        let (origin, dir) = self.screen_ray(transform, size, screen);
+        if dir.y.abs() <= f32::EPSILON {
+            return None;
+        }
+        let t = -origin.y / dir.y;
+        Some(origin + dir * t)
+    }
+
+    /// Point on the ground plane under the cursor, when the camera draws to
+    /// a window the cursor is over
+    fn cursor_to_ground(
+        &self,
+        transform: &GlobalTransform,
+        windows: &Windows,
+    ) -> Option<Vec3>;
+
+    /// The tile under the cursor
+    fn cursor_to_location(
+        &self,
+        transform: &GlobalTransform,
+        windows: &Windows,
+    ) -> Option<Location> {
+        self.cursor_to_ground(transform, windows)
+            .map(Location::from_world)
+    }
+}
+
+impl CameraProjectionExt for Camera {
+    fn screen_ray(
+        &self,
+        transform: &GlobalTransform,
+        size: Vec2,
+        screen: Vec2,
+    ) -> (Vec3, Vec3) {
+        // Unproject at the near plane and partway into the reversed depth
+        // range, perspective puts the far end at infinity
+        let ndc = (screen / size.max(Vec2::ONE)) * 2.0 - Vec2::ONE;
+        let ndc_to_world =
+            transform.compute_matrix() * self.projection_matrix.inverse();
+        let near = ndc_to_world.project_point3(ndc.extend(1.0));
+        let far = ndc_to_world.project_point3(ndc.extend(0.5));
+        (near, far - near)
+    }
+
+    fn cursor_to_ground(
+        &self,
+        transform: &GlobalTransform,
+        windows: &Windows,
+    ) -> Option<Vec3> {
+        let window = match self.target {
+            RenderTarget::Window(id) => windows.get(id),
+            _ => None,
+        }?;
+        let cursor = window.cursor_position()?;
+        let size = Vec2::new(window.width(), window.height());
+        self.screen_to_ground(transform, size, cursor)
+    }
+}
+
+/// Directions along the ground plane the view's right and up point in, and
+/// how many world units one pixel of the window covers there
+fn ground_axes(
+    transform: &Transform,
+    projection: &OrthographicProjection,
+    controls: &YawPitchControls,
+    window: &Window,
+) -> (Vec3, Vec3, Vec2) {
+    let flat = Vec3::new(1.0, 0.0, 1.0);
+    let right = (transform.right() * flat).normalize_or_zero();
+    let forward = (transform.forward() * flat).normalize_or_zero();
+    let pixel = (projection.top - projection.bottom) * projection.scale
+        / window.height().max(1.0);
+    // The ground is foreshortened along the view by the pitch
+    let along = pixel / controls.pitch.sin().max(0.1);
+    (right, forward, Vec2::new(pixel, along))
+}
+
+/// Drag the ground under the cursor while the middle mouse button is held,
+/// or orbit the view while the orbit key is held too
+#[allow(clippy::too_many_arguments)]
+fn drag_pan(
+    mut commands: Commands,
+    time: Res<Time>,
+    mode: Res<CameraControlMode>,
+    settings: Res<CameraSettings>,
+    input: Res<CameraInputConfig>,
+    buttons: Res<Input<MouseButton>>,
+    keys: Res<Input<KeyCode>>,
+    windows: Res<Windows>,
+    mut motion: EventReader<MouseMotion>,
+    mut egui: ResMut<EguiContext>,
+    mut cameras: Query<
+        (
+            Entity,
+            &Transform,
+            &OrthographicProjection,
+            &mut YawPitchControls,
+            Option<&mut Momentum>,
+            Option<&Follow>,
+        ),
+        With<IsometricCamera>,
+    >,
+) {
+    let delta = motion
+        .iter()
+        .fold(Vec2::ZERO, |sum, motion| sum + motion.delta);
+    let dragging = *mode == CameraControlMode::Player
+        && settings.drag_pan
+        && buttons.pressed(input.drag_pan)
+        && !egui.ctx_mut().wants_pointer_input();
+    let window = match windows.get_primary() {
+        Some(window) if dragging => window,
+        _ => return,
+    };
+    let orbiting = keys.pressed(input.orbit);
+    let dt = time.delta_seconds().max(f32::EPSILON);
+
+    for (entity, transform, projection, mut controls, momentum, follow) in
+        cameras.iter_mut()
+    {
+        let (mut pan, mut yaw) = (Vec3::ZERO, 0.0);
+        if orbiting {
+            yaw = -delta.x * settings.orbit_speed;
+        } else {
+            let (right, forward, pixel) =
+                ground_axes(transform, projection, &controls, window);
+            // Window motion grows downwards, and the ground follows the
+            // cursor
+            pan = forward * delta.y * pixel.y - right * delta.x * pixel.x;
+        }
+        // Holding still while dragging settles the momentum too
+        if let Some(mut momentum) = momentum {
+            momentum.held = true;
+            momentum.pan = pan / dt;
+            momentum.yaw = yaw / dt;
+        }
+        if delta == Vec2::ZERO {
+            continue;
+        }
+        controls.focus += pan;
+        if yaw != 0.0 {
+            controls.yaw = (controls.yaw + yaw + PI).rem_euclid(TAU) - PI;
+            commands.entity(entity).remove::<Turn>();
+        }
+        if let Some(follow) = follow {
+            PannedAway::pan(&mut commands, entity, follow);
+        }
+    }
+}
+
+/// Pan and orbit left over from a drag, slowing to a stop once it is let go
+#[derive(Component, Debug, Clone, Default, Reflect, Inspectable)]
+pub struct Momentum {
+    /// World units the focus moves every second
+    pub pan: Vec3,
+    /// Radians the view orbits every second
+    pub yaw: f32,
+    /// Whether the drag is still held this frame, and steering the momentum
+    held: bool,
+}
+
+impl Momentum {
+    /// Keep moving the controls after a drag, damping the motion away
+    fn system(
+        time: Res<Time>,
+        mode: Res<CameraControlMode>,
+        settings: Res<CameraSettings>,
+        mut cameras: Query<(
+            &mut Momentum,
+            &mut YawPitchControls,
+            Option<&Follow>,
+            Option<&FocusPan>,
+            Option<&Turn>,
+        )>,
+    ) {
+        let dt = time.delta_seconds();
+        for (mut momentum, mut controls, follow, pan, turn) in
+            cameras.iter_mut()
+        {
+            if momentum.held {
+                momentum.held = false;
+                continue;
+            }
+            // Anything else moving the camera takes over from the drag
+            let moving = momentum.pan != Vec3::ZERO || momentum.yaw != 0.0;
+            if !moving {
+                continue;
+            }
+            let interrupted = follow.is_some()
+                || pan.is_some()
+                || *mode != CameraControlMode::Player;
+            if !settings.momentum || interrupted {
+                *momentum = Momentum::default();
+                continue;
+            }
+
+            controls.focus += momentum.pan * dt;
+            if turn.is_none() {
+                let yaw = controls.yaw + momentum.yaw * dt;
+                controls.yaw = (yaw + PI).rem_euclid(TAU) - PI;
+            }
+
+            let pan = momentum.pan * (-settings.pan_damping * dt).exp();
+            let yaw = momentum.yaw * (-settings.orbit_damping * dt).exp();
+            // Stop once the motion can't be seen
+            momentum.pan = if pan.length() < 1e-3 { Vec3::ZERO } else { pan };
+            momentum.yaw = if yaw.abs() < 1e-3 || turn.is_some() {
+                0.0
+            } else {
+                yaw
+            };
+        }
+    }
+}
+
+/// Scroll the view towards whichever edges of the window the cursor is
+/// pressed against
+fn edge_scroll(
+    mut commands: Commands,
+    time: Res<Time>,
+    mode: Res<CameraControlMode>,
+    settings: Res<CameraSettings>,
+    windows: Res<Windows>,
+    mut egui: ResMut<EguiContext>,
+    mut cameras: Query<
+        (
+            Entity,
+            &Transform,
+            &OrthographicProjection,
+            &mut YawPitchControls,
+            Option<&Follow>,
+        ),
+        With<IsometricCamera>,
+    >,
+) {
+    if *mode != CameraControlMode::Player || !settings.edge_scroll {
+        return;
+    }
+    let window = match windows.get_primary() {
+        Some(window) => window,
+        None => return,
+    };
+    // Unfocused windows keep the last position the cursor left at
+    let cursor = match window.cursor_position() {
+        Some(cursor) if window.is_focused() => cursor,
+        _ => return,
+    };
+    if egui.ctx_mut().wants_pointer_input() {
+        return;
+    }
+
+    // Cursor positions start at the bottom left
+    let size = Vec2::new(window.width(), window.height());
+    let margin = settings.edge_margin;
+    let mut input = Vec2::ZERO;
+    if cursor.x < margin {
+        input.x -= 1.0;
+    } else if cursor.x > size.x - margin {
+        input.x += 1.0;
+    }
+    if cursor.y < margin {
+        input.y -= 1.0;
+    } else if cursor.y > size.y - margin {
+        input.y += 1.0;
+    }
+    if input == Vec2::ZERO {
+        return;
+    }
+
+    for (entity, transform, projection, mut controls, follow) in
+        cameras.iter_mut()
+    {
+        let (right, forward, _) =
+            ground_axes(transform, projection, &controls, window);
+        let speed = settings.edge_scroll_speed
+            * projection.scale
+            * time.delta_seconds();
+        let delta = (forward * input.y + right * input.x).normalize_or_zero();
+        controls.focus += delta * speed;
+        if let Some(follow) = follow {
+            PannedAway::pan(&mut commands, entity, follow);
+        }
+    }
+}
+
+/// Keep the focus over the [`MapBounds`] give or take the margin, and the
+/// zoom close enough that the void never fills the view
+fn clamp_to_map(
+    mode: Res<CameraControlMode>,
+    settings: Res<CameraSettings>,
+    bounds: Res<MapBounds>,
+    mut cameras: Query<
+        (&mut YawPitchControls, &mut Zoom, &OrthographicProjection),
+        With<IsometricCamera>,
+    >,
+) {
+    // Scripted moves may show whatever they like
+    let clamped = settings.clamp_to_map && *mode == CameraControlMode::Player;
+    let (min, max) = match bounds.area {
+        Some(area) if clamped => area,
+        _ => return,
+    };
+    let margin = Vec2::splat(settings.map_margin.max(0.0) * TILE_SIZE);
+    let (min, max) = (min - margin, max + margin);
+
+    for (mut controls, mut zoom, projection) in cameras.iter_mut() {
+        let focus = Vec2::new(controls.focus.x, controls.focus.z);
+        let clamped = focus.clamp(min, max);
+        if clamped != focus {
+            controls.focus.x = clamped.x;
+            controls.focus.z = clamped.y;
+        }
+
+        // Looking along a diagonal of the map, the view shouldn't need to
+        // be taller than the diagonal foreshortened by the pitch
+        let height = (projection.top - projection.bottom).max(f32::EPSILON);
+        let fit = (max - min).length() * controls.pitch.sin().abs() / height;
+        let target = zoom.target.min(fit.max(settings.min_zoom));
+        if zoom.target != target {
+            zoom.target = target;
+        }
+    }
+}
+
+/// Orthographic scale the camera eases towards, leaving the distance to the
+/// focus alone
+#[derive(Component, Debug, Clone, Copy, Reflect, Inspectable)]
+pub struct Zoom {
+    pub target: f32,
+}
+
+impl Zoom {
+    /// Zoom with the scroll wheel or the zoom keys
+    fn input(
+        mode: Res<CameraControlMode>,
+        settings: Res<CameraSettings>,
+        input: Res<CameraInputConfig>,
+        keys: Res<Input<KeyCode>>,
+        mut scrolls: EventReader<MouseWheel>,
+        mut egui: ResMut<EguiContext>,
+        mut cameras: Query<&mut Zoom, With<IsometricCamera>>,
+    ) {
+        let mut lines: f32 = scrolls
+            .iter()
+            .map(|scroll| match scroll.unit {
+                MouseScrollUnit::Line => scroll.y,
+                MouseScrollUnit::Pixel => scroll.y / 20.0,
+            })
+            .sum();
+        // Scrolling a window or typing into a field shouldn't zoom too
+        let ctx = egui.ctx_mut();
+        if *mode != CameraControlMode::Player {
+            return;
+        }
+        if ctx.wants_pointer_input() {
+            lines = 0.0;
+        }
+        if !ctx.wants_keyboard_input() {
+            lines += keys.just_pressed(input.zoom_in) as u8 as f32;
+            lines -= keys.just_pressed(input.zoom_out) as u8 as f32;
+        }
+
+        let (min, max) =
+            (settings.min_zoom, settings.max_zoom.max(settings.min_zoom));
+        for mut zoom in cameras.iter_mut() {
+            let mut target = zoom.target;
+            if lines != 0.0 {
+                target *= (1.0 - settings.zoom_step).powf(lines);
+            }
+            // Also pulls the zoom back in when the limits change
+            let target = target.clamp(min, max);
+            if zoom.target != target {
+                zoom.target = target;
+            }
+        }
+    }
+
+    /// Ease the orthographic scale towards the zoom
+    fn system(
+        time: Res<Time>,
+        settings: Res<CameraSettings>,
+        mut cameras: Query<(&Zoom, &mut OrthographicProjection)>,
+    ) {
+        let t = 1.0 - (-settings.zoom_speed * time.delta_seconds()).exp();
+        for (zoom, mut projection) in cameras.iter_mut() {
+            if projection.scale == zoom.target {
+                continue;
+            }
+            let scale = projection.scale + (zoom.target - projection.scale) * t;
+            // Snap once the difference can't be seen
+            projection.scale = if (scale - zoom.target).abs() < 1e-4 {
+                zoom.target
+            } else {
+                scale
+            };
+        }
+    }
+}
+
+/// Below this field of view the camera is left orthographic, which it is
+/// all but indistinguishable from
+const MIN_PERSPECTIVE_FOV: f32 = 1.0;
+
+/// Near plane of the perspective projection, in world units
+const PERSPECTIVE_NEAR: f32 = 0.05;
+
+/// How far the [`IsometricCamera`] has blended from orthographic towards
+/// perspective.
+///
+/// The field of view widens as the camera dollies in, keeping the focus
+/// framed the same as the orthographic view so the two can be swapped
+/// without the map jumping around.
+#[derive(Component, Debug, Clone, Copy, Default, Reflect, Inspectable)]
+pub struct ProjectionBlend {
+    /// Blend towards perspective, or back to orthographic
+    pub perspective: bool,
+    /// 0 while orthographic, 1 once in perspective
+    #[inspectable(min = 0.0, max = 1.0)]
+    pub amount: f32,
+}
+
+impl ProjectionBlend {
+    /// Vertical field of view in radians, or nothing while orthographic
+    pub fn fov(&self, settings: &CameraSettings) -> Option<f32> {
+        let t = self.amount.clamp(0.0, 1.0);
+        let fov = t * t * (3.0 - 2.0 * t) * settings.perspective_fov;
+        (fov >= MIN_PERSPECTIVE_FOV).then(|| fov.to_radians())
+    }
+
+    /// Distance from the focus the perspective eye sits at to see as much
+    /// of the ground at the focus as the orthographic view does
+    fn eye_distance(fov: f32, projection: &OrthographicProjection) -> f32 {
+        let height = (projection.top - projection.bottom) * projection.scale;
+        height * 0.5 / (fov * 0.5).tan()
+    }
+
+    fn input(
+        input: Res<CameraInputConfig>,
+        keys: Res<Input<KeyCode>>,
+        mut egui: ResMut<EguiContext>,
+        mut cameras: Query<&mut ProjectionBlend, With<IsometricCamera>>,
+    ) {
+        if egui.ctx_mut().wants_keyboard_input()
+            || !keys.just_pressed(input.toggle_perspective)
+        {
+            return;
+        }
+        for mut blend in cameras.iter_mut() {
+            blend.perspective = !blend.perspective;
+        }
+    }
+
+    /// Step the blend along, and pull the camera back to where the
+    /// perspective eye belongs
+    fn system(
+        time: Res<Time>,
+        settings: Res<CameraSettings>,
+        mut cameras: Query<(
+            &mut ProjectionBlend,
+            &YawPitchControls,
+            &ControllerBasis,
+            &OrthographicProjection,
+            &mut Transform,
+        )>,
+    ) {
+        let step = if settings.projection_blend_time > 0.0 {
+            time.delta_seconds() / settings.projection_blend_time
+        } else {
+            1.0
+        };
+        for (mut blend, controls, basis, projection, mut transform) in
+            cameras.iter_mut()
+        {
+            let was = blend.amount;
+            let target = blend.perspective as u8 as f32;
+            if was != target {
+                let amount = if target > was {
+                    (was + step).min(target)
+                } else {
+                    (was - step).max(target)
+                };
+                blend.amount = amount;
+            } else if was == 0.0 {
+                // Orthographic, the controls place the camera by themselves
+                continue;
+            }
+
+            let mut eye = controls.transform(basis);
+            if let Some(fov) = blend.fov(&settings) {
+                let back = Self::eye_distance(fov, projection) - controls.dist;
+                eye.translation += eye.back() * back.max(0.0);
+            }
+            if *transform != eye {
+                *transform = eye;
+            }
+        }
+    }
+
+    /// Swap in the perspective projection and the frustum it sees, after
+    /// the orthographic projection has set its own
+    fn project(
+        settings: Res<CameraSettings>,
+        mut cameras: Query<(
+            &ProjectionBlend,
+            &OrthographicProjection,
+            &GlobalTransform,
+            &mut Camera,
+            &mut Frustum,
+        )>,
+    ) {
+        for (blend, projection, transform, mut camera, mut frustum) in
+            cameras.iter_mut()
+        {
+            let fov = match blend.fov(&settings) {
+                Some(fov) => fov,
+                None => {
+                    let matrix = projection.get_projection_matrix();
+                    if camera.projection_matrix != matrix {
+                        camera.projection_matrix = matrix;
+                    }
+                    continue;
+                }
+            };
+            let aspect = (projection.right - projection.left)
+                / (projection.top - projection.bottom);
+            let matrix = Mat4::perspective_infinite_reverse_rh(
+                fov,
+                aspect,
+                PERSPECTIVE_NEAR,
+            );
+            camera.projection_matrix = matrix;
+            *frustum = Frustum::from_view_projection(
+                &(matrix * transform.compute_matrix().inverse()),
+                &transform.translation,
+                &transform.back(),
+                projection.far + Self::eye_distance(fov, projection),
+            );
+        }
+    }
+}
+
 /// Define the coordinate system a controller will use
 #[derive(Component, Debug, Clone, Reflect, Inspectable)]
 pub struct ControllerBasis {
@@ -142,4 +933,466 @@ impl YawPitchControls {
     pub fn yaw(&self, basis: &ControllerBasis) -> Quat {
         Quat::from_axis_angle(basis.up, self.yaw)
     }
+
+    /// Direction on the ground a 2d input like a stick or WASD points in as
+    /// seen from this view, where +y moves away from the camera and +x to
+    /// the right of the screen. The length of the input is kept.
+    pub fn ground_input(&self, basis: &ControllerBasis, input: Vec2) -> Vec3 {
+        // The camera sits along the local forward, looking back at the focus
+        let view = -self.local_basis(basis).forward;
+        let away = (view - basis.up * view.dot(basis.up)).normalize_or_zero();
+        let right = away.cross(basis.up);
+        away * input.y + right * input.x
+    }
+
+    /// The grid direction a 2d input points in as seen from this view, or
+    /// nothing without any input
+    pub fn input_direction(
+        &self,
+        basis: &ControllerBasis,
+        input: Vec2,
+    ) -> Option<Direction> {
+        let ground = self.ground_input(basis, input);
+        (ground.length_squared() > f32::EPSILON)
+            .then(|| Facing::from_vec(ground).direction())
+    }
+}
+
+/// Seconds a quarter turn of the view takes
+const TURN_DURATION: f32 = 0.3;
+
+/// Sent once a turn of the view settles on one of the four isometric views,
+/// for anything drawn differently depending on where it is seen from
+#[derive(Debug, Clone, Copy)]
+pub struct CameraRotated {
+    pub camera: Entity,
+    /// Yaw of the new view [-PI, PI]
+    pub yaw: f32,
+}
+
+/// Turn of the view to the isometric view at `to`, eased along from `from`
+#[derive(Component, Debug, Clone)]
+pub struct Turn {
+    from: f32,
+    to: f32,
+    elapsed: f32,
+}
+
+impl Turn {
+    /// Turn the view a quarter turn with the turn keys, from wherever the
+    /// view is already turning to
+    fn input(
+        mut commands: Commands,
+        mode: Res<CameraControlMode>,
+        input: Res<CameraInputConfig>,
+        keys: Res<Input<KeyCode>>,
+        mut egui: ResMut<EguiContext>,
+        cameras: Query<
+            (Entity, &YawPitchControls, Option<&Turn>),
+            With<IsometricCamera>,
+        >,
+    ) {
+        if *mode != CameraControlMode::Player
+            || egui.ctx_mut().wants_keyboard_input()
+        {
+            return;
+        }
+        let turn = match (
+            keys.just_pressed(input.turn_left),
+            keys.just_pressed(input.turn_right),
+        ) {
+            (true, false) => -FRAC_PI_2,
+            (false, true) => FRAC_PI_2,
+            _ => return,
+        };
+
+        for (entity, controls, current) in cameras.iter() {
+            let yaw = current.map_or(controls.yaw, |current| current.to);
+            // Snap to the nearest isometric view, halfway between the axes
+            let steps = ((yaw + turn - FRAC_PI_4) / FRAC_PI_2).round();
+            commands.entity(entity).insert(Turn {
+                from: controls.yaw,
+                to: steps * FRAC_PI_2 + FRAC_PI_4,
+                elapsed: 0.0,
+            });
+        }
+    }
+
+    fn system(
+        mut commands: Commands,
+        time: Res<Time>,
+        mut rotated: EventWriter<CameraRotated>,
+        mut cameras: Query<(Entity, &mut Turn, &mut YawPitchControls)>,
+    ) {
+        for (entity, mut turn, mut controls) in cameras.iter_mut() {
+            turn.elapsed += time.delta_seconds();
+            let t = (turn.elapsed / TURN_DURATION).clamp(0.0, 1.0);
+            // Ease out, so the view answers the key straight away
+            let eased = 1.0 - (1.0 - t).powi(3);
+            controls.yaw = turn.from + (turn.to - turn.from) * eased;
+
+            if t >= 1.0 {
+                controls.yaw = (turn.to + PI).rem_euclid(TAU) - PI;
+                commands.entity(entity).remove::<Turn>();
+                rotated.send(CameraRotated {
+                    camera: entity,
+                    yaw: controls.yaw,
+                });
+            }
+        }
+    }
+}
+
+/// Keep the camera focused on an entity
+#[derive(Component, Debug, Clone)]
+pub struct Follow {
+    pub target: Entity,
+    /// Distance to settle at after switching to this target
+    pub dist: Option<f32>,
+    /// Yaw to settle at after switching to this target
+    pub yaw: Option<f32>,
+}
+
+impl Follow {
+    pub fn new(target: Entity) -> Self {
+        Self {
+            target,
+            dist: None,
+            yaw: None,
+        }
+    }
+
+    /// Start a transition whenever the target is changed
+    fn retarget(
+        mut commands: Commands,
+        query: Query<(Entity, &YawPitchControls), Changed<Follow>>,
+    ) {
+        for (entity, controls) in query.iter() {
+            commands
+                .entity(entity)
+                .insert(FocusTransition::from_controls(controls))
+                .insert(LookAhead::default());
+        }
+    }
+
+    /// Track the target once no transition is running, leading it by its
+    /// movement when the camera looks ahead.
+    ///
+    /// Cameras given a new target are left alone until the transition
+    /// [`Follow::retarget`] starts for them is in place, so the focus never
+    /// snaps to the target first.
+    fn system(
+        mut commands: Commands,
+        time: Res<Time>,
+        settings: Res<CameraSettings>,
+        mut cameras: Query<
+            (
+                Entity,
+                &Follow,
+                ChangeTrackers<Follow>,
+                &mut YawPitchControls,
+                Option<&mut LookAhead>,
+            ),
+            Without<FocusTransition>,
+        >,
+        targets: Query<&GlobalTransform>,
+    ) {
+        for (entity, follow, tracker, mut controls, ahead) in cameras.iter_mut()
+        {
+            if tracker.is_changed() {
+                continue;
+            }
+            match targets.get(follow.target) {
+                Ok(target) => {
+                    let mut focus = target.translation;
+                    if let Some(mut ahead) = ahead {
+                        focus += ahead.lead(
+                            target.translation,
+                            time.delta_seconds(),
+                            &settings,
+                        );
+                    }
+                    if controls.focus != focus {
+                        controls.focus = focus;
+                    }
+                }
+                Err(_) => {
+                    commands.entity(entity).remove::<Follow>();
+                }
+            }
+        }
+    }
+}
+
+/// [`Follow`] the player panned the camera away from, put back as soon as
+/// its target moves again so the camera never loses track of it for good
+#[derive(Component, Debug, Clone)]
+pub struct PannedAway(pub Follow);
+
+impl PannedAway {
+    /// Stop following while the player pans, until the target moves
+    fn pan(commands: &mut Commands, camera: Entity, follow: &Follow) {
+        commands
+            .entity(camera)
+            .remove::<Follow>()
+            .insert(PannedAway(follow.clone()));
+    }
+
+    /// Follow the target again once it has moved, or forget it when
+    /// something else gave the camera a target in the meantime
+    fn system(
+        mut commands: Commands,
+        mode: Res<CameraControlMode>,
+        cameras: Query<(Entity, &PannedAway, Option<&Follow>)>,
+        moved: Query<(), Changed<Location>>,
+        targets: Query<()>,
+    ) {
+        for (entity, panned, follow) in cameras.iter() {
+            let target = panned.0.target;
+            if follow.is_some() || targets.get(target).is_err() {
+                commands.entity(entity).remove::<PannedAway>();
+            } else if *mode == CameraControlMode::Player
+                && moved.get(target).is_ok()
+            {
+                commands
+                    .entity(entity)
+                    .remove::<PannedAway>()
+                    .insert(panned.0.clone());
+            }
+        }
+    }
+}
+
+/// How far ahead of a moving [`Follow`] target the focus sits, reset
+/// whenever the target changes
+#[derive(Component, Debug, Clone, Default)]
+pub struct LookAhead {
+    /// Current lead along the ground
+    pub offset: Vec3,
+    /// Where the target was last frame
+    last: Option<Vec3>,
+}
+
+impl LookAhead {
+    /// Ease the lead towards where the target is heading and return it
+    fn lead(
+        &mut self,
+        position: Vec3,
+        dt: f32,
+        settings: &CameraSettings,
+    ) -> Vec3 {
+        let velocity = match self.last {
+            Some(last) if dt > 0.0 => (position - last) / dt,
+            _ => Vec3::ZERO,
+        };
+        self.last = Some(position);
+
+        let flat = Vec3::new(1.0, 0.0, 1.0);
+        let lead = (velocity * flat * settings.look_ahead)
+            .clamp_length_max(settings.look_ahead_max * TILE_SIZE);
+        let t = 1.0 - (-settings.look_ahead_speed * dt).exp();
+        self.offset = self.offset.lerp(lead, t);
+        self.offset
+    }
+}
+
+/// Arc from the previous focus to a new [`Follow`] target.
+///
+/// As soon as anything other than the transition changes the yaw or distance
+/// the transition stops touching them, so the player can take control at any
+/// time while the focus still finishes moving to the target.
+#[derive(Component, Debug, Clone)]
+pub struct FocusTransition {
+    from: YawPitchControls,
+    /// Values the transition last wrote, to detect outside changes
+    written: (f32, f32),
+    interrupted: bool,
+    elapsed: f32,
+    /// Length of the transition in seconds
+    pub duration: f32,
+    /// Height of the arc above the straight line between focus points
+    pub height: f32,
+}
+
+impl FocusTransition {
+    fn from_controls(controls: &YawPitchControls) -> Self {
+        Self {
+            from: controls.clone(),
+            written: (controls.yaw, controls.dist),
+            interrupted: false,
+            elapsed: 0.0,
+            duration: 0.75,
+            height: 0.25,
+        }
+    }
+
+    fn system(
+        mut commands: Commands,
+        time: Res<Time>,
+        mut cameras: Query<(
+            Entity,
+            &Follow,
+            &mut YawPitchControls,
+            &mut FocusTransition,
+        )>,
+        targets: Query<&GlobalTransform>,
+    ) {
+        for (entity, follow, mut controls, mut transition) in cameras.iter_mut()
+        {
+            let target = match targets.get(follow.target) {
+                Ok(target) => target.translation,
+                Err(_) => {
+                    commands.entity(entity).remove::<FocusTransition>();
+                    continue;
+                }
+            };
+
+            // The player took control, so stop fighting them for it and only
+            // finish moving the focus
+            if (controls.yaw, controls.dist) != transition.written {
+                transition.interrupted = true;
+            }
+
+            transition.elapsed += time.delta_seconds();
+            let t = (transition.elapsed / transition.duration).clamp(0.0, 1.0);
+            let eased = t * t * (3.0 - 2.0 * t);
+
+            let from = &transition.from;
+            let arc = Vec3::Y * transition.height * (t * PI).sin();
+            controls.focus = from.focus.lerp(target, eased) + arc;
+
+            if !transition.interrupted {
+                let dist = follow.dist.unwrap_or(from.dist);
+                controls.dist = from.dist + (dist - from.dist) * eased;
+
+                // Turn the short way around
+                let yaw = follow.yaw.unwrap_or(from.yaw);
+                let delta = (yaw - from.yaw + PI).rem_euclid(TAU) - PI;
+                controls.yaw = from.yaw + delta * eased;
+            }
+
+            transition.written = (controls.yaw, controls.dist);
+            if t >= 1.0 {
+                commands.entity(entity).remove::<FocusTransition>();
+            }
+        }
+    }
+}
+
+/// Send to move the focus of every [`IsometricCamera`] somewhere new over a
+/// moment rather than snapping to it, like after a teleport.
+///
+/// The camera stops following whatever it was following, and is left to the
+/// player again as soon as they pan it themselves.
+#[derive(Debug, Clone, Copy)]
+pub struct CameraTransition {
+    /// Focus to end up at
+    pub to: Vec3,
+    /// Length of the transition in seconds
+    pub duration: f32,
+    pub easing: Easing,
+    /// Zoom to end up at, or keep the zoom as it is
+    pub zoom: Option<f32>,
+}
+
+impl CameraTransition {
+    pub fn to(to: Vec3) -> Self {
+        Self {
+            to,
+            duration: 0.5,
+            easing: Easing::EaseInOut,
+            zoom: None,
+        }
+    }
+
+    /// Zoom to `zoom` along the way
+    pub fn with_zoom(self, zoom: f32) -> Self {
+        Self {
+            zoom: Some(zoom),
+            ..self
+        }
+    }
+
+    fn start(
+        mut commands: Commands,
+        mode: Res<CameraControlMode>,
+        mut transitions: EventReader<CameraTransition>,
+        cameras: Query<
+            (Entity, &YawPitchControls, &Zoom),
+            With<IsometricCamera>,
+        >,
+    ) {
+        // Only the latest matters, and a scripted camera is left alone
+        let transition = match transitions.iter().last() {
+            Some(transition) if *mode == CameraControlMode::Player => {
+                *transition
+            }
+            _ => return,
+        };
+        for (entity, controls, zoom) in cameras.iter() {
+            commands
+                .entity(entity)
+                .remove::<Follow>()
+                .remove::<FocusTransition>()
+                .insert(FocusPan {
+                    transition,
+                    from: (controls.focus, zoom.target),
+                    written: controls.focus,
+                    elapsed: 0.0,
+                });
+        }
+    }
+}
+
+/// [`CameraTransition`] in progress
+#[derive(Component, Debug, Clone)]
+pub struct FocusPan {
+    transition: CameraTransition,
+    /// Focus and zoom the transition started from
+    from: (Vec3, f32),
+    /// Focus the transition last wrote, to detect the player panning
+    written: Vec3,
+    elapsed: f32,
+}
+
+impl FocusPan {
+    fn system(
+        mut commands: Commands,
+        time: Res<Time>,
+        mut cameras: Query<(
+            Entity,
+            &mut FocusPan,
+            &mut YawPitchControls,
+            &mut Zoom,
+        )>,
+    ) {
+        for (entity, mut pan, mut controls, mut zoom) in cameras.iter_mut() {
+            // The player moved the camera, so let them have it, leaving
+            // the first frame to whatever moved it as the pan started
+            if pan.elapsed > 0.0 && controls.focus != pan.written {
+                commands.entity(entity).remove::<FocusPan>();
+                continue;
+            }
+
+            pan.elapsed += time.delta_seconds();
+            let transition = pan.transition;
+            let t = if transition.duration > 0.0 {
+                pan.elapsed / transition.duration
+            } else {
+                1.0
+            };
+            let eased = transition.easing.apply(t);
+
+            let (focus, from_zoom) = pan.from;
+            controls.focus = focus.lerp(transition.to, eased);
+            if let Some(to) = transition.zoom {
+                zoom.target = from_zoom + (to - from_zoom) * eased;
+            }
+
+            pan.written = controls.focus;
+            if t >= 1.0 {
+                commands.entity(entity).remove::<FocusPan>();
+            }
+        }
+    }
 }
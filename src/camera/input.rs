@@ -0,0 +1,158 @@
+use crate::console::{Console, ConsoleCommand};
+use bevy::asset::FileAssetIo;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+pub struct CameraInputPlugin;
+
+impl Plugin for CameraInputPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(CameraInputConfig::load())
+            .add_system(bind_command)
+            .add_system(CameraInputConfig::save.after(bind_command));
+    }
+}
+
+/// Keys and buttons moving the camera, along with the rebindable gameplay
+/// actions, saved whenever they are rebound
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CameraInputConfig {
+    pub zoom_in: KeyCode,
+    pub zoom_out: KeyCode,
+    /// Quarter turn of the view
+    pub turn_left: KeyCode,
+    pub turn_right: KeyCode,
+    /// Pan the view while editing
+    pub pan_up: KeyCode,
+    pub pan_down: KeyCode,
+    pub pan_left: KeyCode,
+    pub pan_right: KeyCode,
+    /// Held to drag the ground around
+    pub drag_pan: MouseButton,
+    /// Held while dragging to orbit the view instead
+    pub orbit: KeyCode,
+    /// Switch between orthographic and perspective
+    pub toggle_perspective: KeyCode,
+    /// Skip the camera track playing
+    pub skip_track: KeyCode,
+    /// Frame the selected tiles, or the hovered one
+    pub frame_selection: KeyCode,
+    /// Switch to the next camera preset
+    pub next_preset: KeyCode,
+    /// Shoot an arrow the way the player is facing
+    pub fire: KeyCode,
+}
+
+impl Default for CameraInputConfig {
+    fn default() -> Self {
+        Self {
+            zoom_in: KeyCode::Equals,
+            zoom_out: KeyCode::Minus,
+            turn_left: KeyCode::Q,
+            turn_right: KeyCode::E,
+            pan_up: KeyCode::W,
+            pan_down: KeyCode::S,
+            pan_left: KeyCode::A,
+            pan_right: KeyCode::D,
+            drag_pan: MouseButton::Middle,
+            orbit: KeyCode::LAlt,
+            toggle_perspective: KeyCode::F4,
+            skip_track: KeyCode::Escape,
+            frame_selection: KeyCode::F,
+            next_preset: KeyCode::V,
+            fire: KeyCode::X,
+        }
+    }
+}
+
+/// File on disk the bindings are saved to, outside the assets so player
+/// settings never end up shipped with the game
+fn config_path() -> PathBuf {
+    FileAssetIo::get_root_path()
+        .join("settings")
+        .join("camera_input.yaml")
+}
+
+impl CameraInputConfig {
+    /// The saved bindings, or the defaults when nothing was saved
+    fn load() -> Self {
+        let path = config_path();
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(_) => return Self::default(),
+        };
+        serde_yaml::from_str(&contents).unwrap_or_else(|err| {
+            warn!("Unable to read camera bindings {:?}: {:?}", path, err);
+            Self::default()
+        })
+    }
+
+    fn save(config: Res<CameraInputConfig>) {
+        if !config.is_changed() || config.is_added() {
+            return;
+        }
+        let path = config_path();
+        let written = serde_yaml::to_string(&*config)
+            .map_err(anyhow::Error::from)
+            .and_then(|contents| {
+                if let Some(folder) = path.parent() {
+                    fs::create_dir_all(folder)?;
+                }
+                fs::write(&path, contents)?;
+                Ok(())
+            });
+        match written {
+            Ok(()) => info!("Saved camera bindings to {:?}", path),
+            Err(err) => {
+                warn!("Unable to save camera bindings {:?}: {:?}", path, err)
+            }
+        }
+    }
+
+    /// Bind `action` to the key or button named `input`, named as they are
+    /// in the saved file
+    pub fn rebind(&mut self, action: &str, input: &str) -> anyhow::Result<()> {
+        let mut value = serde_yaml::to_value(&*self)?;
+        let binding = value
+            .get_mut(action)
+            .ok_or_else(|| anyhow::anyhow!("no camera action {}", action))?;
+        *binding = serde_yaml::from_str(input)?;
+        *self = serde_yaml::from_value(value)?;
+        Ok(())
+    }
+}
+
+/// `bind` lists the camera bindings, `bind <action> <key>` rebinds one, like
+/// `bind turn_left Z`
+fn bind_command(
+    mut events: EventReader<ConsoleCommand>,
+    mut console: ResMut<Console>,
+    mut config: ResMut<CameraInputConfig>,
+) {
+    for event in events.iter().filter(|event| event.name == "bind") {
+        match event.args.as_slice() {
+            [] => {
+                let listed =
+                    serde_yaml::to_string(&*config).unwrap_or_default();
+                for line in listed.lines().filter(|line| *line != "---") {
+                    console.print(line);
+                }
+            }
+            [action, input] => {
+                // Rebind a copy, so a bad binding never reaches the saved file
+                let mut rebound = config.clone();
+                match rebound.rebind(action, input) {
+                    Ok(()) => {
+                        *config = rebound;
+                        console.print(format!("{} bound to {}", action, input));
+                    }
+                    Err(err) => console.print(format!("{}", err)),
+                }
+            }
+            _ => console.print("usage: bind [<action> <key>]"),
+        }
+    }
+}
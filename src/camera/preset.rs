@@ -0,0 +1,207 @@
+use super::{
+    CameraControlMode, CameraInputConfig, CameraSettings, CameraSystem, Easing,
+    IsometricCamera, YawPitchControls, Zoom,
+};
+use crate::console::{Console, ConsoleCommand};
+use bevy::prelude::*;
+use bevy_egui::EguiContext;
+
+pub struct CameraPresetPlugin;
+
+impl Plugin for CameraPresetPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CameraPresets>()
+            .add_event::<SwitchCameraPreset>()
+            .add_system(preset_command)
+            .add_system(cycle_presets)
+            .add_system(
+                SwitchCameraPreset::system
+                    .after(preset_command)
+                    .after(cycle_presets),
+            )
+            .add_system(
+                PresetTransition::system
+                    .after(SwitchCameraPreset::system)
+                    .before(CameraSystem),
+            );
+    }
+}
+
+/// Way of framing the action the player can switch between
+#[derive(Debug, Clone)]
+pub struct CameraPreset {
+    pub name: String,
+    /// Degrees the camera looks down from
+    pub pitch: f32,
+    /// Orthographic scale
+    pub zoom: f32,
+    /// Seconds of movement the focus leads a followed target by
+    pub look_ahead: f32,
+}
+
+/// Every preset and which of them is in use
+pub struct CameraPresets {
+    pub presets: Vec<CameraPreset>,
+    /// Preset last switched to, the camera may have been moved since
+    pub active: usize,
+    /// Seconds taken to ease from one preset to the next
+    pub duration: f32,
+}
+
+impl Default for CameraPresets {
+    fn default() -> Self {
+        let preset = |name: &str, pitch, zoom, look_ahead| CameraPreset {
+            name: name.to_string(),
+            pitch,
+            zoom,
+            look_ahead,
+        };
+        Self {
+            presets: vec![
+                preset("isometric", 45.0, 1.0, 0.35),
+                preset("tactical", 80.0, 1.6, 0.0),
+                preset("action", 30.0, 0.6, 0.5),
+            ],
+            active: 0,
+            duration: 0.4,
+        }
+    }
+}
+
+impl CameraPresets {
+    pub fn find(&self, name: &str) -> Option<usize> {
+        self.presets.iter().position(|preset| preset.name == name)
+    }
+}
+
+/// Send to ease every [`IsometricCamera`] into the preset with this name
+#[derive(Debug, Clone)]
+pub struct SwitchCameraPreset(pub String);
+
+impl SwitchCameraPreset {
+    fn system(
+        mut commands: Commands,
+        mut switches: EventReader<SwitchCameraPreset>,
+        mut presets: ResMut<CameraPresets>,
+        mut settings: ResMut<CameraSettings>,
+        cameras: Query<
+            (Entity, &YawPitchControls, &Zoom),
+            With<IsometricCamera>,
+        >,
+    ) {
+        // Only the latest switch matters
+        let index = match switches
+            .iter()
+            .filter_map(|s| presets.find(&s.0))
+            .next_back()
+        {
+            Some(index) => index,
+            None => return,
+        };
+        presets.active = index;
+        let preset = presets.presets[index].clone();
+        settings.look_ahead = preset.look_ahead;
+
+        for (entity, controls, zoom) in cameras.iter() {
+            commands.entity(entity).insert(PresetTransition {
+                from: (controls.pitch, zoom.target),
+                to: (preset.pitch.to_radians(), preset.zoom),
+                elapsed: 0.0,
+                duration: presets.duration,
+            });
+        }
+    }
+}
+
+/// Pitch and zoom easing towards a preset
+#[derive(Component, Debug, Clone)]
+pub struct PresetTransition {
+    from: (f32, f32),
+    to: (f32, f32),
+    elapsed: f32,
+    duration: f32,
+}
+
+impl PresetTransition {
+    fn system(
+        mut commands: Commands,
+        time: Res<Time>,
+        mode: Res<CameraControlMode>,
+        mut cameras: Query<(
+            Entity,
+            &mut PresetTransition,
+            &mut YawPitchControls,
+            &mut Zoom,
+        )>,
+    ) {
+        for (entity, mut transition, mut controls, mut zoom) in
+            cameras.iter_mut()
+        {
+            // A scripted camera is left to its script
+            if *mode != CameraControlMode::Player {
+                commands.entity(entity).remove::<PresetTransition>();
+                continue;
+            }
+            transition.elapsed += time.delta_seconds();
+            let t = if transition.duration > 0.0 {
+                transition.elapsed / transition.duration
+            } else {
+                1.0
+            };
+            let eased = Easing::EaseInOut.apply(t);
+
+            let ((from_pitch, from_zoom), (pitch, to_zoom)) =
+                (transition.from, transition.to);
+            controls.pitch = from_pitch + (pitch - from_pitch) * eased;
+            zoom.target = from_zoom + (to_zoom - from_zoom) * eased;
+
+            if t >= 1.0 {
+                commands.entity(entity).remove::<PresetTransition>();
+            }
+        }
+    }
+}
+
+/// Switch to the next preset with the preset key
+fn cycle_presets(
+    mode: Res<CameraControlMode>,
+    input: Res<CameraInputConfig>,
+    keys: Res<Input<KeyCode>>,
+    presets: Res<CameraPresets>,
+    mut egui: ResMut<EguiContext>,
+    mut switches: EventWriter<SwitchCameraPreset>,
+) {
+    if *mode != CameraControlMode::Player
+        || presets.presets.is_empty()
+        || egui.ctx_mut().wants_keyboard_input()
+        || !keys.just_pressed(input.next_preset)
+    {
+        return;
+    }
+    let next = (presets.active + 1) % presets.presets.len();
+    switches.send(SwitchCameraPreset(presets.presets[next].name.clone()));
+}
+
+/// `preset` lists the camera presets, `preset <name>` switches to one
+fn preset_command(
+    mut events: EventReader<ConsoleCommand>,
+    mut console: ResMut<Console>,
+    presets: Res<CameraPresets>,
+    mut switches: EventWriter<SwitchCameraPreset>,
+) {
+    for event in events.iter().filter(|event| event.name == "preset") {
+        match event.args.first() {
+            Some(name) if presets.find(name).is_some() => {
+                switches.send(SwitchCameraPreset(name.clone()));
+            }
+            Some(name) => console.print(format!("No camera preset {}", name)),
+            None => {
+                for (i, preset) in presets.presets.iter().enumerate() {
+                    let active =
+                        if i == presets.active { " (active)" } else { "" };
+                    console.print(format!("{}{}", preset.name, active));
+                }
+            }
+        }
+    }
+}
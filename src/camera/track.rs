@@ -0,0 +1,297 @@
+use super::{
+    CameraControlMode, CameraInputConfig, CameraSystem, Follow,
+    IsometricCamera, Turn, YawPitchControls, Zoom,
+};
+use crate::console::{Console, ConsoleCommand};
+use bevy::asset::{AssetLoader, LoadContext, LoadedAsset};
+use bevy::prelude::*;
+use bevy::reflect::TypeUuid;
+use bevy::utils::BoxedFuture;
+use serde::Deserialize;
+use std::f32::consts::{PI, TAU};
+
+pub struct TrackPlugin;
+
+impl Plugin for TrackPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<CameraTrack>()
+            .init_asset_loader::<CameraTrackLoader>()
+            .add_system(track_command)
+            .add_system(TrackPlayback::skip)
+            .add_system(
+                TrackPlayback::system
+                    .after(TrackPlayback::skip)
+                    .before(CameraSystem),
+            )
+            .add_system(TrackPlayback::release.after(TrackPlayback::system));
+    }
+}
+
+/// Scripted camera move for intros and reveals, played by inserting a
+/// [`TrackPlayback`] on the camera.
+///
+/// Each keyframe eases from the one before it, the first from wherever the
+/// player left the camera, and keeps whatever it leaves out.
+#[derive(Debug, Clone, Deserialize, TypeUuid)]
+#[uuid = "6e0a3c1f-92d4-4b7e-8f15-3a9c7d2e4b60"]
+pub struct CameraTrack {
+    pub keyframes: Vec<CameraKeyframe>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct CameraKeyframe {
+    /// Seconds taken to reach this keyframe from the one before
+    pub duration: f32,
+    pub easing: Easing,
+    /// Point on the ground the camera looks at
+    pub focus: Option<[f32; 3]>,
+    /// Degrees around the focus, turning the short way
+    pub yaw: Option<f32>,
+    /// Degrees the camera looks down from
+    pub pitch: Option<f32>,
+    /// Orthographic scale
+    pub zoom: Option<f32>,
+}
+
+/// How a keyframe moves from the one before it
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Easing {
+    Linear,
+    /// Starts slow
+    EaseIn,
+    /// Ends slow
+    EaseOut,
+    /// Starts and ends slow
+    #[default]
+    EaseInOut,
+}
+
+impl Easing {
+    /// Eased progress of `t` in [0, 1]
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t * t,
+            Easing::EaseOut => 1.0 - (1.0 - t).powi(3),
+            Easing::EaseInOut => t * t * (3.0 - 2.0 * t),
+        }
+    }
+}
+
+/// Everything a track moves
+#[derive(Debug, Clone, Copy)]
+struct Pose {
+    focus: Vec3,
+    yaw: f32,
+    pitch: f32,
+    zoom: f32,
+}
+
+impl Pose {
+    /// This pose with whatever the keyframe sets
+    fn then(self, keyframe: &CameraKeyframe) -> Self {
+        Self {
+            focus: keyframe.focus.map_or(self.focus, Vec3::from),
+            yaw: keyframe.yaw.map_or(self.yaw, f32::to_radians),
+            pitch: keyframe.pitch.map_or(self.pitch, f32::to_radians),
+            zoom: keyframe.zoom.unwrap_or(self.zoom),
+        }
+    }
+
+    fn lerp(self, to: Self, t: f32) -> Self {
+        // Turn the short way around
+        let yaw = (to.yaw - self.yaw + PI).rem_euclid(TAU) - PI;
+        Self {
+            focus: self.focus.lerp(to.focus, t),
+            yaw: self.yaw + yaw * t,
+            pitch: self.pitch + (to.pitch - self.pitch) * t,
+            zoom: self.zoom + (to.zoom - self.zoom) * t,
+        }
+    }
+}
+
+/// Plays a [`CameraTrack`] on the camera, taking over from the player until
+/// it ends or is skipped
+#[derive(Component, Debug, Clone)]
+pub struct TrackPlayback {
+    pub track: Handle<CameraTrack>,
+    elapsed: f32,
+    /// Where the camera was when the track started, once it has loaded
+    start: Option<Pose>,
+    /// What the camera was following before the track took it over, put
+    /// back once the track stops
+    follow: Option<Follow>,
+}
+
+impl TrackPlayback {
+    pub fn new(track: Handle<CameraTrack>) -> Self {
+        Self {
+            track,
+            elapsed: 0.0,
+            start: None,
+            follow: None,
+        }
+    }
+
+    /// End the track, following whatever the camera followed before it
+    fn stop(&self, commands: &mut Commands, camera: Entity) {
+        let mut camera = commands.entity(camera);
+        camera.remove::<TrackPlayback>();
+        if let Some(follow) = &self.follow {
+            camera.insert(follow.clone());
+        }
+    }
+
+    fn skip(
+        mut commands: Commands,
+        input: Res<CameraInputConfig>,
+        keys: Res<Input<KeyCode>>,
+        playing: Query<(Entity, &TrackPlayback)>,
+    ) {
+        if keys.just_pressed(input.skip_track) {
+            for (entity, playback) in playing.iter() {
+                playback.stop(&mut commands, entity);
+            }
+        }
+    }
+
+    fn system(
+        mut commands: Commands,
+        time: Res<Time>,
+        tracks: Res<Assets<CameraTrack>>,
+        mut mode: ResMut<CameraControlMode>,
+        mut cameras: Query<(
+            Entity,
+            &mut TrackPlayback,
+            &mut YawPitchControls,
+            &mut Zoom,
+            &mut OrthographicProjection,
+            Option<&Follow>,
+        )>,
+    ) {
+        for (
+            entity,
+            mut playback,
+            mut controls,
+            mut zoom,
+            mut projection,
+            follow,
+        ) in cameras.iter_mut()
+        {
+            let track = match tracks.get(&playback.track) {
+                Some(track) => track,
+                None => continue,
+            };
+            let start = match playback.start {
+                Some(start) => start,
+                None => {
+                    // Nothing else may move the camera until the track ends
+                    *mode = CameraControlMode::Scripted;
+                    commands.entity(entity).remove::<Follow>().remove::<Turn>();
+                    playback.follow = follow.cloned();
+                    let start = Pose {
+                        focus: controls.focus,
+                        yaw: controls.yaw,
+                        pitch: controls.pitch,
+                        zoom: zoom.target,
+                    };
+                    playback.start = Some(start);
+                    start
+                }
+            };
+            playback.elapsed += time.delta_seconds();
+
+            // Walk the keyframes up to the one being eased towards
+            let mut from = start;
+            let mut remaining = playback.elapsed;
+            let mut pose = None;
+            for keyframe in track.keyframes.iter() {
+                let to = from.then(keyframe);
+                if remaining < keyframe.duration {
+                    let t =
+                        keyframe.easing.apply(remaining / keyframe.duration);
+                    pose = Some(from.lerp(to, t));
+                    break;
+                }
+                remaining -= keyframe.duration.max(0.0);
+                from = to;
+            }
+            let finished = pose.is_none();
+            let pose = pose.unwrap_or(from);
+
+            controls.focus = pose.focus;
+            controls.yaw = (pose.yaw + PI).rem_euclid(TAU) - PI;
+            controls.pitch = pose.pitch;
+            // Set the scale too, so the track isn't smoothed twice
+            zoom.target = pose.zoom;
+            projection.scale = pose.zoom;
+
+            if finished {
+                playback.stop(&mut commands, entity);
+            }
+        }
+    }
+
+    /// Hand the camera back to the player once no track is playing
+    fn release(
+        mut mode: ResMut<CameraControlMode>,
+        stopped: RemovedComponents<TrackPlayback>,
+        playing: Query<(), With<TrackPlayback>>,
+    ) {
+        if stopped.iter().next().is_some() && playing.is_empty() {
+            *mode = CameraControlMode::Player;
+        }
+    }
+}
+
+/// Load camera tracks from `.track.yaml` files
+#[derive(Default)]
+pub struct CameraTrackLoader;
+
+impl AssetLoader for CameraTrackLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let track: CameraTrack = serde_yaml::from_slice(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(track));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["track.yaml"]
+    }
+}
+
+/// `track <asset path>` plays a camera track, like
+/// `track tracks/intro.track.yaml`
+fn track_command(
+    mut commands: Commands,
+    mut events: EventReader<ConsoleCommand>,
+    mut console: ResMut<Console>,
+    asset_server: Res<AssetServer>,
+    cameras: Query<Entity, With<IsometricCamera>>,
+) {
+    for event in events.iter().filter(|event| event.name == "track") {
+        let path = match event.args.first() {
+            Some(path) => path,
+            None => {
+                console.print("usage: track <asset path>");
+                continue;
+            }
+        };
+        let track = asset_server.load(path.as_str());
+        for camera in cameras.iter() {
+            commands
+                .entity(camera)
+                .insert(TrackPlayback::new(track.clone()));
+        }
+        console.print(format!("Playing {}", path));
+    }
+}
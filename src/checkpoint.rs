@@ -0,0 +1,229 @@
+use crate::ai::{Enemy, SpawnedFrom};
+use crate::console::Console;
+use crate::editor::GameMode;
+use crate::health::{Corpse, DamageSystem, Died, Health, Invulnerability};
+use crate::item::Inventory;
+use crate::map::{Direction, Location, Marker};
+use crate::material::ScreenEffects;
+use crate::player::{Player, INVULNERABILITY};
+use crate::stats::Experience;
+use crate::turn::Actor;
+use bevy::prelude::*;
+use bevy::utils::HashSet;
+use iyes_loopless::prelude::*;
+
+pub struct CheckpointPlugin;
+
+impl Plugin for CheckpointPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Checkpoints>()
+            .add_event::<CheckpointReached>()
+            .add_event::<Respawned>()
+            .add_enter_system(GameMode::Playing, reset)
+            .add_exit_system(GameMode::Playing, reset)
+            .add_system(reach_checkpoints.run_in_state(GameMode::Playing))
+            .add_system(
+                start_respawn
+                    .after(DamageSystem)
+                    .run_in_state(GameMode::Playing),
+            )
+            .add_system(respawn.run_in_state(GameMode::Playing));
+    }
+}
+
+/// Seconds the screen takes to fade out after the player dies, and to fade
+/// back in once they are back
+const FADE_SECONDS: f32 = 0.8;
+
+/// Seconds the screen stays black in between
+const BLACK_SECONDS: f32 = 0.4;
+
+/// Where the player comes back after dying, and how they were doing when they
+/// got there
+#[derive(Debug, Default)]
+pub struct Checkpoints {
+    saved: Option<SavedPlayer>,
+    /// Enemy markers whose enemy was already dead at the checkpoint
+    cleared: HashSet<Entity>,
+    /// Seconds since the player died, while fading out and back in
+    respawning: Option<f32>,
+}
+
+#[derive(Debug, Clone)]
+struct SavedPlayer {
+    location: Location,
+    direction: Direction,
+    health: Health,
+    inventory: Inventory,
+    experience: Option<Experience>,
+}
+
+impl Checkpoints {
+    /// Whether the enemy of `marker` was dead by the checkpoint, and so stays
+    /// dead when the player respawns
+    pub fn is_cleared(&self, marker: Entity) -> bool {
+        self.cleared.contains(&marker)
+    }
+
+    pub fn is_respawning(&self) -> bool {
+        self.respawning.is_some()
+    }
+}
+
+/// Sent when the player's progress is saved, on reaching a checkpoint and
+/// where they start
+#[derive(Debug, Clone, Copy)]
+pub struct CheckpointReached {
+    pub location: Location,
+}
+
+/// Sent when the player comes back at the last checkpoint, for everything
+/// else to go back to how it was
+#[derive(Debug, Clone, Copy)]
+pub struct Respawned {
+    pub player: Entity,
+}
+
+/// Forget every checkpoint and clear the fade whenever play starts or stops
+fn reset(
+    mut checkpoints: ResMut<Checkpoints>,
+    mut effects: ResMut<ScreenEffects>,
+) {
+    *checkpoints = Checkpoints::default();
+    if effects.fade != 0.0 {
+        effects.fade = 0.0;
+    }
+}
+
+/// Save how the player is doing as they step onto a checkpoint, or wherever
+/// they start until they reach one
+fn reach_checkpoints(
+    mut console: ResMut<Console>,
+    mut checkpoints: ResMut<Checkpoints>,
+    mut reached: EventWriter<CheckpointReached>,
+    markers: Query<(Entity, &Location, &Marker)>,
+    enemies: Query<&SpawnedFrom, (With<Enemy>, Without<Corpse>)>,
+    players: Query<
+        (
+            &Location,
+            &Direction,
+            &Health,
+            &Inventory,
+            Option<&Experience>,
+        ),
+        (With<Player>, Changed<Location>),
+    >,
+) {
+    for (&location, &direction, &health, inventory, experience) in
+        players.iter()
+    {
+        let on_checkpoint = markers.iter().any(|(_, &at, marker)| {
+            at == location && matches!(marker, Marker::Checkpoint)
+        });
+        let first = checkpoints.saved.is_none();
+        if !on_checkpoint && !first {
+            continue;
+        }
+        let again = checkpoints
+            .saved
+            .as_ref()
+            .map(|saved| saved.location == location)
+            .unwrap_or(false);
+
+        let alive: HashSet<Entity> =
+            enemies.iter().map(|&SpawnedFrom(marker)| marker).collect();
+        checkpoints.cleared = markers
+            .iter()
+            .filter(|(_, _, marker)| matches!(marker, Marker::Enemy { .. }))
+            .map(|(marker, ..)| marker)
+            .filter(|marker| !alive.contains(marker))
+            .collect();
+        checkpoints.saved = Some(SavedPlayer {
+            location,
+            direction,
+            health,
+            inventory: inventory.clone(),
+            experience: experience.copied(),
+        });
+        reached.send(CheckpointReached { location });
+        if on_checkpoint && !again {
+            console.print("Checkpoint reached");
+        }
+    }
+}
+
+/// Start fading out once the player dies
+fn start_respawn(
+    mut checkpoints: ResMut<Checkpoints>,
+    mut died: EventReader<Died>,
+    players: Query<(), With<Player>>,
+) {
+    for &Died { entity, .. } in died.iter() {
+        if players.get(entity).is_ok()
+            && checkpoints.saved.is_some()
+            && !checkpoints.is_respawning()
+        {
+            checkpoints.respawning = Some(0.0);
+        }
+    }
+}
+
+/// Fade the screen to black, bring the player back at the last checkpoint as
+/// they were when they reached it, and fade back in
+fn respawn(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut checkpoints: ResMut<Checkpoints>,
+    mut effects: ResMut<ScreenEffects>,
+    mut respawned: EventWriter<Respawned>,
+    mut players: Query<
+        (
+            Entity,
+            &mut Location,
+            &mut Direction,
+            &mut Inventory,
+            Option<&mut Experience>,
+        ),
+        With<Player>,
+    >,
+) {
+    let elapsed = match checkpoints.respawning {
+        Some(elapsed) => elapsed,
+        None => return,
+    };
+    let now = elapsed + time.delta_seconds();
+
+    if elapsed < FADE_SECONDS && now >= FADE_SECONDS {
+        if let Some(saved) = &checkpoints.saved {
+            for (entity, mut location, mut direction, mut inventory, xp) in
+                players.iter_mut()
+            {
+                *location = saved.location;
+                *direction = saved.direction;
+                *inventory = saved.inventory.clone();
+                if let (Some(mut xp), Some(saved)) = (xp, saved.experience) {
+                    *xp = saved;
+                }
+                commands
+                    .entity(entity)
+                    .remove::<Corpse>()
+                    .insert(saved.health)
+                    .insert(Invulnerability::new(INVULNERABILITY))
+                    .insert(Actor { initiative: 0 });
+                respawned.send(Respawned { player: entity });
+            }
+        }
+    }
+
+    let fade_in = now - FADE_SECONDS - BLACK_SECONDS;
+    effects.fade = if now < FADE_SECONDS {
+        now / FADE_SECONDS
+    } else {
+        1.0 - (fade_in / FADE_SECONDS).clamp(0.0, 1.0)
+    };
+    checkpoints.respawning = if fade_in < FADE_SECONDS {
+        Some(now)
+    } else {
+        None
+    };
+}
@@ -0,0 +1,181 @@
+use crate::camera::{Follow, IsometricCamera};
+use crate::material::DebugRenderMode;
+use crate::player::Player;
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+use iyes_loopless::prelude::*;
+
+pub struct ConsolePlugin;
+
+impl Plugin for ConsolePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ConsoleCommand>()
+            .init_resource::<Console>()
+            .add_system_set(
+                ConditionSet::new()
+                    .with_system(toggle_console)
+                    .with_system(console_window)
+                    .with_system(follow_command)
+                    .with_system(render_command)
+                    .into(),
+            );
+    }
+}
+
+/// Debug command entered in the console, like `follow 12`
+#[derive(Debug, Clone)]
+pub struct ConsoleCommand {
+    pub name: String,
+    pub args: Vec<String>,
+}
+
+impl ConsoleCommand {
+    fn parse(line: &str) -> Option<Self> {
+        let mut words = line.split_whitespace().map(String::from);
+        Some(Self {
+            name: words.next()?,
+            args: words.collect(),
+        })
+    }
+}
+
+/// Text typed into the console and the output of past commands
+#[derive(Default)]
+pub struct Console {
+    pub open: bool,
+    pub input: String,
+    pub log: Vec<String>,
+}
+
+impl Console {
+    pub fn print(&mut self, line: impl Into<String>) {
+        let line = line.into();
+        info!("{}", line);
+        self.log.push(line);
+    }
+}
+
+/// Key which opens and closes the console
+const TOGGLE_CONSOLE: KeyCode = KeyCode::Grave;
+
+fn toggle_console(keys: Res<Input<KeyCode>>, mut console: ResMut<Console>) {
+    if keys.just_pressed(TOGGLE_CONSOLE) {
+        console.open = !console.open;
+    }
+}
+
+fn console_window(
+    mut egui: ResMut<EguiContext>,
+    mut console: ResMut<Console>,
+    mut commands: EventWriter<ConsoleCommand>,
+) {
+    if !console.open {
+        return;
+    }
+
+    let console = &mut *console;
+    let mut submitted = false;
+    egui::Window::new("Console").show(egui.ctx_mut(), |ui| {
+        egui::ScrollArea::vertical()
+            .max_height(200.0)
+            .stick_to_bottom()
+            .show(ui, |ui| {
+                for line in console.log.iter() {
+                    ui.monospace(line);
+                }
+            });
+
+        let input = ui.text_edit_singleline(&mut console.input);
+        submitted =
+            input.lost_focus() && ui.input().key_pressed(egui::Key::Enter);
+        if submitted {
+            input.request_focus();
+        }
+    });
+
+    if submitted {
+        let line = std::mem::take(&mut console.input);
+        console.log.push(format!("> {}", line));
+        if let Some(command) = ConsoleCommand::parse(&line) {
+            commands.send(command);
+        }
+    }
+}
+
+/// `follow <entity id or name>` points the camera at any entity, `follow
+/// player` back at the player, and `follow none` lets it move freely again
+fn follow_command(
+    mut commands: Commands,
+    mut events: EventReader<ConsoleCommand>,
+    mut console: ResMut<Console>,
+    cameras: Query<Entity, With<IsometricCamera>>,
+    players: Query<Entity, With<Player>>,
+    entities: Query<(Entity, Option<&Name>)>,
+) {
+    for event in events.iter().filter(|event| event.name == "follow") {
+        let target = match event.args.first().map(String::as_str) {
+            Some(target) => target,
+            None => {
+                console.print("usage: follow <entity id or name>");
+                continue;
+            }
+        };
+
+        // Without a player to return to, the camera is freed
+        let player = players.iter().next().filter(|_| target == "player");
+        if player.is_none() && (target == "player" || target == "none") {
+            for camera in cameras.iter() {
+                commands.entity(camera).remove::<Follow>();
+            }
+            console.print("Camera released");
+            continue;
+        }
+
+        let found = match player {
+            Some(player) => entities.get(player).ok(),
+            None => entities.iter().find(|(entity, name)| {
+                match target.parse::<u32>() {
+                    Ok(id) => entity.id() == id,
+                    Err(_) => name.map(Name::as_str) == Some(target),
+                }
+            }),
+        };
+        match found {
+            Some((entity, _)) => {
+                for camera in cameras.iter() {
+                    commands.entity(camera).insert(Follow::new(entity));
+                }
+                console.print(format!("Following {:?}", entity));
+            }
+            None => console.print(format!("No entity {:?}", target)),
+        }
+    }
+}
+
+/// `render <mode>` switches every sprite to a debug view, like `render
+/// wireframe`, and `render normal` switches back
+fn render_command(
+    mut events: EventReader<ConsoleCommand>,
+    mut console: ResMut<Console>,
+    mut mode: ResMut<DebugRenderMode>,
+) {
+    for event in events.iter().filter(|event| event.name == "render") {
+        let found = event
+            .args
+            .first()
+            .and_then(|name| DebugRenderMode::from_name(name));
+        match found {
+            Some(found) => {
+                *mode = found;
+                console.print(format!("Rendering {}", found.name()));
+            }
+            None => {
+                let names: Vec<_> = DebugRenderMode::ALL
+                    .iter()
+                    .map(|mode| mode.name())
+                    .collect();
+                console.print(format!("usage: render <{}>", names.join(", ")));
+            }
+        }
+    }
+}
@@ -0,0 +1,156 @@
+use crate::ambience::MapAmbience;
+use crate::material::ColorGrade;
+use bevy::prelude::*;
+use bevy_inspector_egui::{Inspectable, RegisterInspectable};
+use iyes_loopless::prelude::*;
+
+pub struct DayNightPlugin;
+
+impl Plugin for DayNightPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_inspectable::<WorldTime>()
+            .register_inspectable::<DayCycle>()
+            .init_resource::<WorldTime>()
+            .init_resource::<DayCycle>()
+            .add_system_set(
+                ConditionSet::new()
+                    .with_system(start_day)
+                    .with_system(advance_time)
+                    .into(),
+            )
+            .add_system(grade_time_of_day);
+    }
+}
+
+/// Hours in a day
+pub const DAY_LENGTH: f32 = 24.0;
+
+/// Time of day in the world
+#[derive(Inspectable, Debug, Clone)]
+pub struct WorldTime {
+    /// Hour of the day [0, 24)
+    pub hour: f32,
+    /// In-game hours which pass every real second
+    pub hours_per_second: f32,
+    pub paused: bool,
+}
+
+impl Default for WorldTime {
+    fn default() -> Self {
+        Self {
+            hour: 12.0,
+            hours_per_second: 0.1,
+            paused: false,
+        }
+    }
+}
+
+/// Color grade at one hour of the [`DayCycle`]
+#[derive(Inspectable, Debug, Clone, PartialEq)]
+pub struct DayKey {
+    /// Hour of the day [0, 24)
+    pub hour: f32,
+    pub tint: Color,
+    pub exposure: f32,
+}
+
+impl Default for DayKey {
+    /// Ungraded noon
+    fn default() -> Self {
+        Self::new(12.0, Color::WHITE, 1.0)
+    }
+}
+
+impl DayKey {
+    fn new(hour: f32, tint: Color, exposure: f32) -> Self {
+        Self {
+            hour,
+            tint,
+            exposure,
+        }
+    }
+}
+
+/// Color grade over the course of a day, blended between the keys and
+/// wrapping around at midnight
+#[derive(Inspectable, Debug, Clone)]
+pub struct DayCycle {
+    pub keys: Vec<DayKey>,
+}
+
+impl Default for DayCycle {
+    fn default() -> Self {
+        Self {
+            keys: vec![
+                DayKey::new(0.0, Color::rgb(0.45, 0.5, 0.85), 0.5),
+                DayKey::new(6.0, Color::rgb(1.0, 0.75, 0.55), 0.85),
+                DayKey::new(12.0, Color::WHITE, 1.0),
+                DayKey::new(18.0, Color::rgb(1.0, 0.65, 0.5), 0.85),
+            ],
+        }
+    }
+}
+
+impl DayCycle {
+    /// Grade at `hour`, blending linearly between the closest keys
+    pub fn grade(&self, hour: f32) -> ColorGrade {
+        if self.keys.is_empty() {
+            return ColorGrade::default();
+        }
+        let mut keys: Vec<&DayKey> = self.keys.iter().collect();
+        keys.sort_by(|a, b| a.hour.total_cmp(&b.hour));
+        let hour = hour.rem_euclid(DAY_LENGTH);
+
+        // Keys either side of the hour, wrapping around midnight
+        let next = keys.iter().position(|key| key.hour > hour);
+        let (before, after) = match next {
+            Some(0) | None => (keys[keys.len() - 1], keys[0]),
+            Some(next) => (keys[next - 1], keys[next]),
+        };
+        let span = (after.hour - before.hour).rem_euclid(DAY_LENGTH);
+        let t = if span > 0.0 {
+            (hour - before.hour).rem_euclid(DAY_LENGTH) / span
+        } else {
+            0.0
+        };
+
+        let [r0, g0, b0, _] = before.tint.as_rgba_f32();
+        let [r1, g1, b1, _] = after.tint.as_rgba_f32();
+        ColorGrade {
+            tint: Color::rgb(
+                r0 + (r1 - r0) * t,
+                g0 + (g1 - g0) * t,
+                b0 + (b1 - b0) * t,
+            ),
+            exposure: before.exposure + (after.exposure - before.exposure) * t,
+        }
+    }
+}
+
+/// Reset the clock to the time of day the map starts at
+fn start_day(ambience: Res<MapAmbience>, mut time: ResMut<WorldTime>) {
+    if ambience.is_changed() {
+        time.hour = ambience.time_of_day.rem_euclid(DAY_LENGTH);
+    }
+}
+
+fn advance_time(clock: Res<Time>, mut time: ResMut<WorldTime>) {
+    if !time.paused && time.hours_per_second != 0.0 {
+        let hour = time.hour + time.hours_per_second * clock.delta_seconds();
+        time.hour = hour.rem_euclid(DAY_LENGTH);
+    }
+}
+
+fn grade_time_of_day(
+    time: Res<WorldTime>,
+    cycle: Res<DayCycle>,
+    mut grade: ResMut<ColorGrade>,
+) {
+    if !time.is_changed() && !cycle.is_changed() {
+        return;
+    }
+    let graded = cycle.grade(time.hour);
+    if *grade != graded {
+        *grade = graded;
+    }
+}
@@ -0,0 +1,613 @@
+use crate::camera::track::TrackPlayback;
+use crate::camera::IsometricCamera;
+use crate::checkpoint::{CheckpointReached, Respawned};
+use crate::console::{Console, ConsoleCommand};
+use crate::editor::GameMode;
+use crate::interact::{Interactable, Interacted, Interaction, LeverToggled};
+use crate::item::Inventory;
+use crate::map::Location;
+use crate::player::Player;
+use bevy::asset::{AssetLoader, LoadContext, LoadedAsset};
+use bevy::prelude::*;
+use bevy::reflect::TypeUuid;
+use bevy::utils::{BoxedFuture, HashMap, HashSet};
+use bevy_egui::{egui, EguiContext};
+use iyes_loopless::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub struct DialoguePlugin;
+
+impl Plugin for DialoguePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<Dialogue>()
+            .init_asset_loader::<DialogueLoader>()
+            .init_resource::<Conversation>()
+            .init_resource::<DialogueFlags>()
+            .init_resource::<CheckpointDialogue>()
+            .add_event::<StartDialogue>()
+            .add_event::<DialogueActed>()
+            .add_event::<DialogueEnded>()
+            .add_enter_system(GameMode::Playing, reset)
+            .add_exit_system(GameMode::Playing, hang_up)
+            .add_system(dialogue_command)
+            .add_system(CheckpointDialogue::save)
+            .add_system(CheckpointDialogue::restore)
+            .add_system(talk_on_interact.run_in_state(GameMode::Playing))
+            .add_system(DialogueTrigger::system.run_in_state(GameMode::Playing))
+            // A conversation shows up the frame after it starts, so the key
+            // which started it doesn't skip the first line
+            .add_system(dialogue_box.before(start_dialogue))
+            .add_system(
+                start_dialogue
+                    .after(talk_on_interact)
+                    .after(DialogueTrigger::system),
+            )
+            .add_system(run_actions.after(dialogue_box));
+    }
+}
+
+/// Keys which move on to the next line when there is nothing to choose
+const CONTINUE: [KeyCode; 2] = [KeyCode::Space, KeyCode::Return];
+
+/// Keys which pick the choice with the same number
+const CHOOSE: [KeyCode; 9] = [
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+];
+
+/// Most nodes without lines passed over in a row before giving up on a
+/// script which loops
+const MAX_HOPS: usize = 64;
+
+/// Conversation with branching nodes, loaded from `.dialogue.yaml` files.
+///
+/// Each node runs its actions as it is entered, shows its lines one after
+/// another, and then offers its choices or moves on by itself.
+#[derive(Debug, Clone, Deserialize, TypeUuid)]
+#[uuid = "3f8d2b71-5c4e-4a9f-b6e0-7d1a9c3e5f28"]
+pub struct Dialogue {
+    /// Name of the node the conversation starts on
+    pub start: String,
+    pub nodes: HashMap<String, DialogueNode>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct DialogueNode {
+    pub lines: Vec<DialogueLine>,
+    /// Offered after the last line, leaving out any whose condition fails
+    pub choices: Vec<DialogueChoice>,
+    /// Node to go to without choices, the first whose condition holds
+    pub branches: Vec<DialogueBranch>,
+    /// Node to go to without choices when no branch is taken, ending the
+    /// conversation if there is none
+    pub next: Option<String>,
+    pub actions: Vec<DialogueAction>,
+}
+
+impl DialogueNode {
+    /// Node to move on to once the last line is read
+    fn follow(&self, facts: &Facts) -> Option<&str> {
+        self.branches
+            .iter()
+            .find(|branch| branch.condition.holds(facts))
+            .map(|branch| branch.next.as_str())
+            .or(self.next.as_deref())
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct DialogueLine {
+    #[serde(default)]
+    pub speaker: Option<String>,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DialogueChoice {
+    pub text: String,
+    /// Node the choice leads to, ending the conversation if there is none
+    #[serde(default)]
+    pub next: Option<String>,
+    #[serde(default)]
+    pub condition: Option<Condition>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DialogueBranch {
+    pub condition: Condition,
+    pub next: String,
+}
+
+/// Something about the game so far a script can check
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Condition {
+    /// Set by an earlier conversation
+    Flag(String),
+    NotFlag(String),
+    /// The player carries at least one
+    HasItem(String),
+}
+
+impl Condition {
+    fn holds(&self, facts: &Facts) -> bool {
+        match self {
+            Condition::Flag(flag) => facts.flags.set.contains(flag),
+            Condition::NotFlag(flag) => !facts.flags.set.contains(flag),
+            Condition::HasItem(item) => facts
+                .inventory
+                .map(|inventory| inventory.count(item) > 0)
+                .unwrap_or(false),
+        }
+    }
+}
+
+/// What a [`Condition`] is checked against
+struct Facts<'a> {
+    flags: &'a DialogueFlags,
+    inventory: Option<&'a Inventory>,
+}
+
+/// Done as a node is entered
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DialogueAction {
+    SetFlag(String),
+    ClearFlag(String),
+    /// Switch everything on `channel`, like doors, as a lever would
+    Switch {
+        channel: u32,
+        on: bool,
+    },
+    /// Play the camera track at the asset path
+    CameraTrack(String),
+}
+
+/// Flags set by conversations, for later ones to check, cleared whenever play
+/// starts
+#[derive(Debug, Default)]
+pub struct DialogueFlags {
+    pub set: HashSet<String>,
+}
+
+/// Send to open a conversation, unless one is already going
+#[derive(Debug, Clone)]
+pub struct StartDialogue {
+    pub dialogue: Handle<Dialogue>,
+    /// Whoever the player is talking to, if anyone
+    pub speaker: Option<Entity>,
+}
+
+/// Sent for every action of a node as it is entered
+#[derive(Debug, Clone)]
+pub struct DialogueActed {
+    pub action: DialogueAction,
+    pub speaker: Option<Entity>,
+}
+
+/// Sent when the conversation with `speaker` runs out or is cut short
+#[derive(Debug, Clone, Copy)]
+pub struct DialogueEnded {
+    pub speaker: Option<Entity>,
+}
+
+/// Conversation shown in the dialogue box, which holds up the turns and the
+/// player's input until it is over
+#[derive(Debug, Default)]
+pub struct Conversation {
+    talking: Option<Talking>,
+}
+
+#[derive(Debug)]
+struct Talking {
+    dialogue: Handle<Dialogue>,
+    speaker: Option<Entity>,
+    /// Node being shown, `None` until the dialogue has loaded
+    node: Option<String>,
+    /// Index of the line shown in the node
+    line: usize,
+}
+
+impl Conversation {
+    pub fn is_active(&self) -> bool {
+        self.talking.is_some()
+    }
+}
+
+/// Run condition for systems which wait while a conversation is going
+pub fn not_talking(conversation: Res<Conversation>) -> bool {
+    !conversation.is_active()
+}
+
+/// Starts a conversation when the player steps onto its marker, placed on a
+/// marker in the map file
+#[derive(Component, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DialogueTrigger {
+    /// Asset path of the dialogue
+    pub dialogue: String,
+    /// Only start the conversation the first time
+    #[serde(default = "DialogueTrigger::default_once")]
+    pub once: bool,
+    #[serde(skip)]
+    fired: bool,
+}
+
+impl DialogueTrigger {
+    fn default_once() -> bool {
+        true
+    }
+
+    fn system(
+        asset_server: Res<AssetServer>,
+        mut start: EventWriter<StartDialogue>,
+        players: Query<&Location, (With<Player>, Changed<Location>)>,
+        mut triggers: Query<(&Location, &mut DialogueTrigger)>,
+    ) {
+        for &player in players.iter() {
+            for (&at, mut trigger) in triggers.iter_mut() {
+                if at != player || (trigger.once && trigger.fired) {
+                    continue;
+                }
+                trigger.fired = true;
+                start.send(StartDialogue {
+                    dialogue: asset_server.load(trigger.dialogue.as_str()),
+                    speaker: None,
+                });
+            }
+        }
+    }
+}
+
+/// Flags and fired triggers as they were at the last checkpoint, put back
+/// when the player respawns there
+#[derive(Debug, Default)]
+struct CheckpointDialogue {
+    flags: HashSet<String>,
+    fired: HashSet<Entity>,
+}
+
+impl CheckpointDialogue {
+    fn save(
+        mut reached: EventReader<CheckpointReached>,
+        mut saved: ResMut<CheckpointDialogue>,
+        flags: Res<DialogueFlags>,
+        triggers: Query<(Entity, &DialogueTrigger)>,
+    ) {
+        if reached.iter().last().is_none() {
+            return;
+        }
+        saved.flags = flags.set.clone();
+        saved.fired = triggers
+            .iter()
+            .filter(|(_, trigger)| trigger.fired)
+            .map(|(entity, _)| entity)
+            .collect();
+    }
+
+    fn restore(
+        mut respawned: EventReader<Respawned>,
+        saved: Res<CheckpointDialogue>,
+        mut flags: ResMut<DialogueFlags>,
+        mut triggers: Query<(Entity, &mut DialogueTrigger)>,
+    ) {
+        if respawned.iter().last().is_none() {
+            return;
+        }
+        flags.set = saved.flags.clone();
+        for (entity, mut trigger) in triggers.iter_mut() {
+            let fired = saved.fired.contains(&entity);
+            if trigger.fired != fired {
+                trigger.fired = fired;
+            }
+        }
+    }
+}
+
+/// Forget every flag and re-arm every trigger whenever play starts
+fn reset(
+    mut flags: ResMut<DialogueFlags>,
+    mut saved: ResMut<CheckpointDialogue>,
+    mut triggers: Query<&mut DialogueTrigger>,
+) {
+    flags.set.clear();
+    *saved = CheckpointDialogue::default();
+    for mut trigger in triggers.iter_mut() {
+        trigger.fired = false;
+    }
+}
+
+/// Cut the conversation short when play stops
+fn hang_up(
+    mut conversation: ResMut<Conversation>,
+    mut ended: EventWriter<DialogueEnded>,
+) {
+    if let Some(talking) = conversation.talking.take() {
+        ended.send(DialogueEnded {
+            speaker: talking.speaker,
+        });
+    }
+}
+
+fn start_dialogue(
+    mut conversation: ResMut<Conversation>,
+    mut start: EventReader<StartDialogue>,
+) {
+    for start in start.iter() {
+        if conversation.is_active() {
+            continue;
+        }
+        conversation.talking = Some(Talking {
+            dialogue: start.dialogue.clone(),
+            speaker: start.speaker,
+            node: None,
+            line: 0,
+        });
+    }
+}
+
+/// Talk to whoever the player interacts with
+fn talk_on_interact(
+    asset_server: Res<AssetServer>,
+    mut interacted: EventReader<Interacted>,
+    mut start: EventWriter<StartDialogue>,
+    interactables: Query<&Interactable>,
+) {
+    for &Interacted { target, .. } in interacted.iter() {
+        if let Ok(Interactable {
+            kind: Interaction::Talk { dialogue },
+            ..
+        }) = interactables.get(target)
+        {
+            start.send(StartDialogue {
+                dialogue: asset_server.load(dialogue.as_str()),
+                speaker: Some(target),
+            });
+        }
+    }
+}
+
+/// Enter the node named `next`, running its actions, and pass over any
+/// which have nothing to show, giving the node to show or `None` once the
+/// conversation is over
+fn enter<'a>(
+    dialogue: &'a Dialogue,
+    mut next: Option<&'a str>,
+    speaker: Option<Entity>,
+    flags: &mut DialogueFlags,
+    inventory: Option<&Inventory>,
+    acted: &mut EventWriter<DialogueActed>,
+) -> Option<String> {
+    for _ in 0..MAX_HOPS {
+        let name = next?;
+        let node = match dialogue.nodes.get(name) {
+            Some(node) => node,
+            None => {
+                warn!("Dialogue has no node named {}", name);
+                return None;
+            }
+        };
+        for action in node.actions.iter() {
+            // Flags are set right away, for the branches of this node
+            match action {
+                DialogueAction::SetFlag(flag) => {
+                    flags.set.insert(flag.clone());
+                }
+                DialogueAction::ClearFlag(flag) => {
+                    flags.set.remove(flag);
+                }
+                _ => {}
+            }
+            acted.send(DialogueActed {
+                action: action.clone(),
+                speaker,
+            });
+        }
+        if !node.lines.is_empty() || !node.choices.is_empty() {
+            return Some(name.to_string());
+        }
+        next = node.follow(&Facts { flags, inventory });
+    }
+    warn!("Dialogue passed over {} nodes in a row", MAX_HOPS);
+    None
+}
+
+/// Show the line being said along the bottom of the screen, moving on with a
+/// key or click, and the choices after the last line
+#[allow(clippy::too_many_arguments)]
+fn dialogue_box(
+    mut keys: ResMut<Input<KeyCode>>,
+    dialogues: Res<Assets<Dialogue>>,
+    mut egui: ResMut<EguiContext>,
+    mut conversation: ResMut<Conversation>,
+    mut flags: ResMut<DialogueFlags>,
+    mut acted: EventWriter<DialogueActed>,
+    mut ended: EventWriter<DialogueEnded>,
+    players: Query<&Inventory, With<Player>>,
+) {
+    let talking = match conversation.talking.as_mut() {
+        Some(talking) => talking,
+        None => return,
+    };
+    let dialogue = match dialogues.get(&talking.dialogue) {
+        Some(dialogue) => dialogue,
+        None => return,
+    };
+    let inventory = players.iter().next();
+    if talking.node.is_none() {
+        talking.node = enter(
+            dialogue,
+            Some(&dialogue.start),
+            talking.speaker,
+            &mut flags,
+            inventory,
+            &mut acted,
+        );
+    }
+    let node = match talking.node.as_ref() {
+        Some(name) => &dialogue.nodes[name],
+        None => {
+            ended.send(DialogueEnded {
+                speaker: talking.speaker,
+            });
+            conversation.talking = None;
+            return;
+        }
+    };
+
+    let last = talking.line + 1 >= node.lines.len();
+    let facts = Facts {
+        flags: &flags,
+        inventory,
+    };
+    let choices: Vec<&DialogueChoice> = node
+        .choices
+        .iter()
+        .filter(|choice| match &choice.condition {
+            Some(condition) => condition.holds(&facts),
+            None => true,
+        })
+        .filter(|_| last)
+        .collect();
+
+    let mut advance = false;
+    let mut picked = None;
+    egui::Window::new("Dialogue")
+        .title_bar(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_BOTTOM, [0.0, -24.0])
+        .show(egui.ctx_mut(), |ui| {
+            if let Some(line) = node.lines.get(talking.line) {
+                if let Some(speaker) = &line.speaker {
+                    ui.strong(speaker);
+                }
+                ui.label(&line.text);
+                ui.separator();
+            }
+            if choices.is_empty() {
+                advance = ui.button("Continue").clicked();
+            }
+            for (i, choice) in choices.iter().enumerate() {
+                let text = format!("{}. {}", i + 1, choice.text);
+                if ui.button(text).clicked() {
+                    picked = Some(i);
+                }
+            }
+        });
+
+    // Keep the keys from doing anything else this frame, like interacting
+    // again as the conversation ends
+    if choices.is_empty() {
+        for key in CONTINUE {
+            advance |= keys.clear_just_pressed(key);
+        }
+    }
+    for (i, &key) in CHOOSE.iter().enumerate().take(choices.len()) {
+        if keys.clear_just_pressed(key) {
+            picked = Some(i);
+        }
+    }
+
+    let next = match (picked, advance) {
+        (Some(i), _) => choices[i].next.as_deref(),
+        (None, true) if !last => {
+            talking.line += 1;
+            return;
+        }
+        (None, true) => node.follow(&facts),
+        (None, false) => return,
+    };
+    talking.line = 0;
+    talking.node = enter(
+        dialogue,
+        next,
+        talking.speaker,
+        &mut flags,
+        inventory,
+        &mut acted,
+    );
+}
+
+/// Do what dialogue actions do outside of the conversation
+fn run_actions(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut acted: EventReader<DialogueActed>,
+    mut toggled: EventWriter<LeverToggled>,
+    players: Query<Entity, With<Player>>,
+    cameras: Query<Entity, With<IsometricCamera>>,
+) {
+    for acted in acted.iter() {
+        match &acted.action {
+            &DialogueAction::Switch { channel, on } => {
+                // Credit the switch to the speaker, or else the player
+                let lever = acted.speaker.or_else(|| players.iter().next());
+                if let Some(lever) = lever {
+                    toggled.send(LeverToggled { lever, channel, on });
+                }
+            }
+            DialogueAction::CameraTrack(path) => {
+                let track = asset_server.load(path.as_str());
+                for camera in cameras.iter() {
+                    commands
+                        .entity(camera)
+                        .insert(TrackPlayback::new(track.clone()));
+                }
+            }
+            DialogueAction::SetFlag(_) | DialogueAction::ClearFlag(_) => {}
+        }
+    }
+}
+
+/// Load dialogues from `.dialogue.yaml` files
+#[derive(Default)]
+pub struct DialogueLoader;
+
+impl AssetLoader for DialogueLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let dialogue: Dialogue = serde_yaml::from_slice(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(dialogue));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["dialogue.yaml"]
+    }
+}
+
+/// `dialogue <asset path>` starts a conversation, like
+/// `dialogue dialogues/guard.dialogue.yaml`
+fn dialogue_command(
+    mut events: EventReader<ConsoleCommand>,
+    mut console: ResMut<Console>,
+    asset_server: Res<AssetServer>,
+    mut start: EventWriter<StartDialogue>,
+) {
+    for event in events.iter().filter(|event| event.name == "dialogue") {
+        let path = match event.args.first() {
+            Some(path) => path,
+            None => {
+                console.print("usage: dialogue <asset path>");
+                continue;
+            }
+        };
+        start.send(StartDialogue {
+            dialogue: asset_server.load(path.as_str()),
+            speaker: None,
+        });
+        console.print(format!("Starting {}", path));
+    }
+}
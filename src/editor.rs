@@ -0,0 +1,673 @@
+use crate::ai::PatrolPaths;
+use crate::dialogue::DialogueTrigger;
+use crate::fog::FogOfWar;
+use crate::hazard::Hazard;
+use crate::interact::Interactable;
+use crate::item::Pickup;
+use crate::map::{
+    Direction, Door, Edge, Lit, Location, MapBaking, Marker, Prop, PropBundle,
+    PropLight, SpriteIndex, Tile, TileBundle, TileKind, WallBundle, TILE_SIZE,
+    WALL_HEIGHT,
+};
+use crate::material::{CornerColors, SpriteFlip, UnlitMaterial, ViewLayer};
+use crate::push::Pushable;
+use crate::spawner::Spawner;
+use crate::theme::SpriteKind;
+use bevy::ecs::system::EntityCommands;
+use bevy::prelude::*;
+use bevy::render::mesh::PrimitiveTopology;
+use bevy_inspector_egui::{
+    Inspectable, RegisterInspectable, WorldInspectorParams,
+};
+use iyes_loopless::prelude::*;
+
+mod autosave;
+mod bounds;
+mod brush;
+mod camera;
+mod grid;
+mod layers;
+mod materials;
+mod measure;
+mod props;
+mod replace;
+mod validate;
+mod wizard;
+
+pub struct EditorPlugin;
+
+impl Plugin for EditorPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_inspectable::<EditorOverlay>()
+            .init_resource::<EditorOverlay>()
+            .init_resource::<MapSnapshot>()
+            .add_loopless_state(GameMode::Editing)
+            .add_plugin(autosave::AutosavePlugin)
+            .add_plugin(bounds::BoundsPlugin)
+            .add_plugin(camera::EditorCameraPlugin)
+            .add_plugin(brush::BrushPlugin)
+            .add_plugin(grid::GridPlugin)
+            .add_plugin(layers::LayersPlugin)
+            .add_plugin(materials::MaterialsPlugin)
+            .add_plugin(measure::MeasurePlugin)
+            .add_plugin(props::PropsPlugin)
+            .add_plugin(replace::ReplacePlugin)
+            .add_plugin(validate::ValidatePlugin)
+            .add_plugin(wizard::WizardPlugin)
+            .add_startup_system(setup_overlay)
+            .add_system(toggle_mode)
+            .add_system(sync_editing)
+            .add_exit_system(GameMode::Editing, MapSnapshot::save)
+            .add_exit_system(GameMode::Editing, hide_editor)
+            .add_enter_system(GameMode::Editing, MapSnapshot::restore)
+            .add_enter_system(GameMode::Editing, show_editor)
+            .add_system_set(
+                ConditionSet::new()
+                    .run_in_state(GameMode::Editing)
+                    .with_system(toggle_overlay)
+                    .with_system(draw_overlay)
+                    .into(),
+            );
+    }
+}
+
+/// Whether the map is being authored or played.
+///
+/// Gameplay systems should only run in `Playing`, and editor tools only in
+/// `Editing`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GameMode {
+    Editing,
+    Playing,
+}
+
+/// Key which switches between editing and playing the map
+const TOGGLE_MODE: KeyCode = KeyCode::F5;
+
+fn toggle_mode(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    mode: Res<CurrentState<GameMode>>,
+) {
+    if keys.just_pressed(TOGGLE_MODE) {
+        let next = match mode.0 {
+            GameMode::Editing => GameMode::Playing,
+            GameMode::Playing => GameMode::Editing,
+        };
+        info!("Switching to {:?}", next);
+        commands.insert_resource(NextState(next));
+    }
+}
+
+/// Draw every tile and wall on its own while editing, unless chunks are
+/// configured to stay baked, and lift the fog of war
+fn sync_editing(
+    mode: Res<CurrentState<GameMode>>,
+    mut baking: ResMut<MapBaking>,
+    mut fog: ResMut<FogOfWar>,
+) {
+    let editing = mode.0 == GameMode::Editing;
+    if baking.editing != editing {
+        baking.editing = editing;
+    }
+    if fog.editing != editing {
+        fog.editing = editing;
+    }
+}
+
+fn show_editor(mut inspector: ResMut<WorldInspectorParams>) {
+    inspector.enabled = true;
+}
+
+fn hide_editor(
+    mut inspector: ResMut<WorldInspectorParams>,
+    mut layers: Query<&mut Visibility, With<OverlayLayer>>,
+) {
+    inspector.enabled = false;
+    for mut visibility in layers.iter_mut() {
+        visibility.is_visible = false;
+    }
+}
+
+/// Copy of the map taken when play starts, restored when editing resumes
+#[derive(Default)]
+pub struct MapSnapshot {
+    entries: Option<Vec<SnapshotEntry>>,
+}
+
+enum SnapshotEntry {
+    Tile {
+        location: Location,
+        sprite: SnapshotSprite,
+        hazard: Option<Hazard>,
+    },
+    Wall {
+        edge: Edge,
+        direction: Direction,
+        sprite: SnapshotSprite,
+        door: Option<Door>,
+    },
+    Marker {
+        location: Location,
+        marker: Marker,
+        name: Option<Name>,
+        dialogue: Option<DialogueTrigger>,
+    },
+    Prop {
+        location: Location,
+        direction: Direction,
+        sprite: SnapshotSprite,
+        light: Option<PropLight>,
+        interactable: Option<Interactable>,
+        pickup: Option<Pickup>,
+        pushable: bool,
+        spawner: Option<Spawner>,
+    },
+}
+
+type SpriteState<'a> = (
+    Option<&'a Handle<UnlitMaterial>>,
+    Option<&'a SpriteIndex>,
+    Option<&'a Lit>,
+    Option<&'a SpriteFlip>,
+    Option<&'a CornerColors>,
+    Option<&'a TileKind>,
+    Option<&'a SpriteKind>,
+);
+
+/// How a saved tile, wall, or prop was drawn
+struct SnapshotSprite {
+    material: Handle<UnlitMaterial>,
+    sprite: Option<SpriteIndex>,
+    lit: bool,
+    flip: Option<SpriteFlip>,
+    colors: Option<CornerColors>,
+    kind: Option<TileKind>,
+    /// Kept so the sprite is still reskinned by the theme once restored
+    sprite_kind: Option<SpriteKind>,
+}
+
+impl SnapshotSprite {
+    fn new(
+        (material, sprite, lit, flip, colors, kind, sprite_kind): SpriteState,
+    ) -> Self {
+        Self {
+            // Lit sprites get their material back from the sprite controller
+            material: material.cloned().unwrap_or_default(),
+            sprite: sprite.copied(),
+            lit: lit.is_some(),
+            flip: flip.copied(),
+            colors: colors.copied(),
+            kind: kind.copied(),
+            sprite_kind: sprite_kind.cloned(),
+        }
+    }
+
+    fn insert(self, entity: &mut EntityCommands) {
+        if let Some(sprite) = self.sprite {
+            entity.insert(sprite);
+        }
+        if self.lit {
+            entity.insert(Lit);
+        }
+        if let Some(flip) = self.flip {
+            entity.insert(flip);
+        }
+        if let Some(colors) = self.colors {
+            entity.insert(colors);
+        }
+        if let Some(kind) = self.kind {
+            entity.insert(kind);
+        }
+        if let Some(sprite_kind) = self.sprite_kind {
+            entity.insert(sprite_kind);
+        }
+    }
+}
+
+impl MapSnapshot {
+    fn save(
+        mut snapshot: ResMut<MapSnapshot>,
+        tiles: Query<(&Location, SpriteState, Option<&Hazard>), With<Tile>>,
+        walls: Query<(&Edge, &Direction, SpriteState, Option<&Door>)>,
+        markers: Query<(
+            &Location,
+            &Marker,
+            Option<&Name>,
+            Option<&DialogueTrigger>,
+        )>,
+        props: Query<
+            (
+                &Location,
+                &Direction,
+                SpriteState,
+                Option<&PropLight>,
+                Option<&Interactable>,
+                Option<&Pickup>,
+                Option<&Pushable>,
+                Option<&Spawner>,
+            ),
+            With<Prop>,
+        >,
+    ) {
+        let tiles = tiles.iter().map(|(&location, state, hazard)| {
+            SnapshotEntry::Tile {
+                location,
+                sprite: SnapshotSprite::new(state),
+                hazard: hazard.cloned(),
+            }
+        });
+        let walls = walls.iter().map(|(&edge, &direction, state, door)| {
+            SnapshotEntry::Wall {
+                edge,
+                direction,
+                sprite: SnapshotSprite::new(state),
+                door: door.cloned(),
+            }
+        });
+        let markers =
+            markers.iter().map(|(&location, &marker, name, dialogue)| {
+                SnapshotEntry::Marker {
+                    location,
+                    marker,
+                    name: name.cloned(),
+                    dialogue: dialogue.cloned(),
+                }
+            });
+        let props = props.iter().map(
+            |(
+                &location,
+                &direction,
+                state,
+                light,
+                interactable,
+                pickup,
+                pushable,
+                spawner,
+            )| {
+                SnapshotEntry::Prop {
+                    location,
+                    direction,
+                    sprite: SnapshotSprite::new(state),
+                    light: light.cloned(),
+                    interactable: interactable.cloned(),
+                    pickup: pickup.cloned(),
+                    pushable: pushable.is_some(),
+                    spawner: spawner.cloned(),
+                }
+            },
+        );
+
+        snapshot.entries =
+            Some(tiles.chain(walls).chain(markers).chain(props).collect());
+    }
+
+    /// Replace everything placed on the grid with the saved map
+    fn restore(
+        mut commands: Commands,
+        mut snapshot: ResMut<MapSnapshot>,
+        placed: Query<Entity, Or<(With<Location>, With<Edge>)>>,
+    ) {
+        let entries = match snapshot.entries.take() {
+            Some(entries) => entries,
+            None => return,
+        };
+
+        for entity in placed.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+
+        for entry in entries {
+            match entry {
+                SnapshotEntry::Tile {
+                    location,
+                    sprite,
+                    hazard,
+                } => {
+                    let material = sprite.material.clone();
+                    let mut entity = commands
+                        .spawn_bundle(TileBundle::new(location, material));
+                    if let Some(hazard) = hazard {
+                        entity.insert(hazard);
+                    }
+                    sprite.insert(&mut entity);
+                }
+                SnapshotEntry::Wall {
+                    edge,
+                    direction,
+                    sprite,
+                    door,
+                } => {
+                    let material = sprite.material.clone();
+                    let mut entity = commands.spawn_bundle(
+                        WallBundle::on_edge(edge, direction, material),
+                    );
+                    if let Some(door) = door {
+                        entity.insert(door);
+                    }
+                    sprite.insert(&mut entity);
+                }
+                SnapshotEntry::Marker {
+                    location,
+                    marker,
+                    name,
+                    dialogue,
+                } => {
+                    let mut entity = commands.spawn();
+                    entity.insert(location).insert(marker);
+                    if let Some(name) = name {
+                        entity.insert(name);
+                    }
+                    if let Some(dialogue) = dialogue {
+                        entity.insert(dialogue);
+                    }
+                }
+                SnapshotEntry::Prop {
+                    location,
+                    direction,
+                    sprite,
+                    light,
+                    interactable,
+                    pickup,
+                    pushable,
+                    spawner,
+                } => {
+                    let material = sprite.material.clone();
+                    let mut entity = commands.spawn_bundle(PropBundle::new(
+                        location, direction, material,
+                    ));
+                    if let Some(light) = light {
+                        entity.insert(light);
+                    }
+                    if let Some(interactable) = interactable {
+                        entity.insert(interactable);
+                    }
+                    if let Some(pickup) = pickup {
+                        entity.insert(pickup);
+                    }
+                    if pushable {
+                        entity.insert(Pushable);
+                    }
+                    if let Some(spawner) = spawner {
+                        entity.insert(spawner);
+                    }
+                    sprite.insert(&mut entity);
+                }
+            }
+        }
+    }
+}
+
+/// Which pieces of invisible map data to draw as line overlays
+#[derive(Inspectable, Debug, Clone)]
+pub struct EditorOverlay {
+    pub enabled: bool,
+    /// Arrow pointing out of the visible face of every wall
+    pub walls: bool,
+    /// Spawn points, triggers, and portal links
+    pub markers: bool,
+}
+
+impl Default for EditorOverlay {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            walls: true,
+            markers: true,
+        }
+    }
+}
+
+/// Key which shows or hides the whole overlay
+const TOGGLE_OVERLAY: KeyCode = KeyCode::F2;
+
+fn toggle_overlay(
+    keys: Res<Input<KeyCode>>,
+    mut overlay: ResMut<EditorOverlay>,
+) {
+    if keys.just_pressed(TOGGLE_OVERLAY) {
+        overlay.enabled = !overlay.enabled;
+    }
+}
+
+/// Each layer is drawn as one line list mesh in its own color
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+enum OverlayLayer {
+    Walls,
+    Spawns,
+    Triggers,
+    Portals,
+    Enemies,
+}
+
+impl OverlayLayer {
+    const ALL: [OverlayLayer; 5] = [
+        OverlayLayer::Walls,
+        OverlayLayer::Spawns,
+        OverlayLayer::Triggers,
+        OverlayLayer::Portals,
+        OverlayLayer::Enemies,
+    ];
+
+    fn color(self) -> Color {
+        match self {
+            OverlayLayer::Walls => Color::CYAN,
+            OverlayLayer::Spawns => Color::LIME_GREEN,
+            OverlayLayer::Triggers => Color::YELLOW,
+            OverlayLayer::Portals => Color::FUCHSIA,
+            OverlayLayer::Enemies => Color::ORANGE_RED,
+        }
+    }
+}
+
+fn setup_overlay(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    for layer in OverlayLayer::ALL {
+        commands
+            .spawn_bundle(PbrBundle {
+                mesh: meshes.add(LineList::default().into()),
+                material: materials.add(StandardMaterial {
+                    base_color: layer.color(),
+                    unlit: true,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            })
+            .insert(layer)
+            .insert(ViewLayer::Editor.layers())
+            .insert(Name::new("Editor Overlay"));
+    }
+}
+
+/// Rebuild the overlay line meshes from the current map data
+fn draw_overlay(
+    overlay: Res<EditorOverlay>,
+    patrols: Res<PatrolPaths>,
+    walls: Query<(&Edge, &Direction)>,
+    markers: Query<(&Location, &Marker)>,
+    mut layers: Query<(&OverlayLayer, &Handle<Mesh>, &mut Visibility)>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    for (&layer, handle, mut visibility) in layers.iter_mut() {
+        let mut lines = LineList::default();
+        let shown = overlay.enabled
+            && match layer {
+                OverlayLayer::Walls => overlay.walls,
+                _ => overlay.markers,
+            };
+
+        if shown && layer == OverlayLayer::Walls {
+            for (&edge, &dir) in walls.iter() {
+                // Walls face back into the tile they were placed on
+                let start = Vec3::from(edge) + Vec3::Y * WALL_HEIGHT * 0.5;
+                let facing = Vec3::from(dir.opposite());
+                lines.arrow(start, start + facing * TILE_SIZE * 0.4);
+            }
+        }
+
+        if shown && layer != OverlayLayer::Walls {
+            for (&location, marker) in markers.iter() {
+                let center = Vec3::from(location) + Vec3::Y * 0.01;
+                match (layer, marker) {
+                    (OverlayLayer::Spawns, Marker::Spawn) => {
+                        lines.diamond(center, TILE_SIZE * 0.3);
+                        lines.line(center, center + Vec3::Y * WALL_HEIGHT);
+                    }
+                    (OverlayLayer::Spawns, Marker::Checkpoint) => {
+                        lines.square(center, TILE_SIZE * 0.3);
+                        lines.line(center, center + Vec3::Y * WALL_HEIGHT);
+                    }
+                    (OverlayLayer::Triggers, Marker::Trigger) => {
+                        lines.square(center, TILE_SIZE * 0.45);
+                    }
+                    (OverlayLayer::Portals, &Marker::Portal { target }) => {
+                        let end = Vec3::from(target) + Vec3::Y * 0.01;
+                        let lift = Vec3::Y * WALL_HEIGHT;
+                        lines.square(center, TILE_SIZE * 0.3);
+                        lines.square(end, TILE_SIZE * 0.3);
+                        lines.line(center, center + lift);
+                        lines.line(center + lift, end + lift);
+                        lines.arrow(end + lift, end);
+                    }
+                    (OverlayLayer::Enemies, Marker::Enemy { .. }) => {
+                        lines.diamond(center, TILE_SIZE * 0.3);
+                        lines.line(center, center + Vec3::Y * WALL_HEIGHT);
+                    }
+                    (OverlayLayer::Enemies, Marker::Waypoint { .. }) => {
+                        lines.square(center, TILE_SIZE * 0.2);
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        // Join the waypoints of each patrol route in the order they are
+        // walked, looping back to the first
+        if shown && layer == OverlayLayer::Enemies {
+            let mut waypoints: Vec<(u32, u32, Location)> = markers
+                .iter()
+                .filter_map(|(&location, marker)| match *marker {
+                    Marker::Waypoint { route, order } => {
+                        Some((route, order, location))
+                    }
+                    _ => None,
+                })
+                .collect();
+            waypoints.sort_by_key(|&(route, order, _)| (route, order));
+            for (i, &(route, _, location)) in waypoints.iter().enumerate() {
+                let next = waypoints[i + 1..]
+                    .iter()
+                    .find(|&&(r, _, _)| r == route)
+                    .or_else(|| waypoints.iter().find(|&&(r, _, _)| r == route))
+                    .map(|&(_, _, next)| next);
+                match next {
+                    Some(next) if next != location => {
+                        let lift = Vec3::Y * 0.01;
+                        lines.arrow(
+                            Vec3::from(location) + lift,
+                            Vec3::from(next) + lift,
+                        );
+                    }
+                    _ => {}
+                }
+            }
+
+            // Patrol paths loop back to their first corner the same way
+            let lift = Vec3::Y * 0.01;
+            for patrol in patrols.paths.iter() {
+                let corners = patrol.path.iter().copied();
+                let next = patrol.path.iter().copied().cycle().skip(1);
+                for (from, to) in corners.zip(next) {
+                    if from != to {
+                        lines.arrow(
+                            Vec3::from(from) + lift,
+                            Vec3::from(to) + lift,
+                        );
+                    }
+                }
+            }
+        }
+
+        // Empty meshes are hidden rather than drawn with zero vertices
+        visibility.is_visible = !lines.is_empty();
+        if let Some(mesh) = meshes.get_mut(handle) {
+            *mesh = lines.into();
+        }
+    }
+}
+
+/// Collection of line segments which can be turned into a line list mesh
+#[derive(Default, Clone)]
+struct LineList {
+    positions: Vec<[f32; 3]>,
+}
+
+impl LineList {
+    fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    fn line(&mut self, start: Vec3, end: Vec3) {
+        self.positions.push(start.into());
+        self.positions.push(end.into());
+    }
+
+    /// Line with a small arrow head at `end`
+    fn arrow(&mut self, start: Vec3, end: Vec3) {
+        self.line(start, end);
+        let back = (start - end) * 0.3;
+        let side = back.cross(Vec3::Y).normalize_or_zero() * back.length();
+        let side = if side == Vec3::ZERO {
+            back.cross(Vec3::X).normalize_or_zero() * back.length()
+        } else {
+            side
+        };
+        self.line(end, end + back + side * 0.5);
+        self.line(end, end + back - side * 0.5);
+    }
+
+    /// Square outline on the ground plane
+    fn square(&mut self, center: Vec3, half: f32) {
+        let corners = [
+            center + Vec3::new(half, 0.0, half),
+            center + Vec3::new(-half, 0.0, half),
+            center + Vec3::new(-half, 0.0, -half),
+            center + Vec3::new(half, 0.0, -half),
+        ];
+        for i in 0..4 {
+            self.line(corners[i], corners[(i + 1) % 4]);
+        }
+    }
+
+    /// Diamond outline on the ground plane
+    fn diamond(&mut self, center: Vec3, half: f32) {
+        let corners = [
+            center + Vec3::X * half,
+            center + Vec3::Z * half,
+            center - Vec3::X * half,
+            center - Vec3::Z * half,
+        ];
+        for i in 0..4 {
+            self.line(corners[i], corners[(i + 1) % 4]);
+        }
+    }
+}
+
+impl From<LineList> for Mesh {
+    fn from(lines: LineList) -> Self {
+        // The PBR pipeline expects normals and uvs even though unlit lines
+        // don't need them
+        let count = lines.positions.len();
+        let mut mesh = Mesh::new(PrimitiveTopology::LineList);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, lines.positions);
+        mesh.insert_attribute(
+            Mesh::ATTRIBUTE_NORMAL,
+            vec![[0.0, 1.0, 0.0]; count],
+        );
+        mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, vec![[0.0, 0.0]; count]);
+        mesh
+    }
+}
@@ -0,0 +1,249 @@
+use super::GameMode;
+use crate::map::{ActiveMap, Map, MapCapture};
+use bevy::asset::{AssetServerSettings, FileAssetIo};
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+use iyes_loopless::prelude::*;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+pub struct AutosavePlugin;
+
+impl Plugin for AutosavePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Autosave>()
+            .add_startup_system(find_recoveries)
+            .add_system(recovery_window)
+            .add_system(autosave.run_in_state(GameMode::Editing))
+            // Once the map loaded or saved has been spawned
+            .add_system_to_stage(CoreStage::PostUpdate, track_saves);
+    }
+}
+
+/// Folder inside `maps` where autosaves are written
+const AUTOSAVE_FOLDER: &str = ".autosave";
+
+/// Seconds between autosaves while editing
+const AUTOSAVE_INTERVAL: f32 = 60.0;
+
+/// Periodically saves the map being edited so it can be recovered after a
+/// crash
+pub struct Autosave {
+    timer: Timer,
+    /// Contents of the last autosave, or of the map as it was loaded or
+    /// saved, to skip writing maps which haven't been edited
+    last: Option<String>,
+    /// Take the next capture as the map as it is on disk, instead of
+    /// autosaving it
+    baseline: bool,
+    /// Autosaves newer than their map found at launch
+    recoveries: Vec<PathBuf>,
+}
+
+impl Default for Autosave {
+    fn default() -> Self {
+        Self {
+            timer: Timer::from_seconds(AUTOSAVE_INTERVAL, true),
+            last: None,
+            baseline: false,
+            recoveries: Vec::new(),
+        }
+    }
+}
+
+/// Absolute path of the `maps` folder on disk
+fn maps_folder(settings: &AssetServerSettings) -> PathBuf {
+    FileAssetIo::get_root_path()
+        .join(&settings.asset_folder)
+        .join("maps")
+}
+
+/// File name the map is saved under, taken from the file it was loaded from
+fn file_name(
+    asset_server: &AssetServer,
+    active: &ActiveMap,
+    map: &Map,
+) -> String {
+    asset_server
+        .get_handle_path(&active.handle)
+        .and_then(|path| {
+            path.path()
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+        })
+        .unwrap_or_else(|| format!("{}.map.yaml", map.name))
+}
+
+fn autosave(
+    time: Res<Time>,
+    mut autosave: ResMut<Autosave>,
+    settings: Res<AssetServerSettings>,
+    asset_server: Res<AssetServer>,
+    active: Res<ActiveMap>,
+    maps: Res<Assets<Map>>,
+    capture: MapCapture,
+) {
+    let due = autosave.timer.tick(time.delta()).just_finished();
+    if !due && !autosave.baseline {
+        return;
+    }
+    let map = match maps.get(&active.handle) {
+        Some(map) => map,
+        None => return,
+    };
+
+    let contents = match serde_yaml::to_string(&capture.capture(&map.name)) {
+        Ok(contents) => contents,
+        Err(err) => {
+            warn!("Unable to serialize map for autosave: {:?}", err);
+            return;
+        }
+    };
+    if autosave.baseline {
+        autosave.baseline = false;
+        autosave.last = Some(contents);
+        return;
+    }
+    if autosave.last.as_ref() == Some(&contents) {
+        return;
+    }
+
+    let folder = maps_folder(&settings).join(AUTOSAVE_FOLDER);
+    let path = folder.join(file_name(&asset_server, &active, map));
+    match fs::create_dir_all(&folder).and_then(|_| fs::write(&path, &contents))
+    {
+        Ok(()) => {
+            info!("Autosaved to {:?}", path);
+            autosave.last = Some(contents);
+        }
+        Err(err) => warn!("Unable to autosave to {:?}: {:?}", path, err),
+    }
+}
+
+/// Start comparing autosaves against the map whenever another one is opened
+/// or it is saved, throwing away its autosave once it is saved
+fn track_saves(
+    mut autosave: ResMut<Autosave>,
+    mut events: EventReader<AssetEvent<Map>>,
+    settings: Res<AssetServerSettings>,
+    asset_server: Res<AssetServer>,
+    active: Res<ActiveMap>,
+    maps: Res<Assets<Map>>,
+) {
+    let mut saved = false;
+    let mut loaded = active.is_changed();
+    for event in events.iter() {
+        match event {
+            AssetEvent::Created { handle } if *handle == active.handle => {
+                loaded = true;
+            }
+            AssetEvent::Modified { handle } if *handle == active.handle => {
+                saved = true;
+            }
+            _ => {}
+        }
+    }
+    if !loaded && !saved {
+        return;
+    }
+    autosave.baseline = true;
+    autosave.last = None;
+
+    // A recovered autosave being written to is not the map being saved
+    let path = asset_server.get_handle_path(&active.handle);
+    let recovered = path.map_or(false, |path| {
+        path.path()
+            .starts_with(Path::new("maps").join(AUTOSAVE_FOLDER))
+    });
+    let map = match maps.get(&active.handle) {
+        Some(map) if saved && !recovered => map,
+        _ => return,
+    };
+    let path = maps_folder(&settings).join(AUTOSAVE_FOLDER).join(file_name(
+        &asset_server,
+        &active,
+        map,
+    ));
+    if path.exists() {
+        match fs::remove_file(&path) {
+            Ok(()) => info!("Removed autosave {:?} of saved map", path),
+            Err(err) => warn!("Unable to remove {:?}: {:?}", path, err),
+        }
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
+
+/// Look for autosaves which are newer than the map they were saved from
+fn find_recoveries(
+    mut autosave: ResMut<Autosave>,
+    settings: Res<AssetServerSettings>,
+) {
+    let maps = maps_folder(&settings);
+    let entries = match fs::read_dir(maps.join(AUTOSAVE_FOLDER)) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let saved = path.file_name().map(|name| maps.join(name));
+        let newer = match (modified(&path), saved.and_then(|p| modified(&p))) {
+            (Some(autosaved), Some(saved)) => autosaved > saved,
+            (Some(_), None) => true,
+            _ => false,
+        };
+        if newer {
+            info!("Found autosave {:?}", path);
+            autosave.recoveries.push(path);
+        }
+    }
+}
+
+/// Offer to open autosaves found at launch, or throw them away
+fn recovery_window(
+    mut egui: ResMut<EguiContext>,
+    mut autosave: ResMut<Autosave>,
+    asset_server: Res<AssetServer>,
+    mut active: ResMut<ActiveMap>,
+) {
+    if autosave.recoveries.is_empty() {
+        return;
+    }
+
+    let mut recover = None;
+    let mut discard = None;
+    egui::Window::new("Recover Autosave").show(egui.ctx_mut(), |ui| {
+        ui.label("These maps have unsaved changes from a previous session");
+        for (i, path) in autosave.recoveries.iter().enumerate() {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            ui.horizontal(|ui| {
+                ui.label(name.as_ref());
+                if ui.button("Recover").clicked() {
+                    recover = Some(i);
+                }
+                if ui.button("Discard").clicked() {
+                    discard = Some(i);
+                }
+            });
+        }
+    });
+
+    if let Some(i) = recover {
+        let path = autosave.recoveries.remove(i);
+        if let Some(name) = path.file_name() {
+            let asset = Path::new("maps").join(AUTOSAVE_FOLDER).join(name);
+            info!("Recovering {:?}", asset);
+            active.handle = asset_server.load(asset);
+        }
+    }
+    if let Some(i) = discard {
+        let path = autosave.recoveries.remove(i);
+        if let Err(err) = fs::remove_file(&path) {
+            warn!("Unable to discard {:?}: {:?}", path, err);
+        }
+    }
+}
@@ -0,0 +1,275 @@
+use super::wizard::parse_sprite;
+use super::GameMode;
+use crate::map::{
+    perimeter, Direction, Edge, Lit, Location, MapSprite, Marker, SpriteIndex,
+    Tile, TileBundle, TileKind, WallBundle,
+};
+use crate::material::{CornerColors, SpriteFlip, UnlitMaterial};
+use crate::theme::SpriteKind;
+use bevy::prelude::*;
+use bevy::utils::HashSet;
+use bevy_egui::{egui, EguiContext};
+use iyes_loopless::prelude::*;
+
+pub struct BoundsPlugin;
+
+impl Plugin for BoundsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<BoundsOperation>()
+            .add_event::<GenerateWalls>()
+            .init_resource::<BoundsWindow>()
+            .add_system_set(
+                ConditionSet::new()
+                    .run_in_state(GameMode::Editing)
+                    .with_system(bounds_window)
+                    .with_system(shift)
+                    .with_system(resize)
+                    .with_system(generate_walls)
+                    .into(),
+            );
+    }
+}
+
+/// Edits which move or resize the whole map at once
+#[derive(Debug, Clone, Copy)]
+pub enum BoundsOperation {
+    /// Translate everything on the grid
+    Shift { x: i32, y: i32 },
+    /// Grow (positive) or shrink (negative) one side of the map by a number
+    /// of tiles, carrying the walls on that side along with it
+    Resize { side: Direction, amount: i32 },
+}
+
+/// Enclose every floor region with walls drawn with a sprite, leaving
+/// existing walls in place
+#[derive(Debug, Clone)]
+pub struct GenerateWalls(pub MapSprite);
+
+/// Signed distance of a location along a direction, increasing towards it
+fn along(location: Location, dir: Direction) -> i32 {
+    let (x, y) = dir.offset();
+    location.x * x + location.y * y
+}
+
+fn shift(
+    mut events: EventReader<BoundsOperation>,
+    mut locations: Query<&mut Location>,
+    mut edges: Query<&mut Edge>,
+    mut markers: Query<&mut Marker>,
+) {
+    for event in events.iter() {
+        let (x, y) = match *event {
+            BoundsOperation::Shift { x, y } => (x, y),
+            _ => continue,
+        };
+
+        for mut location in locations.iter_mut() {
+            *location = location.offset(x, y);
+        }
+        for mut edge in edges.iter_mut() {
+            edge.location = edge.location.offset(x, y);
+        }
+        for mut marker in markers.iter_mut() {
+            if let Marker::Portal { target } = &mut *marker {
+                *target = target.offset(x, y);
+            }
+        }
+    }
+}
+
+type TileSprite<'a> = (
+    Option<&'a Handle<UnlitMaterial>>,
+    Option<&'a SpriteIndex>,
+    Option<&'a SpriteKind>,
+    Option<&'a Lit>,
+    Option<&'a SpriteFlip>,
+    Option<&'a CornerColors>,
+    Option<&'a TileKind>,
+);
+
+fn resize(
+    mut commands: Commands,
+    mut events: EventReader<BoundsOperation>,
+    tiles: Query<(Entity, &Location, TileSprite), With<Tile>>,
+    markers: Query<(Entity, &Location), With<Marker>>,
+    mut walls: Query<(Entity, &mut Edge)>,
+) {
+    for event in events.iter() {
+        let (side, amount) = match *event {
+            BoundsOperation::Resize { side, amount } => (side, amount),
+            _ => continue,
+        };
+
+        let boundary = match tiles.iter().map(|(_, &l, _)| along(l, side)).max()
+        {
+            Some(boundary) => boundary,
+            None => continue,
+        };
+        let new_boundary = boundary + amount;
+        let (x, y) = side.offset();
+
+        // Extend every row or column which reaches the boundary
+        for (
+            _,
+            &location,
+            (material, index, kind, lit, flip, colors, tile_kind),
+        ) in tiles.iter()
+        {
+            if along(location, side) != boundary {
+                continue;
+            }
+            for step in 1..=amount {
+                let mut tile = commands.spawn_bundle(TileBundle::new(
+                    location.offset(x * step, y * step),
+                    material.cloned().unwrap_or_default(),
+                ));
+                if let Some(&index) = index {
+                    tile.insert(index);
+                }
+                if let Some(kind) = kind {
+                    tile.insert(kind.clone());
+                }
+                if lit.is_some() {
+                    tile.insert(Lit);
+                }
+                if let Some(&flip) = flip {
+                    tile.insert(flip);
+                }
+                if let Some(&colors) = colors {
+                    tile.insert(colors);
+                }
+                if let Some(&tile_kind) = tile_kind {
+                    tile.insert(tile_kind);
+                }
+            }
+        }
+
+        // Remove everything beyond a shrunk boundary
+        let outside = |l: Location| along(l, side) > new_boundary;
+        for (entity, location, _) in tiles.iter() {
+            if outside(*location) {
+                commands.entity(entity).despawn_recursive();
+            }
+        }
+        for (entity, location) in markers.iter() {
+            if outside(*location) {
+                commands.entity(entity).despawn_recursive();
+            }
+        }
+
+        for (entity, mut edge) in walls.iter_mut() {
+            let (a, b) = edge.tiles();
+            let near = along(a, side).min(along(b, side));
+            let far = along(a, side).max(along(b, side));
+            if near == boundary && far == boundary + 1 {
+                // Walls along the boundary follow it
+                edge.location = edge.location.offset(x * amount, y * amount);
+            } else if outside(a) || outside(b) {
+                commands.entity(entity).despawn_recursive();
+            }
+        }
+    }
+}
+
+fn generate_walls(
+    mut commands: Commands,
+    mut events: EventReader<GenerateWalls>,
+    tiles: Query<&Location, With<Tile>>,
+    walls: Query<&Edge>,
+) {
+    for GenerateWalls(sprite) in events.iter() {
+        let floor: HashSet<Location> = tiles.iter().copied().collect();
+        let placed: HashSet<Edge> = walls.iter().copied().collect();
+
+        let mut count = 0;
+        for (location, direction) in perimeter(&floor) {
+            if placed.contains(&Edge::new(location, direction)) {
+                continue;
+            }
+            let mut wall = commands.spawn_bundle(WallBundle::new(
+                location,
+                direction,
+                Handle::default(),
+            ));
+            sprite.insert(&mut wall);
+            count += 1;
+        }
+        info!("Generated {} walls", count);
+    }
+}
+
+/// Settings for the operations in the "Map Bounds" window
+struct BoundsWindow {
+    shift: (i32, i32),
+    side: Option<Direction>,
+    amount: i32,
+    wall: String,
+}
+
+impl Default for BoundsWindow {
+    fn default() -> Self {
+        Self {
+            shift: (0, 0),
+            side: None,
+            amount: 0,
+            wall: "brick".into(),
+        }
+    }
+}
+
+fn bounds_window(
+    mut egui: ResMut<EguiContext>,
+    mut window: ResMut<BoundsWindow>,
+    mut operations: EventWriter<BoundsOperation>,
+    mut generate: EventWriter<GenerateWalls>,
+) {
+    egui::Window::new("Map Bounds").show(egui.ctx_mut(), |ui| {
+        ui.horizontal(|ui| {
+            ui.label("Shift");
+            ui.add(egui::DragValue::new(&mut window.shift.0).prefix("x "));
+            ui.add(egui::DragValue::new(&mut window.shift.1).prefix("y "));
+            if ui.button("Apply").clicked() {
+                let (x, y) = window.shift;
+                operations.send(BoundsOperation::Shift { x, y });
+            }
+        });
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.label("Side");
+            for (side, name) in [
+                (Direction::NegativeX, "-x"),
+                (Direction::PositiveX, "+x"),
+                (Direction::NegativeY, "-y"),
+                (Direction::PositiveY, "+y"),
+            ] {
+                ui.selectable_value(&mut window.side, Some(side), name);
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("Grow by");
+            ui.add(egui::DragValue::new(&mut window.amount));
+            let side = window.side;
+            if ui
+                .add_enabled(side.is_some(), egui::Button::new("Apply"))
+                .clicked()
+            {
+                if let Some(side) = side {
+                    operations.send(BoundsOperation::Resize {
+                        side,
+                        amount: window.amount,
+                    });
+                }
+            }
+        });
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.label("Wall sprite");
+            ui.text_edit_singleline(&mut window.wall);
+            if ui.button("Enclose floors").clicked() {
+                generate.send(GenerateWalls(parse_sprite(&window.wall)));
+            }
+        });
+    });
+}
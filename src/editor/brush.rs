@@ -0,0 +1,270 @@
+use super::layers::MapLayers;
+use super::GameMode;
+use crate::map::{
+    Direction, Edge, Location, MapLayer, MapSprites, SpriteIndex, Tile,
+    TILE_SIZE,
+};
+use crate::material::SpriteSheet;
+use crate::select::{HoveredPoint, TileClicked};
+use crate::theme::SpriteKind;
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+use iyes_loopless::prelude::*;
+
+pub struct BrushPlugin;
+
+impl Plugin for BrushPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ActiveBrush>()
+            .init_resource::<EditorTool>()
+            .add_system_set(
+                ConditionSet::new()
+                    .run_in_state(GameMode::Editing)
+                    .with_system(sprite_browser)
+                    .with_system(paint)
+                    .with_system(pick)
+                    .into(),
+            );
+    }
+}
+
+/// Sprite painted by the editor's painting tools
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ActiveBrush {
+    pub layer: MapLayer,
+    pub sprite: SpriteIndex,
+    /// Only paint the wall on this side of clicked tiles, instead of every
+    /// wall around them
+    pub side: Option<Direction>,
+}
+
+impl Default for ActiveBrush {
+    fn default() -> Self {
+        Self {
+            layer: MapLayer::Tiles,
+            sprite: SpriteIndex(0),
+            side: None,
+        }
+    }
+}
+
+/// What clicking on the map does while editing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EditorTool {
+    #[default]
+    Select,
+    /// Apply the [`ActiveBrush`] to clicked tiles
+    Paint,
+    /// Place the prop chosen in the "Props" window on clicked tiles
+    Place,
+    /// Copy the sprite of the clicked tile or wall into the [`ActiveBrush`]
+    Pick,
+}
+
+/// Paint the active brush onto clicked tiles, or the walls around them
+fn paint(
+    mut commands: Commands,
+    tool: Res<EditorTool>,
+    brush: Res<ActiveBrush>,
+    layers: Res<MapLayers>,
+    mut clicks: EventReader<TileClicked>,
+    tiles: Query<(Entity, &Location), With<Tile>>,
+    walls: Query<(Entity, &Edge)>,
+) {
+    if *tool != EditorTool::Paint || layers.get(brush.layer).locked {
+        return;
+    }
+
+    for click in clicks.iter() {
+        if click.button != MouseButton::Left {
+            continue;
+        }
+
+        let painted: Vec<Entity> = match brush.layer {
+            MapLayer::Tiles => tiles
+                .iter()
+                .filter(|(_, &location)| location == click.location)
+                .map(|(entity, _)| entity)
+                .collect(),
+            MapLayer::Walls => walls
+                .iter()
+                .filter(|(_, &edge)| match brush.side {
+                    Some(side) => edge == Edge::new(click.location, side),
+                    None => {
+                        let (a, b) = edge.tiles();
+                        a == click.location || b == click.location
+                    }
+                })
+                .map(|(entity, _)| entity)
+                .collect(),
+        };
+
+        // Painted sprites are no longer tied to a theme's sprite kinds
+        for entity in painted {
+            commands
+                .entity(entity)
+                .remove::<SpriteKind>()
+                .insert(brush.sprite);
+        }
+    }
+}
+
+/// How close to a wall, in tiles, a click has to be to pick the wall instead
+/// of the tile
+const PICK_WALL_DISTANCE: f32 = 0.25;
+
+/// Set the active brush from the tile or wall nearest to the click
+fn pick(
+    mut tool: ResMut<EditorTool>,
+    mut brush: ResMut<ActiveBrush>,
+    point: Res<HoveredPoint>,
+    mut clicks: EventReader<TileClicked>,
+    tiles: Query<(&Location, &SpriteIndex), With<Tile>>,
+    walls: Query<(&Edge, &SpriteIndex)>,
+) {
+    if *tool != EditorTool::Pick {
+        return;
+    }
+
+    for click in clicks.iter() {
+        if click.button != MouseButton::Left {
+            continue;
+        }
+
+        // Offset of the click from the middle of the tile, in tiles
+        let center = Vec3::from(click.location);
+        let offset = point.0.map_or(Vec3::ZERO, |p| (p - center) / TILE_SIZE);
+        let wall = Direction::ALL
+            .into_iter()
+            .filter_map(|side| {
+                let edge = Edge::new(click.location, side);
+                let (_, sprite) = walls.iter().find(|(&e, _)| e == edge)?;
+                let dist = 0.5 - offset.dot(side.into());
+                Some((dist, side, *sprite))
+            })
+            .min_by(|a, b| a.0.total_cmp(&b.0));
+
+        let tile = tiles
+            .iter()
+            .find(|(&location, _)| location == click.location)
+            .map(|(_, &sprite)| sprite);
+
+        *brush = match (wall, tile) {
+            (Some((dist, side, sprite)), _)
+                if dist <= PICK_WALL_DISTANCE || tile.is_none() =>
+            {
+                ActiveBrush {
+                    layer: MapLayer::Walls,
+                    sprite,
+                    side: Some(side),
+                }
+            }
+            (_, Some(sprite)) => ActiveBrush {
+                layer: MapLayer::Tiles,
+                sprite,
+                side: None,
+            },
+            _ => continue,
+        };
+        *tool = EditorTool::Paint;
+    }
+}
+
+/// Size of each sprite button in the browser
+const THUMBNAIL_SIZE: f32 = 40.0;
+
+/// Window showing every sprite in the map's sheets, clicking one selects it
+/// as the active brush
+fn sprite_browser(
+    mut egui: ResMut<EguiContext>,
+    sprites: Res<MapSprites>,
+    mut brush: ResMut<ActiveBrush>,
+    mut tool: ResMut<EditorTool>,
+) {
+    let tile_texture = egui.add_image(sprites.tiles.texture.clone_weak());
+    let wall_texture = egui.add_image(sprites.walls.texture.clone_weak());
+
+    egui::Window::new("Sprites").show(egui.ctx_mut(), |ui| {
+        ui.horizontal(|ui| {
+            ui.label("Tool");
+            ui.selectable_value(&mut *tool, EditorTool::Select, "Select");
+            ui.selectable_value(&mut *tool, EditorTool::Paint, "Paint");
+            ui.selectable_value(&mut *tool, EditorTool::Place, "Place");
+            ui.selectable_value(&mut *tool, EditorTool::Pick, "Pick");
+        });
+
+        if brush.layer == MapLayer::Walls {
+            ui.horizontal(|ui| {
+                ui.label("Side");
+                ui.selectable_value(&mut brush.side, None, "All");
+                for (side, name) in [
+                    (Direction::NegativeX, "-x"),
+                    (Direction::PositiveX, "+x"),
+                    (Direction::NegativeY, "-y"),
+                    (Direction::PositiveY, "+y"),
+                ] {
+                    ui.selectable_value(&mut brush.side, Some(side), name);
+                }
+            });
+        }
+
+        for (layer, name, sheet, texture) in [
+            (MapLayer::Tiles, "Tiles", &sprites.tiles, tile_texture),
+            (MapLayer::Walls, "Walls", &sprites.walls, wall_texture),
+        ] {
+            ui.collapsing(name, |ui| {
+                if let Some(sprite) =
+                    sheet_grid(ui, name, sheet, texture, *brush, layer)
+                {
+                    *brush = ActiveBrush {
+                        layer,
+                        sprite,
+                        side: brush.side.filter(|_| brush.layer == layer),
+                    };
+                    *tool = EditorTool::Paint;
+                }
+            });
+        }
+    });
+}
+
+/// Grid of sprite buttons, returns the sprite which was clicked
+fn sheet_grid(
+    ui: &mut egui::Ui,
+    name: &str,
+    sheet: &SpriteSheet,
+    texture: egui::TextureId,
+    brush: ActiveBrush,
+    layer: MapLayer,
+) -> Option<SpriteIndex> {
+    let mut clicked = None;
+    egui::Grid::new(name).show(ui, |ui| {
+        for index in 0..sheet.len() {
+            let rect = sheet.rect(index);
+            let uv = egui::Rect::from_min_size(
+                egui::pos2(rect.offset.x, rect.offset.y),
+                egui::vec2(rect.size.x, rect.size.y),
+            );
+            let selected =
+                brush.layer == layer && brush.sprite == SpriteIndex(index);
+
+            ui.vertical_centered(|ui| {
+                let button = egui::ImageButton::new(
+                    texture,
+                    [THUMBNAIL_SIZE, THUMBNAIL_SIZE],
+                )
+                .uv(uv)
+                .selected(selected);
+                if ui.add(button).clicked() {
+                    clicked = Some(SpriteIndex(index));
+                }
+                ui.small(index.to_string());
+            });
+
+            if (index + 1) % sheet.columns.max(1) == 0 {
+                ui.end_row();
+            }
+        }
+    });
+    clicked
+}
@@ -0,0 +1,136 @@
+use super::GameMode;
+use crate::camera::{
+    CameraInputConfig, CameraSettings, ControllerBasis, Follow,
+    IsometricCamera, Turn, YawPitchControls, Zoom,
+};
+use bevy::prelude::*;
+use bevy_egui::EguiContext;
+use iyes_loopless::prelude::*;
+
+pub struct EditorCameraPlugin;
+
+impl Plugin for EditorCameraPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameplayCamera>()
+            .add_enter_system(GameMode::Editing, GameplayCamera::stash)
+            .add_exit_system(GameMode::Editing, GameplayCamera::restore)
+            .add_system_set(
+                ConditionSet::new()
+                    .run_in_state(GameMode::Editing)
+                    .with_system(pan)
+                    .into(),
+            );
+    }
+}
+
+/// Pan speed in world units per second at the default zoom
+const PAN_SPEED: f32 = 1.0;
+
+/// State of the gameplay camera while the editor camera is in control
+#[derive(Default)]
+pub struct GameplayCamera {
+    saved: Option<(YawPitchControls, f32, Option<Follow>)>,
+    /// Whether gameplay kept the camera over the map, which the editor
+    /// doesn't so the map can grow into the void
+    clamp_to_map: bool,
+    /// Whether gameplay scrolled the view at the edges of the window, which
+    /// the editor always does
+    edge_scroll: bool,
+}
+
+impl GameplayCamera {
+    /// Detach the camera from its follow target and the map while editing
+    fn stash(
+        mut commands: Commands,
+        mut stashed: ResMut<GameplayCamera>,
+        mut settings: ResMut<CameraSettings>,
+        cameras: Query<
+            (Entity, &YawPitchControls, &Zoom, Option<&Follow>),
+            With<IsometricCamera>,
+        >,
+    ) {
+        if let Ok((entity, controls, zoom, follow)) = cameras.get_single() {
+            stashed.saved =
+                Some((controls.clone(), zoom.target, follow.cloned()));
+            commands.entity(entity).remove::<Follow>();
+        }
+        stashed.clamp_to_map = settings.clamp_to_map;
+        settings.clamp_to_map = false;
+        stashed.edge_scroll = settings.edge_scroll;
+        settings.edge_scroll = true;
+    }
+
+    /// Put the camera back the way gameplay left it
+    fn restore(
+        mut commands: Commands,
+        mut stashed: ResMut<GameplayCamera>,
+        mut settings: ResMut<CameraSettings>,
+        mut cameras: Query<
+            (Entity, &mut YawPitchControls, &mut Zoom),
+            With<IsometricCamera>,
+        >,
+    ) {
+        settings.clamp_to_map = stashed.clamp_to_map;
+        settings.edge_scroll = stashed.edge_scroll;
+        let (saved, scale, follow) = match stashed.saved.take() {
+            Some(saved) => saved,
+            None => return,
+        };
+        if let Ok((entity, mut controls, mut zoom)) = cameras.get_single_mut() {
+            *controls = saved;
+            zoom.target = scale;
+            // A turn still running would carry on from the editor's view
+            commands.entity(entity).remove::<Turn>();
+            if let Some(follow) = follow {
+                commands.entity(entity).insert(follow);
+            }
+        }
+    }
+}
+
+/// Move the focus across the ground plane with the pan keys, relative to the
+/// view
+fn pan(
+    mut commands: Commands,
+    time: Res<Time>,
+    bindings: Res<CameraInputConfig>,
+    keys: Res<Input<KeyCode>>,
+    mut egui: ResMut<EguiContext>,
+    mut cameras: Query<
+        (
+            Entity,
+            &ControllerBasis,
+            &OrthographicProjection,
+            &mut YawPitchControls,
+        ),
+        With<IsometricCamera>,
+    >,
+) {
+    if egui.ctx_mut().wants_keyboard_input() {
+        return;
+    }
+
+    let mut input = Vec2::ZERO;
+    for (key, dir) in [
+        (bindings.pan_up, Vec2::Y),
+        (bindings.pan_down, -Vec2::Y),
+        (bindings.pan_right, Vec2::X),
+        (bindings.pan_left, -Vec2::X),
+    ] {
+        if keys.pressed(key) {
+            input += dir;
+        }
+    }
+    if input == Vec2::ZERO {
+        return;
+    }
+
+    for (entity, basis, projection, mut controls) in cameras.iter_mut() {
+        let speed = PAN_SPEED * projection.scale * time.delta_seconds();
+        let delta = controls.ground_input(basis, input).normalize_or_zero();
+        controls.focus += delta * speed;
+
+        // Panning takes over from whatever the camera was following
+        commands.entity(entity).remove::<Follow>();
+    }
+}
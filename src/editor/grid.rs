@@ -0,0 +1,156 @@
+use super::{GameMode, LineList};
+use crate::camera::{IsometricCamera, YawPitchControls};
+use crate::map::TILE_SIZE;
+use crate::material::ViewLayer;
+use bevy::prelude::*;
+use bevy::render::view::NoFrustumCulling;
+use bevy_inspector_egui::{Inspectable, RegisterInspectable};
+use iyes_loopless::prelude::*;
+
+pub struct GridPlugin;
+
+impl Plugin for GridPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_inspectable::<GridOverlay>()
+            .init_resource::<GridOverlay>()
+            .add_startup_system(setup_grid)
+            .add_system(toggle_grid)
+            .add_system(rebuild_grid.after(toggle_grid))
+            .add_system(follow_focus.after(rebuild_grid));
+    }
+}
+
+/// Lines along the tile edges on the ground plane, drawn around the camera
+/// focus so the grid seems to go on forever
+#[derive(Inspectable, Debug, Clone)]
+pub struct GridOverlay {
+    pub enabled: bool,
+    /// Keep drawing the grid after switching to play the map
+    pub playing: bool,
+    /// Tiles between each line
+    #[inspectable(min = 1, max = 16)]
+    pub spacing: u32,
+    /// Lines drawn either side of the focus
+    #[inspectable(min = 1, max = 128)]
+    pub lines: u32,
+    pub color: Color,
+}
+
+impl Default for GridOverlay {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            playing: false,
+            spacing: 1,
+            lines: 24,
+            color: Color::rgba(1.0, 1.0, 1.0, 0.15),
+        }
+    }
+}
+
+/// Key which shows or hides the grid
+const TOGGLE_GRID: KeyCode = KeyCode::F3;
+
+/// Just above blob shadows, which sit just above the tiles
+const GRID_LIFT: f32 = 0.002;
+
+/// Marker for the line mesh drawing the [`GridOverlay`]
+#[derive(Component)]
+struct GridLines;
+
+fn setup_grid(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands
+        .spawn_bundle(PbrBundle {
+            mesh: meshes.add(LineList::default().into()),
+            material: materials.add(StandardMaterial {
+                alpha_mode: AlphaMode::Blend,
+                unlit: true,
+                ..Default::default()
+            }),
+            ..Default::default()
+        })
+        // Bounds are never recalculated as the lines are rebuilt
+        .insert(NoFrustumCulling)
+        .insert(GridLines)
+        .insert(ViewLayer::Editor.layers())
+        .insert(Name::new("Grid Overlay"));
+}
+
+fn toggle_grid(keys: Res<Input<KeyCode>>, mut grid: ResMut<GridOverlay>) {
+    if keys.just_pressed(TOGGLE_GRID) {
+        grid.enabled = !grid.enabled;
+    }
+}
+
+/// Rebuild the grid around the origin whenever its settings change, it is
+/// moved under the focus separately
+fn rebuild_grid(
+    grid: Res<GridOverlay>,
+    lines: Query<(&Handle<Mesh>, &Handle<StandardMaterial>), With<GridLines>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    if !grid.is_changed() {
+        return;
+    }
+
+    let spacing = grid.spacing.max(1) as f32 * TILE_SIZE;
+    let count = grid.lines.max(1) as i32;
+    let reach = spacing * count as f32;
+    let mut list = LineList::default();
+    for i in -count..=count {
+        let offset = i as f32 * spacing;
+        list.line(
+            Vec3::new(offset, 0.0, -reach),
+            Vec3::new(offset, 0.0, reach),
+        );
+        list.line(
+            Vec3::new(-reach, 0.0, offset),
+            Vec3::new(reach, 0.0, offset),
+        );
+    }
+
+    for (mesh, material) in lines.iter() {
+        if let Some(mesh) = meshes.get_mut(mesh) {
+            *mesh = list.clone().into();
+        }
+        if let Some(material) = materials.get_mut(material) {
+            material.base_color = grid.color;
+        }
+    }
+}
+
+/// Snap the grid to the line nearest the camera focus, and hide it when it
+/// is turned off
+fn follow_focus(
+    grid: Res<GridOverlay>,
+    mode: Res<CurrentState<GameMode>>,
+    cameras: Query<&YawPitchControls, With<IsometricCamera>>,
+    mut lines: Query<(&mut Transform, &mut Visibility), With<GridLines>>,
+) {
+    let shown = grid.enabled && (grid.playing || mode.0 == GameMode::Editing);
+    let focus = cameras
+        .iter()
+        .next()
+        .map_or(Vec3::ZERO, |controls| controls.focus);
+
+    // Tiles are centered on multiples of the tile size, so lines sit half a
+    // tile off to follow their edges
+    let spacing = grid.spacing.max(1) as f32 * TILE_SIZE;
+    let half = TILE_SIZE * 0.5;
+    let snap = |v: f32| ((v - half) / spacing).round() * spacing + half;
+    let translation = Vec3::new(snap(focus.x), GRID_LIFT, snap(focus.z));
+
+    for (mut transform, mut visibility) in lines.iter_mut() {
+        if visibility.is_visible != shown {
+            visibility.is_visible = shown;
+        }
+        if transform.translation != translation {
+            transform.translation = translation;
+        }
+    }
+}
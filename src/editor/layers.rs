@@ -0,0 +1,137 @@
+use super::GameMode;
+use crate::map::{Edge, LayerOrder, MapLayer, Tile};
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+use iyes_loopless::prelude::*;
+
+pub struct LayersPlugin;
+
+impl Plugin for LayersPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MapLayers>()
+            .add_exit_system(GameMode::Editing, show_layers)
+            .add_system_set(
+                ConditionSet::new()
+                    .run_in_state(GameMode::Editing)
+                    .with_system(layers_window)
+                    .with_system(hide_layers)
+                    .into(),
+            );
+    }
+}
+
+/// Editor-only state of one layer of the map
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LayerState {
+    pub visible: bool,
+    /// Locked layers ignore painting
+    pub locked: bool,
+}
+
+impl Default for LayerState {
+    fn default() -> Self {
+        Self {
+            visible: true,
+            locked: false,
+        }
+    }
+}
+
+/// Visibility and lock flags for every layer while editing, the order they
+/// are drawn in is the map's [`LayerOrder`]
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct MapLayers {
+    pub tiles: LayerState,
+    pub walls: LayerState,
+}
+
+impl MapLayers {
+    pub fn get(&self, layer: MapLayer) -> LayerState {
+        match layer {
+            MapLayer::Tiles => self.tiles,
+            MapLayer::Walls => self.walls,
+        }
+    }
+
+    fn get_mut(&mut self, layer: MapLayer) -> &mut LayerState {
+        match layer {
+            MapLayer::Tiles => &mut self.tiles,
+            MapLayer::Walls => &mut self.walls,
+        }
+    }
+}
+
+/// Hide layers toggled off in the panel, including anything newly placed
+fn hide_layers(
+    layers: Res<MapLayers>,
+    added: Query<(), Or<(Added<Tile>, Added<Edge>)>>,
+    mut placed: Query<
+        (&mut Visibility, Option<&Edge>),
+        Or<(With<Tile>, With<Edge>)>,
+    >,
+) {
+    if !layers.is_changed() && added.is_empty() {
+        return;
+    }
+
+    for (mut visibility, edge) in placed.iter_mut() {
+        let layer = if edge.is_some() {
+            layers.walls
+        } else {
+            layers.tiles
+        };
+        visibility.is_visible = layer.visible;
+    }
+}
+
+/// Hidden layers are only hidden in the editor
+fn show_layers(
+    mut placed: Query<&mut Visibility, Or<(With<Tile>, With<Edge>)>>,
+) {
+    for mut visibility in placed.iter_mut() {
+        visibility.is_visible = true;
+    }
+}
+
+/// List the layers from the top down, like an image editor, with their
+/// flags and buttons to move them up and down
+fn layers_window(
+    mut egui: ResMut<EguiContext>,
+    mut layers: ResMut<MapLayers>,
+    mut order: ResMut<LayerOrder>,
+) {
+    // Only flag a change when a checkbox or button was actually used
+    let mut edited = layers.clone();
+    let mut reordered = order.clone();
+    egui::Window::new("Layers").show(egui.ctx_mut(), |ui| {
+        egui::Grid::new("layers").show(ui, |ui| {
+            let count = order.0.len();
+            for (index, &layer) in order.0.iter().enumerate().rev() {
+                let state = edited.get_mut(layer);
+                ui.label(layer.name());
+                ui.checkbox(&mut state.visible, "Visible");
+                ui.checkbox(&mut state.locked, "Locked");
+                if ui
+                    .add_enabled(index + 1 < count, egui::Button::new("Up"))
+                    .clicked()
+                {
+                    reordered.swap(index, true);
+                }
+                if ui
+                    .add_enabled(index > 0, egui::Button::new("Down"))
+                    .clicked()
+                {
+                    reordered.swap(index, false);
+                }
+                ui.end_row();
+            }
+        });
+    });
+
+    if edited != *layers {
+        *layers = edited;
+    }
+    if reordered != *order {
+        *order = reordered;
+    }
+}
@@ -0,0 +1,78 @@
+use super::GameMode;
+use crate::material::{MaterialAllocator, SpriteMeshKey};
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy_egui::{egui, EguiContext};
+use iyes_loopless::prelude::*;
+
+pub struct MaterialsPlugin;
+
+impl Plugin for MaterialsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(material_preview.run_in_state(GameMode::Editing));
+    }
+}
+
+/// Size of each sprite thumbnail
+const THUMBNAIL_SIZE: f32 = 32.0;
+
+/// Window listing every sprite mesh in the [`MaterialAllocator`] with the
+/// part of the sheet it draws, to make atlas and indexing bugs easy to spot
+fn material_preview(
+    mut egui: ResMut<EguiContext>,
+    allocator: Res<MaterialAllocator>,
+    asset_server: Res<AssetServer>,
+    users: Query<&Handle<Mesh>>,
+) {
+    let mut counts: HashMap<Handle<Mesh>, usize> = HashMap::default();
+    for handle in users.iter() {
+        *counts.entry(handle.clone_weak()).or_default() += 1;
+    }
+
+    let mut entries: Vec<_> = allocator
+        .iter()
+        .map(|(SpriteMeshKey { sheet, index, .. }, handle)| {
+            let path = asset_server
+                .get_handle_path(&sheet.texture)
+                .map(|path| path.path().display().to_string())
+                .unwrap_or_else(|| format!("{:?}", sheet.texture.id));
+            let texture = egui.add_image(sheet.texture.clone_weak());
+            let thumbnail = (texture, sheet.rect(*index));
+            (path, *index, sheet.columns, sheet.rows, handle, thumbnail)
+        })
+        .collect();
+    entries.sort_by(|a, b| (&a.0, a.1).cmp(&(&b.0, b.1)));
+
+    egui::Window::new("Materials").show(egui.ctx_mut(), |ui| {
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            egui::Grid::new("materials").striped(true).show(ui, |ui| {
+                ui.strong("Sprite");
+                ui.strong("Index");
+                ui.strong("Sheet");
+                ui.strong("Users");
+                ui.end_row();
+
+                for (path, index, columns, rows, handle, (texture, rect)) in
+                    entries.iter()
+                {
+                    let uv = egui::Rect::from_min_size(
+                        egui::pos2(rect.offset.x, rect.offset.y),
+                        egui::vec2(rect.size.x, rect.size.y),
+                    );
+                    ui.add(
+                        egui::Image::new(
+                            *texture,
+                            [THUMBNAIL_SIZE, THUMBNAIL_SIZE],
+                        )
+                        .uv(uv),
+                    );
+                    ui.label(index.to_string());
+                    ui.label(format!("{} ({}x{})", path, columns, rows));
+                    let count = counts.get(*handle).copied().unwrap_or(0);
+                    ui.label(count.to_string());
+                    ui.end_row();
+                }
+            });
+        });
+    });
+}
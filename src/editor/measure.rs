@@ -0,0 +1,70 @@
+use super::GameMode;
+use crate::map::Location;
+use crate::select::{HoveredTile, TileClicked};
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+use iyes_loopless::prelude::*;
+
+pub struct MeasurePlugin;
+
+impl Plugin for MeasurePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LastClicked>().add_system_set(
+            ConditionSet::new()
+                .run_in_state(GameMode::Editing)
+                .with_system(track_clicks)
+                .with_system(coordinate_readout)
+                .into(),
+        );
+    }
+}
+
+/// Tile which distances are measured from
+#[derive(Default, Debug)]
+pub struct LastClicked(pub Option<Location>);
+
+fn track_clicks(
+    mut clicks: EventReader<TileClicked>,
+    mut last: ResMut<LastClicked>,
+) {
+    if let Some(click) = clicks.iter().last() {
+        last.0 = Some(click.location);
+    }
+}
+
+/// Small overlay in the corner with the hovered tile's coordinates and its
+/// distance from the last clicked tile
+fn coordinate_readout(
+    mut egui: ResMut<EguiContext>,
+    hovered: Res<HoveredTile>,
+    last: Res<LastClicked>,
+) {
+    let hovered = match hovered.0 {
+        Some(hovered) => hovered,
+        None => return,
+    };
+
+    egui::Area::new("coordinate readout")
+        .anchor(egui::Align2::LEFT_BOTTOM, [8.0, -8.0])
+        .interactable(false)
+        .show(egui.ctx_mut(), |ui| {
+            let world = Vec3::from(hovered);
+            ui.monospace(format!("tile  ({}, {})", hovered.x, hovered.y));
+            ui.monospace(format!(
+                "world ({:.2}, {:.2}, {:.2})",
+                world.x, world.y, world.z
+            ));
+
+            if let Some(from) = last.0 {
+                let (dx, dy) = (hovered.x - from.x, hovered.y - from.y);
+                let straight = ((dx * dx + dy * dy) as f32).sqrt();
+                ui.monospace(format!(
+                    "from  ({}, {}): {:.2} tiles, {} steps",
+                    from.x,
+                    from.y,
+                    straight,
+                    dx.abs() + dy.abs()
+                ));
+            }
+        });
+}
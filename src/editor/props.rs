@@ -0,0 +1,236 @@
+use super::brush::EditorTool;
+use super::{GameMode, LineList};
+use crate::map::{
+    Direction, Location, Prop, PropBundle, Tile, WallMesh, TILE_SIZE,
+    WALL_HEIGHT,
+};
+use crate::material::{HologramMaterial, PlacementValidity, ViewLayer};
+use crate::select::{HoveredTile, TileClicked};
+use crate::theme::{ActiveTheme, SpriteKind, Theme};
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+use iyes_loopless::prelude::*;
+
+pub struct PropsPlugin;
+
+impl Plugin for PropsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PropPlacer>()
+            .add_startup_system(setup_ghost)
+            .add_exit_system(GameMode::Editing, hide_ghost)
+            .add_system_set(
+                ConditionSet::new()
+                    .run_in_state(GameMode::Editing)
+                    .with_system(prop_window)
+                    .with_system(rotate)
+                    .with_system(place)
+                    .with_system(update_ghost)
+                    .into(),
+            );
+    }
+}
+
+/// Prop placed by the [`EditorTool::Place`] tool and which way it faces
+#[derive(Debug, Clone)]
+pub struct PropPlacer {
+    pub kind: Option<String>,
+    pub direction: Direction,
+}
+
+impl Default for PropPlacer {
+    fn default() -> Self {
+        Self {
+            kind: None,
+            direction: Direction::PositiveX,
+        }
+    }
+}
+
+/// Key which turns the prop to the next direction
+const ROTATE_PROP: KeyCode = KeyCode::R;
+
+fn rotate(
+    keys: Res<Input<KeyCode>>,
+    tool: Res<EditorTool>,
+    mut placer: ResMut<PropPlacer>,
+) {
+    if *tool != EditorTool::Place || !keys.just_pressed(ROTATE_PROP) {
+        return;
+    }
+
+    let next = Direction::ALL
+        .iter()
+        .position(|&dir| dir == placer.direction)
+        .map_or(0, |i| (i + 1) % Direction::ALL.len());
+    placer.direction = Direction::ALL[next];
+}
+
+/// Props stand on floor tiles, one to a tile
+fn can_place(
+    location: Location,
+    tiles: &Query<&Location, With<Tile>>,
+    props: &Query<&Location, With<Prop>>,
+) -> bool {
+    tiles.iter().any(|&tile| tile == location)
+        && !props.iter().any(|&prop| prop == location)
+}
+
+fn place(
+    mut commands: Commands,
+    tool: Res<EditorTool>,
+    placer: Res<PropPlacer>,
+    mut clicks: EventReader<TileClicked>,
+    tiles: Query<&Location, With<Tile>>,
+    props: Query<&Location, With<Prop>>,
+) {
+    if *tool != EditorTool::Place {
+        return;
+    }
+    let kind = match &placer.kind {
+        Some(kind) => kind,
+        None => return,
+    };
+
+    for click in clicks.iter() {
+        if click.button != MouseButton::Left
+            || !can_place(click.location, &tiles, &props)
+        {
+            continue;
+        }
+        commands
+            .spawn_bundle(PropBundle::new(
+                click.location,
+                placer.direction,
+                Handle::default(),
+            ))
+            .insert(SpriteKind::new(kind));
+    }
+}
+
+/// Translucent preview of the prop under the cursor
+#[derive(Component)]
+struct PropGhost;
+
+fn setup_ghost(
+    mut commands: Commands,
+    wall_mesh: Res<WallMesh>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut holograms: ResMut<Assets<HologramMaterial>>,
+) {
+    // The quad looks the same from either side, so an arrow shows which way
+    // the prop faces
+    let mut arrow = LineList::default();
+    let start = Vec3::Y * WALL_HEIGHT * 0.5;
+    arrow.arrow(start, start + Vec3::X * TILE_SIZE * 0.4);
+
+    commands
+        .spawn_bundle(MaterialMeshBundle {
+            mesh: wall_mesh.handle.clone(),
+            material: holograms.add(HologramMaterial::default()),
+            visibility: Visibility { is_visible: false },
+            ..Default::default()
+        })
+        .insert(PlacementValidity::Valid)
+        .insert(ViewLayer::Editor.layers())
+        .insert(PropGhost)
+        .insert(Name::new("Prop Ghost"))
+        .with_children(|ghost| {
+            ghost
+                .spawn_bundle(PbrBundle {
+                    mesh: meshes.add(arrow.into()),
+                    material: materials.add(StandardMaterial {
+                        base_color: Color::CYAN,
+                        unlit: true,
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                })
+                .insert(ViewLayer::Editor.layers());
+        });
+}
+
+/// Follow the cursor, tinted by whether the prop can be placed there
+fn update_ghost(
+    tool: Res<EditorTool>,
+    placer: Res<PropPlacer>,
+    hovered: Res<HoveredTile>,
+    tiles: Query<&Location, With<Tile>>,
+    props: Query<&Location, With<Prop>>,
+    mut ghosts: Query<
+        (Entity, &mut Transform, &mut PlacementValidity, &Children),
+        With<PropGhost>,
+    >,
+    mut visibility: Query<&mut Visibility>,
+) {
+    let location = hovered
+        .0
+        .filter(|_| *tool == EditorTool::Place && placer.kind.is_some());
+
+    for (ghost, mut transform, mut validity, children) in ghosts.iter_mut() {
+        if let Some(location) = location {
+            transform.translation = Vec3::from(location);
+            transform.rotation = placer.direction.into();
+            let valid =
+                PlacementValidity::new(can_place(location, &tiles, &props));
+            if *validity != valid {
+                *validity = valid;
+            }
+        }
+        set_visible(&mut visibility, ghost, location.is_some());
+        for &entity in children.iter() {
+            set_visible(&mut visibility, entity, location.is_some());
+        }
+    }
+}
+
+fn hide_ghost(
+    ghosts: Query<(Entity, &Children), With<PropGhost>>,
+    mut visibility: Query<&mut Visibility>,
+) {
+    for (ghost, children) in ghosts.iter() {
+        set_visible(&mut visibility, ghost, false);
+        for &entity in children.iter() {
+            set_visible(&mut visibility, entity, false);
+        }
+    }
+}
+
+fn set_visible(
+    visibility: &mut Query<&mut Visibility>,
+    entity: Entity,
+    visible: bool,
+) {
+    if let Ok(mut visibility) = visibility.get_mut(entity) {
+        if visibility.is_visible != visible {
+            visibility.is_visible = visible;
+        }
+    }
+}
+
+/// Window listing the props of the active theme
+fn prop_window(
+    mut egui: ResMut<EguiContext>,
+    active: Res<ActiveTheme>,
+    themes: Res<Assets<Theme>>,
+    mut placer: ResMut<PropPlacer>,
+    mut tool: ResMut<EditorTool>,
+) {
+    let props = match themes.get(&active.handle) {
+        Some(theme) => &theme.props,
+        None => return,
+    };
+
+    egui::Window::new("Props").show(egui.ctx_mut(), |ui| {
+        for kind in props.iter() {
+            let selected = placer.kind.as_ref() == Some(kind);
+            if ui.selectable_label(selected, kind).clicked() {
+                placer.kind = Some(kind.clone());
+                *tool = EditorTool::Place;
+            }
+        }
+
+        ui.separator();
+        ui.label(format!("Facing {:?}, press R to rotate", placer.direction));
+    });
+}
@@ -0,0 +1,134 @@
+use super::layers::MapLayers;
+use super::GameMode;
+use crate::map::{Edge, Location, MapLayer, SpriteIndex, Tile};
+use crate::select::Selection;
+use crate::theme::SpriteKind;
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+use iyes_loopless::prelude::*;
+
+pub struct ReplacePlugin;
+
+impl Plugin for ReplacePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ReplaceSprites>()
+            .init_resource::<ReplaceWindow>()
+            .add_system_set(
+                ConditionSet::new()
+                    .run_in_state(GameMode::Editing)
+                    .with_system(replace_window)
+                    .with_system(replace)
+                    .into(),
+            );
+    }
+}
+
+/// Swap every use of one sprite for another on a layer
+#[derive(Debug, Clone, Copy)]
+pub struct ReplaceSprites {
+    pub layer: MapLayer,
+    pub from: SpriteIndex,
+    pub to: SpriteIndex,
+    /// Only replace sprites on, or around, selected tiles
+    pub selection_only: bool,
+}
+
+fn replace(
+    mut commands: Commands,
+    mut events: EventReader<ReplaceSprites>,
+    layers: Res<MapLayers>,
+    selection: Res<Selection>,
+    tiles: Query<(Entity, &Location, &SpriteIndex), With<Tile>>,
+    walls: Query<(Entity, &Edge, &SpriteIndex)>,
+) {
+    for event in events.iter() {
+        if layers.get(event.layer).locked {
+            warn!("Not replacing sprites on locked layer {:?}", event.layer);
+            continue;
+        }
+
+        let selected = |location: Location| {
+            !event.selection_only || selection.contains(location)
+        };
+        let replaced: Vec<Entity> = match event.layer {
+            MapLayer::Tiles => tiles
+                .iter()
+                .filter(|(_, &location, &sprite)| {
+                    sprite == event.from && selected(location)
+                })
+                .map(|(entity, ..)| entity)
+                .collect(),
+            MapLayer::Walls => walls
+                .iter()
+                .filter(|(_, edge, &sprite)| {
+                    let (a, b) = edge.tiles();
+                    sprite == event.from && (selected(a) || selected(b))
+                })
+                .map(|(entity, ..)| entity)
+                .collect(),
+        };
+
+        info!(
+            "Replacing sprite {} with {} on {} {:?}",
+            event.from.0,
+            event.to.0,
+            replaced.len(),
+            event.layer
+        );
+        // Like painting, replaced sprites are no longer tied to the theme
+        for entity in replaced {
+            commands
+                .entity(entity)
+                .remove::<SpriteKind>()
+                .insert(event.to);
+        }
+    }
+}
+
+/// Settings for the "Replace Sprites" window
+struct ReplaceWindow {
+    layer: MapLayer,
+    from: u32,
+    to: u32,
+    selection_only: bool,
+}
+
+impl Default for ReplaceWindow {
+    fn default() -> Self {
+        Self {
+            layer: MapLayer::Tiles,
+            from: 0,
+            to: 0,
+            selection_only: false,
+        }
+    }
+}
+
+fn replace_window(
+    mut egui: ResMut<EguiContext>,
+    mut window: ResMut<ReplaceWindow>,
+    mut replace: EventWriter<ReplaceSprites>,
+) {
+    egui::Window::new("Replace Sprites").show(egui.ctx_mut(), |ui| {
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut window.layer, MapLayer::Tiles, "Tiles");
+            ui.selectable_value(&mut window.layer, MapLayer::Walls, "Walls");
+        });
+        ui.horizontal(|ui| {
+            ui.label("Replace");
+            ui.add(egui::DragValue::new(&mut window.from));
+            ui.label("with");
+            ui.add(egui::DragValue::new(&mut window.to));
+        });
+        ui.checkbox(&mut window.selection_only, "Selection only");
+
+        if ui.button("Replace").clicked() {
+            replace.send(ReplaceSprites {
+                layer: window.layer,
+                from: SpriteIndex(window.from),
+                to: SpriteIndex(window.to),
+                selection_only: window.selection_only,
+            });
+        }
+    });
+}
@@ -0,0 +1,185 @@
+use super::GameMode;
+use crate::camera::CameraTransition;
+use crate::map::{Direction, Edge, Location, Marker, Tile, WallGrid};
+use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
+use bevy_egui::{egui, EguiContext};
+use iyes_loopless::prelude::*;
+
+pub struct ValidatePlugin;
+
+impl Plugin for ValidatePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MapChecklist>().add_system_set(
+            ConditionSet::new()
+                .run_in_state(GameMode::Editing)
+                .with_system(checklist_window)
+                .with_system(validate)
+                .into(),
+        );
+    }
+}
+
+/// Problem found in the map which would make it unplayable
+#[derive(Debug, Clone)]
+pub struct Issue {
+    pub message: String,
+    /// Entity the camera is moved to when the issue is clicked
+    pub focus: Option<Entity>,
+}
+
+/// Result of the last validation pass
+#[derive(Default)]
+pub struct MapChecklist {
+    /// Run the validation pass on the next update
+    pub requested: bool,
+    /// Issues from the last pass, `None` before the first pass
+    pub issues: Option<Vec<Issue>>,
+}
+
+/// Tiles reachable from `start` without crossing walls, going through doors
+/// whether they start open or not
+fn flood_fill(
+    start: Location,
+    floor: &HashMap<Location, Entity>,
+    grid: &WallGrid,
+) -> HashSet<Location> {
+    let mut reached = HashSet::default();
+    let mut open = vec![start];
+    while let Some(location) = open.pop() {
+        if !floor.contains_key(&location) || !reached.insert(location) {
+            continue;
+        }
+        for dir in Direction::ALL {
+            if !grid.is_blocked(location, dir)
+                || grid.is_door(Edge::new(location, dir))
+            {
+                open.push(location.neighbor(dir));
+            }
+        }
+    }
+    reached
+}
+
+fn validate(
+    mut checklist: ResMut<MapChecklist>,
+    grid: Res<WallGrid>,
+    tiles: Query<(Entity, &Location), With<Tile>>,
+    walls: Query<(Entity, &Edge)>,
+    markers: Query<(&Location, &Marker)>,
+) {
+    if !checklist.requested {
+        return;
+    }
+    checklist.requested = false;
+
+    let floor: HashMap<Location, Entity> = tiles
+        .iter()
+        .map(|(entity, &location)| (location, entity))
+        .collect();
+    let mut issues = Vec::new();
+
+    let spawn = markers
+        .iter()
+        .find(|(_, marker)| matches!(marker, Marker::Spawn))
+        .map(|(&location, _)| location);
+    match spawn {
+        None => issues.push(Issue {
+            message: "No spawn marker".into(),
+            focus: None,
+        }),
+        Some(spawn) if !floor.contains_key(&spawn) => issues.push(Issue {
+            message: format!(
+                "Spawn ({}, {}) is not on a floor tile",
+                spawn.x, spawn.y
+            ),
+            focus: None,
+        }),
+        Some(spawn) => {
+            // Group everything the spawn can't reach into connected areas
+            let mut reached = flood_fill(spawn, &floor, &grid);
+            let mut unreached: Vec<_> = floor
+                .iter()
+                .filter(|(location, _)| !reached.contains(*location))
+                .map(|(&location, &entity)| (location, entity))
+                .collect();
+            unreached.sort_by_key(|(location, _)| (location.y, location.x));
+
+            for (location, entity) in unreached {
+                if reached.contains(&location) {
+                    continue;
+                }
+                let area = flood_fill(location, &floor, &grid);
+                issues.push(Issue {
+                    message: format!(
+                        "{} tiles around ({}, {}) can't be reached from spawn",
+                        area.len(),
+                        location.x,
+                        location.y
+                    ),
+                    focus: Some(entity),
+                });
+                reached.extend(area);
+            }
+        }
+    }
+
+    for (entity, edge) in walls.iter() {
+        let (a, b) = edge.tiles();
+        if !floor.contains_key(&a) && !floor.contains_key(&b) {
+            issues.push(Issue {
+                message: format!(
+                    "Wall between ({}, {}) and ({}, {}) has no floor",
+                    a.x, a.y, b.x, b.y
+                ),
+                focus: Some(entity),
+            });
+        }
+    }
+
+    info!("Map check found {} issues", issues.len());
+    checklist.issues = Some(issues);
+}
+
+/// Panel listing the issues found by the last check, clicking one moves the
+/// camera to it
+fn checklist_window(
+    mut egui: ResMut<EguiContext>,
+    mut checklist: ResMut<MapChecklist>,
+    mut transitions: EventWriter<CameraTransition>,
+    targets: Query<&GlobalTransform>,
+) {
+    let mut focus = None;
+    egui::Window::new("Checklist").show(egui.ctx_mut(), |ui| {
+        if ui.button("Check map").clicked() {
+            checklist.requested = true;
+        }
+
+        match &checklist.issues {
+            None => {
+                ui.label("Not checked yet");
+            }
+            Some(issues) if issues.is_empty() => {
+                ui.label("No issues found");
+            }
+            Some(issues) => {
+                for issue in issues.iter() {
+                    match issue.focus {
+                        Some(entity) => {
+                            if ui.link(&issue.message).clicked() {
+                                focus = Some(entity);
+                            }
+                        }
+                        None => {
+                            ui.label(&issue.message);
+                        }
+                    }
+                }
+            }
+        }
+    });
+
+    if let Some(target) = focus.and_then(|entity| targets.get(entity).ok()) {
+        transitions.send(CameraTransition::to(target.translation));
+    }
+}
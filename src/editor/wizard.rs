@@ -0,0 +1,97 @@
+use super::GameMode;
+use crate::map::{ActiveMap, Map, MapSprite};
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+use iyes_loopless::prelude::*;
+
+pub struct WizardPlugin;
+
+impl Plugin for WizardPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<NewMapWizard>()
+            .add_system(new_map_window.run_in_state(GameMode::Editing));
+    }
+}
+
+/// Settings for the next map created by the "New Map" window
+pub struct NewMapWizard {
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+    pub floor: String,
+    pub wall: String,
+}
+
+impl Default for NewMapWizard {
+    fn default() -> Self {
+        Self {
+            name: "untitled".into(),
+            width: 8,
+            height: 8,
+            floor: "floor".into(),
+            wall: "brick".into(),
+        }
+    }
+}
+
+/// Sprites are typed as text, numbers are taken as sprite indices and
+/// anything else as a sprite kind
+pub fn parse_sprite(text: &str) -> MapSprite {
+    let text = text.trim();
+    text.parse()
+        .map(MapSprite::Index)
+        .unwrap_or_else(|_| MapSprite::Kind(text.to_string()))
+}
+
+fn new_map_window(
+    mut egui: ResMut<EguiContext>,
+    mut wizard: ResMut<NewMapWizard>,
+    mut maps: ResMut<Assets<Map>>,
+    mut active: ResMut<ActiveMap>,
+) {
+    let mut create = false;
+    egui::Window::new("New Map").show(egui.ctx_mut(), |ui| {
+        egui::Grid::new("new map").show(ui, |ui| {
+            ui.label("Name");
+            ui.text_edit_singleline(&mut wizard.name);
+            ui.end_row();
+
+            ui.label("Width");
+            ui.add(
+                egui::DragValue::new(&mut wizard.width).clamp_range(1..=256),
+            );
+            ui.end_row();
+
+            ui.label("Height");
+            ui.add(
+                egui::DragValue::new(&mut wizard.height).clamp_range(1..=256),
+            );
+            ui.end_row();
+
+            ui.label("Floor sprite");
+            ui.text_edit_singleline(&mut wizard.floor);
+            ui.end_row();
+
+            ui.label("Wall sprite");
+            ui.text_edit_singleline(&mut wizard.wall);
+            ui.end_row();
+        });
+
+        create = ui.button("Create").clicked();
+    });
+
+    if create {
+        let map = Map::blank(
+            wizard.name.clone(),
+            wizard.width,
+            wizard.height,
+            parse_sprite(&wizard.floor),
+            parse_sprite(&wizard.wall),
+        );
+        info!(
+            "Created map {:?} ({}x{})",
+            map.name, wizard.width, wizard.height
+        );
+        active.handle = maps.add(map);
+    }
+}
@@ -0,0 +1,255 @@
+use crate::camera::IsometricCamera;
+use crate::console::{Console, ConsoleCommand};
+use crate::material::{ScreenEffects, ScreenMaterial, ViewLayer};
+use bevy::prelude::*;
+use bevy::render::view::NoFrustumCulling;
+use bevy::transform::TransformSystem;
+
+pub struct EffectsPlugin;
+
+impl Plugin for EffectsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<CameraImpact>()
+            .init_resource::<Trauma>()
+            .init_resource::<ImpactFading>()
+            .add_system(spawn_screen_overlays)
+            .add_system(impact_command)
+            .add_system(start_impacts.after(impact_command))
+            .add_system(fade_impacts.after(start_impacts))
+            .add_system_to_stage(CoreStage::PreUpdate, Trauma::settle)
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                Trauma::system.before(TransformSystem::TransformPropagate),
+            );
+    }
+}
+
+/// Feedback for something hitting hard enough for the camera to feel it,
+/// like a hit or an explosion
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CameraImpact {
+    /// Added to the [`Trauma`] shaking the cameras [0, 1]
+    pub trauma: f32,
+    /// Color the screen flashes, by its alpha
+    pub flash: Color,
+    /// Pixels the color channels are split apart by at the start of the pulse
+    pub aberration: f32,
+}
+
+impl CameraImpact {
+    /// Taking a hit
+    pub fn hit() -> Self {
+        Self {
+            trauma: 0.35,
+            flash: Color::rgba(0.8, 0.1, 0.1, 0.3),
+            aberration: 3.0,
+        }
+    }
+
+    /// Something exploding nearby
+    pub fn explosion() -> Self {
+        Self {
+            trauma: 0.8,
+            flash: Color::rgba(1.0, 0.85, 0.6, 0.6),
+            aberration: 6.0,
+        }
+    }
+}
+
+/// How quickly the flash and aberration of a [`CameraImpact`] wear off
+pub struct ImpactFading {
+    /// Flash alpha lost every second
+    pub flash: f32,
+    /// Aberration lost every second, as a fraction of what is left
+    pub aberration: f32,
+}
+
+impl Default for ImpactFading {
+    fn default() -> Self {
+        Self {
+            flash: 2.0,
+            aberration: 4.0,
+        }
+    }
+}
+
+/// Decaying shake of every [`IsometricCamera`] which gameplay adds to when
+/// something hits hard.
+///
+/// The shake is layered on the camera transform after the controls have
+/// placed it, and taken back out before anything moves the camera, so it
+/// never pulls the focus away from whatever the camera follows.
+#[derive(Debug, Clone)]
+pub struct Trauma {
+    /// Strength of the shake [0, 1], squared so small shakes stay subtle
+    pub value: f32,
+    /// Trauma lost every second
+    pub decay: f32,
+    /// Furthest the view is pushed sideways at full trauma
+    pub max_offset: f32,
+    /// Furthest the view is rolled at full trauma, in radians
+    pub max_angle: f32,
+    /// How quickly the camera rattles
+    pub frequency: f32,
+    /// View space offset and roll currently applied to the cameras
+    applied: (Vec2, f32),
+}
+
+impl Default for Trauma {
+    fn default() -> Self {
+        Self {
+            value: 0.0,
+            decay: 1.5,
+            max_offset: 0.05,
+            max_angle: f32::to_radians(2.0),
+            frequency: 15.0,
+            applied: (Vec2::ZERO, 0.0),
+        }
+    }
+}
+
+impl Trauma {
+    /// Shake harder, up to full trauma
+    pub fn add(&mut self, amount: f32) {
+        self.value = (self.value + amount).clamp(0.0, 1.0);
+    }
+
+    /// One dimensional perlin noise in [-1, 1], `seed` picks an independent
+    /// channel
+    fn noise(t: f32, seed: u32) -> f32 {
+        // Random slope at each whole number
+        let gradient = |i: i32| {
+            let mut h = (i as u32) ^ seed.wrapping_mul(0x9e37_79b9);
+            h = (h ^ (h >> 16)).wrapping_mul(0x7feb_352d);
+            h = (h ^ (h >> 15)).wrapping_mul(0x846c_a68b);
+            h ^= h >> 16;
+            (h as f32 / u32::MAX as f32) * 2.0 - 1.0
+        };
+        let i = t.floor();
+        let f = t - i;
+        let a = gradient(i as i32) * f;
+        let b = gradient(i as i32 + 1) * (f - 1.0);
+        let fade = f * f * f * (f * (f * 6.0 - 15.0) + 10.0);
+        // Slopes of at most one only reach half way from zero
+        (a + (b - a) * fade) * 2.0
+    }
+
+    /// Take the shake back out of the camera transforms
+    fn settle(
+        mut trauma: ResMut<Trauma>,
+        mut cameras: Query<&mut Transform, With<IsometricCamera>>,
+    ) {
+        let (offset, roll) = trauma.applied;
+        if offset == Vec2::ZERO && roll == 0.0 {
+            return;
+        }
+        for mut transform in cameras.iter_mut() {
+            transform.rotation *= Quat::from_rotation_z(-roll);
+            let offset = transform.rotation * offset.extend(0.0);
+            transform.translation -= offset;
+        }
+        trauma.applied = (Vec2::ZERO, 0.0);
+    }
+
+    /// Rattle the cameras by the remaining trauma, and let it wear off
+    fn system(
+        time: Res<Time>,
+        mut trauma: ResMut<Trauma>,
+        mut cameras: Query<&mut Transform, With<IsometricCamera>>,
+    ) {
+        if trauma.value <= 0.0 {
+            return;
+        }
+        // Wrap the clock before it loses precision as an f32
+        let seconds = (time.seconds_since_startup() % 3600.0) as f32;
+        let t = seconds * trauma.frequency;
+        let amount = trauma.value * trauma.value;
+        let offset = Vec2::new(Self::noise(t, 0), Self::noise(t, 1))
+            * trauma.max_offset
+            * amount;
+        let roll = Self::noise(t, 2) * trauma.max_angle * amount;
+
+        for mut transform in cameras.iter_mut() {
+            let shift = transform.rotation * offset.extend(0.0);
+            transform.translation += shift;
+            transform.rotation *= Quat::from_rotation_z(roll);
+        }
+        trauma.applied = (offset, roll);
+        trauma.value =
+            (trauma.value - trauma.decay * time.delta_seconds()).max(0.0);
+    }
+}
+
+/// Give every camera an overlay drawing the [`ScreenEffects`] on top of
+/// everything it sees
+fn spawn_screen_overlays(
+    mut commands: Commands,
+    cameras: Query<Entity, Added<IsometricCamera>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ScreenMaterial>>,
+) {
+    for camera in cameras.iter() {
+        // Spans clip space, the shader ignores the camera entirely
+        let mesh = meshes.add(shape::Quad::new(Vec2::splat(2.0)).into());
+        let overlay = commands
+            .spawn_bundle(MaterialMeshBundle {
+                mesh,
+                material: materials.add(ScreenMaterial),
+                ..Default::default()
+            })
+            .insert(NoFrustumCulling)
+            .insert(ViewLayer::Screen.layers())
+            .insert(Name::new("Screen Overlay"))
+            .id();
+        commands.entity(camera).add_child(overlay);
+    }
+}
+
+/// Shake every camera and pulse the screen effects
+fn start_impacts(
+    mut impacts: EventReader<CameraImpact>,
+    mut effects: ResMut<ScreenEffects>,
+    mut trauma: ResMut<Trauma>,
+) {
+    for impact in impacts.iter() {
+        trauma.add(impact.trauma);
+
+        // The strongest of overlapping flashes wins
+        if impact.flash.a() >= effects.flash.a() {
+            effects.flash = impact.flash;
+        }
+        effects.aberration = effects.aberration.max(impact.aberration);
+    }
+}
+
+fn fade_impacts(
+    time: Res<Time>,
+    fading: Res<ImpactFading>,
+    mut effects: ResMut<ScreenEffects>,
+) {
+    let dt = time.delta_seconds();
+    if effects.flash.a() > 0.0 {
+        let alpha = effects.flash.a() - fading.flash * dt;
+        effects.flash.set_a(alpha.max(0.0));
+    }
+    if effects.aberration > 0.0 {
+        let aberration = effects.aberration * (-fading.aberration * dt).exp();
+        // Snap to zero once the split is less than a tenth of a pixel
+        effects.aberration = if aberration < 0.1 { 0.0 } else { aberration };
+    }
+}
+
+/// `impact hit` or `impact explosion` plays the feedback of either
+fn impact_command(
+    mut events: EventReader<ConsoleCommand>,
+    mut console: ResMut<Console>,
+    mut impacts: EventWriter<CameraImpact>,
+) {
+    for event in events.iter().filter(|event| event.name == "impact") {
+        match event.args.first().map(String::as_str) {
+            Some("hit") => impacts.send(CameraImpact::hit()),
+            Some("explosion") => impacts.send(CameraImpact::explosion()),
+            _ => console.print("usage: impact <hit or explosion>"),
+        }
+    }
+}
@@ -0,0 +1,166 @@
+use crate::fov::field_of_view;
+use crate::map::{
+    ActiveMap, Edge, Location, SpriteIndex, SpriteSystem, SpriteTint, WallGrid,
+};
+use crate::rules::Rules;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+pub struct FogPlugin;
+
+impl Plugin for FogPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<FogOfWar>()
+            .add_system(update_fog.label(FogSystem).before(SpriteSystem))
+            .add_system(apply_fog.after(FogSystem).before(SpriteSystem));
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, SystemLabel)]
+struct FogSystem;
+
+/// How much of a tile the viewers know about
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TileVisibility {
+    /// Never seen
+    Hidden,
+    /// Seen before, but not right now
+    Explored,
+    /// In sight of a viewer
+    Visible,
+}
+
+/// Tint of explored tiles which are out of sight
+const EXPLORED_TINT: Color = Color::rgb(0.35, 0.35, 0.45);
+
+/// Which tiles have been seen by a [`Viewer`].
+///
+/// Maps without any viewers, like while the map is being edited, have no fog
+/// at all.
+pub struct FogOfWar {
+    pub enabled: bool,
+    /// Set by the editor while it is open
+    pub editing: bool,
+    tiles: HashMap<Location, TileVisibility>,
+    /// Whether any viewer was on the map when the fog was last updated
+    active: bool,
+}
+
+impl Default for FogOfWar {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            editing: false,
+            tiles: HashMap::default(),
+            active: false,
+        }
+    }
+}
+
+impl FogOfWar {
+    pub fn get(&self, location: Location) -> TileVisibility {
+        if !self.active {
+            return TileVisibility::Visible;
+        }
+        self.tiles
+            .get(&location)
+            .copied()
+            .unwrap_or(TileVisibility::Hidden)
+    }
+
+    /// The better known of the two tiles on either side of a wall
+    pub fn edge(&self, edge: Edge) -> TileVisibility {
+        let (a, b) = edge.tiles();
+        self.get(a).max(self.get(b))
+    }
+
+    /// Forget every explored tile
+    pub fn reset(&mut self) {
+        self.tiles.clear();
+    }
+}
+
+/// Unit which reveals the tiles in its line of sight, up to the vision radius
+/// in the [`Rules`]
+#[derive(Component, Debug, Clone, Copy, Default)]
+pub struct Viewer;
+
+/// Reveal the tiles in sight of every viewer, anything which was visible
+/// and no longer is stays explored
+fn update_fog(
+    mut fog: ResMut<FogOfWar>,
+    map: Res<ActiveMap>,
+    rules: Res<Rules>,
+    walls: Res<WallGrid>,
+    viewers: Query<&Location, With<Viewer>>,
+    moved: Query<(), (With<Viewer>, Changed<Location>)>,
+    removed: RemovedComponents<Viewer>,
+) {
+    // Nothing carries over from one map to the next
+    if map.is_changed() {
+        fog.reset();
+    }
+
+    let active = fog.enabled && !fog.editing && !viewers.is_empty();
+    if active != fog.active {
+        fog.active = active;
+    }
+    let stale = !moved.is_empty()
+        || removed.iter().next().is_some()
+        || walls.is_changed()
+        || rules.is_changed()
+        || map.is_changed();
+    if !active || !stale {
+        return;
+    }
+
+    for visibility in fog.tiles.values_mut() {
+        if *visibility == TileVisibility::Visible {
+            *visibility = TileVisibility::Explored;
+        }
+    }
+
+    for &origin in viewers.iter() {
+        for location in field_of_view(origin, rules.vision_radius, &walls) {
+            fog.tiles.insert(location, TileVisibility::Visible);
+        }
+    }
+}
+
+/// Darken explored tiles and black out hidden ones by tinting their sprites
+fn apply_fog(
+    mut commands: Commands,
+    fog: Res<FogOfWar>,
+    mut query: Query<(
+        Entity,
+        Option<&Location>,
+        Option<&Edge>,
+        Option<&mut SpriteTint>,
+        ChangeTrackers<SpriteIndex>,
+    )>,
+) {
+    for (entity, location, edge, tint, sprite) in query.iter_mut() {
+        // New sprites need to be fogged too
+        if !fog.is_changed() && !sprite.is_added() {
+            continue;
+        }
+        let visibility = match (location, edge) {
+            (Some(&location), _) => fog.get(location),
+            (None, Some(&edge)) => fog.edge(edge),
+            (None, None) => continue,
+        };
+        let fogged = match visibility {
+            TileVisibility::Visible => Color::WHITE,
+            TileVisibility::Explored => EXPLORED_TINT,
+            TileVisibility::Hidden => Color::BLACK,
+        };
+        match tint {
+            Some(mut tint) if tint.0 != fogged => tint.0 = fogged,
+            Some(_) => {}
+            None if fogged == Color::WHITE => {}
+            None => {
+                commands.entity(entity).insert(SpriteTint(fogged));
+            }
+        }
+    }
+}
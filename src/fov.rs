@@ -0,0 +1,155 @@
+use crate::map::{Direction, Location, WallGrid};
+use bevy::prelude::*;
+use bevy::utils::HashSet;
+
+pub struct FovPlugin;
+
+impl Plugin for FovPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(FieldOfView::system);
+    }
+}
+
+/// Whether a straight line from the middle of `from` to the middle of `to`
+/// crosses no walls.
+///
+/// A line through the corner between four tiles gets by if either way around
+/// the corner is open, so sight never leaks through two walls meeting at a
+/// corner but isn't stopped by a single wall end either.
+pub fn line_of_sight(from: Location, to: Location, walls: &WallGrid) -> bool {
+    let (dx, dy) = (to.x - from.x, to.y - from.y);
+    let (nx, ny) = (dx.abs(), dy.abs());
+    let step_x = if dx > 0 {
+        Direction::PositiveX
+    } else {
+        Direction::NegativeX
+    };
+    let step_y = if dy > 0 {
+        Direction::PositiveY
+    } else {
+        Direction::NegativeY
+    };
+
+    // Step across whichever tile boundary the line reaches first, comparing
+    // (0.5 + ix) / nx against (0.5 + iy) / ny without dividing
+    let mut location = from;
+    let (mut ix, mut iy) = (0, 0);
+    while ix < nx || iy < ny {
+        let x_first = (1 + 2 * ix) * ny;
+        let y_first = (1 + 2 * iy) * nx;
+        if x_first == y_first {
+            let through_x = location.neighbor(step_x);
+            let through_y = location.neighbor(step_y);
+            let open_x = !walls.is_blocked(location, step_x)
+                && !walls.is_blocked(through_x, step_y);
+            let open_y = !walls.is_blocked(location, step_y)
+                && !walls.is_blocked(through_y, step_x);
+            if !open_x && !open_y {
+                return false;
+            }
+            location = through_x.neighbor(step_y);
+            ix += 1;
+            iy += 1;
+        } else {
+            let dir = if x_first < y_first { step_x } else { step_y };
+            if walls.is_blocked(location, dir) {
+                return false;
+            }
+            location = location.neighbor(dir);
+            if x_first < y_first {
+                ix += 1;
+            } else {
+                iy += 1;
+            }
+        }
+    }
+    true
+}
+
+/// Every tile within `radius` tiles of `origin` with a clear line of sight
+/// from it, including `origin` itself
+pub fn field_of_view(
+    origin: Location,
+    radius: u32,
+    walls: &WallGrid,
+) -> HashSet<Location> {
+    let radius = radius as i32;
+    (-radius..=radius)
+        .flat_map(|y| (-radius..=radius).map(move |x| (x, y)))
+        .filter(|(x, y)| x * x + y * y <= radius * radius)
+        .map(|(x, y)| origin.offset(x, y))
+        .filter(|&location| line_of_sight(origin, location, walls))
+        .collect()
+}
+
+/// Tiles an entity can currently see, kept up to date as it moves and as
+/// walls change
+#[derive(Component, Debug, Clone, Default)]
+pub struct FieldOfView {
+    /// How many tiles away the entity can see
+    pub radius: u32,
+    visible: HashSet<Location>,
+}
+
+impl FieldOfView {
+    pub fn new(radius: u32) -> Self {
+        Self {
+            radius,
+            visible: HashSet::default(),
+        }
+    }
+
+    pub fn can_see(&self, location: Location) -> bool {
+        self.visible.contains(&location)
+    }
+
+    pub fn visible(&self) -> impl Iterator<Item = &Location> {
+        self.visible.iter()
+    }
+
+    fn system(
+        walls: Res<WallGrid>,
+        mut query: Query<(
+            &Location,
+            &mut FieldOfView,
+            ChangeTrackers<Location>,
+            ChangeTrackers<FieldOfView>,
+            Option<(&VisionCone, &Direction, ChangeTrackers<Direction>)>,
+        )>,
+    ) {
+        for (&location, mut fov, moved, changed, cone) in query.iter_mut() {
+            let turned =
+                matches!(&cone, Some((.., turned)) if turned.is_changed());
+            // Also catches a new radius, the system's own update to the
+            // visible tiles doesn't count as a change the next time it runs
+            if walls.is_changed()
+                || moved.is_changed()
+                || changed.is_changed()
+                || turned
+            {
+                let mut visible = field_of_view(location, fov.radius, &walls);
+                if let Some((_, &facing, _)) = cone {
+                    visible
+                        .retain(|&at| VisionCone::covers(location, facing, at));
+                }
+                fov.visible = visible;
+            }
+        }
+    }
+}
+
+/// Narrow the [`FieldOfView`] to the quarter ahead of the [`Direction`]
+/// faced, so whatever is behind or beside goes unseen
+#[derive(Component, Debug, Clone, Copy, Default)]
+pub struct VisionCone;
+
+impl VisionCone {
+    /// Whether looking from `origin` towards `facing` takes in `at`
+    pub fn covers(origin: Location, facing: Direction, at: Location) -> bool {
+        let (fx, fy) = facing.offset();
+        let (dx, dy) = (at.x - origin.x, at.y - origin.y);
+        let ahead = dx * fx + dy * fy;
+        let aside = (dx * fy - dy * fx).abs();
+        at == origin || aside <= ahead
+    }
+}
@@ -0,0 +1,152 @@
+use crate::health::{Damage, DamageSystem, Health};
+use crate::map::{FlashTint, Location, Tile};
+use crate::turn::{TurnQueue, TurnStarted};
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use serde::{Deserialize, Serialize};
+
+pub struct HazardPlugin;
+
+impl Plugin for HazardPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(hurt_on_enter.before(DamageSystem))
+            .add_system(hurt_on_turn.before(DamageSystem));
+    }
+}
+
+/// Tile which hurts whatever ends its move on it, placed on a tile in the map
+/// file
+#[derive(Component, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Hazard {
+    /// Rise and fall with the rounds, only hurting while raised
+    Spikes {
+        #[serde(default = "Hazard::default_damage")]
+        damage: u32,
+        #[serde(default)]
+        timing: SpikeTiming,
+    },
+    /// Burns every turn spent standing in it
+    Lava {
+        #[serde(default = "Hazard::default_damage")]
+        damage: u32,
+    },
+    /// Kills whatever falls in
+    Pit,
+}
+
+impl Hazard {
+    fn default_damage() -> u32 {
+        1
+    }
+
+    /// Whether the hazard hurts during `round`
+    pub fn is_active(&self, round: u32) -> bool {
+        match self {
+            Hazard::Spikes { timing, .. } => timing.is_raised(round),
+            Hazard::Lava { .. } | Hazard::Pit => true,
+        }
+    }
+
+    /// Hit points taken from whatever is caught in it, pits take all of them
+    pub fn damage(&self) -> u32 {
+        match *self {
+            Hazard::Spikes { damage, .. } | Hazard::Lava { damage } => damage,
+            Hazard::Pit => u32::MAX,
+        }
+    }
+}
+
+/// When spikes are raised, counted in rounds of the [`TurnQueue`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SpikeTiming {
+    /// Rounds before the pattern repeats, 0 to stay raised
+    pub period: u32,
+    /// Rounds at the start of each period the spikes are raised for
+    pub raised: u32,
+    /// Rounds to shift the pattern by, so neighbouring spikes can take turns
+    pub offset: u32,
+}
+
+impl Default for SpikeTiming {
+    fn default() -> Self {
+        Self {
+            period: 2,
+            raised: 1,
+            offset: 0,
+        }
+    }
+}
+
+impl SpikeTiming {
+    pub fn is_raised(&self, round: u32) -> bool {
+        if self.period == 0 {
+            return true;
+        }
+        (round + self.offset) % self.period < self.raised
+    }
+}
+
+/// Brief flash on a hazard which just hurt something
+fn flash() -> FlashTint {
+    FlashTint::new(Color::rgba(1.0, 0.4, 0.1, 0.6), 0.2)
+}
+
+/// Hurt anything with [`Health`] which steps onto an active hazard
+fn hurt_on_enter(
+    mut commands: Commands,
+    turns: Res<TurnQueue>,
+    mut damage: EventWriter<Damage>,
+    moved: Query<(Entity, &Location), (With<Health>, Changed<Location>)>,
+    hazards: Query<(Entity, &Location, &Hazard), With<Tile>>,
+) {
+    if moved.is_empty() {
+        return;
+    }
+    let hazards: HashMap<Location, (Entity, &Hazard)> = hazards
+        .iter()
+        .map(|(tile, &location, hazard)| (location, (tile, hazard)))
+        .collect();
+
+    for (target, location) in moved.iter() {
+        if let Some(&(tile, hazard)) = hazards.get(location) {
+            if hazard.is_active(turns.round) {
+                damage.send(Damage {
+                    target,
+                    amount: hazard.damage(),
+                    source: None,
+                });
+                commands.entity(tile).insert(flash());
+            }
+        }
+    }
+}
+
+/// Hurt actors whose turn starts on an active hazard, like spikes rising
+/// under them or lava they stayed in
+fn hurt_on_turn(
+    mut commands: Commands,
+    mut started: EventReader<TurnStarted>,
+    mut damage: EventWriter<Damage>,
+    actors: Query<&Location, With<Health>>,
+    hazards: Query<(Entity, &Location, &Hazard), With<Tile>>,
+) {
+    for &TurnStarted { entity, round } in started.iter() {
+        let location = match actors.get(entity) {
+            Ok(location) => location,
+            Err(_) => continue,
+        };
+        let hazard = hazards
+            .iter()
+            .find(|(_, at, hazard)| *at == location && hazard.is_active(round));
+        if let Some((tile, _, hazard)) = hazard {
+            damage.send(Damage {
+                target: entity,
+                amount: hazard.damage(),
+                source: None,
+            });
+            commands.entity(tile).insert(flash());
+        }
+    }
+}
@@ -0,0 +1,231 @@
+use crate::effects::CameraImpact;
+use crate::fov::FieldOfView;
+use crate::map::{FlashTint, SpriteIndex};
+use crate::rules::Rules;
+use crate::stats::{scale_damage, Stats};
+use crate::team::Team;
+use crate::turn::Actor;
+use bevy::prelude::*;
+
+pub struct HealthPlugin;
+
+impl Plugin for HealthPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<Damage>()
+            .add_event::<Died>()
+            .add_system(Invulnerability::system)
+            .add_system(
+                apply_damage
+                    .label(DamageSystem)
+                    .after(Invulnerability::system),
+            )
+            .add_system(die.after(DamageSystem));
+    }
+}
+
+/// Label of the system which takes [`Damage`] off of [`Health`], send damage
+/// before it to have it land the same frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, SystemLabel)]
+pub struct DamageSystem;
+
+/// Hit points of anything which can be hurt
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct Health {
+    pub current: u32,
+    pub max: u32,
+}
+
+impl Health {
+    /// Unhurt with `max` hit points
+    pub fn new(max: u32) -> Self {
+        Self { current: max, max }
+    }
+
+    /// Share of the hit points left, from 0 to 1
+    pub fn fraction(&self) -> f32 {
+        if self.max == 0 {
+            return 0.0;
+        }
+        self.current as f32 / self.max as f32
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.current == 0
+    }
+}
+
+/// Send to hurt an entity with [`Health`]
+#[derive(Debug, Clone, Copy)]
+pub struct Damage {
+    pub target: Entity,
+    pub amount: u32,
+    /// Whoever dealt the damage, if anyone
+    pub source: Option<Entity>,
+}
+
+/// Sent once an entity's [`Health`] runs out
+#[derive(Debug, Clone, Copy)]
+pub struct Died {
+    pub entity: Entity,
+    pub killer: Option<Entity>,
+}
+
+/// Shrug off any damage for `duration` seconds after being hurt
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Invulnerability {
+    pub duration: f32,
+    remaining: f32,
+}
+
+impl Invulnerability {
+    pub fn new(duration: f32) -> Self {
+        Self {
+            duration,
+            remaining: 0.0,
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.remaining > 0.0
+    }
+
+    fn system(time: Res<Time>, mut query: Query<&mut Invulnerability>) {
+        for mut invulnerability in query.iter_mut() {
+            // Only touch the ones counting down to keep change detection
+            // quiet
+            if invulnerability.is_active() {
+                invulnerability.remaining -= time.delta_seconds();
+            }
+        }
+    }
+}
+
+/// How getting hurt shows, flashing the sprite and maybe shaking the screen
+#[derive(Component, Debug, Clone, Copy)]
+pub struct HitFeedback {
+    /// Mixed into the sprite by its alpha
+    pub flash: Color,
+    /// Seconds the flash takes to fade
+    pub flash_duration: f32,
+    /// Felt by the camera, like when the player is hit
+    pub impact: Option<CameraImpact>,
+}
+
+impl Default for HitFeedback {
+    fn default() -> Self {
+        Self {
+            flash: Color::rgba(1.0, 0.2, 0.2, 0.8),
+            flash_duration: 0.25,
+            impact: None,
+        }
+    }
+}
+
+/// What is left behind when [`Health`] runs out, entities without one are
+/// despawned
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub enum Death {
+    Despawn,
+    /// Stay on the map, swapping to the `sprite` index if there is one
+    Corpse {
+        sprite: Option<u32>,
+    },
+}
+
+/// Marker for an entity which died and was left on the map
+#[derive(Component, Debug, Clone, Copy, Default)]
+pub struct Corpse;
+
+/// Take damage off of the target's [`Health`], unless it came from its own
+/// [`Team`] and the rules leave out friendly fire
+#[allow(clippy::too_many_arguments)]
+fn apply_damage(
+    mut commands: Commands,
+    rules: Res<Rules>,
+    mut damage: EventReader<Damage>,
+    mut died: EventWriter<Died>,
+    mut impacts: EventWriter<CameraImpact>,
+    mut query: Query<(
+        &mut Health,
+        Option<&mut Invulnerability>,
+        Option<&HitFeedback>,
+    )>,
+    teams: Query<&Team>,
+    stats: Query<&Stats>,
+) {
+    for hit in damage.iter() {
+        let team = |entity| teams.get(entity).ok().copied();
+        let friendly = match hit.source.and_then(team) {
+            Some(source) => team(hit.target) == Some(source),
+            None => false,
+        };
+        if friendly && !rules.friendly_fire {
+            continue;
+        }
+        let (mut health, invulnerability, feedback) =
+            match query.get_mut(hit.target) {
+                Ok(target) => target,
+                Err(_) => continue,
+            };
+        if health.is_dead() || hit.amount == 0 {
+            continue;
+        }
+        if let Some(mut invulnerability) = invulnerability {
+            if invulnerability.is_active() {
+                continue;
+            }
+            invulnerability.remaining = invulnerability.duration;
+        }
+
+        let stat = |entity| stats.get(entity).ok().map(Stats::total);
+        let attack = hit.source.and_then(stat).map(|s| s.attack);
+        let defense = stat(hit.target).map(|s| s.defense);
+        let amount =
+            scale_damage(hit.amount, attack.unwrap_or(0), defense.unwrap_or(0));
+        health.current = health.current.saturating_sub(amount);
+        let feedback = feedback.copied().unwrap_or_default();
+        commands
+            .entity(hit.target)
+            .insert(FlashTint::new(feedback.flash, feedback.flash_duration));
+        if let Some(impact) = feedback.impact {
+            impacts.send(impact);
+        }
+
+        if health.is_dead() {
+            died.send(Died {
+                entity: hit.target,
+                killer: hit.source,
+            });
+        }
+    }
+}
+
+/// Despawn the dead, or leave a corpse which no longer takes turns, sees, or
+/// can be hurt
+fn die(
+    mut commands: Commands,
+    mut died: EventReader<Died>,
+    mut query: Query<(Option<&Death>, Option<&mut SpriteIndex>)>,
+) {
+    for &Died { entity, .. } in died.iter() {
+        let (death, sprite) = match query.get_mut(entity) {
+            Ok(dead) => dead,
+            Err(_) => continue,
+        };
+        match death.copied().unwrap_or(Death::Despawn) {
+            Death::Despawn => commands.entity(entity).despawn_recursive(),
+            Death::Corpse { sprite: corpse } => {
+                if let (Some(mut sprite), Some(corpse)) = (sprite, corpse) {
+                    sprite.0 = corpse;
+                }
+                commands
+                    .entity(entity)
+                    .remove::<Actor>()
+                    .remove::<Health>()
+                    .remove::<Invulnerability>()
+                    .remove::<FieldOfView>()
+                    .insert(Corpse);
+            }
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use crate::material::ViewLayer;
+use bevy::prelude::*;
+use bevy::render::mesh::{PrimitiveTopology, VertexAttributeValues};
+
+pub struct HighlightPlugin;
+
+impl Plugin for HighlightPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_to_stage(CoreStage::PostUpdate, outline_highlighted);
+    }
+}
+
+/// Draw a colored outline around the mesh of an entity, like the hovered
+/// tile or a selected unit
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct Highlighted {
+    pub color: Color,
+}
+
+impl Default for Highlighted {
+    fn default() -> Self {
+        Self {
+            color: Color::rgb(1.0, 0.9, 0.2),
+        }
+    }
+}
+
+/// Outline drawn as a child of a [`Highlighted`] entity
+#[derive(Component)]
+pub struct HighlightOutline;
+
+/// How far outlines sit in front of the surface they outline, to keep them
+/// from z-fighting
+const OUTLINE_OFFSET: f32 = 0.002;
+
+/// Spawn outlines for new or recolored highlights, and remove the outlines
+/// of entities which are no longer highlighted
+fn outline_highlighted(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    highlighted: Query<
+        (Entity, &Highlighted, &Handle<Mesh>),
+        Or<(Changed<Highlighted>, Changed<Handle<Mesh>>)>,
+    >,
+    removed: RemovedComponents<Highlighted>,
+    outlines: Query<(Entity, &Parent), With<HighlightOutline>>,
+) {
+    let stale: Vec<Entity> = highlighted
+        .iter()
+        .map(|(entity, ..)| entity)
+        .chain(removed.iter())
+        .collect();
+    for (outline, parent) in outlines.iter() {
+        if stale.contains(&parent.0) {
+            commands.entity(outline).despawn();
+        }
+    }
+
+    for (entity, highlight, mesh) in highlighted.iter() {
+        let outline = match meshes.get(mesh).and_then(outline) {
+            Some(outline) => meshes.add(outline),
+            None => continue,
+        };
+        let material = materials.add(StandardMaterial {
+            base_color: highlight.color,
+            unlit: true,
+            ..Default::default()
+        });
+        commands.entity(entity).with_children(|parent| {
+            parent
+                .spawn_bundle(PbrBundle {
+                    mesh: outline,
+                    material,
+                    ..Default::default()
+                })
+                .insert(HighlightOutline)
+                .insert(ViewLayer::Gameplay.layers());
+        });
+    }
+}
+
+/// Rectangle around a flat mesh, lifted off its surface along the normal
+fn outline(mesh: &Mesh) -> Option<Mesh> {
+    let positions = match mesh.attribute(Mesh::ATTRIBUTE_POSITION)? {
+        VertexAttributeValues::Float32x3(positions) => positions,
+        _ => return None,
+    };
+    let normal = match mesh.attribute(Mesh::ATTRIBUTE_NORMAL)? {
+        VertexAttributeValues::Float32x3(normals) => {
+            Vec3::from(*normals.first()?)
+        }
+        _ => return None,
+    };
+
+    let (min, max) = positions.iter().map(|&p| Vec3::from(p)).fold(
+        (Vec3::splat(f32::MAX), Vec3::splat(f32::MIN)),
+        |(min, max), p| (min.min(p), max.max(p)),
+    );
+
+    // Walk the corners around the two axes the mesh spans
+    let size = max - min;
+    let flat = size.min_element();
+    let (u, v) = if size.x == flat {
+        (Vec3::Y, Vec3::Z)
+    } else if size.y == flat {
+        (Vec3::X, Vec3::Z)
+    } else {
+        (Vec3::X, Vec3::Y)
+    };
+    let offset = normal * OUTLINE_OFFSET;
+    let corner = |a: bool, b: bool| {
+        let pick = |axis: Vec3, high: bool| axis * if high { max } else { min };
+        let rest = Vec3::ONE - u - v;
+        (pick(u, a) + pick(v, b) + rest * (min + max) * 0.5 + offset).into()
+    };
+    let corners: Vec<[f32; 3]> = vec![
+        corner(false, false),
+        corner(true, false),
+        corner(true, true),
+        corner(false, true),
+        corner(false, false),
+    ];
+
+    // The PBR pipeline expects normals and uvs even though unlit lines
+    // don't need them
+    let count = corners.len();
+    let mut mesh = Mesh::new(PrimitiveTopology::LineStrip);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, corners);
+    mesh.insert_attribute(
+        Mesh::ATTRIBUTE_NORMAL,
+        vec![normal.to_array(); count],
+    );
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, vec![[0.0, 0.0]; count]);
+    Some(mesh)
+}
@@ -0,0 +1,239 @@
+use crate::dialogue::not_talking;
+use crate::editor::GameMode;
+use crate::fov::line_of_sight;
+use crate::item::Pickup;
+use crate::map::{Direction, Door, Edge, FlashTint, Location, WallGrid};
+use crate::player::Player;
+use crate::turn::{player_acts, EndTurn, TurnQueue};
+use bevy::prelude::*;
+use bevy_egui::EguiContext;
+use iyes_loopless::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub struct InteractPlugin;
+
+impl Plugin for InteractPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<Interacted>()
+            .add_event::<LeverToggled>()
+            .add_event::<LootGranted>()
+            .add_system(
+                interact
+                    .run_in_state(GameMode::Playing)
+                    .run_if(player_acts)
+                    .run_if(not_talking),
+            )
+            .add_system(toggle_levers.after(interact))
+            .add_system(open_chests.after(interact));
+    }
+}
+
+/// Key which interacts with whatever the player is facing, or else the
+/// nearest thing in reach
+const INTERACT: KeyCode = KeyCode::Space;
+
+/// Something the player can use, placed on a prop in the map file
+#[derive(Component, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Interactable {
+    /// Tiles away the player can be and still reach it, 1 for next to it
+    #[serde(default = "Interactable::default_range")]
+    pub range: u32,
+    pub kind: Interaction,
+}
+
+impl Interactable {
+    fn default_range() -> u32 {
+        1
+    }
+
+    /// Whether an actor at `from` can reach the interactable at `at`
+    pub fn in_reach(
+        &self,
+        from: Location,
+        at: Location,
+        walls: &WallGrid,
+    ) -> bool {
+        let distance = (at.x - from.x).abs() + (at.y - from.y).abs();
+        distance as u32 <= self.range && line_of_sight(from, at, walls)
+    }
+}
+
+/// What happens when an [`Interactable`] is used
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Interaction {
+    /// Flips between on and off, letting anything listening on `channel`
+    /// know
+    Lever {
+        channel: u32,
+        #[serde(default)]
+        on: bool,
+    },
+    /// Hands over its `loot` the first time it is opened, and scatters a
+    /// roll of its loot `table` around it
+    Chest {
+        #[serde(default)]
+        loot: Vec<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        table: Option<String>,
+        #[serde(default)]
+        opened: bool,
+    },
+    /// Starts a conversation, from the dialogue at the asset path
+    Talk { dialogue: String },
+}
+
+/// Sent when an actor uses an [`Interactable`]
+#[derive(Debug, Clone, Copy)]
+pub struct Interacted {
+    pub actor: Entity,
+    pub target: Entity,
+}
+
+/// Sent when a lever is pulled, for doors and triggers on the same channel
+#[derive(Debug, Clone, Copy)]
+pub struct LeverToggled {
+    pub lever: Entity,
+    pub channel: u32,
+    pub on: bool,
+}
+
+/// Sent when a chest is opened, with everything in it
+#[derive(Debug, Clone)]
+pub struct LootGranted {
+    pub chest: Entity,
+    pub to: Entity,
+    pub loot: Vec<String>,
+    /// Loot table to roll, if the chest has one
+    pub table: Option<String>,
+}
+
+/// Use the interactable or door the player faces, or else the nearest one in
+/// reach, which ends the player's turn unless it is only talking.
+///
+/// Doors are in reach from the tiles on either side, and pickups from next to
+/// them.
+#[allow(clippy::too_many_arguments)]
+fn interact(
+    keys: Res<Input<KeyCode>>,
+    walls: Res<WallGrid>,
+    turns: Res<TurnQueue>,
+    mut egui: ResMut<EguiContext>,
+    mut interacted: EventWriter<Interacted>,
+    mut end_turns: EventWriter<EndTurn>,
+    players: Query<(Entity, &Location, &Direction), With<Player>>,
+    interactables: Query<(Entity, &Location, &Interactable)>,
+    doors: Query<(Entity, &Edge), With<Door>>,
+    pickups: Query<(Entity, &Location), With<Pickup>>,
+) {
+    if egui.ctx_mut().wants_keyboard_input() || !keys.just_pressed(INTERACT) {
+        return;
+    }
+
+    for (actor, &from, &facing) in players.iter() {
+        if !turns.is_turn(actor) {
+            continue;
+        }
+        let faced = from.neighbor(facing);
+        let distance =
+            |at: Location| (at.x - from.x).abs() + (at.y - from.y).abs();
+        let props = interactables
+            .iter()
+            .filter(|(_, &at, interactable)| {
+                interactable.in_reach(from, at, &walls)
+            })
+            .map(|(target, &at, _)| (target, at != faced, distance(at)));
+        let faced_edge = Edge::new(from, facing);
+        let doors = doors
+            .iter()
+            .filter(|(_, edge)| {
+                let (a, b) = edge.tiles();
+                a == from || b == from
+            })
+            .map(|(target, &edge)| (target, edge != faced_edge, 1));
+        let pickups = pickups
+            .iter()
+            .filter(|(_, &at)| {
+                distance(at) <= 1 && line_of_sight(from, at, &walls)
+            })
+            .map(|(target, &at)| (target, at != faced, distance(at)));
+        let target = props
+            .chain(doors)
+            .chain(pickups)
+            .min_by_key(|&(_, unfaced, distance)| (unfaced, distance))
+            .map(|(target, ..)| target);
+
+        if let Some(target) = target {
+            interacted.send(Interacted { actor, target });
+            let talking = interactables
+                .get(target)
+                .map(|(.., interactable)| {
+                    matches!(interactable.kind, Interaction::Talk { .. })
+                })
+                .unwrap_or(false);
+            if !talking {
+                end_turns.send(EndTurn(actor));
+            }
+        }
+    }
+}
+
+/// Brief flash showing an interactable reacted
+fn flash() -> FlashTint {
+    FlashTint::new(Color::rgba(1.0, 1.0, 0.8, 0.6), 0.2)
+}
+
+fn toggle_levers(
+    mut commands: Commands,
+    mut interacted: EventReader<Interacted>,
+    mut toggled: EventWriter<LeverToggled>,
+    mut interactables: Query<&mut Interactable>,
+) {
+    for &Interacted { target, .. } in interacted.iter() {
+        let mut interactable = match interactables.get_mut(target) {
+            Ok(interactable) => interactable,
+            Err(_) => continue,
+        };
+        if let Interaction::Lever { channel, on } = &mut interactable.kind {
+            *on = !*on;
+            toggled.send(LeverToggled {
+                lever: target,
+                channel: *channel,
+                on: *on,
+            });
+            commands.entity(target).insert(flash());
+        }
+    }
+}
+
+fn open_chests(
+    mut commands: Commands,
+    mut interacted: EventReader<Interacted>,
+    mut granted: EventWriter<LootGranted>,
+    mut interactables: Query<&mut Interactable>,
+) {
+    for &Interacted { actor, target } in interacted.iter() {
+        let mut interactable = match interactables.get_mut(target) {
+            Ok(interactable) => interactable,
+            Err(_) => continue,
+        };
+        if let Interaction::Chest {
+            loot,
+            table,
+            opened,
+        } = &mut interactable.kind
+        {
+            if *opened {
+                continue;
+            }
+            *opened = true;
+            granted.send(LootGranted {
+                chest: target,
+                to: actor,
+                loot: std::mem::take(loot),
+                table: table.take(),
+            });
+            commands.entity(target).insert(flash());
+        }
+    }
+}
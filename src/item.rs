@@ -0,0 +1,325 @@
+use crate::console::{Console, ConsoleCommand};
+use crate::interact::{Interacted, LootGranted};
+use crate::map::{Location, Occupancy, OccupancySystem};
+use crate::player::Player;
+use crate::stats::StatBlock;
+use bevy::asset::{AssetLoader, LoadContext, LoadedAsset};
+use bevy::prelude::*;
+use bevy::reflect::TypeUuid;
+use bevy::utils::{BoxedFuture, HashMap};
+use serde::{Deserialize, Serialize};
+
+pub struct ItemPlugin;
+
+impl Plugin for ItemPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<ItemCatalog>()
+            .init_asset_loader::<ItemCatalogLoader>()
+            .init_resource::<Items>()
+            .add_event::<ItemsGained>()
+            .add_event::<InventoryChanged>()
+            .add_system(
+                pick_up_on_enter.label(PickupSystem).after(OccupancySystem),
+            )
+            .add_system(pick_up_on_interact.label(PickupSystem))
+            .add_system(take_loot.label(PickupSystem))
+            .add_system(give_command.label(PickupSystem))
+            .add_system(Inventory::gain.after(PickupSystem));
+    }
+}
+
+/// Systems which hand items out, by sending [`ItemsGained`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, SystemLabel)]
+struct PickupSystem;
+
+/// Everything an item can be, loaded from `.items.yaml` files
+#[derive(Debug, Clone, Default, Deserialize, TypeUuid)]
+#[uuid = "2f9d7c41-8a3e-4f0b-b6d2-5e1a9c7f3b84"]
+pub struct ItemCatalog {
+    pub items: HashMap<String, ItemDefinition>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ItemDefinition {
+    /// Shown to the player, the key in the catalog if left out
+    pub name: Option<String>,
+    pub description: String,
+    /// Most of the item which fit in a single inventory slot
+    pub max_stack: u32,
+    /// Added to the stats of whoever carries the item, however many
+    pub stats: StatBlock,
+}
+
+impl Default for ItemDefinition {
+    fn default() -> Self {
+        Self {
+            name: None,
+            description: String::new(),
+            max_stack: 1,
+            stats: StatBlock::default(),
+        }
+    }
+}
+
+/// The item catalog in use
+pub struct Items {
+    pub catalog: Handle<ItemCatalog>,
+}
+
+impl FromWorld for Items {
+    fn from_world(world: &mut World) -> Self {
+        let asset_server = world.resource::<AssetServer>();
+        Self {
+            catalog: asset_server.load("items/base.items.yaml"),
+        }
+    }
+}
+
+/// Load item catalogs from `.items.yaml` files
+#[derive(Default)]
+pub struct ItemCatalogLoader;
+
+impl AssetLoader for ItemCatalogLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let catalog: ItemCatalog = serde_yaml::from_slice(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(catalog));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["items.yaml"]
+    }
+}
+
+/// Items lying on a tile, placed on a prop in the map file
+#[derive(Component, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Pickup {
+    /// Key of the item in the [`ItemCatalog`]
+    pub item: String,
+    #[serde(default = "Pickup::default_count")]
+    pub count: u32,
+    /// Picked up by walking onto it, otherwise only by interacting
+    #[serde(default = "Pickup::default_on_enter")]
+    pub on_enter: bool,
+}
+
+impl Pickup {
+    fn default_count() -> u32 {
+        1
+    }
+
+    fn default_on_enter() -> bool {
+        true
+    }
+}
+
+/// Some of one item
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ItemStack {
+    pub item: String,
+    pub count: u32,
+}
+
+/// Items carried in a fixed number of slots, each holding up to the item's
+/// `max_stack`
+#[derive(Component, Debug, Clone, Default)]
+pub struct Inventory {
+    pub slots: Vec<ItemStack>,
+    pub capacity: usize,
+}
+
+impl Inventory {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            slots: Vec::new(),
+            capacity,
+        }
+    }
+
+    /// How many of `item` are carried across every slot
+    pub fn count(&self, item: &str) -> u32 {
+        self.slots
+            .iter()
+            .filter(|stack| stack.item == item)
+            .map(|stack| stack.count)
+            .sum()
+    }
+
+    /// Add `count` of `item`, topping up existing stacks before starting new
+    /// ones, and return how many didn't fit
+    pub fn add(&mut self, item: &str, mut count: u32, max_stack: u32) -> u32 {
+        let max_stack = max_stack.max(1);
+        for stack in self.slots.iter_mut().filter(|stack| stack.item == item) {
+            let added = count.min(max_stack.saturating_sub(stack.count));
+            stack.count += added;
+            count -= added;
+        }
+        while count > 0 && self.slots.len() < self.capacity {
+            let added = count.min(max_stack);
+            self.slots.push(ItemStack {
+                item: item.to_string(),
+                count: added,
+            });
+            count -= added;
+        }
+        count
+    }
+
+    /// Put what was gained into inventories, leaving whatever didn't fit on
+    /// the pickup it came from
+    fn gain(
+        mut commands: Commands,
+        items: Res<Items>,
+        catalogs: Res<Assets<ItemCatalog>>,
+        mut gained: EventReader<ItemsGained>,
+        mut changed: EventWriter<InventoryChanged>,
+        mut inventories: Query<&mut Inventory>,
+        mut pickups: Query<&mut Pickup>,
+    ) {
+        let catalog = catalogs.get(&items.catalog);
+        for gain in gained.iter() {
+            let mut inventory = match inventories.get_mut(gain.to) {
+                Ok(inventory) => inventory,
+                Err(_) => continue,
+            };
+            let max_stack = match catalog.and_then(|c| c.items.get(&gain.item))
+            {
+                Some(definition) => definition.max_stack,
+                None => {
+                    warn!("No item {} in the catalog", gain.item);
+                    1
+                }
+            };
+            let left = inventory.add(&gain.item, gain.count, max_stack);
+            if left < gain.count {
+                changed.send(InventoryChanged { entity: gain.to });
+            }
+
+            let pickup = gain.from.and_then(|from| {
+                pickups.get_mut(from).ok().map(|pickup| (from, pickup))
+            });
+            if let Some((from, mut pickup)) = pickup {
+                if left == 0 {
+                    commands.entity(from).despawn_recursive();
+                } else if pickup.count != left {
+                    pickup.count = left;
+                }
+            }
+        }
+    }
+}
+
+/// Sent to put items in an [`Inventory`]
+#[derive(Debug, Clone)]
+pub struct ItemsGained {
+    pub to: Entity,
+    pub item: String,
+    pub count: u32,
+    /// Pickup the items were taken from, which keeps whatever doesn't fit
+    pub from: Option<Entity>,
+}
+
+/// Sent whenever the contents of an [`Inventory`] change, for the UI
+#[derive(Debug, Clone, Copy)]
+pub struct InventoryChanged {
+    pub entity: Entity,
+}
+
+fn pick_up_on_enter(
+    occupancy: Res<Occupancy>,
+    mut gained: EventWriter<ItemsGained>,
+    carriers: Query<(Entity, &Location), (With<Inventory>, Changed<Location>)>,
+    pickups: Query<&Pickup>,
+) {
+    for (to, &location) in carriers.iter() {
+        for (from, pickup) in occupancy
+            .entities_at(location)
+            .iter()
+            .filter_map(|&from| Some((from, pickups.get(from).ok()?)))
+            .filter(|(_, pickup)| pickup.on_enter)
+        {
+            gained.send(ItemsGained {
+                to,
+                item: pickup.item.clone(),
+                count: pickup.count,
+                from: Some(from),
+            });
+        }
+    }
+}
+
+fn pick_up_on_interact(
+    mut interacted: EventReader<Interacted>,
+    mut gained: EventWriter<ItemsGained>,
+    pickups: Query<&Pickup>,
+) {
+    for &Interacted { actor, target } in interacted.iter() {
+        if let Ok(pickup) = pickups.get(target) {
+            gained.send(ItemsGained {
+                to: actor,
+                item: pickup.item.clone(),
+                count: pickup.count,
+                from: Some(target),
+            });
+        }
+    }
+}
+
+/// Hand over the loot of opened chests, one of each item named
+fn take_loot(
+    mut loot: EventReader<LootGranted>,
+    mut gained: EventWriter<ItemsGained>,
+) {
+    for granted in loot.iter() {
+        for item in granted.loot.iter() {
+            gained.send(ItemsGained {
+                to: granted.to,
+                item: item.clone(),
+                count: 1,
+                from: None,
+            });
+        }
+    }
+}
+
+/// `give <item> [count]` puts items in the player's inventory
+fn give_command(
+    mut events: EventReader<ConsoleCommand>,
+    mut console: ResMut<Console>,
+    mut gained: EventWriter<ItemsGained>,
+    players: Query<Entity, (With<Player>, With<Inventory>)>,
+) {
+    for event in events.iter().filter(|event| event.name == "give") {
+        let (item, count) = match event.args.as_slice() {
+            [item] => (item, Ok(1)),
+            [item, count] => (item, count.parse::<u32>()),
+            _ => {
+                console.print("usage: give <item> [count]");
+                continue;
+            }
+        };
+        let count = match count {
+            Ok(count) => count,
+            Err(err) => {
+                console.print(format!("{}", err));
+                continue;
+            }
+        };
+        match players.get_single() {
+            Ok(to) => gained.send(ItemsGained {
+                to,
+                item: item.clone(),
+                count,
+                from: None,
+            }),
+            Err(_) => console.print("No player to give items to"),
+        }
+    }
+}
@@ -0,0 +1,170 @@
+use crate::hazard::Hazard;
+use crate::health::{Corpse, Damage, DamageSystem, Health};
+use crate::map::{
+    Direction, Location, NavGrid, Occupancy, OccupancySystem, Tile,
+};
+use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
+use std::collections::VecDeque;
+
+pub struct KnockbackPlugin;
+
+impl Plugin for KnockbackPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<Knockback>()
+            .add_event::<Knocked>()
+            .add_system(
+                resolve_knockbacks
+                    .after(OccupancySystem)
+                    .before(DamageSystem),
+            );
+    }
+}
+
+/// Damage dealt to a unit knocked into a wall, and to both units when one is
+/// knocked into the other
+const IMPACT_DAMAGE: u32 = 1;
+
+/// Attacks from this unit knock whatever they hit back this many tiles
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KnockbackOnHit(pub u32);
+
+/// Send to force a unit up to `distance` tiles in `direction`, a tile at a
+/// time, whatever way it is facing
+#[derive(Debug, Clone, Copy)]
+pub struct Knockback {
+    pub target: Entity,
+    pub direction: Direction,
+    pub distance: u32,
+    /// Whoever did the knocking, credited with any damage from impacts
+    pub source: Option<Entity>,
+}
+
+/// What happened on a step of a [`Knockback`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KnockbackStep {
+    /// Slid onto the next tile
+    Slid,
+    /// Slammed into a wall or obstacle, and stopped
+    Wall,
+    /// Slammed into another unit and stopped, passing the rest of the
+    /// knockback on to it
+    Collided(Entity),
+    /// Slid into a pit, which the pit's [`Hazard`] deals with
+    Fell,
+}
+
+/// Sent for each step of a [`Knockback`], in order, for effects to follow
+#[derive(Debug, Clone, Copy)]
+pub struct Knocked {
+    pub entity: Entity,
+    /// Tile the unit is on after the step
+    pub location: Location,
+    pub step: KnockbackStep,
+}
+
+/// Move every unit knocked back this frame all the way at once, so units
+/// knocked into each other see where the others ended up
+fn resolve_knockbacks(
+    nav: Res<NavGrid>,
+    occupancy: Res<Occupancy>,
+    mut knockbacks: EventReader<Knockback>,
+    mut knocked: EventWriter<Knocked>,
+    mut damage: EventWriter<Damage>,
+    mut units: Query<
+        &mut Location,
+        (With<Health>, Without<Corpse>, Without<Tile>),
+    >,
+    hazards: Query<(&Location, &Hazard), With<Tile>>,
+) {
+    let mut queue: VecDeque<Knockback> = knockbacks.iter().copied().collect();
+    if queue.is_empty() {
+        return;
+    }
+    let pits: HashSet<Location> = hazards
+        .iter()
+        .filter(|(_, hazard)| matches!(hazard, Hazard::Pit))
+        .map(|(&location, _)| location)
+        .collect();
+
+    // Where units knocked back so far have ended up, since the occupancy
+    // only catches up at the end of the frame
+    let mut moved: HashMap<Entity, Location> = HashMap::default();
+    let unit_at = |moved: &HashMap<Entity, Location>, location: Location| {
+        let settled = occupancy
+            .entities_at(location)
+            .iter()
+            .copied()
+            .find(|&e| !moved.contains_key(&e) && units.get(e).is_ok());
+        let arrived = moved
+            .iter()
+            .find(|&(_, &at)| at == location)
+            .map(|(&e, _)| e);
+        arrived.or(settled)
+    };
+
+    while let Some(knock) = queue.pop_front() {
+        let start = match moved.get(&knock.target) {
+            Some(&at) => at,
+            None => match units.get(knock.target) {
+                Ok(&at) => at,
+                Err(_) => continue,
+            },
+        };
+        let hurt = |damage: &mut EventWriter<Damage>, target: Entity| {
+            damage.send(Damage {
+                target,
+                amount: IMPACT_DAMAGE,
+                source: knock.source,
+            });
+        };
+
+        let mut at = start;
+        for travelled in 0..knock.distance {
+            let next = at.neighbor(knock.direction);
+            let step = if nav.is_walled(at, next)
+                || !nav.has_floor(next)
+                || nav.is_obstacle(next)
+            {
+                hurt(&mut damage, knock.target);
+                KnockbackStep::Wall
+            } else if let Some(other) = unit_at(&moved, next) {
+                hurt(&mut damage, knock.target);
+                hurt(&mut damage, other);
+                let remaining = knock.distance - travelled - 1;
+                if remaining > 0 {
+                    queue.push_back(Knockback {
+                        target: other,
+                        distance: remaining,
+                        ..knock
+                    });
+                }
+                KnockbackStep::Collided(other)
+            } else {
+                at = next;
+                moved.insert(knock.target, at);
+                knocked.send(Knocked {
+                    entity: knock.target,
+                    location: at,
+                    step: KnockbackStep::Slid,
+                });
+                if !pits.contains(&at) {
+                    continue;
+                }
+                KnockbackStep::Fell
+            };
+            knocked.send(Knocked {
+                entity: knock.target,
+                location: at,
+                step,
+            });
+            break;
+        }
+    }
+
+    for (entity, at) in moved {
+        if let Ok(mut location) = units.get_mut(entity) {
+            *location = at;
+        }
+    }
+}
@@ -0,0 +1,321 @@
+use crate::health::{DamageSystem, Died};
+use crate::interact::LootGranted;
+use crate::item::Pickup;
+use crate::map::{
+    Direction, Location, MapRng, MapSprite, NavGrid, Occupancy, PropBundle,
+};
+use bevy::asset::{AssetLoader, LoadContext, LoadedAsset};
+use bevy::prelude::*;
+use bevy::reflect::TypeUuid;
+use bevy::utils::{BoxedFuture, HashMap, HashSet};
+use rand::Rng;
+use serde::Deserialize;
+use std::collections::VecDeque;
+
+pub struct LootPlugin;
+
+impl Plugin for LootPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<LootTables>()
+            .init_asset_loader::<LootTablesLoader>()
+            .init_resource::<Loot>()
+            .add_system(drop_on_death.after(DamageSystem))
+            .add_system(drop_from_chests);
+    }
+}
+
+/// Furthest a drop lands from where it was rolled, in steps, before it is
+/// left on the same tile
+const MAX_DROP_DISTANCE: u32 = 4;
+
+/// Weighted drops for each kind of enemy and chest, loaded from `.loot.yaml`
+/// files
+#[derive(Debug, Clone, Deserialize, TypeUuid)]
+#[uuid = "9c1e5a47-2b6d-4f3e-8a90-d4c7b21e6f53"]
+pub struct LootTables {
+    /// Sprite of the pickups dropped, unless a drop has its own
+    pub sprite: MapSprite,
+    pub tables: HashMap<String, LootTable>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct LootTable {
+    /// Times a drop is picked, each on its own
+    #[serde(default = "LootTable::default_rolls")]
+    pub rolls: u32,
+    pub drops: Vec<LootDrop>,
+}
+
+impl LootTable {
+    fn default_rolls() -> u32 {
+        1
+    }
+
+    /// Weight of every drop added up, if it fits
+    pub fn total_weight(&self) -> Option<u32> {
+        self.drops
+            .iter()
+            .try_fold(0u32, |total, drop| total.checked_add(drop.weight))
+    }
+
+    /// Pick `rolls` drops by weight, adding up the count of each item.
+    ///
+    /// Tables whose weights overflow are refused by the loader, and drop
+    /// nothing when built some other way.
+    pub fn roll(&self, rng: &mut impl Rng) -> Vec<(&LootDrop, u32)> {
+        let total = self.total_weight().unwrap_or(0);
+        let mut rolled: Vec<(&LootDrop, u32)> = Vec::new();
+        if total == 0 {
+            return rolled;
+        }
+        for _ in 0..self.rolls {
+            let mut pick = rng.gen_range(0..total);
+            let drop = self.drops.iter().find(|drop| {
+                let found = pick < drop.weight;
+                pick = pick.saturating_sub(drop.weight);
+                found
+            });
+            let drop = match drop {
+                Some(drop) if drop.item.is_some() => drop,
+                _ => continue,
+            };
+            let count = rng.gen_range(drop.count..=drop.max_count());
+            match rolled.iter_mut().find(|(other, _)| other.item == drop.item) {
+                Some((_, total)) => *total += count,
+                None => rolled.push((drop, count)),
+            }
+        }
+        rolled.retain(|&(_, count)| count > 0);
+        rolled
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct LootDrop {
+    /// Key of the item in the [`ItemCatalog`](crate::item::ItemCatalog),
+    /// dropping nothing if left out
+    pub item: Option<String>,
+    /// Chance of the drop against the others in the table
+    pub weight: u32,
+    /// Least of the item dropped
+    pub count: u32,
+    /// Most of the item dropped, the same as `count` if left out
+    pub max: Option<u32>,
+    pub sprite: Option<MapSprite>,
+}
+
+impl Default for LootDrop {
+    fn default() -> Self {
+        Self {
+            item: None,
+            weight: 1,
+            count: 1,
+            max: None,
+            sprite: None,
+        }
+    }
+}
+
+impl LootDrop {
+    fn max_count(&self) -> u32 {
+        self.max.unwrap_or(self.count).max(self.count)
+    }
+}
+
+/// The loot tables in use
+pub struct Loot {
+    pub tables: Handle<LootTables>,
+}
+
+impl FromWorld for Loot {
+    fn from_world(world: &mut World) -> Self {
+        let asset_server = world.resource::<AssetServer>();
+        Self {
+            tables: asset_server.load("loot/base.loot.yaml"),
+        }
+    }
+}
+
+/// Rolls the named [`LootTable`] when the unit dies
+#[derive(Component, Debug, Clone, PartialEq, Eq)]
+pub struct DropsLoot(pub String);
+
+/// Roll `table` and spawn a pickup for each item on the nearest free tile to
+/// `at`, counting a tile only `source` stands on as free
+#[allow(clippy::too_many_arguments)]
+fn scatter(
+    commands: &mut Commands,
+    tables: &LootTables,
+    table: &str,
+    at: Location,
+    source: Option<Entity>,
+    nav: &NavGrid,
+    occupancy: &Occupancy,
+    rng: &mut impl Rng,
+) {
+    let table = match tables.tables.get(table) {
+        Some(table) => table,
+        None => {
+            warn!("No loot table {}", table);
+            return;
+        }
+    };
+    let mut claimed: HashSet<Location> = HashSet::default();
+    for (drop, count) in table.roll(rng) {
+        let free = |location: Location| {
+            !claimed.contains(&location)
+                && occupancy
+                    .entities_at(location)
+                    .iter()
+                    .all(|&entity| Some(entity) == source)
+        };
+        let location = nearest(nav, at, free).unwrap_or(at);
+        claimed.insert(location);
+
+        let item = drop.item.clone().unwrap_or_default();
+        let mut entity = commands.spawn_bundle(PropBundle::new(
+            location,
+            Direction::PositiveY,
+            Handle::default(),
+        ));
+        drop.sprite
+            .as_ref()
+            .unwrap_or(&tables.sprite)
+            .insert(&mut entity);
+        entity
+            .insert(Name::new(format!("{} x{}", item, count)))
+            .insert(Pickup {
+                item,
+                count,
+                on_enter: true,
+            });
+    }
+}
+
+/// Closest tile a walk away from `from` for which `free` holds, trying
+/// `from` first
+fn nearest(
+    nav: &NavGrid,
+    from: Location,
+    free: impl Fn(Location) -> bool,
+) -> Option<Location> {
+    let mut seen = HashSet::from_iter([from]);
+    let mut queue = VecDeque::from([(from, 0)]);
+    while let Some((location, steps)) = queue.pop_front() {
+        if nav.has_floor(location)
+            && !nav.is_obstacle(location)
+            && free(location)
+        {
+            return Some(location);
+        }
+        if steps == MAX_DROP_DISTANCE {
+            continue;
+        }
+        for next in nav.neighbors(location) {
+            if seen.insert(next) {
+                queue.push_back((next, steps + 1));
+            }
+        }
+    }
+    None
+}
+
+/// Scatter the loot table of units as they die
+#[allow(clippy::too_many_arguments)]
+fn drop_on_death(
+    mut commands: Commands,
+    loot: Res<Loot>,
+    tables: Res<Assets<LootTables>>,
+    nav: Res<NavGrid>,
+    occupancy: Res<Occupancy>,
+    mut rng: ResMut<MapRng>,
+    mut died: EventReader<Died>,
+    droppers: Query<(&Location, &DropsLoot)>,
+) {
+    let tables = match tables.get(&loot.tables) {
+        Some(tables) => tables,
+        None => return,
+    };
+    for &Died { entity, .. } in died.iter() {
+        if let Ok((&at, DropsLoot(table))) = droppers.get(entity) {
+            scatter(
+                &mut commands,
+                tables,
+                table,
+                at,
+                Some(entity),
+                &nav,
+                &occupancy,
+                &mut rng.rng,
+            );
+        }
+    }
+}
+
+/// Scatter the loot table of opened chests around them
+#[allow(clippy::too_many_arguments)]
+fn drop_from_chests(
+    mut commands: Commands,
+    loot: Res<Loot>,
+    tables: Res<Assets<LootTables>>,
+    nav: Res<NavGrid>,
+    occupancy: Res<Occupancy>,
+    mut rng: ResMut<MapRng>,
+    mut granted: EventReader<LootGranted>,
+    chests: Query<&Location>,
+) {
+    let tables = match tables.get(&loot.tables) {
+        Some(tables) => tables,
+        None => return,
+    };
+    for granted in granted.iter() {
+        let table = match &granted.table {
+            Some(table) => table,
+            None => continue,
+        };
+        if let Ok(&at) = chests.get(granted.chest) {
+            scatter(
+                &mut commands,
+                tables,
+                table,
+                at,
+                None,
+                &nav,
+                &occupancy,
+                &mut rng.rng,
+            );
+        }
+    }
+}
+
+/// Load loot tables from `.loot.yaml` files
+#[derive(Default)]
+pub struct LootTablesLoader;
+
+impl AssetLoader for LootTablesLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let tables: LootTables = serde_yaml::from_slice(bytes)?;
+            for (name, table) in tables.tables.iter() {
+                if table.total_weight().is_none() {
+                    return Err(anyhow::anyhow!(
+                        "weights of loot table {} add up to more than {}",
+                        name,
+                        u32::MAX,
+                    ));
+                }
+            }
+            load_context.set_default_asset(LoadedAsset::new(tables));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["loot.yaml"]
+    }
+}
@@ -2,86 +2,94 @@ use bevy::prelude::*;
 use bevy_egui::EguiPlugin;
 use bevy_inspector_egui::WorldInspectorPlugin;
 
+mod ai;
+mod ambience;
+mod aoe;
 mod camera;
+mod checkpoint;
+mod console;
+mod daynight;
+mod dialogue;
+mod editor;
+mod effects;
+mod fog;
+mod fov;
+mod hazard;
+mod health;
+mod highlight;
+mod interact;
+mod item;
+mod knockback;
+mod loot;
 mod map;
 mod material;
+mod minimap;
+mod movement;
+mod occlusion;
+mod player;
+mod projectile;
+mod push;
+mod rules;
+mod select;
+mod shadow;
+mod spawner;
+mod stats;
+mod stealth;
+mod team;
+mod theme;
+mod turn;
 
 use map::MapPlugin;
-use material::{RenderPlugin, UnlitMaterial};
+use material::RenderPlugin;
 
 fn main() {
     App::new()
         .add_plugins(DefaultPlugins)
         .add_startup_system(watch_for_changes)
-        .add_plugin(RenderPlugin)
+        .add_plugin(RenderPlugin::default())
         .add_plugin(EguiPlugin)
         .add_plugin(WorldInspectorPlugin::new())
         .add_plugin(camera::CameraPlugin)
+        .add_plugin(console::ConsolePlugin)
         .add_plugin(MapPlugin)
+        .add_plugin(ambience::AmbiencePlugin)
+        .add_plugin(daynight::DayNightPlugin)
+        .add_plugin(rules::RulesPlugin)
+        .add_plugin(fog::FogPlugin)
+        .add_plugin(fov::FovPlugin)
+        .add_plugin(occlusion::OcclusionPlugin)
+        .add_plugin(effects::EffectsPlugin)
+        .add_plugin(shadow::ShadowPlugin)
+        .add_plugin(theme::ThemePlugin)
+        .add_plugin(select::SelectPlugin)
+        .add_plugin(player::PlayerPlugin)
+        .add_plugin(turn::TurnPlugin)
+        .add_plugin(team::TeamPlugin)
+        .add_plugin(ai::AiPlugin)
+        .add_plugin(spawner::SpawnerPlugin)
+        .add_plugin(stealth::StealthPlugin)
+        .add_plugin(health::HealthPlugin)
+        .add_plugin(checkpoint::CheckpointPlugin)
+        .add_plugin(stats::StatsPlugin)
+        .add_plugin(hazard::HazardPlugin)
+        .add_plugin(knockback::KnockbackPlugin)
+        .add_plugin(interact::InteractPlugin)
+        .add_plugin(dialogue::DialoguePlugin)
+        .add_plugin(item::ItemPlugin)
+        .add_plugin(loot::LootPlugin)
+        .add_plugin(push::PushPlugin)
+        .add_plugin(projectile::ProjectilePlugin)
+        .add_plugin(aoe::AreaPlugin)
+        .add_plugin(movement::MovementPlugin)
+        .add_plugin(highlight::HighlightPlugin)
+        .add_plugin(minimap::MinimapPlugin)
+        .add_plugin(editor::EditorPlugin)
         // Systems that create Egui widgets should be run during the
         // `CoreStage::Update` stage, or after the `EguiSystem::BeginFrame`
         // system (which belongs to the `CoreStage::PreUpdate` stage).
-        .add_startup_system(test_map)
         .run();
 }
 
-fn test_map(
-    mut commands: Commands,
-    mut materials: ResMut<Assets<UnlitMaterial>>,
-    asset_server: Res<AssetServer>,
-) {
-    use map::{Direction, Location, TileBundle, WallBundle};
-
-    // Add handle for blank material
-    let material = materials.add(UnlitMaterial::new(
-        asset_server.load("textures/uv_tester.png"),
-    ));
-
-    // Spawn the ground
-    commands.spawn_bundle(TileBundle::new(
-        Location { x: 0, y: 0 },
-        material.clone(),
-    ));
-    commands.spawn_bundle(TileBundle::new(
-        Location { x: 1, y: 0 },
-        material.clone(),
-    ));
-    commands.spawn_bundle(TileBundle::new(
-        Location { x: 0, y: 1 },
-        material.clone(),
-    ));
-    commands.spawn_bundle(TileBundle::new(
-        Location { x: 0, y: -1 },
-        material.clone(),
-    ));
-    commands.spawn_bundle(TileBundle::new(
-        Location { x: -1, y: 0 },
-        material.clone(),
-    ));
-
-    // Spawn walls
-    commands.spawn_bundle(WallBundle::new(
-        Location { x: -1, y: 0 },
-        Direction::NegativeX,
-        material.clone(),
-    ));
-    commands.spawn_bundle(WallBundle::new(
-        Location { x: 1, y: 0 },
-        Direction::PositiveX,
-        material.clone(),
-    ));
-    commands.spawn_bundle(WallBundle::new(
-        Location { x: 0, y: 1 },
-        Direction::PositiveY,
-        material.clone(),
-    ));
-    commands.spawn_bundle(WallBundle::new(
-        Location { x: 0, y: -1 },
-        Direction::NegativeY,
-        material.clone(),
-    ));
-}
-
 fn watch_for_changes(asset_server: ResMut<AssetServer>) {
     info!("Watching for changes");
     asset_server.watch_for_changes().unwrap();
@@ -1,8 +1,37 @@
-use crate::material::{UnlitMaterial, UnlitMaterialBundle};
+use crate::ai::EnemyKind;
+use crate::material::{
+    CornerColors, LitSpriteMaterial, MaterialAllocator, SpriteFlip,
+    SpriteMeshKey, SpriteSheet, UnlitMaterial, UnlitMaterialBundle,
+    WaterMaterial,
+};
+use crate::team::Team;
 use bevy::reflect::TypeUuid;
-use bevy::{prelude::*, render::mesh::Indices};
+use bevy::render::{primitives::Aabb, view::VisibilitySystems};
+use bevy::utils::{HashMap, HashSet};
+use bevy::{math::Vec3A, prelude::*, render::mesh::Indices};
 use bevy_inspector_egui::{Inspectable, RegisterInspectable};
 use iyes_loopless::prelude::*;
+use serde::{Deserialize, Serialize};
+use smallvec::SmallVec;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, VecDeque};
+use std::f32::consts::{FRAC_PI_4, TAU};
+
+mod animate;
+mod asset;
+mod bake;
+mod door;
+mod flash;
+mod light;
+mod tween;
+
+pub use animate::AnimatedUv;
+pub use asset::{ActiveMap, Map, MapCapture, MapRng, MapSprite};
+pub use bake::MapBaking;
+pub use door::Door;
+pub use flash::FlashTint;
+pub use light::PropLight;
+pub use tween::MoveTween;
 
 /// Square tile side length
 pub const TILE_SIZE: f32 = 0.33;
@@ -14,40 +43,523 @@ impl Plugin for MapPlugin {
     fn build(&self, app: &mut App) {
         app.register_inspectable::<Location>()
             .register_inspectable::<Direction>()
+            .register_inspectable::<Edge>()
+            .register_inspectable::<Facing>()
+            .register_inspectable::<Marker>()
+            .register_inspectable::<SpriteIndex>()
+            .register_inspectable::<TileKind>()
             .add_system_set(
                 ConditionSet::new()
                     .with_system(location_controller)
                     .with_system(direction_controller)
+                    .with_system(facing_controller)
+                    .with_system(edge_controller)
                     .into(),
             )
+            .init_resource::<LayerOrder>()
+            .add_system(
+                layer_controller
+                    .after(location_controller)
+                    .after(edge_controller),
+            )
+            .add_system(sprite_controller.label(SpriteSystem))
+            .add_system(Occupancy::system.label(OccupancySystem))
+            .add_system_to_stage(CoreStage::PostUpdate, WallGrid::system)
+            .add_system_to_stage(CoreStage::PostUpdate, MapBounds::system)
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                NavGrid::system.after(WallGrid::system),
+            )
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                update_sprite_bounds.after(VisibilitySystems::CalculateBounds),
+            )
+            .add_plugin(animate::AnimatePlugin)
+            .add_plugin(asset::MapAssetPlugin)
+            .add_plugin(bake::BakePlugin)
+            .add_plugin(door::DoorPlugin)
+            .add_plugin(flash::FlashPlugin)
+            .add_plugin(light::LightPlugin)
+            .add_plugin(tween::TweenPlugin)
+            .init_resource::<WallGrid>()
+            .init_resource::<MapBounds>()
+            .init_resource::<NavGrid>()
+            .init_resource::<Occupancy>()
+            .init_resource::<MapSprites>()
             .init_resource::<TileMesh>()
             .init_resource::<WallMesh>();
     }
 }
 
-#[derive(Component, Inspectable, PartialEq, Eq, Hash, Copy, Clone)]
+#[derive(
+    Component,
+    Inspectable,
+    PartialEq,
+    Eq,
+    Hash,
+    Copy,
+    Clone,
+    Debug,
+    Default,
+    Serialize,
+    Deserialize,
+)]
 pub struct Location {
     pub x: i32,
     pub y: i32,
 }
 
+impl Location {
+    /// The tile containing a point on the ground plane
+    pub fn from_world(point: Vec3) -> Self {
+        Self {
+            x: (point.x / TILE_SIZE).round() as i32,
+            y: (point.z / TILE_SIZE).round() as i32,
+        }
+    }
+
+    /// Location moved by a number of tiles
+    pub fn offset(self, x: i32, y: i32) -> Self {
+        Self {
+            x: self.x + x,
+            y: self.y + y,
+        }
+    }
+
+    /// The adjacent location in the given direction
+    pub fn neighbor(self, dir: Direction) -> Self {
+        let (x, y) = dir.offset();
+        self.offset(x, y)
+    }
+}
+
 impl From<Location> for Vec3 {
     fn from(l: Location) -> Self {
         Self::new(l.x as f32 * TILE_SIZE, 0.0, l.y as f32 * TILE_SIZE)
     }
 }
 
-/// When location is changed, change the transform to match
+/// When location is changed, change the transform to match, unless a
+/// [`MoveTween`] slides it there instead
 fn location_controller(
-    mut query: Query<(&Location, &mut Transform), Changed<Location>>,
+    mut query: Query<
+        (&Location, &mut Transform),
+        (Changed<Location>, Without<MoveTween>),
+    >,
 ) {
     for (loc, mut transform) in query.iter_mut() {
         transform.translation = (*loc).into();
     }
 }
 
+/// Sprite sheets used by the tiles and walls of the map
+pub struct MapSprites {
+    pub tiles: SpriteSheet,
+    pub walls: SpriteSheet,
+}
+
+impl FromWorld for MapSprites {
+    fn from_world(world: &mut World) -> Self {
+        let texture = world
+            .resource::<AssetServer>()
+            .load("textures/uv_tester.png");
+        Self {
+            tiles: SpriteSheet::new(texture.clone(), 4, 4),
+            walls: SpriteSheet::new(texture, 4, 4),
+        }
+    }
+}
+
+impl MapSprites {
+    /// The sheet used by an entity, walls if it is on an edge
+    pub fn sheet(&self, is_wall: bool) -> &SpriteSheet {
+        if is_wall {
+            &self.walls
+        } else {
+            &self.tiles
+        }
+    }
+}
+
+/// Index of the sprite drawn on a tile or wall in its [`MapSprites`] sheet
+#[derive(
+    Component, Inspectable, PartialEq, Eq, Hash, Copy, Clone, Debug, Default,
+)]
+pub struct SpriteIndex(pub u32);
+
+/// Label of the system which picks the mesh and material of each sprite
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
+pub struct SpriteSystem;
+
+/// Label of the system which catches the [`Occupancy`] up with everything
+/// spawned, moved, or despawned since it last ran, anything reading the
+/// occupancy runs after it
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
+pub struct OccupancySystem;
+
+/// Draw a tile, wall, or prop with a [`LitSpriteMaterial`] so it is shaded
+/// by the lights in the scene
+#[derive(Component, Default, Clone, Copy, Debug)]
+pub struct Lit;
+
+/// What a floor tile is made of, which changes how it is drawn
+#[derive(
+    Component,
+    Inspectable,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Debug,
+    Default,
+    Serialize,
+    Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum TileKind {
+    #[default]
+    Floor,
+    /// Translucent, with its sprite flowing across the tile
+    Water,
+}
+
+impl TileKind {
+    pub fn is_floor(&self) -> bool {
+        *self == TileKind::Floor
+    }
+
+    /// How many times the usual cost it takes to step onto the tile
+    pub fn move_cost(&self) -> u32 {
+        match self {
+            TileKind::Floor => 1,
+            TileKind::Water => 2,
+        }
+    }
+}
+
+/// Color a tile, wall, or prop is multiplied by
+#[derive(Component, Clone, Copy, PartialEq, Debug)]
+pub struct SpriteTint(pub Color);
+
+impl Default for SpriteTint {
+    fn default() -> Self {
+        Self(Color::WHITE)
+    }
+}
+
+/// Light left at the top left, top right, bottom left, and bottom right
+/// corners of a tile, in the order of [`CornerColors`], to darken where walls
+/// meet the floor without touching the map's own colors
+#[derive(Component, Clone, Copy, PartialEq, Debug)]
+pub struct CornerShade(pub [f32; 4]);
+
+impl Default for CornerShade {
+    fn default() -> Self {
+        Self([1.0; 4])
+    }
+}
+
+impl CornerShade {
+    pub fn is_unshaded(&self) -> bool {
+        self.0.iter().all(|&light| light >= 1.0)
+    }
+}
+
+/// Fraction of a sprite's pixels dithered away, from zero for fully
+/// drawn to one for invisible
+#[derive(Component, Default, Clone, Copy, PartialEq, Debug)]
+pub struct SpriteFade(pub f32);
+
+/// Unlit material of a sprite drawn from `sheet`, tinted, faded, and
+/// flashed
+fn unlit_variant(
+    allocator: &mut MaterialAllocator,
+    sheet: &SpriteSheet,
+    (tint, fade, flash): (
+        Option<&SpriteTint>,
+        Option<&SpriteFade>,
+        Option<&FlashTint>,
+    ),
+    materials: &mut Assets<UnlitMaterial>,
+    images: &Assets<Image>,
+) -> Handle<UnlitMaterial> {
+    let tint = tint.copied().unwrap_or_default().0;
+    let fade = fade.copied().unwrap_or_default().0;
+    let flash = flash.map_or(Color::NONE, FlashTint::current);
+    allocator.variant(sheet, tint, fade, flash, materials, images)
+}
+
+/// When the sprite index, flip, corner colors or shade, tint, fade, flash, or
+/// tile kind is changed, or the sprite is lit or unlit, swap to the mesh and
+/// material for that sprite
+#[allow(clippy::too_many_arguments)]
+fn sprite_controller(
+    mut commands: Commands,
+    sprites: Res<MapSprites>,
+    tile_mesh: Res<TileMesh>,
+    wall_mesh: Res<WallMesh>,
+    mut allocator: ResMut<MaterialAllocator>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    images: Res<Assets<Image>>,
+    mut materials: ResMut<Assets<UnlitMaterial>>,
+    mut lit_materials: ResMut<Assets<LitSpriteMaterial>>,
+    mut water_materials: ResMut<Assets<WaterMaterial>>,
+    mut query: Query<
+        (
+            Entity,
+            &SpriteIndex,
+            &mut Handle<Mesh>,
+            Option<&mut Handle<UnlitMaterial>>,
+            (
+                Option<&Edge>,
+                Option<&Prop>,
+                Option<&Lit>,
+                Option<&TileKind>,
+            ),
+            (
+                Option<&SpriteFlip>,
+                Option<&CornerColors>,
+                Option<&CornerShade>,
+            ),
+            (Option<&SpriteTint>, Option<&SpriteFade>, Option<&FlashTint>),
+        ),
+        Or<(
+            Changed<SpriteIndex>,
+            Changed<Lit>,
+            Changed<SpriteFlip>,
+            Changed<CornerColors>,
+            Changed<CornerShade>,
+            Changed<SpriteTint>,
+            Changed<SpriteFade>,
+            Changed<FlashTint>,
+            Changed<TileKind>,
+        )>,
+    >,
+    unlit: RemovedComponents<Lit>,
+    unflashed: RemovedComponents<FlashTint>,
+    sprited: Query<
+        (
+            Option<&Edge>,
+            Option<&TileKind>,
+            (Option<&SpriteTint>, Option<&SpriteFade>, Option<&FlashTint>),
+        ),
+        (With<SpriteIndex>, Without<Lit>),
+    >,
+) {
+    for (
+        entity,
+        index,
+        mut mesh,
+        material,
+        (edge, prop, lit, kind),
+        (flip, colors, shade),
+        variant,
+    ) in query.iter_mut()
+    {
+        let sheet = sprites.sheet(edge.is_some());
+
+        // Water scrolls across the whole tile, so it keeps the plain quad
+        if kind == Some(&TileKind::Water) {
+            *mesh = tile_mesh.handle.clone();
+            let tint = variant.0.copied().unwrap_or_default().0;
+            let water =
+                allocator.water(sheet, index.0, tint, &mut water_materials);
+            commands
+                .entity(entity)
+                .remove::<Handle<UnlitMaterial>>()
+                .remove::<Handle<LitSpriteMaterial>>()
+                .insert(water);
+            continue;
+        }
+
+        // Props stand upright on the same quad as walls
+        let base = if edge.is_some() || prop.is_some() {
+            &wall_mesh.handle
+        } else {
+            &tile_mesh.handle
+        };
+        let key = SpriteMeshKey {
+            base: base.clone_weak(),
+            sheet: sheet.clone(),
+            index: index.0,
+            flip: flip.copied().unwrap_or_default(),
+            colors: match shade.filter(|shade| !shade.is_unshaded()) {
+                Some(shade) => {
+                    Some(colors.copied().unwrap_or_default().shaded(shade.0))
+                }
+                None => colors.copied(),
+            },
+        };
+        *mesh = allocator.mesh(key, &mut meshes, &images);
+
+        match (lit, material) {
+            (Some(_), _) => {
+                let tint = variant.0.copied().unwrap_or_default().0;
+                let fade = variant.1.copied().unwrap_or_default().0;
+                let lit = allocator.lit_variant(
+                    sheet,
+                    tint,
+                    fade,
+                    &mut lit_materials,
+                );
+                commands
+                    .entity(entity)
+                    .remove::<Handle<UnlitMaterial>>()
+                    .remove::<Handle<WaterMaterial>>()
+                    .insert(lit);
+            }
+            (None, Some(mut material)) => {
+                let unlit = unlit_variant(
+                    &mut allocator,
+                    sheet,
+                    variant,
+                    &mut materials,
+                    &images,
+                );
+                if *material != unlit {
+                    *material = unlit;
+                }
+            }
+            (None, None) => {
+                let unlit = unlit_variant(
+                    &mut allocator,
+                    sheet,
+                    variant,
+                    &mut materials,
+                    &images,
+                );
+                commands
+                    .entity(entity)
+                    .remove::<Handle<LitSpriteMaterial>>()
+                    .remove::<Handle<WaterMaterial>>()
+                    .insert(unlit);
+            }
+        }
+    }
+
+    // Entities which stopped flashing go back to their plain variant too
+    for entity in unlit.iter().chain(unflashed.iter()) {
+        if let Ok((edge, kind, variant)) = sprited.get(entity) {
+            if kind == Some(&TileKind::Water) {
+                continue;
+            }
+            let sheet = sprites.sheet(edge.is_some());
+            let unlit = unlit_variant(
+                &mut allocator,
+                sheet,
+                variant,
+                &mut materials,
+                &images,
+            );
+            commands
+                .entity(entity)
+                .remove::<Handle<LitSpriteMaterial>>()
+                .insert(unlit);
+        }
+    }
+}
+
+/// Thinnest the culling bounds of a sprite can be, flat quads would otherwise
+/// be culled as soon as they touch the edge of the view
+const MIN_BOUNDS_THICKNESS: f32 = 0.01;
+
+/// Culling bounds of a sprite mesh, thickened so a flat tile or wall is
+/// treated as a thin slab
+fn sprite_bounds(mesh: &Mesh) -> Option<Aabb> {
+    let aabb = mesh.compute_aabb()?;
+    Some(Aabb {
+        center: aabb.center,
+        half_extents: aabb
+            .half_extents
+            .max(Vec3A::splat(MIN_BOUNDS_THICKNESS * 0.5)),
+    })
+}
+
+/// Keep the culling bounds of every tile, wall, and prop in step with its
+/// mesh.
+///
+/// Bevy only computes bounds for entities which have none, so the bounds of
+/// a sprite would otherwise belong to whichever mesh it was spawned with.
+fn update_sprite_bounds(
+    mut commands: Commands,
+    meshes: Res<Assets<Mesh>>,
+    mut events: EventReader<AssetEvent<Mesh>>,
+    sprites: Query<(Entity, &Handle<Mesh>), With<SpriteIndex>>,
+    swapped: Query<
+        (Entity, &Handle<Mesh>),
+        (With<SpriteIndex>, Changed<Handle<Mesh>>),
+    >,
+) {
+    // Meshes still loading when they were swapped in are caught here
+    let loaded: HashSet<Handle<Mesh>> = events
+        .iter()
+        .filter_map(|event| match event {
+            AssetEvent::Created { handle }
+            | AssetEvent::Modified { handle } => Some(handle.clone_weak()),
+            AssetEvent::Removed { .. } => None,
+        })
+        .collect();
+    let reloaded = sprites
+        .iter()
+        .filter(|(_, mesh)| !loaded.is_empty() && loaded.contains(*mesh));
+
+    for (entity, mesh) in swapped.iter().chain(reloaded) {
+        if let Some(aabb) = meshes.get(mesh).and_then(sprite_bounds) {
+            commands.entity(entity).insert(aabb);
+        }
+    }
+}
+
+/// Invisible map data attached to a tile, only shown by editor overlays
+#[derive(
+    Component,
+    Inspectable,
+    Clone,
+    Copy,
+    PartialEq,
+    Debug,
+    Serialize,
+    Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Marker {
+    /// Where units enter the map
+    Spawn,
+    /// Area which fires an event when entered
+    Trigger,
+    /// Teleports units standing on it to `target`
+    Portal { target: Location },
+    /// Where an enemy of `kind` and `team` starts, patrolling the path with
+    /// the marker's name, or else the waypoints of `route` if it has any
+    Enemy {
+        route: u32,
+        #[serde(default, skip_serializing_if = "Team::is_enemy")]
+        team: Team,
+        #[serde(default, skip_serializing_if = "EnemyKind::is_grunt")]
+        kind: EnemyKind,
+    },
+    /// Stop on a patrol `route`, visited in increasing `order` and then back
+    /// to the first
+    Waypoint { route: u32, order: u32 },
+    /// Remembers how the player was doing when they step onto it, bringing
+    /// them back here that way after they die
+    Checkpoint,
+}
+
 /// Direction on the (x,y) plane
-#[derive(Copy, Clone, Component, Inspectable)]
+#[derive(
+    Copy,
+    Clone,
+    Component,
+    Inspectable,
+    PartialEq,
+    Eq,
+    Hash,
+    Debug,
+    Serialize,
+    Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
 pub enum Direction {
     PositiveX,
     NegativeY,
@@ -55,6 +567,42 @@ pub enum Direction {
     PositiveY,
 }
 
+impl Direction {
+    pub const ALL: [Direction; 4] = [
+        Direction::PositiveX,
+        Direction::NegativeY,
+        Direction::NegativeX,
+        Direction::PositiveY,
+    ];
+
+    /// Grid offset of one step in this direction
+    pub fn offset(self) -> (i32, i32) {
+        match self {
+            Direction::PositiveX => (1, 0),
+            Direction::NegativeY => (0, -1),
+            Direction::NegativeX => (-1, 0),
+            Direction::PositiveY => (0, 1),
+        }
+    }
+
+    pub fn opposite(self) -> Self {
+        match self {
+            Direction::PositiveX => Direction::NegativeX,
+            Direction::NegativeY => Direction::PositiveY,
+            Direction::NegativeX => Direction::PositiveX,
+            Direction::PositiveY => Direction::NegativeY,
+        }
+    }
+}
+
+impl From<Direction> for Vec3 {
+    /// Unit vector on the ground plane
+    fn from(dir: Direction) -> Self {
+        let (x, y) = dir.offset();
+        Self::new(x as f32, 0.0, y as f32)
+    }
+}
+
 impl From<Direction> for Quat {
     fn from(dir: Direction) -> Self {
         let angle: f32 = match dir {
@@ -75,14 +623,757 @@ fn direction_controller(
     }
 }
 
+/// Axis an [`Edge`] crosses when moving between the two tiles it separates
+#[derive(Copy, Clone, Inspectable, PartialEq, Eq, Hash, Debug)]
+pub enum Axis {
+    X,
+    Y,
+}
+
+/// Boundary between two adjacent tiles, addressed canonically.
+///
+/// Edges live on a second grid offset from the tile grid by half a tile: the
+/// edge `(location, Axis::X)` separates `location` from its `PositiveX`
+/// neighbor, and `(location, Axis::Y)` separates it from its `PositiveY`
+/// neighbor.  Every edge has exactly one address no matter which side it is
+/// described from, so a wall can never be placed twice on the same boundary.
+#[derive(Component, Inspectable, PartialEq, Eq, Hash, Copy, Clone, Debug)]
+pub struct Edge {
+    pub location: Location,
+    pub axis: Axis,
+}
+
+impl Edge {
+    /// The edge on the `dir` side of the tile at `location`
+    pub fn new(location: Location, dir: Direction) -> Self {
+        match dir {
+            Direction::PositiveX => Self {
+                location,
+                axis: Axis::X,
+            },
+            Direction::PositiveY => Self {
+                location,
+                axis: Axis::Y,
+            },
+            Direction::NegativeX => Self {
+                location: location.neighbor(dir),
+                axis: Axis::X,
+            },
+            Direction::NegativeY => Self {
+                location: location.neighbor(dir),
+                axis: Axis::Y,
+            },
+        }
+    }
+
+    /// The edge separating two tiles, if they are adjacent
+    pub fn between(a: Location, b: Location) -> Option<Self> {
+        Direction::ALL
+            .into_iter()
+            .find(|&dir| a.neighbor(dir) == b)
+            .map(|dir| Self::new(a, dir))
+    }
+
+    /// The tile which has this edge on its `dir` side, the inverse of
+    /// [`Edge::new`]
+    pub fn side(&self, dir: Direction) -> Location {
+        match dir {
+            Direction::PositiveX | Direction::PositiveY => self.location,
+            Direction::NegativeX | Direction::NegativeY => {
+                self.location.neighbor(dir.opposite())
+            }
+        }
+    }
+
+    /// The two tiles on either side of the edge, ordered negative to positive
+    pub fn tiles(&self) -> (Location, Location) {
+        let positive = match self.axis {
+            Axis::X => self.location.neighbor(Direction::PositiveX),
+            Axis::Y => self.location.neighbor(Direction::PositiveY),
+        };
+        (self.location, positive)
+    }
+}
+
+impl From<Edge> for Vec3 {
+    /// Midpoint of the edge on the ground plane
+    fn from(edge: Edge) -> Self {
+        let (a, b) = edge.tiles();
+        (Vec3::from(a) + Vec3::from(b)) * 0.5
+    }
+}
+
+/// When an edge is changed, move the transform to the middle of the edge
+fn edge_controller(mut query: Query<(&Edge, &mut Transform), Changed<Edge>>) {
+    for (edge, mut transform) in query.iter_mut() {
+        transform.translation = (*edge).into();
+    }
+}
+
+/// Part of the map a tile or wall is placed in, and which the editor's
+/// brushes paint
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MapLayer {
+    Tiles,
+    Walls,
+}
+
+impl MapLayer {
+    pub fn name(self) -> &'static str {
+        match self {
+            MapLayer::Tiles => "Tiles",
+            MapLayer::Walls => "Walls",
+        }
+    }
+}
+
+/// Height each layer is lifted above the one below it, too little to see
+/// but enough to win the depth test where layers overlap
+const LAYER_LIFT: f32 = TILE_SIZE * 0.002;
+
+/// Order the layers of the map are drawn in, from the bottom up
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct LayerOrder(pub Vec<MapLayer>);
+
+impl Default for LayerOrder {
+    fn default() -> Self {
+        Self(vec![MapLayer::Tiles, MapLayer::Walls])
+    }
+}
+
+impl LayerOrder {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Swap the layer at `index` with the one `above` or below it, if
+    /// there is one
+    pub fn swap(&mut self, index: usize, above: bool) {
+        let other = if above {
+            index + 1
+        } else {
+            match index.checked_sub(1) {
+                Some(other) => other,
+                None => return,
+            }
+        };
+        if other < self.0.len() {
+            self.0.swap(index, other);
+        }
+    }
+
+    /// Height `layer` is lifted off the ground by, layers missing from the
+    /// order stay on it
+    pub fn lift(&self, layer: MapLayer) -> f32 {
+        let rank = self.0.iter().position(|&l| l == layer).unwrap_or(0);
+        rank as f32 * LAYER_LIFT
+    }
+}
+
+/// Lift tiles and walls to the height of their layer whenever they are
+/// moved or the layers are reordered
+fn layer_controller(
+    order: Res<LayerOrder>,
+    mut placed: Query<
+        (&mut Transform, Option<&Edge>),
+        Or<(With<Tile>, With<Edge>)>,
+    >,
+    moved: Query<Entity, Or<(Changed<Location>, Changed<Edge>)>>,
+) {
+    let lift = |edge: Option<&Edge>| {
+        order.lift(if edge.is_some() {
+            MapLayer::Walls
+        } else {
+            MapLayer::Tiles
+        })
+    };
+
+    if order.is_changed() {
+        for (mut transform, edge) in placed.iter_mut() {
+            transform.translation.y = lift(edge);
+        }
+        return;
+    }
+    for entity in moved.iter() {
+        if let Ok((mut transform, edge)) = placed.get_mut(entity) {
+            transform.translation.y = lift(edge);
+        }
+    }
+}
+
+/// Every side of a floor tile which borders empty space, as the tile and the
+/// direction of that side, ordered row by row
+pub fn perimeter(tiles: &HashSet<Location>) -> Vec<(Location, Direction)> {
+    let mut sorted: Vec<Location> = tiles.iter().copied().collect();
+    sorted.sort_by_key(|location| (location.y, location.x));
+
+    sorted
+        .into_iter()
+        .flat_map(|location| {
+            Direction::ALL
+                .into_iter()
+                .filter(move |&dir| !tiles.contains(&location.neighbor(dir)))
+                .map(move |dir| (location, dir))
+        })
+        .collect()
+}
+
+/// Index of every wall by the edge it occupies.
+///
+/// This is the single source of truth for which boundaries are blocked; a
+/// wall spawned on an edge which is already occupied is despawned.  A
+/// [`Door`] blocks its edge only while it is closed.
+#[derive(Default)]
+pub struct WallGrid {
+    walls: HashMap<Edge, Entity>,
+    /// Every door by its edge, and whether it is open
+    doors: HashMap<Edge, bool>,
+}
+
+impl WallGrid {
+    /// The wall occupying an edge
+    pub fn get(&self, edge: Edge) -> Option<Entity> {
+        self.walls.get(&edge).copied()
+    }
+
+    /// Whether the `dir` side of the tile at `location` has a wall, or a
+    /// closed door
+    pub fn is_blocked(&self, location: Location, dir: Direction) -> bool {
+        let edge = Edge::new(location, dir);
+        self.walls.contains_key(&edge) && !self.is_open(edge)
+    }
+
+    /// Whether the edge has a door, open or not
+    pub fn is_door(&self, edge: Edge) -> bool {
+        self.doors.contains_key(&edge)
+    }
+
+    pub fn is_open(&self, edge: Edge) -> bool {
+        self.doors.get(&edge).copied().unwrap_or(false)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&Edge, &Entity)> {
+        self.walls.iter()
+    }
+
+    /// Keep the index in sync with spawned, moved, and despawned walls
+    fn system(
+        mut commands: Commands,
+        mut grid: ResMut<WallGrid>,
+        walls: Query<(Entity, &Edge), Changed<Edge>>,
+        removed: RemovedComponents<Edge>,
+        doors: Query<(&Edge, &Door)>,
+        toggled: Query<(), Changed<Door>>,
+        removed_doors: RemovedComponents<Door>,
+    ) {
+        let mut moved = false;
+        for entity in removed.iter() {
+            grid.walls.retain(|_, e| *e != entity);
+            moved = true;
+        }
+
+        for (entity, edge) in walls.iter() {
+            // Moved walls leave their old edge behind
+            grid.walls.retain(|_, e| *e != entity);
+            match grid.walls.get(edge) {
+                Some(&other) if other != entity => {
+                    warn!(
+                        "Edge {:?} already has wall {:?}, removing {:?}",
+                        edge, other, entity
+                    );
+                    commands.entity(entity).despawn();
+                }
+                _ => {
+                    grid.walls.insert(*edge, entity);
+                }
+            }
+            moved = true;
+        }
+
+        if moved || !toggled.is_empty() || removed_doors.iter().next().is_some()
+        {
+            let doors: HashMap<Edge, bool> = doors
+                .iter()
+                .map(|(&edge, door)| (edge, door.open))
+                .collect();
+            // Doors swinging don't count as a change unless one opened or
+            // closed
+            if doors != grid.doors {
+                grid.doors = doors;
+            }
+        }
+    }
+}
+
+/// Part of the ground plane covered by floor tiles, out to the outer edges of
+/// the outermost tiles
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct MapBounds {
+    /// Smallest and largest corner on the ground plane, as x and z, `None`
+    /// while the map has no tiles
+    pub area: Option<(Vec2, Vec2)>,
+}
+
+impl MapBounds {
+    /// Cover every tile again whenever tiles move or are removed
+    fn system(
+        mut bounds: ResMut<MapBounds>,
+        tiles: Query<&Location, With<Tile>>,
+        changed: Query<(), (With<Tile>, Changed<Location>)>,
+        removed: RemovedComponents<Tile>,
+    ) {
+        if changed.is_empty() && removed.iter().next().is_none() {
+            return;
+        }
+
+        let half = Vec2::splat(TILE_SIZE * 0.5);
+        let area = tiles
+            .iter()
+            .map(|&location| {
+                let center = Vec3::from(location);
+                Vec2::new(center.x, center.z)
+            })
+            .fold(None, |area: Option<(Vec2, Vec2)>, center| {
+                Some(match area {
+                    Some((min, max)) => (min.min(center), max.max(center)),
+                    None => (center, center),
+                })
+            })
+            .map(|(min, max)| (min - half, max + half));
+        if bounds.area != area {
+            bounds.area = area;
+        }
+    }
+}
+
+/// Which tiles can be walked between, so movement, AI, and pathfinding all
+/// agree on where anything can go.
+///
+/// Rebuilt from the floor tiles and the [`WallGrid`] whenever either changes.
+#[derive(Debug, Clone, Default)]
+pub struct NavGrid {
+    floor: HashSet<Location>,
+    blocked: HashSet<Edge>,
+    obstacles: HashSet<Location>,
+    /// Tiles which cost more than usual to step onto, like water
+    costs: HashMap<Location, u32>,
+}
+
+impl NavGrid {
+    /// Whether the tile at `location` has floor to stand on
+    pub fn has_floor(&self, location: Location) -> bool {
+        self.floor.contains(&location)
+    }
+
+    /// Whether a wall or closed door stands between two tiles, or they aren't
+    /// adjacent at all
+    pub fn is_walled(&self, from: Location, to: Location) -> bool {
+        match Edge::between(from, to) {
+            Some(edge) => self.blocked.contains(&edge),
+            None => true,
+        }
+    }
+
+    /// Whether an [`Obstacle`] stands on the tile at `location`
+    pub fn is_obstacle(&self, location: Location) -> bool {
+        self.obstacles.contains(&location)
+    }
+
+    /// Whether a single step leads from `from` to `to`: the two tiles are
+    /// adjacent, `to` has floor and no obstacle, and no wall stands between
+    /// them
+    pub fn is_passable(&self, from: Location, to: Location) -> bool {
+        match Edge::between(from, to) {
+            Some(edge) => {
+                self.has_floor(to)
+                    && !self.is_obstacle(to)
+                    && !self.blocked.contains(&edge)
+            }
+            None => false,
+        }
+    }
+
+    /// Every tile a single step away from `location`
+    pub fn neighbors(
+        &self,
+        location: Location,
+    ) -> impl Iterator<Item = Location> + '_ {
+        Direction::ALL
+            .into_iter()
+            .map(move |dir| location.neighbor(dir))
+            .filter(move |&to| self.is_passable(location, to))
+    }
+
+    /// How many times the usual cost it takes to step onto `location`
+    pub fn step_cost(&self, location: Location) -> u32 {
+        self.costs.get(&location).copied().unwrap_or(1)
+    }
+
+    /// Every tile which can be walked to from `from` spending at most
+    /// `budget`, where each step costs `move_cost` times the
+    /// [`NavGrid::step_cost`] of the tile stepped onto, never stepping on a
+    /// tile for which `avoid` is true
+    pub fn reachable(
+        &self,
+        from: Location,
+        budget: u32,
+        move_cost: u32,
+        avoid: impl Fn(Location) -> bool,
+    ) -> Reachable {
+        let mut reached = Reachable {
+            from,
+            tiles: HashMap::default(),
+        };
+        reached.tiles.insert(from, (0, from));
+        let mut queue = BinaryHeap::from([Reverse((0, from.x, from.y))]);
+        while let Some(Reverse((cost, x, y))) = queue.pop() {
+            let location = Location { x, y };
+            if reached.tiles[&location].0 < cost {
+                continue;
+            }
+            for next in self.neighbors(location) {
+                let total = cost + move_cost * self.step_cost(next);
+                let cheaper = match reached.tiles.get(&next) {
+                    Some(&(known, _)) => total < known,
+                    None => true,
+                };
+                if total > budget || !cheaper || avoid(next) {
+                    continue;
+                }
+                reached.tiles.insert(next, (total, location));
+                queue.push(Reverse((total, next.x, next.y)));
+            }
+        }
+        reached
+    }
+
+    /// Shortest walk from `from` to `to`, as every tile stepped on after
+    /// `from` and ending on `to`, never stepping on a tile for which
+    /// `avoid` is true unless it is `to`
+    pub fn path(
+        &self,
+        from: Location,
+        to: Location,
+        avoid: impl Fn(Location) -> bool,
+    ) -> Option<Vec<Location>> {
+        if from == to {
+            return Some(Vec::new());
+        }
+        let mut came_from = HashMap::default();
+        let mut queue = VecDeque::from([from]);
+        came_from.insert(from, from);
+        while let Some(location) = queue.pop_front() {
+            if location == to {
+                let mut path = vec![to];
+                let mut step = to;
+                while came_from[&step] != from {
+                    step = came_from[&step];
+                    path.push(step);
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for next in self.neighbors(location) {
+                if came_from.contains_key(&next) || (next != to && avoid(next))
+                {
+                    continue;
+                }
+                came_from.insert(next, location);
+                queue.push_back(next);
+            }
+        }
+        None
+    }
+
+    /// Rebuild whenever tiles, walls, or obstacles are spawned, moved, or
+    /// despawned
+    #[allow(clippy::too_many_arguments)]
+    fn system(
+        mut nav: ResMut<NavGrid>,
+        walls: Res<WallGrid>,
+        tiles: Query<(&Location, Option<&TileKind>), With<Tile>>,
+        changed: Query<
+            (),
+            (With<Tile>, Or<(Changed<Location>, Changed<TileKind>)>),
+        >,
+        removed: RemovedComponents<Tile>,
+        obstacles: Query<&Location, With<Obstacle>>,
+        moved: Query<
+            (),
+            (With<Obstacle>, Or<(Changed<Location>, Added<Obstacle>)>),
+        >,
+        cleared: RemovedComponents<Obstacle>,
+    ) {
+        if !moved.is_empty() || cleared.iter().next().is_some() {
+            nav.obstacles = obstacles.iter().copied().collect();
+        }
+        if !walls.is_changed()
+            && changed.is_empty()
+            && removed.iter().next().is_none()
+        {
+            return;
+        }
+
+        nav.floor = tiles.iter().map(|(&location, _)| location).collect();
+        nav.costs = tiles
+            .iter()
+            .filter_map(|(&location, kind)| {
+                let cost = kind.map_or(1, TileKind::move_cost);
+                (cost != 1).then_some((location, cost))
+            })
+            .collect();
+        nav.blocked = walls
+            .iter()
+            .map(|(&edge, _)| edge)
+            .filter(|&edge| !walls.is_open(edge))
+            .collect();
+    }
+}
+
+/// Tiles found by [`NavGrid::reachable`], with the cheapest way to each
+#[derive(Debug, Clone, Default)]
+pub struct Reachable {
+    from: Location,
+    /// Cost of reaching each tile, and the tile it is stepped onto from
+    tiles: HashMap<Location, (u32, Location)>,
+}
+
+impl Reachable {
+    /// Cost of walking to `to`, if it can be reached
+    pub fn cost(&self, to: Location) -> Option<u32> {
+        self.tiles.get(&to).map(|&(cost, _)| cost)
+    }
+
+    /// Every tile which can be reached, other than the start
+    pub fn tiles(&self) -> impl Iterator<Item = Location> + '_ {
+        self.tiles
+            .keys()
+            .copied()
+            .filter(move |&at| at != self.from)
+    }
+
+    /// Cheapest walk to `to`, as every tile stepped on after the start and
+    /// ending on `to`
+    pub fn path(&self, to: Location) -> Option<Vec<Location>> {
+        self.tiles.get(&to)?;
+        let mut path = Vec::new();
+        let mut step = to;
+        while step != self.from {
+            path.push(step);
+            step = self.tiles[&step].1;
+        }
+        path.reverse();
+        Some(path)
+    }
+}
+
+/// Everything standing on each tile, like units, props, and pickups, so
+/// finding what is at a location doesn't mean going through every entity.
+///
+/// Kept in sync as entities with a [`Location`] are spawned, moved, and
+/// despawned by the [`OccupancySystem`]. Tiles and markers are left out.
+///
+/// Systems moving several things in one go, which would otherwise see where
+/// they stood when the system started, update it with
+/// [`Occupancy::move_to`] as they go.
+#[derive(Debug, Clone, Default)]
+pub struct Occupancy {
+    tiles: HashMap<Location, SmallVec<[Entity; 2]>>,
+    placed: HashMap<Entity, Location>,
+}
+
+impl Occupancy {
+    /// Everything standing on the tile at `location`
+    pub fn entities_at(&self, location: Location) -> &[Entity] {
+        self.tiles
+            .get(&location)
+            .map_or(&[], |entities| entities.as_slice())
+    }
+
+    /// Whether anything stands on the tile at `location`
+    pub fn is_occupied(&self, location: Location) -> bool {
+        !self.entities_at(location).is_empty()
+    }
+
+    /// Tile `entity` stands on, as of the last time it was updated
+    pub fn location_of(&self, entity: Entity) -> Option<Location> {
+        self.placed.get(&entity).copied()
+    }
+
+    fn remove(&mut self, entity: Entity) {
+        let location = match self.placed.remove(&entity) {
+            Some(location) => location,
+            None => return,
+        };
+        if let Some(entities) = self.tiles.get_mut(&location) {
+            entities.retain(|&mut e| e != entity);
+            if entities.is_empty() {
+                self.tiles.remove(&location);
+            }
+        }
+    }
+
+    /// Move `entity` onto the tile at `location` right away, instead of
+    /// waiting for the [`OccupancySystem`] to notice
+    pub fn move_to(&mut self, entity: Entity, location: Location) {
+        if self.placed.get(&entity) == Some(&location) {
+            return;
+        }
+        self.remove(entity);
+        self.placed.insert(entity, location);
+        self.tiles.entry(location).or_default().push(entity);
+    }
+
+    /// Move entities between tiles as they change location, and forget
+    /// them once they are gone
+    fn system(
+        mut occupancy: ResMut<Occupancy>,
+        moved: Query<
+            (Entity, &Location),
+            (Changed<Location>, Without<Tile>, Without<Marker>),
+        >,
+        removed: RemovedComponents<Location>,
+    ) {
+        let removed: Vec<Entity> = removed.iter().collect();
+        if moved.is_empty() && removed.is_empty() {
+            return;
+        }
+        for entity in removed {
+            occupancy.remove(entity);
+        }
+        for (entity, &location) in moved.iter() {
+            occupancy.move_to(entity, location);
+        }
+    }
+}
+
+/// Free orientation on the (x,y) plane for entities which aren't locked to the
+/// four grid directions, like billboards and projectiles.
+///
+/// Angles increase in the same order as [`Direction`]: `East` is
+/// `Direction::PositiveX` and `North` is `Direction::NegativeY`.
+#[derive(
+    Copy,
+    Clone,
+    Component,
+    Inspectable,
+    PartialEq,
+    Debug,
+    Default,
+    Serialize,
+    Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Facing {
+    #[default]
+    East,
+    NorthEast,
+    North,
+    NorthWest,
+    West,
+    SouthWest,
+    South,
+    SouthEast,
+    /// Arbitrary angle in radians
+    Angle(f32),
+}
+
+impl Facing {
+    const OCTANTS: [Facing; 8] = [
+        Facing::East,
+        Facing::NorthEast,
+        Facing::North,
+        Facing::NorthWest,
+        Facing::West,
+        Facing::SouthWest,
+        Facing::South,
+        Facing::SouthEast,
+    ];
+
+    /// Rotation around the up axis in radians, in the range [0, TAU)
+    pub fn angle(self) -> f32 {
+        match self {
+            Facing::Angle(angle) => angle.rem_euclid(TAU),
+            octant => {
+                let i = Self::OCTANTS.iter().position(|&f| f == octant);
+                i.unwrap_or_default() as f32 * FRAC_PI_4
+            }
+        }
+    }
+
+    /// Snap an angle in radians to the nearest of the eight directions
+    pub fn from_angle(angle: f32) -> Self {
+        let octant = (angle.rem_euclid(TAU) / FRAC_PI_4).round() as usize;
+        Self::OCTANTS[octant % 8]
+    }
+
+    /// Face along a vector on the ground plane, keeping the exact angle
+    pub fn from_vec(v: Vec3) -> Self {
+        Facing::Angle((-v.z).atan2(v.x).rem_euclid(TAU))
+    }
+
+    /// Snap to the nearest of the eight directions
+    pub fn snapped(self) -> Self {
+        Self::from_angle(self.angle())
+    }
+
+    /// The nearest grid direction, ties rounding counter-clockwise
+    pub fn direction(self) -> Direction {
+        let quadrant = (self.angle() / (2.0 * FRAC_PI_4)).round() as usize;
+        [
+            Direction::PositiveX,
+            Direction::NegativeY,
+            Direction::NegativeX,
+            Direction::PositiveY,
+        ][quadrant % 4]
+    }
+
+    /// Which row of a sprite sheet to draw when seen from a camera with the
+    /// given yaw, for sheets with `rows` evenly spaced views (usually 4 or 8)
+    /// starting from the front and going counter-clockwise.
+    pub fn sprite_row(self, view_yaw: f32, rows: usize) -> usize {
+        if rows == 0 {
+            return 0;
+        }
+        let step = TAU / rows as f32;
+        let relative = (self.angle() - view_yaw).rem_euclid(TAU);
+        (relative / step).round() as usize % rows
+    }
+}
+
+impl From<Direction> for Facing {
+    fn from(dir: Direction) -> Self {
+        match dir {
+            Direction::PositiveX => Facing::East,
+            Direction::NegativeY => Facing::North,
+            Direction::NegativeX => Facing::West,
+            Direction::PositiveY => Facing::South,
+        }
+    }
+}
+
+impl From<Facing> for Quat {
+    fn from(facing: Facing) -> Self {
+        Self::from_axis_angle(Vec3::Y, facing.angle())
+    }
+}
+
+fn facing_controller(
+    mut query: Query<(&Facing, &mut Transform), Changed<Facing>>,
+) {
+    for (facing, mut transform) in query.iter_mut() {
+        transform.rotation = (*facing).into();
+    }
+}
+
 const TILE_MESH_HANDLE: HandleUntyped =
     HandleUntyped::weak_from_u64(Mesh::TYPE_UUID, 0x857e0e2d7312f367);
 
 pub struct TileMesh {
-    /// This probably won't be used, but we need at least one strong handle
-    /// to the mesh to persist in order to prevent it from being unexpectedly
-    /// dropped.
-    #[allow(unused)]
+    /// We need at least one strong handle to the mesh to persist in order to
+    /// prevent it from being unexpectedly dropped.
     pub handle: Handle<Mesh>,
 }
 
@@ -97,8 +1388,13 @@ impl FromWorld for TileMesh {
     }
 }
 
+/// Marker for floor tiles
+#[derive(Component, Default, Clone, Copy, Debug)]
+pub struct Tile;
+
 #[derive(Bundle)]
 pub struct TileBundle {
+    pub tile: Tile,
     pub grid_pos: Location,
     #[bundle]
     pub render: UnlitMaterialBundle,
@@ -108,6 +1404,7 @@ impl TileBundle {
     /// Create a tile at the given location with the provided material
     pub fn new(grid_pos: Location, material: Handle<UnlitMaterial>) -> Self {
         Self {
+            tile: Tile,
             grid_pos,
             render: UnlitMaterialBundle {
                 material,
@@ -125,28 +1422,85 @@ impl TileBundle {
 /// Simple vertical wall
 #[derive(Bundle)]
 pub struct WallBundle {
-    location: Location,
+    edge: Edge,
     direction: Direction,
     #[bundle]
     render: UnlitMaterialBundle,
 }
 
 impl WallBundle {
+    /// Create a wall on the `direction` side of the tile at `location`,
+    /// facing back into that tile
     pub fn new(
         location: Location,
         direction: Direction,
         material: Handle<UnlitMaterial>,
     ) -> Self {
-        let grid_pos: Vec3 = location.into();
+        Self::on_edge(Edge::new(location, direction), direction, material)
+    }
+
+    /// Create a wall on an edge, oriented as though it was placed on the
+    /// `direction` side of a tile
+    pub fn on_edge(
+        edge: Edge,
+        direction: Direction,
+        material: Handle<UnlitMaterial>,
+    ) -> Self {
+        let grid_pos: Vec3 = edge.into();
         Self {
-            location,
+            edge,
             direction,
             render: UnlitMaterialBundle {
                 material,
-                transform: Transform::from_translation(grid_pos.into()),
+                transform: Transform::from_translation(grid_pos)
+                    .with_rotation(direction.into()),
+                global_transform: GlobalTransform::from_translation(grid_pos)
+                    .with_rotation(direction.into()),
+                mesh: WALL_MESH_HANDLE.typed::<Mesh>(),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+/// Marker for props, upright sprites standing in the middle of a tile
+#[derive(Component, Default, Clone, Copy, Debug)]
+pub struct Prop;
+
+/// Marker for anything standing on a tile which nothing can walk through,
+/// like a pushable block
+#[derive(Component, Default, Clone, Copy, Debug)]
+pub struct Obstacle;
+
+/// Prop drawn on the same upright quad as walls, facing `direction`
+#[derive(Bundle)]
+pub struct PropBundle {
+    pub prop: Prop,
+    pub grid_pos: Location,
+    pub direction: Direction,
+    #[bundle]
+    pub render: UnlitMaterialBundle,
+}
+
+impl PropBundle {
+    pub fn new(
+        grid_pos: Location,
+        direction: Direction,
+        material: Handle<UnlitMaterial>,
+    ) -> Self {
+        let translation: Vec3 = grid_pos.into();
+        Self {
+            prop: Prop,
+            grid_pos,
+            direction,
+            render: UnlitMaterialBundle {
+                material,
+                transform: Transform::from_translation(translation)
+                    .with_rotation(direction.into()),
                 global_transform: GlobalTransform::from_translation(
-                    grid_pos.into(),
-                ),
+                    translation,
+                )
+                .with_rotation(direction.into()),
                 mesh: WALL_MESH_HANDLE.typed::<Mesh>(),
                 ..Default::default()
             },
@@ -158,23 +1512,21 @@ const WALL_MESH_HANDLE: HandleUntyped =
     HandleUntyped::weak_from_u64(Mesh::TYPE_UUID, 0x903dba46d6b08058);
 
 pub struct WallMesh {
-    /// This probably won't be used, but we need at least one strong handle
-    /// to the mesh to persist in order to prevent it from being unexpectedly
-    /// dropped.
-    #[allow(unused)]
+    /// We need at least one strong handle to the mesh to persist in order to
+    /// prevent it from being unexpectedly dropped.
     pub handle: Handle<Mesh>,
 }
 
 impl FromWorld for WallMesh {
     fn from_world(world: &mut World) -> Self {
-        // The local coordinates (model space) are relative to the center of the
-        // tile on the ground plane.  Tiles are square and all share the same
-        // width.  The ground plane is the (x,z) plane in bevy (ugh).
+        // The local coordinates (model space) are relative to the middle of
+        // the edge on the ground plane.  Tiles are square and all share the
+        // same width.  The ground plane is the (x,z) plane in bevy (ugh).
 
         // Since Direction::PositiveX is rotation zero, the plane wall plane
         // should be a subset of the (y,z) plane
-        let x = TILE_SIZE * 0.5;
-        let z = x;
+        let x = 0.0;
+        let z = TILE_SIZE * 0.5;
 
         let mut mesh =
             Mesh::new(bevy::render::mesh::PrimitiveTopology::TriangleStrip);
@@ -0,0 +1,50 @@
+use super::SpriteIndex;
+use bevy::prelude::*;
+
+pub struct AnimatePlugin;
+
+impl Plugin for AnimatePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(animate_sprites);
+    }
+}
+
+/// Cycle the [`SpriteIndex`] of a tile, wall, or prop through `frames`.
+///
+/// Every animation runs on the same clock, so neighbouring tiles with the
+/// same frames stay in step.
+#[derive(Component, Debug, Clone, PartialEq)]
+pub struct AnimatedUv {
+    /// Sprite indices in the order they are shown
+    pub frames: Vec<u32>,
+    /// Frames shown per second
+    pub fps: f32,
+}
+
+impl AnimatedUv {
+    /// The frame showing `seconds` after the animation started
+    pub fn frame(&self, seconds: f64) -> Option<u32> {
+        if self.frames.is_empty() {
+            return None;
+        }
+        let step = (seconds * self.fps.max(0.0) as f64) as usize;
+        Some(self.frames[step % self.frames.len()])
+    }
+}
+
+/// Swap to the current frame, the sprite controller then picks the mesh which
+/// shows it
+fn animate_sprites(
+    time: Res<Time>,
+    mut query: Query<(&AnimatedUv, &mut SpriteIndex)>,
+) {
+    let seconds = time.seconds_since_startup();
+    for (animation, mut index) in query.iter_mut() {
+        match animation.frame(seconds) {
+            // Only touch the index on a new frame to keep change detection
+            // quiet
+            Some(frame) if index.0 != frame => index.0 = frame,
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,716 @@
+use super::{perimeter, Direction, Edge, Lit, Location, Marker, SpriteIndex};
+use super::{
+    Door, LayerOrder, Prop, PropBundle, PropLight, Tile, TileBundle, TileKind,
+    WallBundle,
+};
+use crate::ai::{PatrolPath, PatrolPaths};
+use crate::ambience::MapAmbience;
+use crate::dialogue::DialogueTrigger;
+use crate::hazard::Hazard;
+use crate::interact::Interactable;
+use crate::item::Pickup;
+use crate::material::{CornerColors, SpriteFlip};
+use crate::push::Pushable;
+use crate::rules::RuleOverrides;
+use crate::spawner::Spawner;
+use crate::stats::{StatBlock, StatJitter};
+use crate::team::Hostility;
+use crate::theme::SpriteKind;
+use bevy::asset::{AssetLoader, LoadContext, LoadedAsset};
+use bevy::ecs::system::{EntityCommands, SystemParam};
+use bevy::prelude::*;
+use bevy::reflect::TypeUuid;
+use bevy::utils::{BoxedFuture, HashSet};
+use iyes_loopless::prelude::*;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+pub struct MapAssetPlugin;
+
+impl Plugin for MapAssetPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<Map>()
+            .init_asset_loader::<MapLoader>()
+            .init_resource::<ActiveMap>()
+            .init_resource::<MapRng>()
+            .add_system_set(
+                ConditionSet::new().with_system(spawn_active_map).into(),
+            );
+    }
+}
+
+/// Everything placed on the grid for one level, loaded from `.map.yaml` files
+#[derive(Debug, Clone, Default, Serialize, Deserialize, TypeUuid)]
+#[uuid = "0b5e3a4d-8f61-4f3e-9b0a-2c7d1e6a9f42"]
+pub struct Map {
+    pub name: String,
+    /// Seed for randomized entries, a new seed is picked every time the map
+    /// is spawned if this is missing
+    #[serde(default)]
+    pub seed: Option<u64>,
+    #[serde(default)]
+    pub ambience: MapAmbience,
+    /// Shade every tile, wall, and prop with the scene's lights unless the
+    /// entry says otherwise
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub lit: bool,
+    /// Gameplay constants which differ from the global rules on this map
+    #[serde(default, skip_serializing_if = "RuleOverrides::is_empty")]
+    pub rules: RuleOverrides,
+    #[serde(default)]
+    pub tiles: Vec<MapTile>,
+    #[serde(default)]
+    pub walls: Vec<MapWall>,
+    /// Enclose every floor region with walls drawn with this sprite, on
+    /// boundaries which don't already have a wall
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auto_walls: Option<MapSprite>,
+    #[serde(default)]
+    pub markers: Vec<MapMarker>,
+    #[serde(default)]
+    pub props: Vec<MapProp>,
+    /// Routes walked by the enemies they are named after
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub patrols: Vec<PatrolPath>,
+    /// Teams which fight each other on this map
+    #[serde(default, skip_serializing_if = "Hostility::is_default")]
+    pub hostility: Hostility,
+    /// Order the tiles and walls are drawn in where they overlap
+    #[serde(default, skip_serializing_if = "LayerOrder::is_default")]
+    pub layers: LayerOrder,
+}
+
+/// Sprite drawn on a tile or wall, either an index into the sheet or the
+/// name of a sprite kind resolved by the active theme
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum MapSprite {
+    Index(u32),
+    Kind(String),
+}
+
+impl MapSprite {
+    /// Sprite of a placed entity, preferring the theme's kind so the map can
+    /// still be reskinned
+    pub fn from_components(
+        index: Option<&SpriteIndex>,
+        kind: Option<&SpriteKind>,
+    ) -> Option<Self> {
+        match (kind, index) {
+            (Some(kind), _) => Some(MapSprite::Kind(kind.0.clone())),
+            (None, Some(index)) => Some(MapSprite::Index(index.0)),
+            (None, None) => None,
+        }
+    }
+
+    pub fn insert(&self, entity: &mut EntityCommands) {
+        match self {
+            MapSprite::Index(index) => entity.insert(SpriteIndex(*index)),
+            MapSprite::Kind(kind) => entity.insert(SpriteKind::new(kind)),
+        };
+    }
+}
+
+/// Optional randomization of a map entry, resolved with the [`MapRng`] when
+/// the map is spawned
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Randomize {
+    /// Sprites to pick from, with equal weight, in place of the entry's own
+    pub variants: Vec<MapSprite>,
+    /// Probability the entry is spawned at all [0, 1]
+    pub chance: f32,
+    /// How far each stat of the unit spawned from a marker may be moved up
+    /// or down from its base
+    pub stats: StatBlock,
+}
+
+impl Default for Randomize {
+    fn default() -> Self {
+        Self {
+            variants: Vec::new(),
+            chance: 1.0,
+            stats: StatBlock::default(),
+        }
+    }
+}
+
+impl Randomize {
+    fn spawns(&self, rng: &mut impl Rng) -> bool {
+        self.chance >= 1.0 || rng.gen::<f32>() < self.chance
+    }
+
+    fn sprite<'a>(
+        &'a self,
+        sprite: &'a MapSprite,
+        rng: &mut impl Rng,
+    ) -> &'a MapSprite {
+        if self.variants.is_empty() {
+            sprite
+        } else {
+            &self.variants[rng.gen_range(0..self.variants.len())]
+        }
+    }
+
+    fn stats(&self, rng: &mut impl Rng) -> Option<StatJitter> {
+        (self.stats != StatBlock::default())
+            .then(|| StatJitter(self.stats.jitter(rng)))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MapTile {
+    #[serde(flatten)]
+    pub location: Location,
+    pub sprite: MapSprite,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub random: Option<Randomize>,
+    /// Whether to use the lit material, overriding the map's [`Map::lit`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lit: Option<bool>,
+    #[serde(default, skip_serializing_if = "SpriteFlip::is_identity")]
+    pub flip: SpriteFlip,
+    /// Colors blended across the sprite from its corners
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub colors: Option<CornerColors>,
+    #[serde(default, skip_serializing_if = "TileKind::is_floor")]
+    pub kind: TileKind,
+    /// Spikes, lava, or a pit hurting whatever ends its move on the tile
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hazard: Option<Hazard>,
+}
+
+/// Wall on the `direction` side of the tile at `location`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MapWall {
+    #[serde(flatten)]
+    pub location: Location,
+    pub direction: Direction,
+    pub sprite: MapSprite,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub random: Option<Randomize>,
+    /// Whether to use the lit material, overriding the map's [`Map::lit`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lit: Option<bool>,
+    #[serde(default, skip_serializing_if = "SpriteFlip::is_identity")]
+    pub flip: SpriteFlip,
+    /// Colors blended across the sprite from its corners
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub colors: Option<CornerColors>,
+    /// Open and close the wall as a door
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub door: Option<Door>,
+}
+
+impl MapWall {
+    pub fn edge(&self) -> Edge {
+        Edge::new(self.location, self.direction)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MapMarker {
+    #[serde(flatten)]
+    pub location: Location,
+    pub marker: Marker,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub random: Option<Randomize>,
+    /// Given to whatever the marker spawns, like an enemy following the
+    /// patrol path of the same name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Conversation started when the player steps onto the marker
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dialogue: Option<DialogueTrigger>,
+}
+
+/// Prop standing on the tile at `location`, facing `direction`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MapProp {
+    #[serde(flatten)]
+    pub location: Location,
+    pub direction: Direction,
+    pub sprite: MapSprite,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub random: Option<Randomize>,
+    /// Whether to use the lit material, overriding the map's [`Map::lit`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lit: Option<bool>,
+    #[serde(default, skip_serializing_if = "SpriteFlip::is_identity")]
+    pub flip: SpriteFlip,
+    /// Colors blended across the sprite from its corners
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub colors: Option<CornerColors>,
+    /// Point light the prop carries, turning it into a torch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub light: Option<PropLight>,
+    /// What the player can do with the prop, like pull a lever or open a
+    /// chest
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interactable: Option<Interactable>,
+    /// Items the player can pick up from the prop's tile
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pickup: Option<Pickup>,
+    /// Whether the prop is a block the player can push and pull around
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub pushable: bool,
+    /// Waves of enemies sent out from the prop's tile
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spawner: Option<Spawner>,
+}
+
+impl Map {
+    /// Rectangular room of `width` by `height` floor tiles with walls all the
+    /// way around, starting at the origin
+    pub fn blank(
+        name: impl Into<String>,
+        width: u32,
+        height: u32,
+        floor: MapSprite,
+        wall: MapSprite,
+    ) -> Self {
+        let (width, height) = (width as i32, height as i32);
+        let mut map = Map {
+            name: name.into(),
+            ..Default::default()
+        };
+
+        for y in 0..height {
+            for x in 0..width {
+                let location = Location { x, y };
+                map.tiles.push(MapTile {
+                    location,
+                    sprite: floor.clone(),
+                    random: None,
+                    lit: None,
+                    flip: SpriteFlip::default(),
+                    colors: None,
+                    kind: TileKind::Floor,
+                    hazard: None,
+                });
+
+                // Border walls face back into the room
+                for direction in Direction::ALL {
+                    let Location { x: nx, y: ny } =
+                        location.neighbor(direction);
+                    if nx < 0 || ny < 0 || nx >= width || ny >= height {
+                        map.walls.push(MapWall {
+                            location,
+                            direction,
+                            sprite: wall.clone(),
+                            random: None,
+                            lit: None,
+                            flip: SpriteFlip::default(),
+                            colors: None,
+                            door: None,
+                        });
+                    }
+                }
+            }
+        }
+
+        map
+    }
+
+    /// Spawn every tile, wall, marker, and prop in the map, resolving randomized
+    /// entries with `rng`
+    pub fn spawn(&self, commands: &mut Commands, rng: &mut impl Rng) {
+        let default = Randomize::default();
+
+        for tile in self.tiles.iter() {
+            let random = tile.random.as_ref().unwrap_or(&default);
+            if !random.spawns(rng) {
+                continue;
+            }
+            let mut entity = commands.spawn_bundle(TileBundle::new(
+                tile.location,
+                Handle::default(),
+            ));
+            random.sprite(&tile.sprite, rng).insert(&mut entity);
+            if tile.lit.unwrap_or(self.lit) {
+                entity.insert(Lit);
+            }
+            if !tile.flip.is_identity() {
+                entity.insert(tile.flip);
+            }
+            if let Some(colors) = tile.colors {
+                entity.insert(colors);
+            }
+            if !tile.kind.is_floor() {
+                entity.insert(tile.kind);
+            }
+            if let Some(hazard) = &tile.hazard {
+                entity.insert(hazard.clone());
+            }
+        }
+
+        for wall in self.walls.iter() {
+            let random = wall.random.as_ref().unwrap_or(&default);
+            if !random.spawns(rng) {
+                continue;
+            }
+            let mut entity = commands.spawn_bundle(WallBundle::new(
+                wall.location,
+                wall.direction,
+                Handle::default(),
+            ));
+            random.sprite(&wall.sprite, rng).insert(&mut entity);
+            if wall.lit.unwrap_or(self.lit) {
+                entity.insert(Lit);
+            }
+            if !wall.flip.is_identity() {
+                entity.insert(wall.flip);
+            }
+            if let Some(colors) = wall.colors {
+                entity.insert(colors);
+            }
+            if let Some(door) = &wall.door {
+                entity.insert(door.clone());
+            }
+        }
+
+        if let Some(sprite) = &self.auto_walls {
+            self.spawn_auto_walls(commands, sprite);
+        }
+
+        for marker in self.markers.iter() {
+            let random = marker.random.as_ref().unwrap_or(&default);
+            if !random.spawns(rng) {
+                continue;
+            }
+            let mut entity = commands.spawn();
+            entity.insert(marker.location).insert(marker.marker);
+            if let Some(name) = &marker.name {
+                entity.insert(Name::new(name.clone()));
+            }
+            if let Some(dialogue) = &marker.dialogue {
+                entity.insert(dialogue.clone());
+            }
+            if let Some(jitter) = random.stats(rng) {
+                entity.insert(jitter);
+            }
+        }
+
+        for prop in self.props.iter() {
+            let random = prop.random.as_ref().unwrap_or(&default);
+            if !random.spawns(rng) {
+                continue;
+            }
+            let mut entity = commands.spawn_bundle(PropBundle::new(
+                prop.location,
+                prop.direction,
+                Handle::default(),
+            ));
+            random.sprite(&prop.sprite, rng).insert(&mut entity);
+            if prop.lit.unwrap_or(self.lit) {
+                entity.insert(Lit);
+            }
+            if !prop.flip.is_identity() {
+                entity.insert(prop.flip);
+            }
+            if let Some(colors) = prop.colors {
+                entity.insert(colors);
+            }
+            if let Some(light) = &prop.light {
+                entity.insert(light.clone());
+            }
+            if let Some(interactable) = &prop.interactable {
+                entity.insert(interactable.clone());
+            }
+            if let Some(pickup) = &prop.pickup {
+                entity.insert(pickup.clone());
+            }
+            if prop.pushable {
+                entity.insert(Pushable);
+            }
+            if let Some(spawner) = &prop.spawner {
+                entity.insert(spawner.clone());
+            }
+        }
+    }
+
+    /// Walls on every floor boundary not covered by an explicit wall
+    fn spawn_auto_walls(&self, commands: &mut Commands, sprite: &MapSprite) {
+        let tiles: HashSet<Location> =
+            self.tiles.iter().map(|tile| tile.location).collect();
+        let placed: HashSet<Edge> =
+            self.walls.iter().map(|wall| wall.edge()).collect();
+
+        for (location, direction) in perimeter(&tiles) {
+            if placed.contains(&Edge::new(location, direction)) {
+                continue;
+            }
+            let mut entity = commands.spawn_bundle(WallBundle::new(
+                location,
+                direction,
+                Handle::default(),
+            ));
+            sprite.insert(&mut entity);
+            if self.lit {
+                entity.insert(Lit);
+            }
+        }
+    }
+}
+
+type PlacedSprite<'a> = (
+    Option<&'a SpriteIndex>,
+    Option<&'a SpriteKind>,
+    Option<&'a SpriteFlip>,
+    Option<&'a CornerColors>,
+);
+
+/// Everything placed on the grid, to turn the map being edited back into a
+/// [`Map`]
+#[derive(SystemParam)]
+pub struct MapCapture<'w, 's> {
+    ambience: Res<'w, MapAmbience>,
+    rules: Res<'w, RuleOverrides>,
+    patrols: Res<'w, PatrolPaths>,
+    hostility: Res<'w, Hostility>,
+    layers: Res<'w, LayerOrder>,
+    tiles: Query<
+        'w,
+        's,
+        (
+            &'static Location,
+            PlacedSprite<'static>,
+            Option<&'static Lit>,
+            Option<&'static TileKind>,
+            Option<&'static Hazard>,
+        ),
+        With<Tile>,
+    >,
+    walls: Query<
+        'w,
+        's,
+        (
+            &'static Edge,
+            &'static Direction,
+            PlacedSprite<'static>,
+            Option<&'static Lit>,
+            Option<&'static Door>,
+        ),
+    >,
+    markers: Query<
+        'w,
+        's,
+        (
+            &'static Location,
+            &'static Marker,
+            Option<&'static Name>,
+            Option<&'static DialogueTrigger>,
+        ),
+    >,
+    props: Query<
+        'w,
+        's,
+        (
+            &'static Location,
+            &'static Direction,
+            PlacedSprite<'static>,
+            Option<&'static Lit>,
+            Option<&'static PropLight>,
+            Option<&'static Interactable>,
+            Option<&'static Pickup>,
+            Option<&'static Pushable>,
+            Option<&'static Spawner>,
+        ),
+        With<Prop>,
+    >,
+}
+
+impl<'w, 's> MapCapture<'w, 's> {
+    /// The map as it is currently placed, named `name`.
+    ///
+    /// Randomized entries are saved as they were resolved.
+    pub fn capture(&self, name: &str) -> Map {
+        let mut map = Map {
+            name: name.to_string(),
+            ambience: self.ambience.clone(),
+            rules: self.rules.clone(),
+            patrols: self.patrols.paths.clone(),
+            hostility: self.hostility.clone(),
+            layers: self.layers.clone(),
+            ..Default::default()
+        };
+
+        for (&location, (index, kind, flip, colors), lit, tile, hazard) in
+            self.tiles.iter()
+        {
+            if let Some(sprite) = MapSprite::from_components(index, kind) {
+                map.tiles.push(MapTile {
+                    location,
+                    sprite,
+                    random: None,
+                    lit: lit.map(|_| true),
+                    flip: flip.copied().unwrap_or_default(),
+                    colors: colors.copied(),
+                    kind: tile.copied().unwrap_or_default(),
+                    hazard: hazard.cloned(),
+                });
+            }
+        }
+        for (edge, &direction, (index, kind, flip, colors), lit, door) in
+            self.walls.iter()
+        {
+            if let Some(sprite) = MapSprite::from_components(index, kind) {
+                map.walls.push(MapWall {
+                    location: edge.side(direction),
+                    direction,
+                    sprite,
+                    random: None,
+                    lit: lit.map(|_| true),
+                    flip: flip.copied().unwrap_or_default(),
+                    colors: colors.copied(),
+                    door: door.cloned(),
+                });
+            }
+        }
+        for (&location, &marker, name, dialogue) in self.markers.iter() {
+            map.markers.push(MapMarker {
+                location,
+                marker,
+                random: None,
+                name: name.map(|name| name.to_string()),
+                dialogue: dialogue.cloned(),
+            });
+        }
+        for (
+            &location,
+            &direction,
+            (index, kind, flip, colors),
+            lit,
+            light,
+            interactable,
+            pickup,
+            pushable,
+            spawner,
+        ) in self.props.iter()
+        {
+            if let Some(sprite) = MapSprite::from_components(index, kind) {
+                map.props.push(MapProp {
+                    location,
+                    direction,
+                    sprite,
+                    random: None,
+                    lit: lit.map(|_| true),
+                    flip: flip.copied().unwrap_or_default(),
+                    colors: colors.copied(),
+                    light: light.cloned(),
+                    interactable: interactable.cloned(),
+                    pickup: pickup.cloned(),
+                    pushable: pushable.is_some(),
+                    spawner: spawner.cloned(),
+                });
+            }
+        }
+
+        // Keep saved files stable between captures
+        let key = |l: &Location| (l.y, l.x);
+        map.tiles.sort_by_key(|tile| key(&tile.location));
+        map.walls
+            .sort_by_key(|wall| (key(&wall.location), wall.direction as u8));
+        map.markers.sort_by_key(|marker| key(&marker.location));
+        map.props.sort_by_key(|prop| key(&prop.location));
+        map
+    }
+}
+
+#[derive(Default)]
+pub struct MapLoader;
+
+impl AssetLoader for MapLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let map: Map = serde_yaml::from_slice(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(map));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["map.yaml"]
+    }
+}
+
+/// The map currently spawned in the world
+pub struct ActiveMap {
+    pub handle: Handle<Map>,
+}
+
+impl FromWorld for ActiveMap {
+    fn from_world(world: &mut World) -> Self {
+        Self {
+            handle: world.resource::<AssetServer>().load("maps/test.map.yaml"),
+        }
+    }
+}
+
+/// Random number generator used while spawning maps and rolling loot, so a
+/// seed plays out the same way
+pub struct MapRng {
+    /// Seed the generator was last reset with, to reproduce a spawn
+    pub seed: u64,
+    pub rng: StdRng,
+}
+
+impl Default for MapRng {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl MapRng {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+}
+
+/// Replace everything on the grid whenever the active map is switched or
+/// its file changes
+fn spawn_active_map(
+    mut commands: Commands,
+    active: Res<ActiveMap>,
+    mut events: EventReader<AssetEvent<Map>>,
+    maps: Res<Assets<Map>>,
+    mut rng: ResMut<MapRng>,
+    placed: Query<Entity, Or<(With<Location>, With<Edge>)>>,
+) {
+    let reloaded = events.iter().any(|event| match event {
+        AssetEvent::Created { handle } | AssetEvent::Modified { handle } => {
+            *handle == active.handle
+        }
+        AssetEvent::Removed { .. } => false,
+    });
+    if !reloaded && !active.is_changed() {
+        return;
+    }
+
+    let map = match maps.get(&active.handle) {
+        Some(map) => map,
+        None => return,
+    };
+
+    *rng = MapRng::new(map.seed.unwrap_or_else(rand::random));
+    info!("Spawning map {:?} with seed {}", map.name, rng.seed);
+    for entity in placed.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    map.spawn(&mut commands, &mut rng.rng);
+    commands.insert_resource(map.ambience.clone());
+    commands.insert_resource(map.rules.clone());
+    commands.insert_resource(PatrolPaths {
+        paths: map.patrols.clone(),
+    });
+    commands.insert_resource(map.hostility.clone());
+    commands.insert_resource(map.layers.clone());
+}
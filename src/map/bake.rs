@@ -0,0 +1,375 @@
+use super::{sprite_bounds, AnimatedUv, Edge, Location, SpriteIndex, Tile};
+use crate::material::{
+    LitSpriteMaterial, UnlitMaterial, WaterMaterial, ATTRIBUTE_SPRITE_LAYER,
+};
+use bevy::prelude::*;
+use bevy::render::mesh::{Indices, PrimitiveTopology, VertexAttributeValues};
+use bevy::utils::{HashMap, HashSet};
+
+pub struct BakePlugin;
+
+impl Plugin for BakePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MapBaking>()
+            .init_resource::<BakedChunks>()
+            // After the sprite controller has picked every sprite's mesh
+            .add_system_to_stage(CoreStage::PostUpdate, bake_chunks);
+    }
+}
+
+/// Width and height of a chunk, in tiles
+pub const CHUNK_SIZE: i32 = 16;
+
+/// Settings for merging the static map geometry into one mesh per chunk
+pub struct MapBaking {
+    pub enabled: bool,
+    /// Keep chunks baked in the editor, instead of drawing every tile and
+    /// wall as its own entity which can be inspected
+    pub while_editing: bool,
+    /// Set by the editor while it is open
+    pub editing: bool,
+}
+
+impl Default for MapBaking {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            while_editing: false,
+            editing: false,
+        }
+    }
+}
+
+impl MapBaking {
+    pub fn active(&self) -> bool {
+        self.enabled && (self.while_editing || !self.editing)
+    }
+}
+
+/// Merged mesh of every tile and wall in one chunk sharing a material
+#[derive(Component, Debug, Clone, Copy)]
+pub struct MapChunk {
+    pub chunk: IVec2,
+}
+
+/// Chunk a tile belongs to
+fn chunk_of(location: Location) -> IVec2 {
+    IVec2::new(
+        location.x.div_euclid(CHUNK_SIZE),
+        location.y.div_euclid(CHUNK_SIZE),
+    )
+}
+
+/// Entities drawn by each baked chunk, to rebuild only what changed
+#[derive(Default)]
+struct BakedChunks {
+    /// Chunk mesh entities of each chunk
+    chunks: HashMap<IVec2, Vec<Entity>>,
+    /// Chunk each hidden tile or wall was baked into
+    members: HashMap<Entity, IVec2>,
+}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+enum ChunkMaterial {
+    Unlit(Handle<UnlitMaterial>),
+    Lit(Handle<LitSpriteMaterial>),
+}
+
+type Piece<'a> = (
+    Entity,
+    &'a Transform,
+    &'a Handle<Mesh>,
+    Option<&'a Handle<UnlitMaterial>>,
+    Option<&'a Handle<LitSpriteMaterial>>,
+    Option<&'a Location>,
+    Option<&'a Edge>,
+);
+
+/// Animated sprites change too often to be worth baking
+type Static = (
+    Or<(With<Tile>, With<Edge>)>,
+    With<SpriteIndex>,
+    Without<AnimatedUv>,
+);
+
+type Moved = Or<(
+    Changed<Transform>,
+    Changed<Handle<Mesh>>,
+    Changed<Handle<UnlitMaterial>>,
+    Changed<Handle<LitSpriteMaterial>>,
+    // Water is drawn on its own, so its chunk is rebuilt without it
+    Changed<Handle<WaterMaterial>>,
+)>;
+
+/// Rebuild the chunk meshes of every chunk where a tile or wall was placed,
+/// changed, or removed, hiding the entities which were merged
+#[allow(clippy::too_many_arguments)]
+fn bake_chunks(
+    mut commands: Commands,
+    baking: Res<MapBaking>,
+    mut baked: ResMut<BakedChunks>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    pieces: Query<Piece, Static>,
+    changed: Query<(Entity, Option<&Location>, Option<&Edge>), (Static, Moved)>,
+    animated: Query<Entity, Added<AnimatedUv>>,
+    mut visibility: Query<&mut Visibility>,
+    mut mesh_events: EventReader<AssetEvent<Mesh>>,
+    removed_tiles: RemovedComponents<Tile>,
+    removed_walls: RemovedComponents<Edge>,
+    removed_animations: RemovedComponents<AnimatedUv>,
+) {
+    // Sprite meshes are rebuilt in place, like when their inset is resolved
+    let modified: HashSet<Handle<Mesh>> = mesh_events
+        .iter()
+        .filter_map(|event| match event {
+            AssetEvent::Modified { handle } => Some(handle.clone_weak()),
+            _ => None,
+        })
+        .collect();
+
+    if !baking.active() {
+        if !baked.chunks.is_empty() {
+            info!("Unbaking {} map chunks", baked.chunks.len());
+        }
+        for (_, chunk) in baked.chunks.drain() {
+            for entity in chunk {
+                commands.entity(entity).despawn();
+            }
+        }
+        for (entity, _) in baked.members.drain() {
+            if let Ok(mut visibility) = visibility.get_mut(entity) {
+                visibility.is_visible = true;
+            }
+        }
+        return;
+    }
+
+    let piece_chunk = |location: Option<&Location>, edge: Option<&Edge>| {
+        location
+            .or_else(|| edge.map(|edge| &edge.location))
+            .copied()
+    };
+
+    let mut dirty: HashSet<IVec2> = HashSet::default();
+    if baking.is_changed() {
+        dirty.extend(pieces.iter().filter_map(|(.., location, edge)| {
+            piece_chunk(location, edge).map(chunk_of)
+        }));
+    }
+    for (entity, location, edge) in changed.iter() {
+        dirty.extend(piece_chunk(location, edge).map(chunk_of));
+        // A piece moved out of the chunk it was baked into leaves it too
+        dirty.extend(baked.members.get(&entity).copied());
+    }
+    if !modified.is_empty() {
+        dirty.extend(pieces.iter().filter_map(
+            |(_, _, mesh, .., location, edge)| {
+                modified
+                    .contains(mesh)
+                    .then(|| piece_chunk(location, edge).map(chunk_of))
+                    .flatten()
+            },
+        ));
+    }
+    for entity in removed_tiles.iter().chain(removed_walls.iter()) {
+        if let Some(chunk) = baked.members.remove(&entity) {
+            dirty.insert(chunk);
+        }
+    }
+    // Pieces which start animating are left out of their chunk and shown
+    // again when it is rebuilt, and come back into it once they stop
+    dirty.extend(
+        animated
+            .iter()
+            .filter_map(|entity| baked.members.get(&entity).copied()),
+    );
+    dirty.extend(removed_animations.iter().filter_map(|entity| {
+        let (.., location, edge) = pieces.get(entity).ok()?;
+        piece_chunk(location, edge).map(chunk_of)
+    }));
+    if dirty.is_empty() {
+        return;
+    }
+
+    // Every piece in a dirty chunk, grouped by the material it is drawn with
+    let mut groups: HashMap<
+        (IVec2, ChunkMaterial),
+        Vec<(Entity, Transform, Mesh)>,
+    > = HashMap::default();
+    for (entity, transform, mesh, unlit, lit, location, edge) in pieces.iter() {
+        let chunk = match piece_chunk(location, edge).map(chunk_of) {
+            Some(chunk) if dirty.contains(&chunk) => chunk,
+            _ => continue,
+        };
+        let material = match (unlit, lit) {
+            (_, Some(lit)) => ChunkMaterial::Lit(lit.clone()),
+            (Some(unlit), _) => ChunkMaterial::Unlit(unlit.clone()),
+            (None, None) => continue,
+        };
+        // Pieces whose mesh is still loading are drawn on their own
+        if let Some(mesh) = meshes.get(mesh) {
+            groups.entry((chunk, material)).or_default().push((
+                entity,
+                *transform,
+                mesh.clone(),
+            ));
+        }
+    }
+
+    for chunk in dirty.iter() {
+        for entity in baked.chunks.remove(chunk).unwrap_or_default() {
+            commands.entity(entity).despawn();
+        }
+    }
+    let hidden: Vec<Entity> = baked
+        .members
+        .iter()
+        .filter(|(_, chunk)| dirty.contains(chunk))
+        .map(|(&entity, _)| entity)
+        .collect();
+    for entity in hidden {
+        baked.members.remove(&entity);
+        if let Ok(mut visibility) = visibility.get_mut(entity) {
+            visibility.is_visible = true;
+        }
+    }
+
+    for ((chunk, material), parts) in groups {
+        let merged = merge(&parts);
+        let mut entity = commands.spawn();
+        entity.insert(MapChunk { chunk });
+        // A chunk of floor is as flat as its tiles
+        if let Some(aabb) = sprite_bounds(&merged) {
+            entity.insert(aabb);
+        }
+        let mesh = meshes.add(merged);
+        match material {
+            ChunkMaterial::Unlit(material) => {
+                entity.insert_bundle(MaterialMeshBundle {
+                    mesh,
+                    material,
+                    ..Default::default()
+                });
+            }
+            ChunkMaterial::Lit(material) => {
+                entity.insert_bundle(MaterialMeshBundle {
+                    mesh,
+                    material,
+                    ..Default::default()
+                });
+            }
+        }
+        baked.chunks.entry(chunk).or_default().push(entity.id());
+
+        for (entity, ..) in parts {
+            baked.members.insert(entity, chunk);
+            if let Ok(mut visibility) = visibility.get_mut(entity) {
+                visibility.is_visible = false;
+            }
+        }
+    }
+    debug!("Baked {} map chunks", dirty.len());
+}
+
+/// One triangle list of every mesh, moved into place by its transform
+fn merge(parts: &[(Entity, Transform, Mesh)]) -> Mesh {
+    let mut positions: Vec<[f32; 3]> = Vec::new();
+    let mut normals: Vec<[f32; 3]> = Vec::new();
+    let mut uvs: Vec<[f32; 2]> = Vec::new();
+    let mut colors: Vec<u32> = Vec::new();
+    let mut layers: Vec<u32> = Vec::new();
+    let mut indices: Vec<u32> = Vec::new();
+    // Pieces without corner colors are white where others have them
+    let colored = parts
+        .iter()
+        .any(|(.., mesh)| mesh.attribute(Mesh::ATTRIBUTE_COLOR).is_some());
+    // Sprites drawn from a texture array all carry their layer
+    let layered = parts
+        .iter()
+        .any(|(.., mesh)| mesh.attribute(ATTRIBUTE_SPRITE_LAYER).is_some());
+
+    for (_, transform, mesh) in parts {
+        let (part_positions, part_normals, part_uvs) = match (
+            mesh.attribute(Mesh::ATTRIBUTE_POSITION),
+            mesh.attribute(Mesh::ATTRIBUTE_NORMAL),
+            mesh.attribute(Mesh::ATTRIBUTE_UV_0),
+        ) {
+            (
+                Some(VertexAttributeValues::Float32x3(positions)),
+                Some(VertexAttributeValues::Float32x3(normals)),
+                Some(VertexAttributeValues::Float32x2(uvs)),
+            ) => (positions, normals, uvs),
+            _ => {
+                warn!("Skipping sprite mesh with unexpected attributes");
+                continue;
+            }
+        };
+
+        let base = positions.len() as u32;
+        let part_indices: Vec<u32> = match mesh.indices() {
+            Some(Indices::U16(part)) => {
+                part.iter().map(|&i| i as u32).collect()
+            }
+            Some(Indices::U32(part)) => part.clone(),
+            None => (0..part_positions.len() as u32).collect(),
+        };
+        match mesh.primitive_topology() {
+            PrimitiveTopology::TriangleList => {
+                indices.extend(part_indices.iter().map(|i| base + i));
+            }
+            // Alternate the winding of every other triangle of the strip
+            PrimitiveTopology::TriangleStrip => {
+                for (n, tri) in part_indices.windows(3).enumerate() {
+                    let [a, b, c] = [tri[0], tri[1], tri[2]];
+                    let tri = if n % 2 == 0 { [a, b, c] } else { [b, a, c] };
+                    indices.extend(tri.iter().map(|i| base + i));
+                }
+            }
+            topology => {
+                warn!("Skipping sprite mesh with {:?} topology", topology);
+                continue;
+            }
+        }
+
+        positions.extend(
+            part_positions
+                .iter()
+                .map(|&p| transform.mul_vec3(Vec3::from(p)).to_array()),
+        );
+        normals.extend(
+            part_normals
+                .iter()
+                .map(|&n| (transform.rotation * Vec3::from(n)).to_array()),
+        );
+        uvs.extend(part_uvs.iter().copied());
+        if colored {
+            match mesh.attribute(Mesh::ATTRIBUTE_COLOR) {
+                Some(VertexAttributeValues::Uint32(part)) => {
+                    colors.extend(part.iter().copied())
+                }
+                _ => colors.extend(part_uvs.iter().map(|_| u32::MAX)),
+            }
+        }
+        if layered {
+            match mesh.attribute(ATTRIBUTE_SPRITE_LAYER) {
+                Some(VertexAttributeValues::Uint32(part)) => {
+                    layers.extend(part.iter().copied())
+                }
+                _ => layers.extend(part_uvs.iter().map(|_| 0)),
+            }
+        }
+    }
+
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    if colored {
+        mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
+    }
+    if layered {
+        mesh.insert_attribute(ATTRIBUTE_SPRITE_LAYER, layers);
+    }
+    mesh.set_indices(Some(Indices::U32(indices)));
+    mesh
+}
@@ -0,0 +1,137 @@
+use super::{Direction, Edge, SpriteIndex, TILE_SIZE};
+use crate::interact::{Interacted, LeverToggled};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::FRAC_PI_2;
+
+pub struct DoorPlugin;
+
+impl Plugin for DoorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(toggle_doors)
+            .add_system(Door::swing.after(toggle_doors));
+    }
+}
+
+/// Wall which opens and closes, only blocking its edge while closed.
+///
+/// The player opens it by walking up to it and interacting, and a lever on
+/// the same `channel` opens it while the lever is on.
+#[derive(Component, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Door {
+    pub open: bool,
+    /// Levers which open and close the door
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channel: Option<u32>,
+    /// Sprite shown while the door is open, instead of swinging the closed
+    /// sprite aside
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub open_sprite: Option<u32>,
+    /// Seconds taken to swing fully open or closed
+    pub duration: f32,
+    /// Sprite to go back to when closing
+    #[serde(skip)]
+    closed_sprite: Option<u32>,
+    /// How far open the door has swung [0, 1]
+    #[serde(skip)]
+    swung: f32,
+}
+
+impl Default for Door {
+    fn default() -> Self {
+        Self {
+            open: false,
+            channel: None,
+            open_sprite: None,
+            duration: 0.25,
+            closed_sprite: None,
+            swung: 0.0,
+        }
+    }
+}
+
+impl Door {
+    /// Swing towards open or closed about the hinge at one end of the edge,
+    /// or swap to the open sprite if there is one
+    fn swing(
+        time: Res<Time>,
+        mut doors: Query<(
+            &mut Door,
+            &Edge,
+            &Direction,
+            &mut Transform,
+            Option<&mut SpriteIndex>,
+            ChangeTrackers<Door>,
+        )>,
+    ) {
+        for (mut door, &edge, &direction, mut transform, sprite, tracker) in
+            doors.iter_mut()
+        {
+            let target = if door.open { 1.0 } else { 0.0 };
+            if tracker.is_added() {
+                // Doors placed open start out open
+                door.swung = target;
+            }
+
+            if let (Some(open_sprite), Some(mut sprite)) =
+                (door.open_sprite, sprite)
+            {
+                let shown = match door.open {
+                    true => Some(open_sprite),
+                    false => door.closed_sprite,
+                };
+                if door.open && door.closed_sprite.is_none() {
+                    door.closed_sprite = Some(sprite.0);
+                }
+                match shown {
+                    Some(shown) if sprite.0 != shown => sprite.0 = shown,
+                    _ => {}
+                }
+                continue;
+            }
+
+            if door.swung == target && !tracker.is_added() {
+                continue;
+            }
+            let step = if door.duration > 0.0 {
+                time.delta_seconds() / door.duration
+            } else {
+                1.0
+            };
+            door.swung = if door.open {
+                (door.swung + step).min(1.0)
+            } else {
+                (door.swung - step).max(0.0)
+            };
+
+            // The wall quad spans the edge along its local z axis
+            let closed = Quat::from(direction);
+            let hinge = Vec3::new(0.0, 0.0, -TILE_SIZE * 0.5);
+            let turn = Quat::from_rotation_y(-FRAC_PI_2 * door.swung);
+            transform.rotation = closed * turn;
+            transform.translation =
+                Vec3::from(edge) + closed * (hinge - turn * hinge);
+        }
+    }
+}
+
+/// Open or close doors the player interacts with or a lever is pulled for
+fn toggle_doors(
+    mut interacted: EventReader<Interacted>,
+    mut toggled: EventReader<LeverToggled>,
+    mut doors: Query<&mut Door>,
+) {
+    for &Interacted { target, .. } in interacted.iter() {
+        if let Ok(mut door) = doors.get_mut(target) {
+            door.open = !door.open;
+        }
+    }
+    for lever in toggled.iter() {
+        for mut door in doors.iter_mut() {
+            if door.channel == Some(lever.channel) && door.open != lever.on {
+                door.open = lever.on;
+            }
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use super::SpriteSystem;
+use bevy::prelude::*;
+
+pub struct FlashPlugin;
+
+impl Plugin for FlashPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(fade_flashes.before(SpriteSystem))
+            .add_system(flash_bodies.after(fade_flashes));
+    }
+}
+
+/// Briefly mix an unlit tile, wall, or prop towards `color`, fading back
+/// out over `duration` seconds, like flashing white or red when hit.
+///
+/// Units light up the [`StandardMaterial`] of their body instead, which
+/// every body has a copy of its own.
+///
+/// Inserting another flash restarts it, and the component is removed once
+/// the flash is over.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct FlashTint {
+    /// Mixed in by its alpha at the start of the flash
+    pub color: Color,
+    /// Seconds the flash takes to fade out
+    pub duration: f32,
+    /// Seconds since the flash started
+    elapsed: f32,
+}
+
+impl FlashTint {
+    pub fn new(color: Color, duration: f32) -> Self {
+        Self {
+            color,
+            duration,
+            elapsed: 0.0,
+        }
+    }
+
+    /// Color mixed in at this point of the flash, its alpha fading to zero
+    pub fn current(&self) -> Color {
+        let left = if self.duration > 0.0 {
+            1.0 - self.elapsed / self.duration
+        } else {
+            0.0
+        };
+        let mut color = self.color;
+        color.set_a(self.color.a() * left.clamp(0.0, 1.0));
+        color
+    }
+}
+
+fn fade_flashes(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut flashes: Query<(Entity, &mut FlashTint)>,
+) {
+    for (entity, mut flash) in flashes.iter_mut() {
+        if flash.elapsed >= flash.duration {
+            commands.entity(entity).remove::<FlashTint>();
+        } else {
+            flash.elapsed += time.delta_seconds();
+        }
+    }
+}
+
+/// Light up the body of every flashing unit by the flash color, and put it
+/// out again once the flash is over
+fn flash_bodies(
+    flashes: Query<(&FlashTint, &Children), Changed<FlashTint>>,
+    unflashed: RemovedComponents<FlashTint>,
+    children: Query<&Children>,
+    bodies: Query<&Handle<StandardMaterial>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let flashing = flashes
+        .iter()
+        .map(|(flash, children)| (flash.current(), children));
+    let unflashed = unflashed
+        .iter()
+        .filter_map(|entity| children.get(entity).ok())
+        .map(|children| (Color::NONE, children));
+    for (flash, children) in flashing.chain(unflashed) {
+        let emissive = Color::rgb(
+            flash.r() * flash.a(),
+            flash.g() * flash.a(),
+            flash.b() * flash.a(),
+        );
+        for handle in children.iter().filter_map(|&c| bodies.get(c).ok()) {
+            if let Some(material) = materials.get_mut(handle) {
+                material.emissive = emissive;
+            }
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use super::WALL_HEIGHT;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub struct LightPlugin;
+
+impl Plugin for LightPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(spawn_prop_lights).add_system(flicker_lights);
+    }
+}
+
+/// Point light carried by a prop, like a torch, lighting every [`Lit`]
+/// sprite around it.
+///
+/// [`Lit`]: super::Lit
+#[derive(Component, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PropLight {
+    pub color: Color,
+    pub intensity: f32,
+    /// Distance past which the light has no effect
+    pub range: f32,
+    /// Height of the light above the tile
+    pub height: f32,
+    /// Fraction of the intensity lost at the dimmest point of a flicker,
+    /// zero for a steady light
+    pub flicker: f32,
+    /// How quickly the light flickers
+    pub flicker_rate: f32,
+}
+
+impl Default for PropLight {
+    fn default() -> Self {
+        Self {
+            color: Color::rgb(1.0, 0.6, 0.25),
+            intensity: 4.0,
+            range: 2.0,
+            height: WALL_HEIGHT * 0.8,
+            flicker: 0.0,
+            flicker_rate: 1.0,
+        }
+    }
+}
+
+/// Marker for the point light spawned as a child of a prop with a
+/// [`PropLight`]
+#[derive(Component)]
+pub struct PropLightSource;
+
+/// Randomly dim a point light below `intensity`, like a torch or candle
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct Flicker {
+    /// Intensity of the light when it is brightest
+    pub intensity: f32,
+    /// Fraction of the intensity lost at the dimmest point [0, 1]
+    pub amount: f32,
+    /// How quickly the light flickers
+    pub rate: f32,
+}
+
+impl Flicker {
+    /// Intensity `seconds` into the flicker, `phase` keeps lights which
+    /// started together from flickering in step
+    pub fn intensity(&self, seconds: f64, phase: f32) -> f32 {
+        // Wrap the clock before it loses precision as an f32
+        let t = ((seconds * self.rate as f64) % 3600.0) as f32 + phase;
+        // Incommensurate sine waves never visibly repeat
+        let noise = (t * 2.3).sin() * 0.5
+            + (t * 5.7 + 1.3).sin() * 0.3
+            + (t * 11.1 + 2.1).sin() * 0.2;
+        self.intensity
+            * (1.0 - self.amount.clamp(0.0, 1.0) * (noise * 0.5 + 0.5))
+    }
+}
+
+/// Give every prop with a new or changed [`PropLight`] a point light child
+fn spawn_prop_lights(
+    mut commands: Commands,
+    props: Query<(Entity, &PropLight, Option<&Children>), Changed<PropLight>>,
+    sources: Query<(), With<PropLightSource>>,
+) {
+    for (entity, light, children) in props.iter() {
+        for &child in children.iter().flat_map(|children| children.iter()) {
+            if sources.get(child).is_ok() {
+                commands.entity(child).despawn();
+            }
+        }
+
+        let mut source = commands.spawn_bundle(PointLightBundle {
+            point_light: PointLight {
+                color: light.color,
+                intensity: light.intensity,
+                range: light.range,
+                ..Default::default()
+            },
+            transform: Transform::from_xyz(0.0, light.height, 0.0),
+            ..Default::default()
+        });
+        source.insert(PropLightSource);
+        if light.flicker > 0.0 {
+            source.insert(Flicker {
+                intensity: light.intensity,
+                amount: light.flicker,
+                rate: light.flicker_rate,
+            });
+        }
+        let source = source.id();
+        commands.entity(entity).add_child(source);
+    }
+}
+
+fn flicker_lights(
+    time: Res<Time>,
+    mut lights: Query<(Entity, &Flicker, &mut PointLight)>,
+) {
+    let seconds = time.seconds_since_startup();
+    for (entity, flicker, mut light) in lights.iter_mut() {
+        let phase = entity.id() as f32 * 1.618;
+        light.intensity = flicker.intensity(seconds, phase);
+    }
+}
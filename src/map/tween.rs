@@ -0,0 +1,83 @@
+use super::Location;
+use crate::camera::Easing;
+use bevy::prelude::*;
+
+pub struct TweenPlugin;
+
+impl Plugin for TweenPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(MoveTween::system);
+    }
+}
+
+/// Slide the transform to a new [`Location`] instead of jumping there.
+///
+/// The location changes the moment anything moves, so game logic never waits
+/// on the animation; only the transform lags behind.
+#[derive(Component, Debug, Clone)]
+pub struct MoveTween {
+    /// Seconds taken to slide a single tile
+    pub duration: f32,
+    pub easing: Easing,
+    /// Where the slide started, `None` once the transform has arrived
+    from: Option<Vec3>,
+    elapsed: f32,
+}
+
+impl MoveTween {
+    pub fn new(duration: f32, easing: Easing) -> Self {
+        Self {
+            duration,
+            easing,
+            from: None,
+            elapsed: 0.0,
+        }
+    }
+
+    /// Whether the transform is still sliding towards the location
+    pub fn is_moving(&self) -> bool {
+        self.from.is_some()
+    }
+
+    fn system(
+        time: Res<Time>,
+        mut query: Query<(
+            &Location,
+            ChangeTrackers<Location>,
+            &mut MoveTween,
+            &mut Transform,
+        )>,
+    ) {
+        for (&location, tracker, mut tween, mut transform) in query.iter_mut() {
+            let to = Vec3::from(location);
+            if tracker.is_added() {
+                // Appear in place rather than slide in from wherever the
+                // transform started
+                transform.translation = to;
+                tween.from = None;
+                continue;
+            }
+            if tracker.is_changed() {
+                // Start from wherever the last slide got to, so changing
+                // course mid step never jumps
+                tween.from = Some(transform.translation);
+                tween.elapsed = 0.0;
+            }
+
+            let from = match tween.from {
+                Some(from) => from,
+                None => continue,
+            };
+            tween.elapsed += time.delta_seconds();
+            let t = if tween.duration > 0.0 {
+                tween.elapsed / tween.duration
+            } else {
+                1.0
+            };
+            transform.translation = from.lerp(to, tween.easing.apply(t));
+            if t >= 1.0 {
+                tween.from = None;
+            }
+        }
+    }
+}
@@ -1,91 +1,2708 @@
 use bevy::{
+    asset::HandleId,
     ecs::system::{lifetimeless::SRes, SystemParamItem},
     pbr::{MaterialPipeline, SpecializedMaterial},
     prelude::*,
     reflect::TypeUuid,
     render::{
-        mesh::MeshVertexBufferLayout,
-        render_asset::{PrepareAssetError, RenderAsset, RenderAssets},
-        render_resource::*,
-        renderer::RenderDevice,
+        mesh::{
+            MeshVertexAttribute, MeshVertexBufferLayout, VertexAttributeValues,
+        },
+        render_asset::{
+            PrepareAssetError, PrepareAssetLabel, RenderAsset, RenderAssets,
+        },
+        render_resource::{
+            std140::{AsStd140, Std140},
+            *,
+        },
+        renderer::{RenderDevice, RenderQueue},
+        texture::TextureFormatPixelInfo,
     },
+    utils::{HashMap, HashSet},
+};
+use bevy_inspector_egui::{Inspectable, RegisterInspectable};
+use cache::AssetCache;
+use serde::{Deserialize, Serialize};
+use std::num::NonZeroU8;
+
+mod aberration;
+mod cache;
+mod debug;
+mod view;
+
+pub use debug::DebugRenderMode;
+pub use view::{
+    camera_target, MinimapCamera, PortraitCamera, SecondaryCameraBundle,
+    SecondaryCameraPlugin, ViewLayer,
 };
 
 /// Setup custom materials
-pub struct RenderPlugin;
+#[derive(Default)]
+pub struct RenderPlugin {
+    /// How unlit sprites are read out of their sheets
+    pub sprite_backend: SpriteBackend,
+}
+
+impl Plugin for RenderPlugin {
+    fn build(&self, app: &mut App) {
+        use bevy::render::{RenderApp, RenderStage};
+
+        app.add_plugin(MaterialPlugin::<UnlitMaterial>::default())
+            .add_plugin(MaterialPlugin::<LitSpriteMaterial>::default())
+            .add_plugin(MaterialPlugin::<WaterMaterial>::default())
+            .add_plugin(MaterialPlugin::<HologramMaterial>::default())
+            .add_plugin(MaterialPlugin::<ScreenMaterial>::default())
+            .add_plugin(MaterialPlugin::<BackdropMaterial>::default())
+            .add_plugin(aberration::AberrationPlugin)
+            .add_plugin(SecondaryCameraPlugin::<MinimapCamera>::default())
+            .add_plugin(SecondaryCameraPlugin::<PortraitCamera>::default())
+            .register_inspectable::<Handle<UnlitMaterial>>()
+            .init_resource::<DefaultTexture>()
+            .insert_resource(MaterialAllocator::new(self.sprite_backend))
+            .init_resource::<ColorGrade>()
+            .init_resource::<DistanceFog>()
+            .init_resource::<ScreenEffects>()
+            .init_resource::<PixelArtSettings>()
+            .init_resource::<DebugRenderMode>()
+            .add_system(log_shader_reloads)
+            .add_system(resample_materials)
+            .add_system(debug::redraw_materials)
+            .add_system(inset_sprite_meshes)
+            .add_system(build_sprite_arrays)
+            .add_system(check_unlit_meshes)
+            .add_system_to_stage(CoreStage::PostUpdate, evict_sprite_assets)
+            .add_system(tint_holograms);
+
+        app.sub_app_mut(RenderApp)
+            .add_system_to_stage(RenderStage::Extract, extract_default_texture)
+            .add_system_to_stage(RenderStage::Extract, extract_color_grade)
+            .add_system_to_stage(RenderStage::Extract, extract_distance_fog)
+            .add_system_to_stage(RenderStage::Extract, extract_screen_effects)
+            .add_system_to_stage(
+                RenderStage::Extract,
+                extract_pixel_art_settings,
+            )
+            .add_system_to_stage(
+                RenderStage::Extract,
+                debug::extract_debug_render_mode,
+            )
+            .add_system_to_stage(RenderStage::Extract, extract_material_time)
+            .add_system_to_stage(
+                RenderStage::Prepare,
+                regrade_materials.after(PrepareAssetLabel::AssetPrepare),
+            )
+            .add_system_to_stage(
+                RenderStage::Prepare,
+                animate_water.after(PrepareAssetLabel::AssetPrepare),
+            )
+            .add_system_to_stage(
+                RenderStage::Prepare,
+                animate_holograms.after(PrepareAssetLabel::AssetPrepare),
+            )
+            .add_system_to_stage(
+                RenderStage::Prepare,
+                update_screen_materials.after(PrepareAssetLabel::AssetPrepare),
+            );
+    }
+}
+
+/// Where unlit sprite materials read their sprites from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum SpriteBackend {
+    /// The whole sheet, with every mesh's UVs squeezed into its sprite
+    #[default]
+    Atlas,
+    /// A copy of the sheet with one texture array layer per sprite, picked
+    /// by the [`ATTRIBUTE_SPRITE_LAYER`] of each vertex.
+    ///
+    /// Sampling never leaves the sprite, so neighbours can't bleed in
+    /// however the sheet is filtered and no inset is needed. Lit sprites,
+    /// water, and emissive textures still read from the sheet.
+    TextureArray,
+}
+
+/// Texture array layer an unlit sprite is drawn from, when sprites use the
+/// [`SpriteBackend::TextureArray`]
+pub const ATTRIBUTE_SPRITE_LAYER: MeshVertexAttribute =
+    MeshVertexAttribute::new("Sprite_Layer", 2071946453, VertexFormat::Uint32);
+
+/// How a sprite texture is sampled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SpriteSampling {
+    /// Nearest shows texels as hard squares, linear blends between them
+    pub filter: FilterMode,
+    /// What is sampled outside of the texture
+    pub address_mode: AddressMode,
+    /// Maximum anisotropic filtering samples, rounded to a power of two up
+    /// to 16, only used by linear filtering
+    pub anisotropy: u8,
+}
+
+impl SpriteSampling {
+    /// Crisp texels for chunky pixel art
+    pub const PIXEL_ART: Self = Self {
+        filter: FilterMode::Nearest,
+        address_mode: AddressMode::ClampToEdge,
+        anisotropy: 1,
+    };
+
+    /// Smoothly filtered, for painted or high resolution art
+    pub const SMOOTH: Self = Self {
+        filter: FilterMode::Linear,
+        address_mode: AddressMode::ClampToEdge,
+        anisotropy: 16,
+    };
+
+    fn descriptor(&self) -> SamplerDescriptor<'static> {
+        let anisotropy = match self.filter {
+            FilterMode::Linear if self.anisotropy > 1 => {
+                NonZeroU8::new(self.anisotropy.next_power_of_two().min(16))
+            }
+            _ => None,
+        };
+        SamplerDescriptor {
+            label: Some("Sprite Sampler"),
+            address_mode_u: self.address_mode,
+            address_mode_v: self.address_mode,
+            address_mode_w: self.address_mode,
+            mag_filter: self.filter,
+            min_filter: self.filter,
+            mipmap_filter: self.filter,
+            anisotropy_clamp: anisotropy,
+            ..Default::default()
+        }
+    }
+}
+
+impl Default for SpriteSampling {
+    fn default() -> Self {
+        Self::PIXEL_ART
+    }
+}
+
+/// Sampling of every sprite material which doesn't pick its own
+#[derive(Debug, Clone, Default)]
+pub struct PixelArtSettings {
+    pub sampling: SpriteSampling,
+}
+
+fn extract_pixel_art_settings(
+    settings: Res<PixelArtSettings>,
+    mut commands: Commands,
+) {
+    commands.insert_resource(settings.clone())
+}
+
+/// Prepare every material again with the new sampler when the settings
+/// change
+fn resample_materials(
+    settings: Res<PixelArtSettings>,
+    mut unlit: ResMut<Assets<UnlitMaterial>>,
+    mut lit: ResMut<Assets<LitSpriteMaterial>>,
+) {
+    if !settings.is_changed() || settings.is_added() {
+        return;
+    }
+    // Mutable iteration marks each material as modified
+    unlit.iter_mut().for_each(drop);
+    lit.iter_mut().for_each(drop);
+}
+
+/// Color every sprite material is multiplied by after shading, like the
+/// light of the time of day
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ColorGrade {
+    pub tint: Color,
+    /// Brightness multiplier
+    pub exposure: f32,
+}
+
+impl Default for ColorGrade {
+    fn default() -> Self {
+        Self {
+            tint: Color::WHITE,
+            exposure: 1.0,
+        }
+    }
+}
+
+impl ColorGrade {
+    /// Linear RGB multiplier as seen by the shaders
+    fn uniform(&self) -> Vec4 {
+        let [r, g, b, _] = self.tint.as_linear_rgba_f32();
+        Vec3::new(r, g, b).extend(1.0) * self.exposure
+    }
+}
+
+/// Atmosphere unlit sprites fade into the further they are across the ground
+/// from the center of the view
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DistanceFog {
+    /// Color faded into, by its alpha
+    pub color: Color,
+    /// Distance the fade starts at, in world units
+    pub start: f32,
+    /// Distance the fade is complete at, in world units
+    pub end: f32,
+}
+
+impl Default for DistanceFog {
+    /// No fog at all
+    fn default() -> Self {
+        Self {
+            color: Color::NONE,
+            start: 0.0,
+            end: 0.0,
+        }
+    }
+}
+
+impl DistanceFog {
+    /// Linear RGBA color and range as seen by the shaders
+    fn uniform(&self) -> (Vec4, Vec2) {
+        let color = self.color.as_linear_rgba_f32().into();
+        (color, Vec2::new(self.start, self.end.max(self.start)))
+    }
+}
+
+fn extract_distance_fog(fog: Res<DistanceFog>, mut commands: Commands) {
+    commands.insert_resource(*fog)
+}
+
+fn extract_color_grade(grade: Res<ColorGrade>, mut commands: Commands) {
+    commands.insert_resource(*grade)
+}
+
+/// Effects applied to the whole screen on top of the [`ColorGrade`], like
+/// the flash of a hit
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScreenEffects {
+    /// Darkening towards the corners of the screen [0, 1]
+    pub vignette: f32,
+    /// Color the screen is blended towards by its alpha
+    pub flash: Color,
+    /// Pixels the red and blue channels of the whole screen are split apart
+    /// by at its edges
+    pub aberration: f32,
+    /// How far the whole screen has faded to black [0, 1], over the flash
+    /// and everything else
+    pub fade: f32,
+}
+
+impl Default for ScreenEffects {
+    fn default() -> Self {
+        Self {
+            vignette: 0.25,
+            flash: Color::NONE,
+            aberration: 0.0,
+            fade: 0.0,
+        }
+    }
+}
+
+fn extract_screen_effects(effects: Res<ScreenEffects>, mut commands: Commands) {
+    commands.insert_resource(*effects)
+}
+
+/// Rewrite the uniforms of every prepared material drawn with a stale grade
+/// or fog, so grading never has to recreate materials
+fn regrade_materials(
+    grade: Res<ColorGrade>,
+    fog: Res<DistanceFog>,
+    queue: Res<RenderQueue>,
+    mut unlit: ResMut<RenderAssets<UnlitMaterial>>,
+    mut lit: ResMut<RenderAssets<LitSpriteMaterial>>,
+) {
+    let target = grade.uniform();
+    let (fog_color, fog_range) = fog.uniform();
+    for material in unlit.values_mut() {
+        let uniform = &mut material.uniform;
+        if uniform.grade != target
+            || uniform.fog_color != fog_color
+            || uniform.fog_range != fog_range
+        {
+            uniform.grade = target;
+            uniform.fog_color = fog_color;
+            uniform.fog_range = fog_range;
+            let uniform = uniform.as_std140();
+            queue.write_buffer(&material.buffer, 0, uniform.as_bytes());
+        }
+    }
+    for material in lit.values_mut() {
+        let uniform = &mut material.uniform;
+        if uniform.grade != target {
+            uniform.grade = target;
+            let uniform = uniform.as_std140();
+            queue.write_buffer(&material.buffer, 0, uniform.as_bytes());
+        }
+    }
+}
+
+/// Seconds since startup as seen by animated materials, wrapped before it
+/// loses precision as an f32
+#[derive(Debug, Clone, Copy, Default)]
+struct MaterialTime(f32);
+
+fn extract_material_time(time: Res<Time>, mut commands: Commands) {
+    let seconds = time.seconds_since_startup() % 3600.0;
+    commands.insert_resource(MaterialTime(seconds as f32))
+}
+
+/// Advance the clock and grade of every water material
+fn animate_water(
+    time: Res<MaterialTime>,
+    grade: Res<ColorGrade>,
+    queue: Res<RenderQueue>,
+    mut water: ResMut<RenderAssets<WaterMaterial>>,
+) {
+    for material in water.values_mut() {
+        material.uniform.time = time.0;
+        material.uniform.grade = grade.uniform();
+        let uniform = material.uniform.as_std140();
+        queue.write_buffer(&material.buffer, 0, uniform.as_bytes());
+    }
+}
+
+/// Advance the clock of every hologram material
+fn animate_holograms(
+    time: Res<MaterialTime>,
+    queue: Res<RenderQueue>,
+    mut holograms: ResMut<RenderAssets<HologramMaterial>>,
+) {
+    for material in holograms.values_mut() {
+        material.uniform.time = time.0;
+        let uniform = material.uniform.as_std140();
+        queue.write_buffer(&material.buffer, 0, uniform.as_bytes());
+    }
+}
+
+/// Keep every screen overlay drawing the current [`ScreenEffects`]
+fn update_screen_materials(
+    effects: Res<ScreenEffects>,
+    queue: Res<RenderQueue>,
+    mut screens: ResMut<RenderAssets<ScreenMaterial>>,
+) {
+    let target = ScreenMaterialUniformData::new(&effects);
+    for material in screens.values_mut() {
+        if material.uniform != target {
+            material.uniform = target.clone();
+            let uniform = material.uniform.as_std140();
+            queue.write_buffer(&material.buffer, 0, uniform.as_bytes());
+        }
+    }
+}
+
+/// Pipelines using a shader are recreated by the pipeline cache when it
+/// changes on disk, log it so shader edits can be seen landing
+fn log_shader_reloads(
+    mut events: EventReader<AssetEvent<Shader>>,
+    asset_server: Res<AssetServer>,
+) {
+    for event in events.iter() {
+        if let AssetEvent::Modified { handle } = event {
+            match asset_server.get_handle_path(handle) {
+                Some(path) => info!("Reloading shader {:?}", path.path()),
+                None => info!("Reloading shader {:?}", handle.id),
+            }
+        }
+    }
+}
+
+/// Fallback texture
+#[derive(Clone)]
+pub struct DefaultTexture {
+    handle: Handle<Image>,
+}
+
+impl FromWorld for DefaultTexture {
+    fn from_world(world: &mut World) -> Self {
+        Self {
+            handle: world
+                .resource_mut::<AssetServer>()
+                .load("textures/default_texture.png"),
+        }
+    }
+}
+
+fn extract_default_texture(
+    texture: Res<DefaultTexture>,
+    mut commands: Commands,
+) {
+    commands.insert_resource(texture.clone())
+}
+
+pub type UnlitMaterialBundle = MaterialMeshBundle<UnlitMaterial>;
+
+/// Grid of equally sized sprites packed into one texture
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpriteSheet {
+    pub texture: Handle<Image>,
+    /// Normal map laid out exactly like `texture`, used by lit sprites
+    pub normals: Option<Handle<Image>>,
+    /// Glow laid out exactly like `texture`, added on top of the sprite
+    /// regardless of its tint or lighting
+    pub emissive: Option<Handle<Image>>,
+    pub columns: u32,
+    pub rows: u32,
+    /// Texels trimmed from every side of a sprite, half a texel keeps
+    /// neighbouring sprites from bleeding in when the sheet is filtered or
+    /// multisampled
+    pub inset: f32,
+}
+
+// The inset is never NaN
+impl Eq for SpriteSheet {}
+
+impl std::hash::Hash for SpriteSheet {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.texture.hash(state);
+        self.normals.hash(state);
+        self.emissive.hash(state);
+        self.columns.hash(state);
+        self.rows.hash(state);
+        self.inset.to_bits().hash(state);
+    }
+}
+
+impl SpriteSheet {
+    pub fn new(texture: Handle<Image>, columns: u32, rows: u32) -> Self {
+        Self {
+            texture,
+            normals: None,
+            emissive: None,
+            columns,
+            rows,
+            inset: 0.0,
+        }
+    }
+
+    pub fn with_inset(mut self, inset: f32) -> Self {
+        self.inset = inset.max(0.0);
+        self
+    }
+
+    pub fn with_normals(mut self, normals: Handle<Image>) -> Self {
+        self.normals = Some(normals);
+        self
+    }
+
+    pub fn with_emissive(mut self, emissive: Handle<Image>) -> Self {
+        self.emissive = Some(emissive);
+        self
+    }
+
+    /// Number of sprites in the sheet
+    pub fn len(&self) -> u32 {
+        self.columns * self.rows
+    }
+
+    /// Area of the texture covered by the sprite at `index`, counting left to
+    /// right and then top to bottom
+    pub fn rect(&self, index: u32) -> SpriteRect {
+        let columns = self.columns.max(1);
+        let rows = self.rows.max(1);
+        let size = Vec2::new(1.0 / columns as f32, 1.0 / rows as f32);
+        let cell =
+            Vec2::new((index % columns) as f32, (index / columns) as f32);
+        SpriteRect {
+            offset: cell * size,
+            size,
+            flip: SpriteFlip::default(),
+        }
+    }
+}
+
+/// Sub-rectangle of a texture in UV coordinates
+#[derive(Inspectable, Debug, Clone, Copy, PartialEq)]
+pub struct SpriteRect {
+    /// Top left corner
+    pub offset: Vec2,
+    pub size: Vec2,
+    /// Mirroring and rotation of the sprite inside the rectangle
+    pub flip: SpriteFlip,
+}
+
+impl Default for SpriteRect {
+    /// The whole texture
+    fn default() -> Self {
+        Self {
+            offset: Vec2::ZERO,
+            size: Vec2::ONE,
+            flip: SpriteFlip::default(),
+        }
+    }
+}
+
+impl SpriteRect {
+    /// Map a UV of the whole quad into the rectangle
+    pub fn uv(&self, uv: Vec2) -> Vec2 {
+        self.offset + self.flip.uv(uv) * self.size
+    }
+
+    /// Shrink the rectangle by `margin` on every side
+    pub fn inset(self, margin: Vec2) -> Self {
+        Self {
+            offset: self.offset + margin,
+            size: (self.size - margin * 2.0).max(Vec2::ZERO),
+            ..self
+        }
+    }
+}
+
+/// Mirror or turn a sprite, so one sprite can be reused for every wall
+/// orientation and mirrored floor variants
+#[derive(
+    Component,
+    Inspectable,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    Default,
+    Serialize,
+    Deserialize,
+)]
+#[serde(default)]
+pub struct SpriteFlip {
+    /// Mirror left to right
+    pub x: bool,
+    /// Mirror top to bottom
+    pub y: bool,
+    /// Clockwise quarter turns, applied before mirroring
+    #[inspectable(max = 3)]
+    pub turns: u8,
+}
+
+const SPRITE_FLIP_X: u32 = 1;
+const SPRITE_FLIP_Y: u32 = 2;
+/// Quarter turns are stored in the bits above the mirroring flags
+const SPRITE_FLIP_TURNS_SHIFT: u32 = 2;
+
+impl SpriteFlip {
+    pub fn is_identity(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Flags packed the way the sprite shaders read them
+    pub fn bits(&self) -> u32 {
+        let mut bits = (self.turns as u32 % 4) << SPRITE_FLIP_TURNS_SHIFT;
+        if self.x {
+            bits |= SPRITE_FLIP_X;
+        }
+        if self.y {
+            bits |= SPRITE_FLIP_Y;
+        }
+        bits
+    }
+
+    /// Flip a UV in the [0, 1] square, matching the sprite shaders
+    pub fn uv(&self, uv: Vec2) -> Vec2 {
+        let mut uv = uv;
+        for _ in 0..self.turns % 4 {
+            uv = Vec2::new(uv.y, 1.0 - uv.x);
+        }
+        if self.x {
+            uv.x = 1.0 - uv.x;
+        }
+        if self.y {
+            uv.y = 1.0 - uv.y;
+        }
+        uv
+    }
+}
+
+/// Colors multiplied with a sprite at the top left, top right, bottom left,
+/// and bottom right corners of the sprite, blended across it, to fade
+/// between terrains or bake in shadows without another texture
+#[derive(Component, Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CornerColors(pub [Color; 4]);
+
+// Colors are never NaN
+impl Eq for CornerColors {}
+
+impl std::hash::Hash for CornerColors {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.packed().hash(state);
+    }
+}
+
+impl Default for CornerColors {
+    fn default() -> Self {
+        Self([Color::WHITE; 4])
+    }
+}
+
+impl CornerColors {
+    /// Packed linear RGBA of each corner, as stored in the mesh
+    fn packed(&self) -> [u32; 4] {
+        self.0.map(Color::as_linear_rgba_u32)
+    }
+
+    /// Colors with each corner's RGB multiplied by `light`, in the same order
+    pub fn shaded(self, light: [f32; 4]) -> Self {
+        let mut colors = self.0;
+        for (color, light) in colors.iter_mut().zip(light) {
+            let [r, g, b, a] = color.as_rgba_f32();
+            *color = Color::rgba(r * light, g * light, b * light, a);
+        }
+        Self(colors)
+    }
+
+    /// Packed color of the corner closest to a UV of the whole quad
+    fn at(&self, uv: Vec2) -> u32 {
+        let corner = (uv.x > 0.5) as usize + 2 * (uv.y > 0.5) as usize;
+        self.packed()[corner]
+    }
+}
+
+/// Render flat material
+///
+/// Materials are shared by every sprite drawn from the same sheet, so
+/// tweaking one in the inspector changes all of them.
+#[derive(Inspectable, Debug, Clone, TypeUuid)]
+#[uuid = "f1aacff7-3eea-4a71-836a-efbcb11fe870"]
+pub struct UnlitMaterial {
+    texture: Option<Handle<Image>>,
+    /// Added to the sprite after tinting and grading
+    emissive: Option<Handle<Image>>,
+    rect: SpriteRect,
+    /// Multiplied with the texture, an alpha below one fades the sprite out
+    /// when blending
+    color: Color,
+    /// How transparent pixels in the texture are drawn
+    #[inspectable(ignore)]
+    alpha_mode: AlphaMode,
+    /// Overrides the sampling from the [`PixelArtSettings`]
+    #[inspectable(ignore)]
+    sampling: Option<SpriteSampling>,
+    /// Fraction of pixels discarded in an ordered dither pattern, fading the
+    /// sprite out without having to sort it like a blended one
+    #[inspectable(min = 0.0, max = 1.0)]
+    dither: f32,
+    /// Mixed into the sprite by its alpha after tinting and grading, to
+    /// flash it a solid color
+    flash: Color,
+    /// The texture split into one layer per sprite, drawn in its place
+    #[inspectable(ignore)]
+    array: Option<Handle<Image>>,
+    /// Columns and rows of sprites in the texture, to find where a layer's
+    /// sprite is in the mesh UVs
+    #[inspectable(ignore)]
+    grid: Vec2,
+}
+
+impl Default for UnlitMaterial {
+    fn default() -> Self {
+        Self {
+            texture: None,
+            emissive: None,
+            rect: SpriteRect::default(),
+            color: Color::WHITE,
+            alpha_mode: AlphaMode::default(),
+            sampling: None,
+            dither: 0.0,
+            flash: Color::NONE,
+            array: None,
+            grid: Vec2::ONE,
+        }
+    }
+}
+
+impl UnlitMaterial {
+    pub fn new(texture: Handle<Image>) -> Self {
+        Self {
+            texture: Some(texture),
+            ..Default::default()
+        }
+    }
+
+    /// Material showing the whole of a sprite sheet
+    pub fn sheet(sheet: &SpriteSheet) -> Self {
+        Self {
+            texture: Some(sheet.texture.clone()),
+            emissive: sheet.emissive.clone(),
+            ..Default::default()
+        }
+    }
+
+    /// Material showing one sprite from a sprite sheet
+    pub fn sprite(sheet: &SpriteSheet, index: u32) -> Self {
+        Self {
+            rect: sheet.rect(index),
+            ..Self::sheet(sheet)
+        }
+    }
+
+    /// Mirror or turn the sprite
+    pub fn with_flip(mut self, flip: SpriteFlip) -> Self {
+        self.rect.flip = flip;
+        self
+    }
+
+    /// Tint the material, or make it transparent with an alpha below one
+    pub fn with_color(mut self, color: Color) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn with_alpha_mode(mut self, alpha_mode: AlphaMode) -> Self {
+        self.alpha_mode = alpha_mode;
+        self
+    }
+
+    pub fn with_sampling(mut self, sampling: SpriteSampling) -> Self {
+        self.sampling = Some(sampling);
+        self
+    }
+
+    /// Dither away `dither` of the pixels, from zero for none to one for all
+    pub fn with_dither(mut self, dither: f32) -> Self {
+        self.dither = dither.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Mix the sprite towards `flash` by its alpha
+    pub fn with_flash(mut self, flash: Color) -> Self {
+        self.flash = flash;
+        self
+    }
+
+    /// Draw from `array`, `sheet` split into one layer per sprite, instead
+    /// of the sheet itself
+    pub fn with_array(
+        mut self,
+        array: Handle<Image>,
+        sheet: &SpriteSheet,
+    ) -> Self {
+        self.array = Some(array);
+        self.grid =
+            Vec2::new(sheet.columns.max(1) as f32, sheet.rows.max(1) as f32);
+        self
+    }
+
+    pub fn texture(&self) -> Option<&Handle<Image>> {
+        self.texture.as_ref()
+    }
+
+    pub fn emissive(&self) -> Option<&Handle<Image>> {
+        self.emissive.as_ref()
+    }
+
+    pub fn rect(&self) -> SpriteRect {
+        self.rect
+    }
+
+    pub fn color(&self) -> Color {
+        self.color
+    }
+
+    pub fn alpha_mode(&self) -> AlphaMode {
+        self.alpha_mode
+    }
+
+    pub fn dither(&self) -> f32 {
+        self.dither
+    }
+
+    pub fn flash(&self) -> Color {
+        self.flash
+    }
+
+    pub fn array(&self) -> Option<&Handle<Image>> {
+        self.array.as_ref()
+    }
+
+    pub fn sampling(&self) -> Option<SpriteSampling> {
+        self.sampling
+    }
+}
+
+/// Number of distinct dither levels, one per cell of the 4x4 Bayer matrix
+/// used by the sprite shaders
+pub const DITHER_LEVELS: u8 = 16;
+
+/// Number of distinct strengths a flash of a sprite variant fades through
+pub const FLASH_LEVELS: u8 = 8;
+
+/// Unused material variants kept around in case they are asked for again
+const VARIANT_CACHE_SIZE: usize = 256;
+
+/// Unused water materials kept around in case they are asked for again
+const WATER_CACHE_SIZE: usize = 64;
+
+/// Unused sprite meshes kept around in case they are asked for again
+const MESH_CACHE_SIZE: usize = 2048;
+
+/// Shares one material between every sprite drawn from the same sheet.
+///
+/// Sprites pick their part of the sheet with the UVs of a mesh shared by
+/// every user of that sprite, so drawing a new sprite never creates another
+/// material, uniform buffer, or bind group.
+///
+/// The allocator outlives maps and reloads, so everything but the sheet
+/// materials is forgotten once nothing has drawn with it for a while.
+pub struct MaterialAllocator {
+    backend: SpriteBackend,
+    materials: HashMap<SpriteSheet, Handle<UnlitMaterial>>,
+    /// Tinted, dithered, and flashed copies of sheet materials, keyed by
+    /// packed tint RGBA, dither level, and packed flash RGBA
+    variants: AssetCache<(SpriteSheet, u32, u8, u32), UnlitMaterial>,
+    lit: HashMap<SpriteSheet, Handle<LitSpriteMaterial>>,
+    /// Tinted and dithered copies of lit sheet materials, keyed by packed
+    /// tint RGBA and dither level
+    lit_variants: AssetCache<(SpriteSheet, u32, u8), LitSpriteMaterial>,
+    /// Water materials each showing one sprite, which scroll within it,
+    /// keyed by sprite index and packed tint RGBA
+    water: AssetCache<(SpriteSheet, u32, u32), WaterMaterial>,
+    meshes: AssetCache<SpriteMeshKey, Mesh>,
+    /// Sprite sheet textures split into texture arrays, filled in by
+    /// `build_sprite_arrays` once the sheet is loaded
+    arrays: HashMap<Handle<Image>, (SpriteSheet, Handle<Image>)>,
+}
+
+/// Everything which sets a sprite mesh apart from the others
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SpriteMeshKey {
+    /// Quad whose UVs are squeezed into the sprite
+    pub base: Handle<Mesh>,
+    pub sheet: SpriteSheet,
+    pub index: u32,
+    pub flip: SpriteFlip,
+    pub colors: Option<CornerColors>,
+}
+
+impl MaterialAllocator {
+    pub fn new(backend: SpriteBackend) -> Self {
+        Self {
+            backend,
+            materials: HashMap::default(),
+            variants: AssetCache::new(VARIANT_CACHE_SIZE),
+            lit: HashMap::default(),
+            lit_variants: AssetCache::new(VARIANT_CACHE_SIZE),
+            water: AssetCache::new(WATER_CACHE_SIZE),
+            meshes: AssetCache::new(MESH_CACHE_SIZE),
+            arrays: HashMap::default(),
+        }
+    }
+
+    /// Unlit material drawing the whole of `sheet` with the sprite backend
+    fn unlit(
+        &mut self,
+        sheet: &SpriteSheet,
+        images: &Assets<Image>,
+    ) -> UnlitMaterial {
+        let material = UnlitMaterial::sheet(sheet);
+        match self.backend {
+            SpriteBackend::Atlas => material,
+            SpriteBackend::TextureArray => {
+                // Reserved now, the array is built once the sheet loads
+                let (_, array) = self
+                    .arrays
+                    .entry(sheet.texture.clone())
+                    .or_insert_with(|| {
+                        let array = HandleId::random::<Image>();
+                        (sheet.clone(), images.get_handle(array))
+                    });
+                material.with_array(array.clone(), sheet)
+            }
+        }
+    }
+
+    /// Material drawing the whole of `sheet`
+    pub fn sheet(
+        &mut self,
+        sheet: &SpriteSheet,
+        materials: &mut Assets<UnlitMaterial>,
+        images: &Assets<Image>,
+    ) -> Handle<UnlitMaterial> {
+        if let Some(material) = self.materials.get(sheet) {
+            return material.clone();
+        }
+        let material = materials.add(self.unlit(sheet, images));
+        self.materials.insert(sheet.clone(), material.clone());
+        material
+    }
+
+    /// Material drawing the whole of `sheet` multiplied by `tint` with
+    /// `dither` of its pixels discarded, rounded to one of the
+    /// [`DITHER_LEVELS`], and mixed towards `flash` by its alpha, rounded to
+    /// one of the [`FLASH_LEVELS`]
+    pub fn variant(
+        &mut self,
+        sheet: &SpriteSheet,
+        tint: Color,
+        dither: f32,
+        flash: Color,
+        materials: &mut Assets<UnlitMaterial>,
+        images: &Assets<Image>,
+    ) -> Handle<UnlitMaterial> {
+        let level =
+            (dither.clamp(0.0, 1.0) * DITHER_LEVELS as f32).round() as u8;
+        let strength =
+            (flash.a().clamp(0.0, 1.0) * FLASH_LEVELS as f32).round() as u8;
+        let mut flash = flash;
+        flash.set_a(strength as f32 / FLASH_LEVELS as f32);
+        if level == 0 && strength == 0 && tint == Color::WHITE {
+            return self.sheet(sheet, materials, images);
+        }
+        // Every fully faded flash shares the same key
+        let packed = if strength == 0 {
+            0
+        } else {
+            flash.as_rgba_u32()
+        };
+        let key = (sheet.clone(), tint.as_rgba_u32(), level, packed);
+        if let Some(material) = self.variants.get(&key) {
+            return material;
+        }
+        let material = self
+            .unlit(sheet, images)
+            .with_color(tint)
+            .with_dither(level as f32 / DITHER_LEVELS as f32)
+            .with_flash(flash);
+        let material = materials.add(material);
+        self.variants.insert(key, material.clone());
+        material
+    }
+
+    /// Lit material drawing the whole of `sheet`
+    pub fn lit(
+        &mut self,
+        sheet: &SpriteSheet,
+        materials: &mut Assets<LitSpriteMaterial>,
+    ) -> Handle<LitSpriteMaterial> {
+        self.lit
+            .entry(sheet.clone())
+            .or_insert_with(|| materials.add(LitSpriteMaterial::sheet(sheet)))
+            .clone()
+    }
+
+    /// Lit material drawing the whole of `sheet` multiplied by `tint` with
+    /// `dither` of its pixels discarded, rounded to one of the
+    /// [`DITHER_LEVELS`]
+    pub fn lit_variant(
+        &mut self,
+        sheet: &SpriteSheet,
+        tint: Color,
+        dither: f32,
+        materials: &mut Assets<LitSpriteMaterial>,
+    ) -> Handle<LitSpriteMaterial> {
+        let level =
+            (dither.clamp(0.0, 1.0) * DITHER_LEVELS as f32).round() as u8;
+        if level == 0 && tint == Color::WHITE {
+            return self.lit(sheet, materials);
+        }
+        let key = (sheet.clone(), tint.as_rgba_u32(), level);
+        if let Some(material) = self.lit_variants.get(&key) {
+            return material;
+        }
+        let material = LitSpriteMaterial::sheet(sheet)
+            .with_color(tint)
+            .with_dither(level as f32 / DITHER_LEVELS as f32);
+        let material = materials.add(material);
+        self.lit_variants.insert(key, material.clone());
+        material
+    }
+
+    /// Water material flowing through the sprite at `index` in `sheet`
+    /// multiplied by `tint`, drawn on the plain quad instead of a sprite mesh
+    pub fn water(
+        &mut self,
+        sheet: &SpriteSheet,
+        index: u32,
+        tint: Color,
+        materials: &mut Assets<WaterMaterial>,
+    ) -> Handle<WaterMaterial> {
+        let key = (sheet.clone(), index, tint.as_rgba_u32());
+        if let Some(material) = self.water.get(&key) {
+            return material;
+        }
+        let material =
+            materials.add(WaterMaterial::sprite(sheet, index).with_tint(tint));
+        self.water.insert(key, material.clone());
+        material
+    }
+
+    /// Copy of the base mesh of `key` with its UVs squeezed into the sprite
+    /// at its index in the sheet, flipped and colored
+    pub fn mesh(
+        &mut self,
+        key: SpriteMeshKey,
+        meshes: &mut Assets<Mesh>,
+        images: &Assets<Image>,
+    ) -> Handle<Mesh> {
+        if let Some(mesh) = self.meshes.get(&key) {
+            return mesh;
+        }
+
+        let mesh = match sprite_mesh(&key, meshes, images, self.backend) {
+            Some(mesh) => mesh,
+            None => {
+                warn!("Sprite mesh ({:?}) not found", key.base);
+                return key.base;
+            }
+        };
+        let handle = meshes.add(mesh);
+        self.meshes.insert(key, handle.clone());
+        handle
+    }
+
+    /// Every sprite mesh allocated so far
+    pub fn iter(
+        &self,
+    ) -> impl Iterator<Item = (&SpriteMeshKey, &Handle<Mesh>)> {
+        self.meshes.iter()
+    }
+}
+
+/// Forget the least recently used sprite meshes and materials nothing draws
+/// with once the [`MaterialAllocator`] holds too many
+fn evict_sprite_assets(
+    mut allocator: ResMut<MaterialAllocator>,
+    meshes: Query<&Handle<Mesh>>,
+    unlit: Query<&Handle<UnlitMaterial>>,
+    lit: Query<&Handle<LitSpriteMaterial>>,
+    water: Query<&Handle<WaterMaterial>>,
+) {
+    let full = allocator.variants.is_full()
+        || allocator.lit_variants.is_full()
+        || allocator.water.is_full()
+        || allocator.meshes.is_full();
+    if !full {
+        return;
+    }
+
+    let meshes: HashSet<HandleId> = meshes.iter().map(|h| h.id).collect();
+    let unlit: HashSet<HandleId> = unlit.iter().map(|h| h.id).collect();
+    let lit: HashSet<HandleId> = lit.iter().map(|h| h.id).collect();
+    let water: HashSet<HandleId> = water.iter().map(|h| h.id).collect();
+    let allocator = &mut *allocator;
+    let evicted = allocator.variants.evict(|h| unlit.contains(&h.id))
+        + allocator.lit_variants.evict(|h| lit.contains(&h.id))
+        + allocator.water.evict(|h| water.contains(&h.id))
+        + allocator.meshes.evict(|h| meshes.contains(&h.id));
+    debug!("Evicted {} unused sprite meshes and materials", evicted);
+}
+
+/// Set when the material has an emissive texture
+const UNLIT_FLAGS_EMISSIVE: u32 = 1;
+
+/// Copy of the base mesh of `key` with its UVs squeezed into the sprite.
+///
+/// The inset is skipped until the size of the sheet's texture is known.
+fn sprite_mesh(
+    key: &SpriteMeshKey,
+    meshes: &Assets<Mesh>,
+    images: &Assets<Image>,
+    backend: SpriteBackend,
+) -> Option<Mesh> {
+    let SpriteMeshKey {
+        sheet,
+        index,
+        flip,
+        colors,
+        ..
+    } = key;
+    let mut mesh = meshes.get(&key.base)?.clone();
+    let texels = images
+        .get(&sheet.texture)
+        .map(|image| image.size())
+        .filter(|size| size.x > 0.0 && size.y > 0.0);
+    let margin = match texels {
+        Some(size) => Vec2::splat(sheet.inset) / size,
+        None => Vec2::ZERO,
+    };
+    let rect = SpriteRect {
+        flip: *flip,
+        ..sheet.rect(*index)
+    }
+    .inset(margin);
+
+    if let Some(VertexAttributeValues::Float32x2(uvs)) =
+        mesh.attribute_mut(Mesh::ATTRIBUTE_UV_0)
+    {
+        // Corners are picked from the UVs of the quad, before they move
+        let corners: Option<Vec<u32>> = colors.map(|colors| {
+            uvs.iter().map(|&uv| colors.at(Vec2::from(uv))).collect()
+        });
+        for uv in uvs.iter_mut() {
+            *uv = rect.uv(Vec2::from(*uv)).into();
+        }
+        if let Some(corners) = corners {
+            mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, corners);
+        }
+    }
+    if backend == SpriteBackend::TextureArray {
+        let count = mesh.count_vertices();
+        mesh.insert_attribute(ATTRIBUTE_SPRITE_LAYER, vec![*index; count]);
+    }
+    Some(mesh)
+}
+
+/// Rebuild the meshes of inset sprites once their sheet's texture is loaded
+/// or changes size, since the inset is measured in texels
+fn inset_sprite_meshes(
+    mut events: EventReader<AssetEvent<Image>>,
+    allocator: Res<MaterialAllocator>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    images: Res<Assets<Image>>,
+) {
+    for event in events.iter() {
+        let texture = match event {
+            AssetEvent::Created { handle }
+            | AssetEvent::Modified { handle } => handle,
+            AssetEvent::Removed { .. } => continue,
+        };
+        for (key, handle) in allocator.iter() {
+            let sheet = &key.sheet;
+            if sheet.inset <= 0.0 || sheet.texture != *texture {
+                continue;
+            }
+            if let Some(mesh) =
+                sprite_mesh(key, &meshes, &images, allocator.backend)
+            {
+                if let Some(target) = meshes.get_mut(handle) {
+                    *target = mesh;
+                }
+            }
+        }
+    }
+}
+
+/// `sheet` split into one texture array layer per sprite, in the order of
+/// their indices
+fn sprite_array(image: &Image, sheet: &SpriteSheet) -> Option<Image> {
+    let size = image.texture_descriptor.size;
+    if image.is_compressed() || size.depth_or_array_layers != 1 {
+        return None;
+    }
+    let (columns, rows) = (sheet.columns.max(1), sheet.rows.max(1));
+    let (width, height) = (size.width / columns, size.height / rows);
+    if width == 0 || height == 0 {
+        return None;
+    }
+
+    let format = image.texture_descriptor.format;
+    let pixel = format.pixel_size();
+    let stride = size.width as usize * pixel;
+    let line = width as usize * pixel;
+    let layers = columns * rows;
+    let mut data = Vec::with_capacity(line * (height * layers) as usize);
+    for layer in 0..layers {
+        let x = (layer % columns * width) as usize * pixel;
+        let y = (layer / columns * height) as usize;
+        for row in y..y + height as usize {
+            let start = row * stride + x;
+            data.extend_from_slice(&image.data[start..start + line]);
+        }
+    }
+    Some(Image::new(
+        Extent3d {
+            width,
+            height,
+            depth_or_array_layers: layers,
+        },
+        TextureDimension::D2,
+        data,
+        format,
+    ))
+}
+
+/// Fill in the sprite arrays of the [`SpriteBackend::TextureArray`] once
+/// their sheets load, and again whenever a sheet changes
+fn build_sprite_arrays(
+    mut events: EventReader<AssetEvent<Image>>,
+    allocator: Res<MaterialAllocator>,
+    mut images: ResMut<Assets<Image>>,
+    mut failed: Local<HashSet<Handle<Image>>>,
+) {
+    let changed: HashSet<Handle<Image>> = events
+        .iter()
+        .filter_map(|event| match event {
+            AssetEvent::Created { handle }
+            | AssetEvent::Modified { handle } => Some(handle.clone_weak()),
+            AssetEvent::Removed { .. } => None,
+        })
+        .collect();
+
+    for (texture, (sheet, array)) in allocator.arrays.iter() {
+        let stale = changed.contains(texture)
+            || (images.get(array).is_none() && !failed.contains(texture));
+        if !stale {
+            continue;
+        }
+        let image = match images.get(texture) {
+            Some(image) => image,
+            None => continue,
+        };
+        match sprite_array(image, sheet) {
+            Some(built) => {
+                failed.remove(texture);
+                images.set_untracked(array, built);
+            }
+            None => {
+                warn!(
+                    "Sprite sheet ({:?}) can't be split into a texture array",
+                    texture
+                );
+                failed.insert(texture.clone_weak());
+            }
+        }
+    }
+}
+
+/// Uniform data of an [`UnlitMaterial`] as seen by the shaders
+#[derive(Clone, Default, AsStd140)]
+struct UnlitMaterialUniformData {
+    uv_offset: Vec2,
+    uv_size: Vec2,
+    /// Linear RGBA
+    color: Vec4,
+    /// Pixels less opaque than this are discarded when masking
+    alpha_cutoff: f32,
+    /// [`SpriteFlip::bits`]
+    uv_flags: u32,
+    /// Fraction of pixels discarded by the dither pattern
+    dither: f32,
+    flags: u32,
+    /// [`ColorGrade::uniform`]
+    grade: Vec4,
+    /// Linear RGBA
+    flash: Vec4,
+    /// [`UnlitMaterial::grid`]
+    grid: Vec2,
+    /// [`DistanceFog::uniform`]
+    fog_color: Vec4,
+    fog_range: Vec2,
+}
+
+/// GPU representation of `[UnlitMaterial]`
+#[derive(Clone)]
+pub struct GpuUnlitMaterial {
+    buffer: Buffer,
+    #[allow(unused)]
+    sampler: Sampler,
+    #[allow(unused)]
+    array_view: TextureView,
+    /// Drawn from a texture array by the layer of each vertex
+    array: bool,
+    debug: DebugRenderMode,
+    /// Kept to rewrite the buffer when the [`ColorGrade`] changes
+    uniform: UnlitMaterialUniformData,
+    bind_group: BindGroup,
+    alpha_mode: AlphaMode,
+}
+
+impl RenderAsset for UnlitMaterial {
+    type ExtractedAsset = UnlitMaterial;
+    type PreparedAsset = GpuUnlitMaterial;
+    type Param = (
+        SRes<RenderDevice>,
+        SRes<RenderAssets<Image>>,
+        SRes<DefaultTexture>,
+        SRes<MaterialPipeline<Self>>,
+        SRes<ColorGrade>,
+        SRes<PixelArtSettings>,
+        SRes<DebugRenderMode>,
+    );
+
+    fn extract_asset(&self) -> Self::ExtractedAsset {
+        self.clone()
+    }
+
+    fn prepare_asset(
+        asset: Self::ExtractedAsset,
+        (
+            device,
+            gpu_images,
+            default_texture,
+            pipeline,
+            grade,
+            settings,
+            debug,
+        ): &mut SystemParamItem<Self::Param>,
+    ) -> Result<Self::PreparedAsset, PrepareAssetError<Self::ExtractedAsset>>
+    {
+        let texture = match gpu_images.get(
+            &asset
+                .texture
+                .clone()
+                .unwrap_or(default_texture.handle.clone()),
+        ) {
+            Some(texture) => texture,
+            // Try again if the image isn't loaded
+            None => {
+                debug!("Texture ({:?}) not yet loaded", asset.texture);
+                return Err(PrepareAssetError::RetryNextUpdate(asset));
+            }
+        };
+
+        // Without an emissive texture the texture is bound in its place and
+        // ignored
+        let emissive = match &asset.emissive {
+            Some(handle) => match gpu_images.get(handle) {
+                Some(emissive) => emissive,
+                None => {
+                    debug!("Emissive texture ({:?}) not yet loaded", handle);
+                    return Err(PrepareAssetError::RetryNextUpdate(asset));
+                }
+            },
+            None => texture,
+        };
+
+        // Without an array the fallback is bound as a single layer instead
+        let array = match &asset.array {
+            Some(handle) => match gpu_images.get(handle) {
+                Some(array) => array,
+                None => {
+                    debug!("Sprite array ({:?}) not yet built", handle);
+                    return Err(PrepareAssetError::RetryNextUpdate(asset));
+                }
+            },
+            None => match gpu_images.get(&default_texture.handle) {
+                Some(fallback) => fallback,
+                None => return Err(PrepareAssetError::RetryNextUpdate(asset)),
+            },
+        };
+        let array_view = array.texture.create_view(&TextureViewDescriptor {
+            label: Some("Sprite Array View"),
+            dimension: Some(TextureViewDimension::D2Array),
+            ..Default::default()
+        });
+
+        let sampler = device.create_sampler(
+            &asset.sampling.unwrap_or(settings.sampling).descriptor(),
+        );
+
+        let uniform = UnlitMaterialUniformData {
+            uv_offset: asset.rect.offset,
+            uv_size: asset.rect.size,
+            color: asset.color.as_linear_rgba_f32().into(),
+            alpha_cutoff: match asset.alpha_mode {
+                AlphaMode::Mask(cutoff) => cutoff,
+                _ => 0.0,
+            },
+            uv_flags: asset.rect.flip.bits(),
+            dither: asset.dither,
+            flags: if asset.emissive.is_some() {
+                UNLIT_FLAGS_EMISSIVE
+            } else {
+                0
+            },
+            grade: grade.uniform(),
+            flash: asset.flash.as_linear_rgba_f32().into(),
+            grid: asset.grid,
+            // Caught up by `regrade_materials` before the first draw
+            fog_color: Vec4::ZERO,
+            fog_range: Vec2::ZERO,
+        };
+        let buffer = device.create_buffer_with_data(&BufferInitDescriptor {
+            label: Some("Unlit Material Uniform Buffer"),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            contents: uniform.as_std140().as_bytes(),
+        });
+
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(
+                        &texture.texture_view,
+                    ),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::Sampler(&sampler),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 3,
+                    resource: BindingResource::TextureView(
+                        &emissive.texture_view,
+                    ),
+                },
+                BindGroupEntry {
+                    binding: 4,
+                    resource: BindingResource::Sampler(&sampler),
+                },
+                BindGroupEntry {
+                    binding: 5,
+                    resource: BindingResource::TextureView(&array_view),
+                },
+            ],
+            label: Some("Unlit Texture Material Bind Group Layout"),
+            layout: &pipeline.material_layout,
+        });
+
+        Ok(GpuUnlitMaterial {
+            buffer,
+            sampler,
+            array_view,
+            array: asset.array.is_some(),
+            debug: **debug,
+            uniform,
+            bind_group,
+            alpha_mode: asset.alpha_mode,
+        })
+    }
+}
+
+/// Read only the vertex attributes a sprite shader uses, normals only when
+/// it is shaded, layers only when it reads a sprite array, and the colors
+/// of meshes which have them, like sprites with [`CornerColors`].
+///
+/// The sample count and the mesh layout are already part of the key Bevy
+/// caches pipelines by, so a pipeline is specialized again whenever either
+/// changes.
+fn specialize_sprite_vertex(
+    descriptor: &mut RenderPipelineDescriptor,
+    layout: &MeshVertexBufferLayout,
+    normals: bool,
+    layers: bool,
+) -> Result<(), SpecializedMeshPipelineError> {
+    let mut attributes = vec![Mesh::ATTRIBUTE_POSITION.at_shader_location(0)];
+    if normals {
+        attributes.push(Mesh::ATTRIBUTE_NORMAL.at_shader_location(1));
+    }
+    attributes.push(Mesh::ATTRIBUTE_UV_0.at_shader_location(2));
+    if layers {
+        attributes.push(ATTRIBUTE_SPRITE_LAYER.at_shader_location(4));
+        descriptor.vertex.shader_defs.push("SPRITE_ARRAY".into());
+        if let Some(fragment) = descriptor.fragment.as_mut() {
+            fragment.shader_defs.push("SPRITE_ARRAY".into());
+        }
+    }
+
+    let colors = layout.contains(Mesh::ATTRIBUTE_COLOR);
+    if colors {
+        attributes.push(Mesh::ATTRIBUTE_COLOR.at_shader_location(3));
+        descriptor.vertex.shader_defs.push("VERTEX_COLORS".into());
+        if let Some(fragment) = descriptor.fragment.as_mut() {
+            fragment.shader_defs.push("VERTEX_COLORS".into());
+        }
+    }
+    descriptor.vertex.buffers = vec![layout.get_layout(&attributes)?];
+    Ok(())
+}
+
+/// Vertex attributes a mesh needs to be drawn with an [`UnlitMaterial`].
+/// The shader never reads normals, but the mesh pipeline it is specialized
+/// from lays them out before the material gets a say
+const UNLIT_ATTRIBUTES: [MeshVertexAttribute; 3] = [
+    Mesh::ATTRIBUTE_POSITION,
+    Mesh::ATTRIBUTE_NORMAL,
+    Mesh::ATTRIBUTE_UV_0,
+];
+
+/// Warn once about every mesh which can't be drawn with the unlit material
+/// it was given, naming what it lacks instead of failing every frame inside
+/// the pipeline cache
+fn check_unlit_meshes(
+    meshes: Res<Assets<Mesh>>,
+    query: Query<
+        (Entity, Option<&Name>, &Handle<Mesh>),
+        (
+            With<Handle<UnlitMaterial>>,
+            Or<(Changed<Handle<Mesh>>, Changed<Handle<UnlitMaterial>>)>,
+        ),
+    >,
+    mut warned: Local<HashSet<Handle<Mesh>>>,
+) {
+    for (entity, name, handle) in query.iter() {
+        let mesh = match meshes.get(handle) {
+            Some(mesh) if !warned.contains(handle) => mesh,
+            _ => continue,
+        };
+        let missing: Vec<&str> = UNLIT_ATTRIBUTES
+            .iter()
+            .filter(|attribute| mesh.attribute(attribute.id).is_none())
+            .map(|attribute| attribute.name)
+            .collect();
+        if !missing.is_empty() {
+            warn!(
+                "Mesh of {:?} ({}) can't be drawn with an unlit material, it \
+                 is missing {}",
+                entity,
+                name.map_or("unnamed", Name::as_str),
+                missing.join(", "),
+            );
+            warned.insert(handle.clone_weak());
+        }
+    }
+}
+
+/// Pipeline variant of an [`UnlitMaterial`], one per kind of [`AlphaMode`],
+/// [`SpriteBackend`], and [`DebugRenderMode`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct UnlitMaterialKey {
+    pub alpha: UnlitAlphaKey,
+    /// Drawn from a sprite array rather than the whole sheet
+    pub array: bool,
+    pub debug: DebugRenderMode,
+}
+
+/// Kind of [`AlphaMode`] an [`UnlitMaterial`] is drawn with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UnlitAlphaKey {
+    Opaque,
+    Mask,
+    Blend,
+}
+
+impl SpecializedMaterial for UnlitMaterial {
+    type Key = UnlitMaterialKey;
+
+    fn key(
+        render_asset: &<UnlitMaterial as RenderAsset>::PreparedAsset,
+    ) -> Self::Key {
+        let alpha = match render_asset.alpha_mode {
+            AlphaMode::Opaque => UnlitAlphaKey::Opaque,
+            AlphaMode::Mask(_) => UnlitAlphaKey::Mask,
+            AlphaMode::Blend => UnlitAlphaKey::Blend,
+        };
+        UnlitMaterialKey {
+            alpha,
+            array: render_asset.array,
+            debug: render_asset.debug,
+        }
+    }
+
+    fn specialize(
+        _pipeline: &MaterialPipeline<Self>,
+        descriptor: &mut RenderPipelineDescriptor,
+        key: Self::Key,
+        layout: &MeshVertexBufferLayout,
+    ) -> Result<(), SpecializedMeshPipelineError> {
+        specialize_sprite_vertex(descriptor, layout, false, key.array)?;
+        let fragment = descriptor.fragment.as_mut().unwrap();
+
+        match key.alpha {
+            UnlitAlphaKey::Opaque => {}
+            UnlitAlphaKey::Mask => {
+                fragment.shader_defs.push("ALPHA_MASK".into());
+            }
+            UnlitAlphaKey::Blend => {
+                fragment.shader_defs.push("ALPHA_BLEND".into());
+                for target in fragment.targets.iter_mut() {
+                    target.blend = Some(BlendState::ALPHA_BLENDING);
+                }
+                // Blended sprites must not hide what is drawn behind them
+                if let Some(depth) = descriptor.depth_stencil.as_mut() {
+                    depth.depth_write_enabled = false;
+                }
+            }
+        }
+        key.debug.specialize(descriptor);
+        Ok(())
+    }
+
+    fn vertex_shader(asset_server: &AssetServer) -> Option<Handle<Shader>> {
+        Some(asset_server.load("shaders/unlit_material.wgsl"))
+    }
+
+    fn fragment_shader(asset_server: &AssetServer) -> Option<Handle<Shader>> {
+        Some(asset_server.load("shaders/unlit_material.wgsl"))
+    }
+
+    fn bind_group(
+        render_asset: &<Self as RenderAsset>::PreparedAsset,
+    ) -> &BindGroup {
+        &render_asset.bind_group
+    }
+
+    fn alpha_mode(
+        render_asset: &<Self as RenderAsset>::PreparedAsset,
+    ) -> AlphaMode {
+        render_asset.alpha_mode
+    }
+
+    fn bind_group_layout(render_device: &RenderDevice) -> BindGroupLayout {
+        render_device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float {
+                            filterable: true,
+                        },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: ShaderStages::VERTEX | ShaderStages::FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: BufferSize::new(
+                            UnlitMaterialUniformData::std140_size_static()
+                                as u64,
+                        ),
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float {
+                            filterable: true,
+                        },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 4,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 5,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float {
+                            filterable: true,
+                        },
+                        view_dimension: TextureViewDimension::D2Array,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+            ],
+            label: Some("Unlit Material Bind Group"),
+        })
+    }
+}
+
+/// Sprite material shaded by the scene's lights, with an optional normal map
+/// and emissive texture sampled from the same sub-rectangle of parallel
+/// sheets
+#[derive(Debug, Clone, Default, TypeUuid)]
+#[uuid = "3c9f6a8e-1d2b-4f5a-8e7c-6b0d9a4f2e13"]
+pub struct LitSpriteMaterial {
+    texture: Option<Handle<Image>>,
+    normals: Option<Handle<Image>>,
+    emissive: Option<Handle<Image>>,
+    rect: SpriteRect,
+    /// Multiplied with the texture before shading
+    color: Color,
+    /// Fraction of pixels discarded in an ordered dither pattern, like
+    /// [`UnlitMaterial`]'s
+    dither: f32,
+}
+
+impl LitSpriteMaterial {
+    /// Material showing the whole of a sprite sheet
+    pub fn sheet(sheet: &SpriteSheet) -> Self {
+        Self {
+            texture: Some(sheet.texture.clone()),
+            normals: sheet.normals.clone(),
+            emissive: sheet.emissive.clone(),
+            rect: SpriteRect::default(),
+            color: Color::WHITE,
+            dither: 0.0,
+        }
+    }
+
+    /// Tint the material
+    pub fn with_color(mut self, color: Color) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Dither away `dither` of the pixels, from zero for none to one for all
+    pub fn with_dither(mut self, dither: f32) -> Self {
+        self.dither = dither.clamp(0.0, 1.0);
+        self
+    }
+}
+
+/// Set when the material has a normal map
+const LIT_SPRITE_FLAGS_NORMAL_MAP: u32 = 1;
+/// Set when the material has an emissive texture
+const LIT_SPRITE_FLAGS_EMISSIVE: u32 = 2;
+
+/// Uniform data of a [`LitSpriteMaterial`] as seen by the shaders
+#[derive(Clone, Default, AsStd140)]
+struct LitSpriteMaterialUniformData {
+    uv_offset: Vec2,
+    uv_size: Vec2,
+    /// Linear RGBA
+    color: Vec4,
+    flags: u32,
+    /// [`SpriteFlip::bits`]
+    uv_flags: u32,
+    /// [`ColorGrade::uniform`]
+    grade: Vec4,
+    /// Fraction of pixels discarded by the dither pattern
+    dither: f32,
+}
+
+/// GPU representation of `[LitSpriteMaterial]`
+#[derive(Clone)]
+pub struct GpuLitSpriteMaterial {
+    buffer: Buffer,
+    #[allow(unused)]
+    sampler: Sampler,
+    debug: DebugRenderMode,
+    /// Kept to rewrite the buffer when the [`ColorGrade`] changes
+    uniform: LitSpriteMaterialUniformData,
+    bind_group: BindGroup,
+}
+
+impl RenderAsset for LitSpriteMaterial {
+    type ExtractedAsset = LitSpriteMaterial;
+    type PreparedAsset = GpuLitSpriteMaterial;
+    type Param = (
+        SRes<RenderDevice>,
+        SRes<RenderAssets<Image>>,
+        SRes<DefaultTexture>,
+        SRes<MaterialPipeline<Self>>,
+        SRes<ColorGrade>,
+        SRes<PixelArtSettings>,
+        SRes<DebugRenderMode>,
+    );
+
+    fn extract_asset(&self) -> Self::ExtractedAsset {
+        self.clone()
+    }
+
+    fn prepare_asset(
+        asset: Self::ExtractedAsset,
+        (
+            device,
+            gpu_images,
+            default_texture,
+            pipeline,
+            grade,
+            settings,
+            debug,
+        ): &mut SystemParamItem<Self::Param>,
+    ) -> Result<Self::PreparedAsset, PrepareAssetError<Self::ExtractedAsset>>
+    {
+        let texture = asset
+            .texture
+            .clone()
+            .unwrap_or_else(|| default_texture.handle.clone());
+        let texture = match gpu_images.get(&texture) {
+            Some(texture) => texture,
+            None => {
+                debug!("Texture ({:?}) not yet loaded", asset.texture);
+                return Err(PrepareAssetError::RetryNextUpdate(asset));
+            }
+        };
+
+        // Without a normal map the texture is bound in its place and ignored
+        let normals = match &asset.normals {
+            Some(handle) => match gpu_images.get(handle) {
+                Some(normals) => normals,
+                None => {
+                    debug!("Normal map ({:?}) not yet loaded", handle);
+                    return Err(PrepareAssetError::RetryNextUpdate(asset));
+                }
+            },
+            None => texture,
+        };
+        let emissive = match &asset.emissive {
+            Some(handle) => match gpu_images.get(handle) {
+                Some(emissive) => emissive,
+                None => {
+                    debug!("Emissive texture ({:?}) not yet loaded", handle);
+                    return Err(PrepareAssetError::RetryNextUpdate(asset));
+                }
+            },
+            None => texture,
+        };
+
+        let sampler = device.create_sampler(&settings.sampling.descriptor());
+
+        let mut flags = 0;
+        if asset.normals.is_some() {
+            flags |= LIT_SPRITE_FLAGS_NORMAL_MAP;
+        }
+        if asset.emissive.is_some() {
+            flags |= LIT_SPRITE_FLAGS_EMISSIVE;
+        }
+        let uniform = LitSpriteMaterialUniformData {
+            uv_offset: asset.rect.offset,
+            uv_size: asset.rect.size,
+            color: asset.color.as_linear_rgba_f32().into(),
+            flags,
+            uv_flags: asset.rect.flip.bits(),
+            grade: grade.uniform(),
+            dither: asset.dither,
+        };
+        let buffer = device.create_buffer_with_data(&BufferInitDescriptor {
+            label: Some("Lit Sprite Material Uniform Buffer"),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            contents: uniform.as_std140().as_bytes(),
+        });
+
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(
+                        &texture.texture_view,
+                    ),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::Sampler(&sampler),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: BindingResource::TextureView(
+                        &normals.texture_view,
+                    ),
+                },
+                BindGroupEntry {
+                    binding: 3,
+                    resource: BindingResource::Sampler(&sampler),
+                },
+                BindGroupEntry {
+                    binding: 4,
+                    resource: buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 5,
+                    resource: BindingResource::TextureView(
+                        &emissive.texture_view,
+                    ),
+                },
+                BindGroupEntry {
+                    binding: 6,
+                    resource: BindingResource::Sampler(&sampler),
+                },
+            ],
+            label: Some("Lit Sprite Material Bind Group"),
+            layout: &pipeline.material_layout,
+        });
+
+        Ok(GpuLitSpriteMaterial {
+            buffer,
+            sampler,
+            debug: **debug,
+            uniform,
+            bind_group,
+        })
+    }
+}
+
+impl SpecializedMaterial for LitSpriteMaterial {
+    type Key = DebugRenderMode;
+
+    fn key(
+        render_asset: &<LitSpriteMaterial as RenderAsset>::PreparedAsset,
+    ) -> Self::Key {
+        render_asset.debug
+    }
+
+    fn specialize(
+        _pipeline: &MaterialPipeline<Self>,
+        descriptor: &mut RenderPipelineDescriptor,
+        key: Self::Key,
+        layout: &MeshVertexBufferLayout,
+    ) -> Result<(), SpecializedMeshPipelineError> {
+        specialize_sprite_vertex(descriptor, layout, true, false)?;
+        key.specialize(descriptor);
+        Ok(())
+    }
+
+    fn vertex_shader(asset_server: &AssetServer) -> Option<Handle<Shader>> {
+        Some(asset_server.load("shaders/lit_sprite_material.wgsl"))
+    }
+
+    fn fragment_shader(asset_server: &AssetServer) -> Option<Handle<Shader>> {
+        Some(asset_server.load("shaders/lit_sprite_material.wgsl"))
+    }
+
+    fn bind_group(
+        render_asset: &<Self as RenderAsset>::PreparedAsset,
+    ) -> &BindGroup {
+        &render_asset.bind_group
+    }
+
+    fn bind_group_layout(render_device: &RenderDevice) -> BindGroupLayout {
+        let texture = |binding| BindGroupLayoutEntry {
+            binding,
+            visibility: ShaderStages::FRAGMENT,
+            ty: BindingType::Texture {
+                sample_type: TextureSampleType::Float { filterable: true },
+                view_dimension: TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let sampler = |binding| BindGroupLayoutEntry {
+            binding,
+            visibility: ShaderStages::FRAGMENT,
+            ty: BindingType::Sampler(SamplerBindingType::Filtering),
+            count: None,
+        };
+
+        render_device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            entries: &[
+                texture(0),
+                sampler(1),
+                texture(2),
+                sampler(3),
+                BindGroupLayoutEntry {
+                    binding: 4,
+                    visibility: ShaderStages::VERTEX | ShaderStages::FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: BufferSize::new(
+                            LitSpriteMaterialUniformData::std140_size_static()
+                                as u64,
+                        ),
+                    },
+                    count: None,
+                },
+                texture(5),
+                sampler(6),
+            ],
+            label: Some("Lit Sprite Material Bind Group Layout"),
+        })
+    }
+}
+
+/// Translucent liquid which scrolls and ripples through one sprite
+#[derive(Debug, Clone, TypeUuid)]
+#[uuid = "9d4c2b7e-6a1f-4e3d-8b5a-1f0e7c6d2a94"]
+pub struct WaterMaterial {
+    texture: Option<Handle<Image>>,
+    rect: SpriteRect,
+    /// Multiplied with the texture, the alpha sets how much shows through
+    pub color: Color,
+    /// Distance the sprite moves every second, in sprites
+    pub scroll: Vec2,
+    /// How far the ripples push the sprite around, in sprites
+    pub wave_amplitude: f32,
+    /// Ripples across one sprite
+    pub wave_frequency: f32,
+}
+
+impl Default for WaterMaterial {
+    fn default() -> Self {
+        Self {
+            texture: None,
+            rect: SpriteRect::default(),
+            color: Color::rgba(0.8, 0.9, 1.0, 0.8),
+            scroll: Vec2::new(0.05, 0.02),
+            wave_amplitude: 0.02,
+            wave_frequency: 12.0,
+        }
+    }
+}
+
+impl WaterMaterial {
+    /// Water flowing through one sprite from a sprite sheet
+    pub fn sprite(sheet: &SpriteSheet, index: u32) -> Self {
+        Self {
+            texture: Some(sheet.texture.clone()),
+            rect: sheet.rect(index),
+            ..Default::default()
+        }
+    }
+
+    /// Multiply the color of the water by `tint`, keeping its alpha
+    pub fn with_tint(mut self, tint: Color) -> Self {
+        self.color = Color::rgba(
+            self.color.r() * tint.r(),
+            self.color.g() * tint.g(),
+            self.color.b() * tint.b(),
+            self.color.a(),
+        );
+        self
+    }
+}
+
+/// Uniform data of a [`WaterMaterial`] as seen by the shaders
+#[derive(Clone, Default, AsStd140)]
+struct WaterMaterialUniformData {
+    uv_offset: Vec2,
+    uv_size: Vec2,
+    /// Linear RGBA
+    color: Vec4,
+    scroll: Vec2,
+    wave_amplitude: f32,
+    wave_frequency: f32,
+    /// [`MaterialTime`]
+    time: f32,
+    /// [`ColorGrade::uniform`]
+    grade: Vec4,
+}
+
+/// GPU representation of `[WaterMaterial]`
+#[derive(Clone)]
+pub struct GpuWaterMaterial {
+    buffer: Buffer,
+    #[allow(unused)]
+    sampler: Sampler,
+    /// Kept to rewrite the buffer as time passes
+    uniform: WaterMaterialUniformData,
+    bind_group: BindGroup,
+}
+
+impl RenderAsset for WaterMaterial {
+    type ExtractedAsset = WaterMaterial;
+    type PreparedAsset = GpuWaterMaterial;
+    type Param = (
+        SRes<RenderDevice>,
+        SRes<RenderAssets<Image>>,
+        SRes<DefaultTexture>,
+        SRes<MaterialPipeline<Self>>,
+        SRes<PixelArtSettings>,
+    );
+
+    fn extract_asset(&self) -> Self::ExtractedAsset {
+        self.clone()
+    }
+
+    fn prepare_asset(
+        asset: Self::ExtractedAsset,
+        (device, gpu_images, default_texture, pipeline, settings): &mut SystemParamItem<
+            Self::Param,
+        >,
+    ) -> Result<Self::PreparedAsset, PrepareAssetError<Self::ExtractedAsset>>
+    {
+        let texture = asset
+            .texture
+            .clone()
+            .unwrap_or_else(|| default_texture.handle.clone());
+        let texture = match gpu_images.get(&texture) {
+            Some(texture) => texture,
+            None => {
+                debug!("Texture ({:?}) not yet loaded", asset.texture);
+                return Err(PrepareAssetError::RetryNextUpdate(asset));
+            }
+        };
+        let sampler = device.create_sampler(&settings.sampling.descriptor());
+
+        // Time and grade are filled in every frame by `animate_water`
+        let uniform = WaterMaterialUniformData {
+            uv_offset: asset.rect.offset,
+            uv_size: asset.rect.size,
+            color: asset.color.as_linear_rgba_f32().into(),
+            scroll: asset.scroll,
+            wave_amplitude: asset.wave_amplitude,
+            wave_frequency: asset.wave_frequency,
+            time: 0.0,
+            grade: Vec4::ONE,
+        };
+        let buffer = device.create_buffer_with_data(&BufferInitDescriptor {
+            label: Some("Water Material Uniform Buffer"),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            contents: uniform.as_std140().as_bytes(),
+        });
+
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(
+                        &texture.texture_view,
+                    ),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::Sampler(&sampler),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: buffer.as_entire_binding(),
+                },
+            ],
+            label: Some("Water Material Bind Group"),
+            layout: &pipeline.material_layout,
+        });
+
+        Ok(GpuWaterMaterial {
+            buffer,
+            sampler,
+            uniform,
+            bind_group,
+        })
+    }
+}
+
+impl SpecializedMaterial for WaterMaterial {
+    type Key = ();
+
+    fn key(_: &<WaterMaterial as RenderAsset>::PreparedAsset) -> Self::Key {}
+
+    fn specialize(
+        _pipeline: &MaterialPipeline<Self>,
+        descriptor: &mut RenderPipelineDescriptor,
+        _: Self::Key,
+        _layout: &MeshVertexBufferLayout,
+    ) -> Result<(), SpecializedMeshPipelineError> {
+        if let Some(fragment) = descriptor.fragment.as_mut() {
+            for target in fragment.targets.iter_mut() {
+                target.blend = Some(BlendState::ALPHA_BLENDING);
+            }
+        }
+        if let Some(depth) = descriptor.depth_stencil.as_mut() {
+            depth.depth_write_enabled = false;
+        }
+        Ok(())
+    }
+
+    fn vertex_shader(asset_server: &AssetServer) -> Option<Handle<Shader>> {
+        Some(asset_server.load("shaders/water_material.wgsl"))
+    }
+
+    fn fragment_shader(asset_server: &AssetServer) -> Option<Handle<Shader>> {
+        Some(asset_server.load("shaders/water_material.wgsl"))
+    }
+
+    fn bind_group(
+        render_asset: &<Self as RenderAsset>::PreparedAsset,
+    ) -> &BindGroup {
+        &render_asset.bind_group
+    }
+
+    fn alpha_mode(_: &<Self as RenderAsset>::PreparedAsset) -> AlphaMode {
+        AlphaMode::Blend
+    }
+
+    fn bind_group_layout(render_device: &RenderDevice) -> BindGroupLayout {
+        render_device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float {
+                            filterable: true,
+                        },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: BufferSize::new(
+                            WaterMaterialUniformData::std140_size_static()
+                                as u64,
+                        ),
+                    },
+                    count: None,
+                },
+            ],
+            label: Some("Water Material Bind Group Layout"),
+        })
+    }
+}
+
+/// Translucent, flickering projection of one sprite, for previewing where
+/// something will be placed before committing to it
+///
+/// Left ungraded so previews read the same at any time of day.
+#[derive(Debug, Clone, TypeUuid)]
+#[uuid = "c3e85a1b-7d2f-4b96-a0e4-5f8d1c3b9e72"]
+pub struct HologramMaterial {
+    texture: Option<Handle<Image>>,
+    rect: SpriteRect,
+    /// Multiplied with the texture, usually set by a [`PlacementValidity`]
+    pub tint: Color,
+    /// Bright lines scrolling up through one sprite
+    pub scanlines: f32,
+}
 
-impl Plugin for RenderPlugin {
-    fn build(&self, app: &mut App) {
-        use bevy::render::{RenderApp, RenderStage};
+impl Default for HologramMaterial {
+    fn default() -> Self {
+        Self {
+            texture: None,
+            rect: SpriteRect::default(),
+            tint: Color::rgba(0.4, 0.8, 1.0, 0.5),
+            scanlines: 24.0,
+        }
+    }
+}
 
-        app.add_plugin(MaterialPlugin::<UnlitMaterial>::default())
-            .init_resource::<DefaultTexture>();
+impl HologramMaterial {
+    /// Hologram of one sprite from a sprite sheet
+    pub fn sprite(sheet: &SpriteSheet, index: u32) -> Self {
+        Self {
+            texture: Some(sheet.texture.clone()),
+            rect: sheet.rect(index),
+            ..Default::default()
+        }
+    }
+}
 
-        app.sub_app_mut(RenderApp)
-            .add_system_to_stage(RenderStage::Extract, extract_default_texture);
+/// Whether the [`HologramMaterial`] of a placement preview shows it can be
+/// placed where it is, the material's tint is kept in step
+///
+/// Every preview needs its own material, since the tint is shared by
+/// everything using it.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlacementValidity {
+    Valid,
+    Invalid,
+}
+
+impl PlacementValidity {
+    pub fn new(valid: bool) -> Self {
+        if valid {
+            Self::Valid
+        } else {
+            Self::Invalid
+        }
+    }
+
+    /// Green when valid and red when not, the alpha is left to the material
+    pub fn tint(self) -> Color {
+        match self {
+            Self::Valid => Color::rgb(0.3, 1.0, 0.4),
+            Self::Invalid => Color::rgb(1.0, 0.25, 0.2),
+        }
     }
 }
 
-/// Fallback texture
+/// Tint holograms to match their [`PlacementValidity`]
+fn tint_holograms(
+    previews: Query<
+        (&PlacementValidity, &Handle<HologramMaterial>),
+        Or<(
+            Changed<PlacementValidity>,
+            Changed<Handle<HologramMaterial>>,
+        )>,
+    >,
+    mut materials: ResMut<Assets<HologramMaterial>>,
+) {
+    for (validity, handle) in previews.iter() {
+        if let Some(material) = materials.get_mut(handle) {
+            let mut tint = validity.tint();
+            tint.set_a(material.tint.a());
+            material.tint = tint;
+        }
+    }
+}
+
+/// Uniform data of a [`HologramMaterial`] as seen by the shaders
+#[derive(Clone, Default, AsStd140)]
+struct HologramMaterialUniformData {
+    uv_offset: Vec2,
+    uv_size: Vec2,
+    /// Linear RGBA
+    tint: Vec4,
+    scanlines: f32,
+    /// [`MaterialTime`]
+    time: f32,
+}
+
+/// GPU representation of `[HologramMaterial]`
 #[derive(Clone)]
-pub struct DefaultTexture {
-    handle: Handle<Image>,
+pub struct GpuHologramMaterial {
+    buffer: Buffer,
+    #[allow(unused)]
+    sampler: Sampler,
+    /// Kept to rewrite the buffer as time passes
+    uniform: HologramMaterialUniformData,
+    bind_group: BindGroup,
 }
 
-impl FromWorld for DefaultTexture {
-    fn from_world(world: &mut World) -> Self {
+impl RenderAsset for HologramMaterial {
+    type ExtractedAsset = HologramMaterial;
+    type PreparedAsset = GpuHologramMaterial;
+    type Param = (
+        SRes<RenderDevice>,
+        SRes<RenderAssets<Image>>,
+        SRes<DefaultTexture>,
+        SRes<MaterialPipeline<Self>>,
+        SRes<PixelArtSettings>,
+    );
+
+    fn extract_asset(&self) -> Self::ExtractedAsset {
+        self.clone()
+    }
+
+    fn prepare_asset(
+        asset: Self::ExtractedAsset,
+        (device, gpu_images, default_texture, pipeline, settings): &mut SystemParamItem<
+            Self::Param,
+        >,
+    ) -> Result<Self::PreparedAsset, PrepareAssetError<Self::ExtractedAsset>>
+    {
+        let texture = asset
+            .texture
+            .clone()
+            .unwrap_or_else(|| default_texture.handle.clone());
+        let texture = match gpu_images.get(&texture) {
+            Some(texture) => texture,
+            None => {
+                debug!("Texture ({:?}) not yet loaded", asset.texture);
+                return Err(PrepareAssetError::RetryNextUpdate(asset));
+            }
+        };
+        let sampler = device.create_sampler(&settings.sampling.descriptor());
+
+        // Time is filled in every frame by `animate_holograms`
+        let uniform = HologramMaterialUniformData {
+            uv_offset: asset.rect.offset,
+            uv_size: asset.rect.size,
+            tint: asset.tint.as_linear_rgba_f32().into(),
+            scanlines: asset.scanlines,
+            time: 0.0,
+        };
+        let buffer = device.create_buffer_with_data(&BufferInitDescriptor {
+            label: Some("Hologram Material Uniform Buffer"),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            contents: uniform.as_std140().as_bytes(),
+        });
+
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(
+                        &texture.texture_view,
+                    ),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::Sampler(&sampler),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: buffer.as_entire_binding(),
+                },
+            ],
+            label: Some("Hologram Material Bind Group"),
+            layout: &pipeline.material_layout,
+        });
+
+        Ok(GpuHologramMaterial {
+            buffer,
+            sampler,
+            uniform,
+            bind_group,
+        })
+    }
+}
+
+impl SpecializedMaterial for HologramMaterial {
+    type Key = ();
+
+    fn key(_: &<HologramMaterial as RenderAsset>::PreparedAsset) -> Self::Key {}
+
+    fn specialize(
+        _pipeline: &MaterialPipeline<Self>,
+        descriptor: &mut RenderPipelineDescriptor,
+        _: Self::Key,
+        layout: &MeshVertexBufferLayout,
+    ) -> Result<(), SpecializedMeshPipelineError> {
+        descriptor.vertex.buffers = vec![layout.get_layout(&[
+            Mesh::ATTRIBUTE_POSITION.at_shader_location(0),
+            Mesh::ATTRIBUTE_UV_0.at_shader_location(2),
+        ])?];
+        // Previews are seen from every side and through each other
+        descriptor.primitive.cull_mode = None;
+        if let Some(fragment) = descriptor.fragment.as_mut() {
+            for target in fragment.targets.iter_mut() {
+                target.blend = Some(BlendState::ALPHA_BLENDING);
+            }
+        }
+        if let Some(depth) = descriptor.depth_stencil.as_mut() {
+            depth.depth_write_enabled = false;
+        }
+        Ok(())
+    }
+
+    fn vertex_shader(asset_server: &AssetServer) -> Option<Handle<Shader>> {
+        Some(asset_server.load("shaders/hologram_material.wgsl"))
+    }
+
+    fn fragment_shader(asset_server: &AssetServer) -> Option<Handle<Shader>> {
+        Some(asset_server.load("shaders/hologram_material.wgsl"))
+    }
+
+    fn bind_group(
+        render_asset: &<Self as RenderAsset>::PreparedAsset,
+    ) -> &BindGroup {
+        &render_asset.bind_group
+    }
+
+    fn alpha_mode(_: &<Self as RenderAsset>::PreparedAsset) -> AlphaMode {
+        AlphaMode::Blend
+    }
+
+    fn bind_group_layout(render_device: &RenderDevice) -> BindGroupLayout {
+        render_device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float {
+                            filterable: true,
+                        },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: BufferSize::new(
+                            HologramMaterialUniformData::std140_size_static()
+                                as u64,
+                        ),
+                    },
+                    count: None,
+                },
+            ],
+            label: Some("Hologram Material Bind Group Layout"),
+        })
+    }
+}
+
+/// Overlay covering the whole screen which draws the vignette, flash, and fade
+/// of the [`ScreenEffects`], meant for a quad spanning clip space from -1 to 1
+#[derive(Debug, Clone, Default, TypeUuid)]
+#[uuid = "4f0a6c1d-2b8e-4d7a-9c35-e18b7f2d6a03"]
+pub struct ScreenMaterial;
+
+/// Uniform data of a [`ScreenMaterial`] as seen by the shaders
+#[derive(Clone, Default, PartialEq, AsStd140)]
+struct ScreenMaterialUniformData {
+    /// Linear RGBA
+    flash: Vec4,
+    vignette: f32,
+    fade: f32,
+}
+
+impl ScreenMaterialUniformData {
+    fn new(effects: &ScreenEffects) -> Self {
         Self {
-            handle: world
-                .resource_mut::<AssetServer>()
-                .load("textures/default_texture.png"),
+            flash: effects.flash.as_linear_rgba_f32().into(),
+            vignette: effects.vignette,
+            fade: effects.fade,
         }
     }
 }
 
-fn extract_default_texture(
-    texture: Res<DefaultTexture>,
-    mut commands: Commands,
-) {
-    commands.insert_resource(texture.clone())
+/// GPU representation of `[ScreenMaterial]`
+#[derive(Clone)]
+pub struct GpuScreenMaterial {
+    buffer: Buffer,
+    /// Kept to rewrite the buffer when the [`ScreenEffects`] change
+    uniform: ScreenMaterialUniformData,
+    bind_group: BindGroup,
 }
 
-pub type UnlitMaterialBundle = MaterialMeshBundle<UnlitMaterial>;
+impl RenderAsset for ScreenMaterial {
+    type ExtractedAsset = ScreenMaterial;
+    type PreparedAsset = GpuScreenMaterial;
+    type Param = (
+        SRes<RenderDevice>,
+        SRes<MaterialPipeline<Self>>,
+        SRes<ScreenEffects>,
+    );
 
-/// Render flat material
-#[derive(Debug, Clone, TypeUuid)]
-#[uuid = "f1aacff7-3eea-4a71-836a-efbcb11fe870"]
-pub struct UnlitMaterial {
-    texture: Option<Handle<Image>>,
+    fn extract_asset(&self) -> Self::ExtractedAsset {
+        self.clone()
+    }
+
+    fn prepare_asset(
+        _: Self::ExtractedAsset,
+        (device, pipeline, effects): &mut SystemParamItem<Self::Param>,
+    ) -> Result<Self::PreparedAsset, PrepareAssetError<Self::ExtractedAsset>>
+    {
+        let uniform = ScreenMaterialUniformData::new(effects);
+        let buffer = device.create_buffer_with_data(&BufferInitDescriptor {
+            label: Some("Screen Material Uniform Buffer"),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            contents: uniform.as_std140().as_bytes(),
+        });
+
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+            label: Some("Screen Material Bind Group"),
+            layout: &pipeline.material_layout,
+        });
+
+        Ok(GpuScreenMaterial {
+            buffer,
+            uniform,
+            bind_group,
+        })
+    }
 }
 
-impl UnlitMaterial {
-    pub fn new(texture: Handle<Image>) -> Self {
-        Self {
-            texture: Some(texture),
+impl SpecializedMaterial for ScreenMaterial {
+    type Key = ();
+
+    fn key(_: &<ScreenMaterial as RenderAsset>::PreparedAsset) -> Self::Key {}
+
+    fn specialize(
+        _pipeline: &MaterialPipeline<Self>,
+        descriptor: &mut RenderPipelineDescriptor,
+        _: Self::Key,
+        _layout: &MeshVertexBufferLayout,
+    ) -> Result<(), SpecializedMeshPipelineError> {
+        if let Some(fragment) = descriptor.fragment.as_mut() {
+            for target in fragment.targets.iter_mut() {
+                target.blend = Some(BlendState::ALPHA_BLENDING);
+            }
         }
+        // Drawn over everything, no matter what is in front of the camera
+        if let Some(depth) = descriptor.depth_stencil.as_mut() {
+            depth.depth_write_enabled = false;
+            depth.depth_compare = CompareFunction::Always;
+        }
+        descriptor.primitive.cull_mode = None;
+        Ok(())
+    }
+
+    fn vertex_shader(asset_server: &AssetServer) -> Option<Handle<Shader>> {
+        Some(asset_server.load("shaders/screen_material.wgsl"))
+    }
+
+    fn fragment_shader(asset_server: &AssetServer) -> Option<Handle<Shader>> {
+        Some(asset_server.load("shaders/screen_material.wgsl"))
+    }
+
+    fn bind_group(
+        render_asset: &<Self as RenderAsset>::PreparedAsset,
+    ) -> &BindGroup {
+        &render_asset.bind_group
+    }
+
+    fn alpha_mode(_: &<Self as RenderAsset>::PreparedAsset) -> AlphaMode {
+        AlphaMode::Blend
+    }
+
+    fn bind_group_layout(render_device: &RenderDevice) -> BindGroupLayout {
+        render_device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            entries: &[BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: BufferSize::new(
+                        ScreenMaterialUniformData::std140_size_static() as u64,
+                    ),
+                },
+                count: None,
+            }],
+            label: Some("Screen Material Bind Group Layout"),
+        })
     }
 }
 
-impl Default for UnlitMaterial {
+/// Layer of the backdrop behind the map, meant for a quad spanning clip space
+/// from -1 to 1 like the [`ScreenMaterial`].
+///
+/// Drawn at the far plane, so it only shows where nothing else was drawn.
+#[derive(Debug, Clone, TypeUuid)]
+#[uuid = "b83e5d12-7c40-4a9f-a6d1-3e2f9c0b8d57"]
+pub struct BackdropMaterial {
+    /// Tiled across the screen, multiplied with the gradient
+    pub texture: Option<Handle<Image>>,
+    /// Color at the top of the screen
+    pub top: Color,
+    /// Color at the bottom of the screen
+    pub bottom: Color,
+    /// Fraction of the camera's movement the texture follows, zero pins it
+    /// to the screen
+    pub parallax: f32,
+    /// Screen heights covered by one repeat of the texture
+    pub scale: f32,
+}
+
+impl Default for BackdropMaterial {
     fn default() -> Self {
-        Self { texture: None }
+        Self {
+            texture: None,
+            top: Color::WHITE,
+            bottom: Color::WHITE,
+            parallax: 0.0,
+            scale: 1.0,
+        }
     }
 }
 
-/// GPU representation of `[UnlitMaterial]`
+/// Set when the backdrop has a texture
+const BACKDROP_FLAGS_TEXTURE: u32 = 1;
+
+/// Uniform data of a [`BackdropMaterial`] as seen by the shaders
+#[derive(Clone, Default, AsStd140)]
+struct BackdropMaterialUniformData {
+    /// Linear RGBA
+    top: Vec4,
+    /// Linear RGBA
+    bottom: Vec4,
+    parallax: f32,
+    scale: f32,
+    flags: u32,
+}
+
+/// GPU representation of `[BackdropMaterial]`
 #[derive(Clone)]
-pub struct GpuUnlitMaterial {
+pub struct GpuBackdropMaterial {
+    #[allow(unused)]
+    buffer: Buffer,
+    #[allow(unused)]
+    sampler: Sampler,
     bind_group: BindGroup,
 }
 
-impl RenderAsset for UnlitMaterial {
-    type ExtractedAsset = UnlitMaterial;
-    type PreparedAsset = GpuUnlitMaterial;
+impl RenderAsset for BackdropMaterial {
+    type ExtractedAsset = BackdropMaterial;
+    type PreparedAsset = GpuBackdropMaterial;
     type Param = (
         SRes<RenderDevice>,
         SRes<RenderAssets<Image>>,
         SRes<DefaultTexture>,
         SRes<MaterialPipeline<Self>>,
+        SRes<PixelArtSettings>,
     );
 
     fn extract_asset(&self) -> Self::ExtractedAsset {
@@ -94,24 +2711,48 @@ impl RenderAsset for UnlitMaterial {
 
     fn prepare_asset(
         asset: Self::ExtractedAsset,
-        (device, gpu_images, default_texture, pipeline): &mut SystemParamItem<
+        (device, gpu_images, default_texture, pipeline, settings): &mut SystemParamItem<
             Self::Param,
         >,
     ) -> Result<Self::PreparedAsset, PrepareAssetError<Self::ExtractedAsset>>
     {
-        let texture = match gpu_images.get(
-            &asset
-                .texture
-                .clone()
-                .unwrap_or(default_texture.handle.clone()),
-        ) {
+        // Without a texture the default is bound in its place and ignored
+        let texture = asset
+            .texture
+            .clone()
+            .unwrap_or_else(|| default_texture.handle.clone());
+        let texture = match gpu_images.get(&texture) {
             Some(texture) => texture,
-            // Try again if the image isn't loaded
             None => {
                 debug!("Texture ({:?}) not yet loaded", asset.texture);
                 return Err(PrepareAssetError::RetryNextUpdate(asset));
             }
         };
+        // Tiled, so it has to wrap no matter how sprites are sampled
+        let sampler = device.create_sampler(
+            &SpriteSampling {
+                address_mode: AddressMode::Repeat,
+                ..settings.sampling
+            }
+            .descriptor(),
+        );
+
+        let uniform = BackdropMaterialUniformData {
+            top: asset.top.as_linear_rgba_f32().into(),
+            bottom: asset.bottom.as_linear_rgba_f32().into(),
+            parallax: asset.parallax,
+            scale: asset.scale.max(f32::EPSILON),
+            flags: if asset.texture.is_some() {
+                BACKDROP_FLAGS_TEXTURE
+            } else {
+                0
+            },
+        };
+        let buffer = device.create_buffer_with_data(&BufferInitDescriptor {
+            label: Some("Backdrop Material Uniform Buffer"),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            contents: uniform.as_std140().as_bytes(),
+        });
 
         let bind_group = device.create_bind_group(&BindGroupDescriptor {
             entries: &[
@@ -123,21 +2764,29 @@ impl RenderAsset for UnlitMaterial {
                 },
                 BindGroupEntry {
                     binding: 1,
-                    resource: BindingResource::Sampler(&texture.sampler),
+                    resource: BindingResource::Sampler(&sampler),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: buffer.as_entire_binding(),
                 },
             ],
-            label: Some("Unlit Texture Material Bind Group Layout"),
+            label: Some("Backdrop Material Bind Group"),
             layout: &pipeline.material_layout,
         });
 
-        Ok(GpuUnlitMaterial { bind_group })
+        Ok(GpuBackdropMaterial {
+            buffer,
+            sampler,
+            bind_group,
+        })
     }
 }
 
-impl SpecializedMaterial for UnlitMaterial {
+impl SpecializedMaterial for BackdropMaterial {
     type Key = ();
 
-    fn key(_: &<UnlitMaterial as RenderAsset>::PreparedAsset) -> Self::Key {}
+    fn key(_: &<BackdropMaterial as RenderAsset>::PreparedAsset) -> Self::Key {}
 
     fn specialize(
         _pipeline: &MaterialPipeline<Self>,
@@ -145,17 +2794,27 @@ impl SpecializedMaterial for UnlitMaterial {
         _: Self::Key,
         _layout: &MeshVertexBufferLayout,
     ) -> Result<(), SpecializedMeshPipelineError> {
-        descriptor.vertex.entry_point = "main".into();
-        descriptor.fragment.as_mut().unwrap().entry_point = "main".into();
+        if let Some(fragment) = descriptor.fragment.as_mut() {
+            for target in fragment.targets.iter_mut() {
+                target.blend = Some(BlendState::ALPHA_BLENDING);
+            }
+        }
+        // At the far plane, which only passes where the depth was never
+        // written
+        if let Some(depth) = descriptor.depth_stencil.as_mut() {
+            depth.depth_write_enabled = false;
+            depth.depth_compare = CompareFunction::GreaterEqual;
+        }
+        descriptor.primitive.cull_mode = None;
         Ok(())
     }
 
     fn vertex_shader(asset_server: &AssetServer) -> Option<Handle<Shader>> {
-        Some(asset_server.load("shaders/unlit_material.vert"))
+        Some(asset_server.load("shaders/backdrop_material.wgsl"))
     }
 
     fn fragment_shader(asset_server: &AssetServer) -> Option<Handle<Shader>> {
-        Some(asset_server.load("shaders/unlit_material.frag"))
+        Some(asset_server.load("shaders/backdrop_material.wgsl"))
     }
 
     fn bind_group(
@@ -164,6 +2823,10 @@ impl SpecializedMaterial for UnlitMaterial {
         &render_asset.bind_group
     }
 
+    fn alpha_mode(_: &<Self as RenderAsset>::PreparedAsset) -> AlphaMode {
+        AlphaMode::Blend
+    }
+
     fn bind_group_layout(render_device: &RenderDevice) -> BindGroupLayout {
         render_device.create_bind_group_layout(&BindGroupLayoutDescriptor {
             entries: &[
@@ -172,7 +2835,7 @@ impl SpecializedMaterial for UnlitMaterial {
                     visibility: ShaderStages::FRAGMENT,
                     ty: BindingType::Texture {
                         sample_type: TextureSampleType::Float {
-                            filterable: false,
+                            filterable: true,
                         },
                         view_dimension: TextureViewDimension::D2,
                         multisampled: false,
@@ -182,11 +2845,24 @@ impl SpecializedMaterial for UnlitMaterial {
                 BindGroupLayoutEntry {
                     binding: 1,
                     visibility: ShaderStages::FRAGMENT,
-                    ty: BindingType::Sampler(SamplerBindingType::NonFiltering),
+                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: BufferSize::new(
+                            BackdropMaterialUniformData::std140_size_static()
+                                as u64,
+                        ),
+                    },
                     count: None,
                 },
             ],
-            label: Some("Unlit Material Bind Group"),
+            label: Some("Backdrop Material Bind Group Layout"),
         })
     }
 }
@@ -0,0 +1,313 @@
+use super::ScreenEffects;
+use bevy::core_pipeline::{draw_3d_graph, Opaque3d};
+use bevy::prelude::*;
+use bevy::render::{
+    camera::{ExtractedCamera, RenderTarget},
+    render_graph::{
+        Node, NodeRunError, RenderGraph, RenderGraphContext, SlotInfo, SlotType,
+    },
+    render_phase::RenderPhase,
+    render_resource::{
+        std140::{AsStd140, Std140},
+        *,
+    },
+    renderer::{RenderContext, RenderDevice},
+    texture::{BevyDefault, TextureCache},
+    view::ViewTarget,
+    RenderApp, RenderStage,
+};
+
+/// Split the red and blue channels of everything drawn to a window apart by
+/// the [`ScreenEffects::aberration`], in a pass over the whole frame after
+/// the main pass.
+///
+/// While the screen is split, windowed views draw into a texture of their
+/// own which the pass reads back into the window, so sprites never sample
+/// outside their part of the sheet.
+pub struct AberrationPlugin;
+
+impl Plugin for AberrationPlugin {
+    fn build(&self, app: &mut App) {
+        let render_app = app.sub_app_mut(RenderApp);
+        render_app
+            .init_resource::<AberrationPipeline>()
+            .add_system_to_stage(RenderStage::Queue, queue_aberration);
+
+        let node = AberrationNode::new(&mut render_app.world);
+        let mut graph = render_app.world.resource_mut::<RenderGraph>();
+        let draw_3d = graph
+            .get_sub_graph_mut(draw_3d_graph::NAME)
+            .expect("3d draw graph is added by the core pipeline");
+        let input = draw_3d.input_node().expect("3d draw graph input").id;
+        draw_3d.add_node(AberrationNode::NAME, node);
+        draw_3d
+            .add_slot_edge(
+                input,
+                draw_3d_graph::input::VIEW_ENTITY,
+                AberrationNode::NAME,
+                AberrationNode::IN_VIEW,
+            )
+            .unwrap();
+        draw_3d
+            .add_node_edge(draw_3d_graph::node::MAIN_PASS, AberrationNode::NAME)
+            .unwrap();
+    }
+}
+
+/// Uniform data of the aberration pass as seen by the shader
+#[derive(Clone, Default, AsStd140)]
+struct AberrationUniformData {
+    /// [`ScreenEffects::aberration`]
+    aberration: f32,
+}
+
+struct AberrationPipeline {
+    layout: BindGroupLayout,
+    sampler: Sampler,
+    pipeline: CachedRenderPipelineId,
+}
+
+impl FromWorld for AberrationPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let device = world.resource::<RenderDevice>();
+        let layout =
+            device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+                entries: &[
+                    BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: BindingType::Texture {
+                            sample_type: TextureSampleType::Float {
+                                filterable: true,
+                            },
+                            view_dimension: TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                    BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: BindingType::Buffer {
+                            ty: BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: BufferSize::new(
+                                AberrationUniformData::std140_size_static()
+                                    as u64,
+                            ),
+                        },
+                        count: None,
+                    },
+                ],
+                label: Some("Aberration Bind Group Layout"),
+            });
+        // Split channels land between texels, so blend them
+        let sampler = device.create_sampler(&SamplerDescriptor {
+            label: Some("Aberration Sampler"),
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let shader = world
+            .resource::<AssetServer>()
+            .load("shaders/aberration.wgsl");
+        let descriptor = RenderPipelineDescriptor {
+            label: Some("Aberration Pipeline".into()),
+            layout: Some(vec![layout.clone()]),
+            vertex: VertexState {
+                shader: shader.clone(),
+                shader_defs: Vec::new(),
+                entry_point: "vertex".into(),
+                buffers: Vec::new(),
+            },
+            fragment: Some(FragmentState {
+                shader,
+                shader_defs: Vec::new(),
+                entry_point: "fragment".into(),
+                targets: vec![ColorTargetState {
+                    format: TextureFormat::bevy_default(),
+                    blend: None,
+                    write_mask: ColorWrites::ALL,
+                }],
+            }),
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+        };
+        let pipeline = world
+            .resource_mut::<PipelineCache>()
+            .queue_render_pipeline(descriptor);
+
+        Self {
+            layout,
+            sampler,
+            pipeline,
+        }
+    }
+}
+
+/// Window a view draws to while the main pass draws into a texture the
+/// aberration pass reads from
+#[derive(Component)]
+struct AberrationTarget {
+    window: TextureView,
+    bind_group: BindGroup,
+}
+
+/// Point every 3d view drawing to a window at a texture of its own while the
+/// screen is split, leaving the window for the aberration pass. The UI is
+/// drawn by its own view afterwards and stays sharp
+fn queue_aberration(
+    mut commands: Commands,
+    effects: Res<ScreenEffects>,
+    device: Res<RenderDevice>,
+    pipeline: Res<AberrationPipeline>,
+    pipeline_cache: Res<PipelineCache>,
+    mut texture_cache: ResMut<TextureCache>,
+    mut views: Query<
+        (Entity, &ExtractedCamera, &mut ViewTarget),
+        With<RenderPhase<Opaque3d>>,
+    >,
+) {
+    // Nothing is redirected until the pass is ready to draw the window
+    let ready = pipeline_cache
+        .get_render_pipeline(pipeline.pipeline)
+        .is_some();
+    if effects.aberration <= 0.0 || !ready {
+        return;
+    }
+
+    let uniform = AberrationUniformData {
+        aberration: effects.aberration,
+    };
+    let buffer = device.create_buffer_with_data(&BufferInitDescriptor {
+        label: Some("Aberration Uniform Buffer"),
+        usage: BufferUsages::UNIFORM,
+        contents: uniform.as_std140().as_bytes(),
+    });
+
+    for (entity, camera, mut target) in views.iter_mut() {
+        let size = match (&camera.target, camera.physical_size) {
+            (RenderTarget::Window(_), Some(size)) => size,
+            _ => continue,
+        };
+        let texture = texture_cache.get(
+            &device,
+            TextureDescriptor {
+                label: Some("Aberration Texture"),
+                size: Extent3d {
+                    width: size.x,
+                    height: size.y,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format: TextureFormat::bevy_default(),
+                usage: TextureUsages::RENDER_ATTACHMENT
+                    | TextureUsages::TEXTURE_BINDING,
+            },
+        );
+        let window =
+            std::mem::replace(&mut target.view, texture.default_view.clone());
+
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(
+                        &texture.default_view,
+                    ),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::Sampler(&pipeline.sampler),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: buffer.as_entire_binding(),
+                },
+            ],
+            label: Some("Aberration Bind Group"),
+            layout: &pipeline.layout,
+        });
+        commands
+            .entity(entity)
+            .insert(AberrationTarget { window, bind_group });
+    }
+}
+
+/// Draw the split channels of a redirected view back into its window
+struct AberrationNode {
+    query: QueryState<&'static AberrationTarget>,
+}
+
+impl AberrationNode {
+    const NAME: &'static str = "aberration";
+    const IN_VIEW: &'static str = "view";
+
+    fn new(world: &mut World) -> Self {
+        Self {
+            query: QueryState::new(world),
+        }
+    }
+}
+
+impl Node for AberrationNode {
+    fn input(&self) -> Vec<SlotInfo> {
+        vec![SlotInfo::new(Self::IN_VIEW, SlotType::Entity)]
+    }
+
+    fn update(&mut self, world: &mut World) {
+        self.query.update_archetypes(world);
+    }
+
+    fn run(
+        &self,
+        graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let view = graph.get_input_entity(Self::IN_VIEW)?;
+        // Views left alone this frame already drew to their window
+        let target = match self.query.get_manual(world, view) {
+            Ok(target) => target,
+            Err(_) => return Ok(()),
+        };
+        let pipeline = world.resource::<AberrationPipeline>();
+        let pipeline = match world
+            .resource::<PipelineCache>()
+            .get_render_pipeline(pipeline.pipeline)
+        {
+            Some(pipeline) => pipeline,
+            None => return Ok(()),
+        };
+
+        let mut pass = render_context.command_encoder.begin_render_pass(
+            &RenderPassDescriptor {
+                label: Some("aberration_pass"),
+                color_attachments: &[RenderPassColorAttachment {
+                    view: &target.window,
+                    resolve_target: None,
+                    ops: Operations {
+                        load: LoadOp::Load,
+                        store: true,
+                    },
+                }],
+                depth_stencil_attachment: None,
+            },
+        );
+        pass.set_pipeline(pipeline);
+        pass.set_bind_group(0, &target.bind_group, &[]);
+        // One triangle covering the whole screen
+        pass.draw(0..3, 0..1);
+        Ok(())
+    }
+}
@@ -0,0 +1,71 @@
+use bevy::asset::{Asset, HandleId};
+use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
+use std::hash::Hash;
+
+/// Generated assets shared by key, which forgets the least recently used
+/// entries nothing draws with once it holds more than its capacity.
+///
+/// Entries still in use are never evicted, so every user of a key keeps
+/// sharing the same asset however full the cache gets.
+pub(super) struct AssetCache<K, T: Asset> {
+    entries: HashMap<K, (Handle<T>, u64)>,
+    capacity: usize,
+    /// Counts up with every lookup, ordering entries by their last use
+    clock: u64,
+}
+
+impl<K: Eq + Hash, T: Asset> AssetCache<K, T> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: HashMap::default(),
+            capacity,
+            clock: 0,
+        }
+    }
+
+    /// Handle stored under `key`, marked as just used
+    pub fn get(&mut self, key: &K) -> Option<Handle<T>> {
+        self.clock += 1;
+        let (handle, used) = self.entries.get_mut(key)?;
+        *used = self.clock;
+        Some(handle.clone())
+    }
+
+    pub fn insert(&mut self, key: K, handle: Handle<T>) {
+        self.clock += 1;
+        self.entries.insert(key, (handle, self.clock));
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &Handle<T>)> {
+        self.entries.iter().map(|(key, (handle, _))| (key, handle))
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.entries.len() > self.capacity
+    }
+
+    /// Forget the least recently used entries which aren't `in_use` until
+    /// the cache is back within its capacity, returning how many were
+    /// forgotten
+    pub fn evict(&mut self, in_use: impl Fn(&Handle<T>) -> bool) -> usize {
+        let excess = self.entries.len().saturating_sub(self.capacity);
+        if excess == 0 {
+            return 0;
+        }
+
+        let mut unused: Vec<(u64, HandleId)> = self
+            .entries
+            .values()
+            .filter(|(handle, _)| !in_use(handle))
+            .map(|(handle, used)| (*used, handle.id))
+            .collect();
+        unused.sort_unstable_by_key(|&(used, _)| used);
+        let forgotten: HashSet<HandleId> =
+            unused.into_iter().take(excess).map(|(_, id)| id).collect();
+
+        self.entries
+            .retain(|_, (handle, _)| !forgotten.contains(&handle.id));
+        forgotten.len()
+    }
+}
@@ -0,0 +1,122 @@
+use bevy::prelude::*;
+use bevy::render::{
+    render_resource::{
+        BlendComponent, BlendFactor, BlendOperation, BlendState,
+        CompareFunction, PolygonMode, RenderPipelineDescriptor, WgpuFeatures,
+    },
+    renderer::RenderDevice,
+};
+
+/// How every sprite material is drawn, swapped for a diagnostic view to see
+/// what the meshes and batching are doing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum DebugRenderMode {
+    /// Drawn as the game sees it
+    #[default]
+    Normal,
+    /// Only the edges of each triangle, when the GPU can draw lines
+    Wireframe,
+    /// Checkerboard of each sprite's own uvs, magenta wherever the uvs leave
+    /// the sprite
+    UvChecker,
+    /// Every fragment adds a little heat, whether or not it is hidden, so
+    /// brighter areas are drawn over more times
+    Overdraw,
+}
+
+impl DebugRenderMode {
+    pub const ALL: [Self; 4] = [
+        Self::Normal,
+        Self::Wireframe,
+        Self::UvChecker,
+        Self::Overdraw,
+    ];
+
+    /// Short name used by the console
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Normal => "normal",
+            Self::Wireframe => "wireframe",
+            Self::UvChecker => "uv",
+            Self::Overdraw => "overdraw",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|mode| mode.name() == name)
+    }
+
+    /// Change a sprite pipeline to draw in this mode, on top of whatever its
+    /// material already set up
+    pub(super) fn specialize(self, descriptor: &mut RenderPipelineDescriptor) {
+        match self {
+            Self::Normal => {}
+            Self::Wireframe => {
+                descriptor.primitive.polygon_mode = PolygonMode::Line;
+                // Both faces, or half the edges of turned sprites vanish
+                descriptor.primitive.cull_mode = None;
+            }
+            Self::UvChecker => {
+                if let Some(fragment) = descriptor.fragment.as_mut() {
+                    fragment.shader_defs.push("DEBUG_UV_CHECKER".into());
+                }
+            }
+            Self::Overdraw => {
+                let additive = BlendComponent {
+                    src_factor: BlendFactor::One,
+                    dst_factor: BlendFactor::One,
+                    operation: BlendOperation::Add,
+                };
+                if let Some(fragment) = descriptor.fragment.as_mut() {
+                    fragment.shader_defs.push("DEBUG_OVERDRAW".into());
+                    for target in fragment.targets.iter_mut() {
+                        target.blend = Some(BlendState {
+                            color: additive,
+                            alpha: additive,
+                        });
+                    }
+                }
+                if let Some(depth) = descriptor.depth_stencil.as_mut() {
+                    depth.depth_write_enabled = false;
+                    depth.depth_compare = CompareFunction::Always;
+                }
+            }
+        }
+    }
+}
+
+/// Extract the mode, falling back to drawing normally when the GPU can't
+/// draw wireframes
+pub(super) fn extract_debug_render_mode(
+    mode: Res<DebugRenderMode>,
+    device: Res<RenderDevice>,
+    mut commands: Commands,
+    mut warned: Local<bool>,
+) {
+    let mut mode = *mode;
+    if mode == DebugRenderMode::Wireframe
+        && !device.features().contains(WgpuFeatures::POLYGON_MODE_LINE)
+    {
+        if !*warned {
+            warn!("Wireframes aren't supported by this GPU");
+            *warned = true;
+        }
+        mode = DebugRenderMode::Normal;
+    }
+    commands.insert_resource(mode);
+}
+
+/// Prepare every sprite material again when the mode changes, which picks
+/// the pipeline they are drawn with
+pub(super) fn redraw_materials(
+    mode: Res<DebugRenderMode>,
+    mut unlit: ResMut<Assets<super::UnlitMaterial>>,
+    mut lit: ResMut<Assets<super::LitSpriteMaterial>>,
+) {
+    if !mode.is_changed() || mode.is_added() {
+        return;
+    }
+    // Mutable iteration marks each material as modified
+    unlit.iter_mut().for_each(drop);
+    lit.iter_mut().for_each(drop);
+}
@@ -0,0 +1,196 @@
+use bevy::core_pipeline::{
+    draw_3d_graph, node, AlphaMask3d, Opaque3d, Transparent3d,
+};
+use bevy::prelude::*;
+use bevy::render::{
+    camera::{ActiveCamera, CameraTypePlugin, RenderTarget},
+    render_graph::{
+        Node, NodeRunError, RenderGraph, RenderGraphContext, SlotValue,
+    },
+    render_phase::RenderPhase,
+    render_resource::{
+        Extent3d, TextureDescriptor, TextureDimension, TextureFormat,
+        TextureUsages,
+    },
+    renderer::RenderContext,
+    view::RenderLayers,
+    RenderApp, RenderStage,
+};
+use std::marker::PhantomData;
+
+/// Which cameras see an entity, kept apart as [`RenderLayers`] so secondary
+/// cameras can leave out whatever they don't need.
+///
+/// Entities without render layers are on the first layer, so anything not
+/// tagged is treated as part of the map.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ViewLayer {
+    /// Tiles, walls, props, and everything else making up the level
+    Map = 0,
+    /// Things happening on the map, like highlights and units
+    Gameplay = 1,
+    /// Gizmos only meant for authoring the map
+    Editor = 2,
+    /// Quads drawn over or behind a camera's whole view, which only make
+    /// sense for the camera they belong to
+    Screen = 3,
+}
+
+impl ViewLayer {
+    pub fn layers(self) -> RenderLayers {
+        RenderLayers::layer(self as u8)
+    }
+
+    /// Render layers seeing every one of `layers`
+    pub fn all_of(layers: &[ViewLayer]) -> RenderLayers {
+        layers
+            .iter()
+            .fold(RenderLayers::none(), |all, &layer| all.with(layer as u8))
+    }
+}
+
+/// Camera drawing a small overview of the map into an image
+#[derive(Component, Default)]
+pub struct MinimapCamera;
+
+/// Camera drawing a close up of something into an image, like the unit
+/// currently selected
+#[derive(Component, Default)]
+pub struct PortraitCamera;
+
+/// Draw the one active camera marked by `M` into its image before the main
+/// pass, so the image can be shown anywhere in the frame.
+///
+/// Give the camera [`RenderLayers`] leaving out whatever shows its image,
+/// a texture can't be drawn into and read from in the same pass.
+pub struct SecondaryCameraPlugin<M>(PhantomData<M>);
+
+impl<M> Default for SecondaryCameraPlugin<M> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<M: Component + Default> Plugin for SecondaryCameraPlugin<M> {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(CameraTypePlugin::<M>::default());
+
+        let render_app = app.sub_app_mut(RenderApp);
+        let driver = SecondaryCameraDriver::<M>::new(&mut render_app.world);
+        render_app
+            .add_system_to_stage(RenderStage::Extract, extract_phases::<M>);
+
+        let name = std::any::type_name::<M>();
+        let mut graph = render_app.world.resource_mut::<RenderGraph>();
+        graph.add_node(name, driver);
+        graph
+            .add_node_edge(node::MAIN_PASS_DEPENDENCIES, name)
+            .unwrap();
+        graph.add_node_edge(node::CLEAR_PASS_DRIVER, name).unwrap();
+        graph.add_node_edge(name, node::MAIN_PASS_DRIVER).unwrap();
+    }
+}
+
+/// Image a secondary camera can draw into and the UI can show
+pub fn camera_target(size: UVec2, images: &mut Assets<Image>) -> Handle<Image> {
+    let size = Extent3d {
+        width: size.x.max(1),
+        height: size.y.max(1),
+        depth_or_array_layers: 1,
+    };
+    let mut image = Image {
+        texture_descriptor: TextureDescriptor {
+            label: Some("Secondary Camera Target"),
+            size,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::Bgra8UnormSrgb,
+            mip_level_count: 1,
+            sample_count: 1,
+            usage: TextureUsages::TEXTURE_BINDING
+                | TextureUsages::COPY_DST
+                | TextureUsages::RENDER_ATTACHMENT,
+        },
+        ..Default::default()
+    };
+    // Zeroed until the first frame is drawn
+    image.resize(size);
+    images.add(image)
+}
+
+/// Orthographic camera marked by `M` drawing only `layers` into an image
+#[derive(Bundle)]
+pub struct SecondaryCameraBundle<M: Component> {
+    #[bundle]
+    pub camera: OrthographicCameraBundle<M>,
+    pub layers: RenderLayers,
+}
+
+impl<M: Component + Default> SecondaryCameraBundle<M> {
+    /// Camera looking down -z like a new 3d camera, move it into place with
+    /// its transform
+    pub fn new(image: Handle<Image>, layers: RenderLayers) -> Self {
+        let base = OrthographicCameraBundle::new_3d();
+        Self {
+            camera: OrthographicCameraBundle {
+                camera: Camera {
+                    target: RenderTarget::Image(image),
+                    ..base.camera
+                },
+                orthographic_projection: base.orthographic_projection,
+                visible_entities: base.visible_entities,
+                frustum: base.frustum,
+                transform: base.transform,
+                global_transform: base.global_transform,
+                marker: M::default(),
+            },
+            layers,
+        }
+    }
+}
+
+fn extract_phases<M: Component>(
+    mut commands: Commands,
+    active: Res<ActiveCamera<M>>,
+) {
+    if let Some(entity) = active.get() {
+        commands.get_or_spawn(entity).insert_bundle((
+            RenderPhase::<Opaque3d>::default(),
+            RenderPhase::<AlphaMask3d>::default(),
+            RenderPhase::<Transparent3d>::default(),
+        ));
+    }
+}
+
+/// Run the 3d graph for the secondary camera marked by `M`
+struct SecondaryCameraDriver<M: Component> {
+    query: QueryState<Entity, With<M>>,
+}
+
+impl<M: Component> SecondaryCameraDriver<M> {
+    fn new(render_world: &mut World) -> Self {
+        Self {
+            query: QueryState::new(render_world),
+        }
+    }
+}
+
+impl<M: Component> Node for SecondaryCameraDriver<M> {
+    fn update(&mut self, world: &mut World) {
+        self.query.update_archetypes(world);
+    }
+
+    fn run(
+        &self,
+        graph: &mut RenderGraphContext,
+        _render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        for camera in self.query.iter_manual(world) {
+            graph.run_sub_graph(
+                draw_3d_graph::NAME,
+                vec![SlotValue::Entity(camera)],
+            )?;
+        }
+        Ok(())
+    }
+}
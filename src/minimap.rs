@@ -0,0 +1,116 @@
+use crate::camera::{IsometricCamera, YawPitchControls};
+use crate::material::{
+    camera_target, MinimapCamera, SecondaryCameraBundle, ViewLayer,
+};
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+
+pub struct MinimapPlugin;
+
+impl Plugin for MinimapPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Minimap>()
+            .add_startup_system(setup_minimap)
+            .add_system(toggle_minimap)
+            .add_system(follow_focus)
+            .add_system(minimap_window);
+    }
+}
+
+/// Top down view of the map around the camera focus, drawn by a
+/// [`MinimapCamera`] which leaves out gizmos and screen effects
+pub struct Minimap {
+    pub enabled: bool,
+    /// Size of the image the map is drawn into, in pixels
+    pub size: UVec2,
+    /// Half the height of the map shown, in world units
+    pub scale: f32,
+    image: Option<Handle<Image>>,
+}
+
+impl Default for Minimap {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            size: UVec2::splat(192),
+            scale: 2.0,
+            image: None,
+        }
+    }
+}
+
+/// Key which shows or hides the minimap
+const TOGGLE_MINIMAP: KeyCode = KeyCode::M;
+
+/// How far above the focus the minimap camera looks down from
+const MINIMAP_HEIGHT: f32 = 10.0;
+
+fn setup_minimap(
+    mut commands: Commands,
+    mut minimap: ResMut<Minimap>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let image = camera_target(minimap.size, &mut images);
+    let layers = ViewLayer::all_of(&[ViewLayer::Map, ViewLayer::Gameplay]);
+    let mut camera =
+        SecondaryCameraBundle::<MinimapCamera>::new(image.clone(), layers);
+    camera.camera.orthographic_projection.scale = minimap.scale;
+    commands
+        .spawn_bundle(camera)
+        .insert(Name::new("Minimap Camera"));
+    minimap.image = Some(image);
+}
+
+fn toggle_minimap(
+    keys: Res<Input<KeyCode>>,
+    mut egui: ResMut<EguiContext>,
+    mut minimap: ResMut<Minimap>,
+) {
+    // Don't steal the key from text fields
+    if egui.ctx_mut().wants_keyboard_input() {
+        return;
+    }
+    if keys.just_pressed(TOGGLE_MINIMAP) {
+        minimap.enabled = !minimap.enabled;
+    }
+}
+
+/// Keep the minimap centered on the focus of the main camera, north up
+fn follow_focus(
+    minimap: Res<Minimap>,
+    main: Query<&YawPitchControls, With<IsometricCamera>>,
+    mut cameras: Query<
+        (&mut Transform, &mut OrthographicProjection),
+        With<MinimapCamera>,
+    >,
+) {
+    let focus = match main.get_single() {
+        Ok(controls) => controls.focus,
+        Err(_) => return,
+    };
+    for (mut transform, mut projection) in cameras.iter_mut() {
+        let target =
+            Transform::from_translation(focus + Vec3::Y * MINIMAP_HEIGHT)
+                .looking_at(focus, -Vec3::Z);
+        if *transform != target {
+            *transform = target;
+        }
+        if projection.scale != minimap.scale {
+            projection.scale = minimap.scale;
+        }
+    }
+}
+
+fn minimap_window(mut egui: ResMut<EguiContext>, minimap: Res<Minimap>) {
+    let image = match &minimap.image {
+        Some(image) if minimap.enabled => image,
+        _ => return,
+    };
+    let texture = egui.add_image(image.clone_weak());
+    let size = egui::Vec2::new(minimap.size.x as f32, minimap.size.y as f32);
+    egui::Window::new("Minimap")
+        .resizable(false)
+        .show(egui.ctx_mut(), |ui| {
+            ui.image(texture, size);
+        });
+}
@@ -0,0 +1,273 @@
+use crate::aoe::{AimSystem, Aiming};
+use crate::dialogue::{not_talking, Conversation};
+use crate::editor::GameMode;
+use crate::health::Health;
+use crate::map::{
+    Direction, Location, MoveTween, NavGrid, Occupancy, TileMesh,
+};
+use crate::material::ViewLayer;
+use crate::player::Player;
+use crate::push::Pushable;
+use crate::rules::Rules;
+use crate::select::{HoveredTile, TileClicked};
+use crate::turn::{player_acts, ActionPoints, EndTurn, TurnQueue};
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy_egui::EguiContext;
+use iyes_loopless::prelude::*;
+use std::collections::VecDeque;
+
+pub struct MovementPlugin;
+
+impl Plugin for MovementPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MovePreviewStyle>()
+            .add_system(
+                walk_to_clicked
+                    .before(AimSystem)
+                    .run_in_state(GameMode::Playing)
+                    .run_if(player_acts)
+                    .run_if(not_talking),
+            )
+            .add_system(
+                end_turn_early
+                    .run_in_state(GameMode::Playing)
+                    .run_if(player_acts)
+                    .run_if(not_talking),
+            )
+            .add_system(
+                WalkPath::system
+                    .run_in_state(GameMode::Playing)
+                    .run_if(not_talking),
+            )
+            .add_system(preview_moves.after(walk_to_clicked));
+    }
+}
+
+/// Key which ends the player's turn with action points left over
+const PASS: KeyCode = KeyCode::Return;
+
+/// Tiles still to walk through one after another, stepping into the next
+/// once the last step has finished sliding
+#[derive(Component, Debug, Clone, Default)]
+pub struct WalkPath {
+    pub steps: VecDeque<Location>,
+}
+
+impl WalkPath {
+    /// Take the next step of each walk, paying for it out of the walker's
+    /// [`ActionPoints`], and end the turn once the points run out
+    fn system(
+        mut commands: Commands,
+        rules: Res<Rules>,
+        nav: Res<NavGrid>,
+        walkable: Walkable,
+        mut end_turns: EventWriter<EndTurn>,
+        mut walkers: Query<(
+            Entity,
+            &mut WalkPath,
+            &mut Location,
+            &mut Direction,
+            &mut ActionPoints,
+            Option<&MoveTween>,
+        )>,
+    ) {
+        for (entity, mut walk, mut location, mut facing, mut points, tween) in
+            walkers.iter_mut()
+        {
+            if matches!(tween, Some(tween) if tween.is_moving()) {
+                continue;
+            }
+            let here = *location;
+            let step = walk.steps.pop_front().filter(|&step| {
+                nav.is_passable(here, step)
+                    && walkable.is_free(step)
+                    && points.spend(rules.move_cost * nav.step_cost(step))
+            });
+            let step = match step {
+                Some(step) => step,
+                None => {
+                    commands.entity(entity).remove::<WalkPath>();
+                    if points.is_spent() {
+                        end_turns.send(EndTurn(entity));
+                    }
+                    continue;
+                }
+            };
+
+            if let Some(dir) = Direction::ALL
+                .into_iter()
+                .find(|&d| here.neighbor(d) == step)
+            {
+                if *facing != dir {
+                    *facing = dir;
+                }
+            }
+            *location = step;
+        }
+    }
+}
+
+/// Which tiles have nothing standing in the way of walking onto them
+#[derive(SystemParam)]
+pub struct Walkable<'w, 's> {
+    occupancy: Res<'w, Occupancy>,
+    blockers: Query<'w, 's, (), Or<(With<Health>, With<Pushable>)>>,
+}
+
+impl<'w, 's> Walkable<'w, 's> {
+    pub fn is_free(&self, location: Location) -> bool {
+        let entities = self.occupancy.entities_at(location);
+        !entities.iter().any(|&e| self.blockers.get(e).is_ok())
+    }
+}
+
+/// Walk the player to the tile clicked, if it is in reach of the action
+/// points left
+#[allow(clippy::too_many_arguments)]
+fn walk_to_clicked(
+    mut commands: Commands,
+    rules: Res<Rules>,
+    nav: Res<NavGrid>,
+    turns: Res<TurnQueue>,
+    aiming: Res<Aiming>,
+    walkable: Walkable,
+    mut clicked: EventReader<TileClicked>,
+    players: Query<
+        (Entity, &Location, &ActionPoints),
+        (With<Player>, Without<WalkPath>),
+    >,
+) {
+    let clicks: Vec<Location> = clicked
+        .iter()
+        .filter(|click| click.button == MouseButton::Left)
+        .map(|click| click.location)
+        .collect();
+    if aiming.0 {
+        return;
+    }
+    for to in clicks {
+        for (entity, &from, points) in players.iter() {
+            if !turns.is_turn(entity) {
+                continue;
+            }
+            let reach =
+                nav.reachable(from, points.current, rules.move_cost, |at| {
+                    !walkable.is_free(at)
+                });
+            if let Some(path) = reach.path(to).filter(|path| !path.is_empty()) {
+                commands
+                    .entity(entity)
+                    .insert(WalkPath { steps: path.into() });
+            }
+        }
+    }
+}
+
+fn end_turn_early(
+    keys: Res<Input<KeyCode>>,
+    turns: Res<TurnQueue>,
+    mut egui: ResMut<EguiContext>,
+    mut end_turns: EventWriter<EndTurn>,
+    players: Query<Entity, (With<Player>, Without<WalkPath>)>,
+) {
+    if egui.ctx_mut().wants_keyboard_input() || !keys.just_pressed(PASS) {
+        return;
+    }
+    for entity in players.iter().filter(|&e| turns.is_turn(e)) {
+        end_turns.send(EndTurn(entity));
+    }
+}
+
+/// Materials the tiles the player can walk to are drawn with
+pub struct MovePreviewStyle {
+    /// Every tile in reach
+    pub reach: Handle<StandardMaterial>,
+    /// The walk to the hovered tile
+    pub path: Handle<StandardMaterial>,
+}
+
+impl FromWorld for MovePreviewStyle {
+    fn from_world(world: &mut World) -> Self {
+        let mut materials = world.resource_mut::<Assets<StandardMaterial>>();
+        let mut add = |color: Color| {
+            materials.add(StandardMaterial {
+                base_color: color,
+                alpha_mode: AlphaMode::Blend,
+                unlit: true,
+                ..Default::default()
+            })
+        };
+        Self {
+            reach: add(Color::rgba(0.3, 0.6, 1.0, 0.2)),
+            path: add(Color::rgba(0.4, 0.8, 1.0, 0.5)),
+        }
+    }
+}
+
+/// Marker for the overlay drawn on a tile in the player's move preview
+#[derive(Component)]
+pub struct MoveOverlay;
+
+/// What the move preview was last drawn for
+type PreviewKey = Option<(Location, u32, Option<Location>)>;
+
+/// Shade every tile the player can still walk to this turn, and the walk to
+/// the hovered tile, while the player is choosing where to go
+#[allow(clippy::too_many_arguments)]
+fn preview_moves(
+    mut commands: Commands,
+    mut drawn: Local<PreviewKey>,
+    rules: Res<Rules>,
+    nav: Res<NavGrid>,
+    turns: Res<TurnQueue>,
+    aiming: Res<Aiming>,
+    conversation: Res<Conversation>,
+    hovered: Res<HoveredTile>,
+    style: Res<MovePreviewStyle>,
+    mesh: Res<TileMesh>,
+    walkable: Walkable,
+    players: Query<
+        (Entity, &Location, &ActionPoints),
+        (With<Player>, Without<WalkPath>),
+    >,
+    overlays: Query<Entity, With<MoveOverlay>>,
+) {
+    let player = players.iter().find(|&(entity, ..)| {
+        turns.is_turn(entity) && !aiming.0 && !conversation.is_active()
+    });
+    let key =
+        player.map(|(_, &from, points)| (from, points.current, hovered.0));
+    if key == *drawn && !nav.is_changed() {
+        return;
+    }
+    *drawn = key;
+    for entity in overlays.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    let (from, points) = match player {
+        Some((_, &from, points)) => (from, points.current),
+        None => return,
+    };
+    let reach = nav
+        .reachable(from, points, rules.move_cost, |at| !walkable.is_free(at));
+    let path = hovered.0.and_then(|to| reach.path(to)).unwrap_or_default();
+    for location in reach.tiles() {
+        let material = match path.contains(&location) {
+            true => style.path.clone(),
+            false => style.reach.clone(),
+        };
+        // Lift slightly off the ground to avoid z-fighting with the tile
+        let translation = Vec3::from(location) + Vec3::Y * 0.001;
+        commands
+            .spawn_bundle(PbrBundle {
+                mesh: mesh.handle.clone(),
+                material,
+                transform: Transform::from_translation(translation),
+                ..Default::default()
+            })
+            .insert(MoveOverlay)
+            .insert(ViewLayer::Gameplay.layers());
+    }
+}
@@ -0,0 +1,116 @@
+use crate::camera::{CameraSystem, IsometricCamera, YawPitchControls};
+use crate::map::{
+    Edge, Prop, SpriteFade, SpriteSystem, TILE_SIZE, WALL_HEIGHT,
+};
+use bevy::prelude::*;
+
+pub struct OcclusionPlugin;
+
+impl Plugin for OcclusionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<WallFading>().add_system(
+            fade_occluders.after(CameraSystem).before(SpriteSystem),
+        );
+    }
+}
+
+/// Settings for dithering out walls between the camera and its focus, so
+/// whatever the camera follows is never hidden behind the level
+pub struct WallFading {
+    pub enabled: bool,
+    /// Walls closer than this to the line of sight are faded
+    pub radius: f32,
+    /// Also fade walls and props close enough to the camera to be sliced
+    /// open by its near plane, which happens on cramped maps at low pitch
+    pub near_plane: bool,
+    /// Distance past the near plane walls and props start fading at
+    pub near_margin: f32,
+    /// Fraction of a faded wall's pixels which are dithered away
+    pub fade: f32,
+    /// How quickly walls fade in and out, in fade per second
+    pub speed: f32,
+}
+
+impl Default for WallFading {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            radius: TILE_SIZE * 1.5,
+            near_plane: true,
+            near_margin: TILE_SIZE * 0.5,
+            fade: 0.75,
+            speed: 4.0,
+        }
+    }
+}
+
+/// Distance from a wall's center to its furthest corner
+fn wall_radius() -> f32 {
+    Vec2::new(TILE_SIZE, WALL_HEIGHT).length() * 0.5
+}
+
+/// Distance from the eye to the near plane along the view, whether the
+/// camera is orthographic or in perspective
+fn near_distance(camera: &Camera) -> f32 {
+    // Reversed depth puts the near plane at 1
+    let near = camera.projection_matrix.inverse().project_point3(Vec3::Z);
+    -near.z
+}
+
+/// Fade walls and props standing in front of the camera focus or against
+/// the near plane, and bring back the ones which no longer are
+fn fade_occluders(
+    mut commands: Commands,
+    settings: Res<WallFading>,
+    time: Res<Time>,
+    camera: Query<
+        (&Camera, &GlobalTransform, &YawPitchControls),
+        With<IsometricCamera>,
+    >,
+    mut walls: Query<
+        (Entity, &GlobalTransform, Option<&mut SpriteFade>),
+        Or<(With<Edge>, With<Prop>)>,
+    >,
+) {
+    let view = camera.get_single().ok().filter(|_| settings.enabled);
+    let sight = view
+        .map(|(_, transform, controls)| (transform.forward(), controls.focus));
+    let near =
+        view.filter(|_| settings.near_plane)
+            .map(|(camera, transform, _)| {
+                let near = near_distance(camera) + settings.near_margin;
+                (transform.translation, transform.forward(), near)
+            });
+    let radius = wall_radius();
+    let step = settings.speed * time.delta_seconds();
+
+    for (entity, transform, fade) in walls.iter_mut() {
+        let center = transform.translation + Vec3::Y * WALL_HEIGHT * 0.5;
+        // The camera is orthographic, so every view ray runs along forward
+        let occludes = sight.map_or(false, |(forward, focus)| {
+            let offset = center - focus;
+            let depth = offset.dot(forward);
+            depth < 0.0 && (offset - forward * depth).length() < settings.radius
+        });
+        let clipped = near.map_or(false, |(eye, forward, near)| {
+            (center - eye).dot(forward) - radius < near
+        });
+        let occludes = occludes || clipped;
+        let target = if occludes { settings.fade } else { 0.0 };
+
+        match fade {
+            Some(mut fade) if fade.0 != target => {
+                fade.0 = if fade.0 < target {
+                    (fade.0 + step).min(target)
+                } else {
+                    (fade.0 - step).max(target)
+                };
+            }
+            Some(_) => {}
+            None if occludes => {
+                commands.entity(entity).insert(SpriteFade(step.min(target)));
+            }
+            None => {}
+        }
+    }
+}
@@ -0,0 +1,303 @@
+use crate::camera::{
+    ControllerBasis, Easing, Follow, IsometricCamera, YawPitchControls,
+};
+use crate::dialogue::not_talking;
+use crate::editor::GameMode;
+use crate::effects::CameraImpact;
+use crate::fog::Viewer;
+use crate::health::{Damage, Death, Health, HitFeedback, Invulnerability};
+use crate::item::Inventory;
+use crate::map::{
+    Direction, Location, Marker, MoveTween, NavGrid, OccupancySystem,
+    TILE_SIZE, WALL_HEIGHT,
+};
+use crate::material::ViewLayer;
+use crate::movement::WalkPath;
+use crate::push::Pushing;
+use crate::rules::Rules;
+use crate::stats::{Experience, Stats, StatusEffects};
+use crate::team::{Hostility, Team};
+use crate::turn::{player_acts, ActionPoints, Actor, EndTurn, TurnQueue};
+use bevy::prelude::*;
+use bevy_egui::EguiContext;
+use iyes_loopless::prelude::*;
+
+pub struct PlayerPlugin;
+
+impl Plugin for PlayerPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MoveRepeat>()
+            .add_enter_system(GameMode::Playing, spawn_player)
+            .add_exit_system(GameMode::Playing, despawn_player)
+            .add_system(
+                move_player
+                    .after(OccupancySystem)
+                    .run_in_state(GameMode::Playing)
+                    .run_if(player_acts)
+                    .run_if(not_talking),
+            );
+    }
+}
+
+/// Marker for the character the player moves around the map
+#[derive(Component, Debug, Clone, Copy, Default)]
+pub struct Player;
+
+/// Keys which step the player a tile, relative to the view so up always
+/// walks away from the camera
+fn move_keys() -> [(KeyCode, Vec2); 4] {
+    [
+        (KeyCode::W, Vec2::Y),
+        (KeyCode::S, -Vec2::Y),
+        (KeyCode::D, Vec2::X),
+        (KeyCode::A, -Vec2::X),
+    ]
+}
+
+/// Held while stepping away from a pushable block to drag it along
+const PULL: KeyCode = KeyCode::LShift;
+
+/// Seconds a move key is held before the player keeps walking
+const REPEAT_DELAY: f32 = 0.25;
+
+/// Seconds between steps while a move key is held
+const REPEAT_INTERVAL: f32 = 0.15;
+
+/// Seconds the player takes to slide into the next tile, shorter than the
+/// repeat so held keys walk smoothly without falling behind
+pub const STEP_DURATION: f32 = 0.12;
+
+/// Hit points the player starts with
+const PLAYER_HEALTH: u32 = 5;
+
+/// Seconds the player shrugs off damage for after being hurt
+pub const INVULNERABILITY: f32 = 0.5;
+
+/// Inventory slots the player starts with
+const INVENTORY_SLOTS: usize = 12;
+
+/// Damage dealt by walking into something with [`Health`]
+const ATTACK_DAMAGE: u32 = 1;
+
+/// Time until the next step while a move key is held
+#[derive(Default)]
+struct MoveRepeat {
+    remaining: f32,
+}
+
+/// Spawn the player on the map's spawn marker, and point the camera at it
+fn spawn_player(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    rules: Res<Rules>,
+    markers: Query<(&Location, &Marker)>,
+    cameras: Query<Entity, With<IsometricCamera>>,
+) {
+    let location = markers
+        .iter()
+        .find(|(_, marker)| matches!(marker, Marker::Spawn))
+        .map(|(&location, _)| location)
+        .unwrap_or_else(|| {
+            warn!("Map has no spawn, the player starts at the origin");
+            Location::default()
+        });
+
+    let body = spawn_body(
+        &mut commands,
+        &mut meshes,
+        &mut materials,
+        Color::rgb(0.9, 0.75, 0.3),
+    );
+    let player = commands
+        .spawn_bundle(TransformBundle::from_transform(
+            Transform::from_translation(location.into()),
+        ))
+        .insert(location)
+        .insert(MoveTween::new(STEP_DURATION, Easing::EaseOut))
+        .insert(Direction::PositiveY)
+        .insert(Player)
+        .insert(Actor { initiative: 0 })
+        .insert(Team::Player)
+        .insert(ActionPoints::new(rules.action_points))
+        .insert(Health::new(PLAYER_HEALTH))
+        .insert(Invulnerability::new(INVULNERABILITY))
+        .insert(Inventory::new(INVENTORY_SLOTS))
+        .insert(Stats::default())
+        .insert(Experience::default())
+        .insert(StatusEffects::default())
+        .insert(HitFeedback {
+            impact: Some(CameraImpact::hit()),
+            ..Default::default()
+        })
+        // Stay where the camera can see what happened
+        .insert(Death::Corpse { sprite: None })
+        .insert(Viewer)
+        .insert(Name::new("Player"))
+        .add_child(body)
+        .id();
+
+    for camera in cameras.iter() {
+        commands.entity(camera).insert(Follow::new(player));
+    }
+}
+
+/// Capsule standing on the floor, shown for the player and anything else
+/// walking around the map until they have sprites of their own
+pub fn spawn_body(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<StandardMaterial>,
+    color: Color,
+) -> Entity {
+    let radius = TILE_SIZE * 0.25;
+    let height = WALL_HEIGHT * 0.6;
+    commands
+        .spawn_bundle(PbrBundle {
+            mesh: meshes.add(
+                shape::Capsule {
+                    radius,
+                    depth: height - radius * 2.0,
+                    ..Default::default()
+                }
+                .into(),
+            ),
+            material: materials.add(color.into()),
+            // Stand on the floor rather than sink halfway into it
+            transform: Transform::from_xyz(0.0, height * 0.5, 0.0),
+            ..Default::default()
+        })
+        .insert(ViewLayer::Gameplay.layers())
+        .id()
+}
+
+fn despawn_player(
+    mut commands: Commands,
+    players: Query<Entity, With<Player>>,
+) {
+    for entity in players.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// Step the player one tile per press of a move key, or repeatedly while it
+/// is held, turning to face the step even when a wall is in the way.
+///
+/// Walking into anything with [`Health`] on a hostile [`Team`] attacks it
+/// instead, anyone else is in the way, and walking into a pushable block
+/// pushes it. Each step spends [`ActionPoints`], and the
+/// player's turn ends once they run out or on an attack, turning to face a
+/// wall is free.
+#[allow(clippy::too_many_arguments)]
+fn move_player(
+    time: Res<Time>,
+    keys: Res<Input<KeyCode>>,
+    rules: Res<Rules>,
+    nav: Res<NavGrid>,
+    turns: Res<TurnQueue>,
+    hostility: Res<Hostility>,
+    mut end_turns: EventWriter<EndTurn>,
+    mut damage: EventWriter<Damage>,
+    mut repeat: ResMut<MoveRepeat>,
+    mut egui: ResMut<EguiContext>,
+    cameras: Query<
+        (&YawPitchControls, &ControllerBasis),
+        With<IsometricCamera>,
+    >,
+    mut pushing: Pushing,
+    teams: Query<&Team>,
+    mut players: Query<
+        (Entity, &mut Location, &mut Direction, &mut ActionPoints),
+        (With<Player>, Without<WalkPath>),
+    >,
+) {
+    if egui.ctx_mut().wants_keyboard_input() {
+        return;
+    }
+    let pressed = move_keys().iter().any(|&(key, _)| keys.just_pressed(key));
+    let input = move_keys()
+        .iter()
+        .filter(|&&(key, _)| keys.pressed(key))
+        .fold(Vec2::ZERO, |sum, &(_, dir)| sum + dir);
+
+    // Step right away on a press, and then every so often while held
+    let step = if pressed {
+        repeat.remaining = REPEAT_DELAY;
+        true
+    } else if input != Vec2::ZERO {
+        repeat.remaining -= time.delta_seconds();
+        if repeat.remaining <= 0.0 {
+            repeat.remaining += REPEAT_INTERVAL;
+            true
+        } else {
+            false
+        }
+    } else {
+        false
+    };
+    if !step {
+        return;
+    }
+
+    let dir = match cameras.get_single() {
+        Ok((controls, basis)) => controls.input_direction(basis, input),
+        Err(_) => None,
+    };
+    let dir = match dir {
+        Some(dir) => dir,
+        None => return,
+    };
+
+    for (entity, mut location, mut facing, mut points) in players.iter_mut() {
+        if !turns.is_turn(entity) {
+            continue;
+        }
+        // Pulling walks backwards, still facing the block
+        let behind = location.neighbor(dir.opposite());
+        let pulling = keys.pressed(PULL) && pushing.is_block(behind);
+        let face = if pulling { dir.opposite() } else { dir };
+        if *facing != face {
+            *facing = face;
+        }
+
+        let next = location.neighbor(dir);
+        if nav.is_walled(*location, next) {
+            continue;
+        }
+        if let Some(target) = pushing.occupant(next) {
+            let hostile = match (teams.get(entity), teams.get(target)) {
+                (Ok(&team), Ok(&other)) => hostility.is_hostile(team, other),
+                _ => true,
+            };
+            if !hostile {
+                continue;
+            }
+            damage.send(Damage {
+                target,
+                amount: ATTACK_DAMAGE,
+                source: Some(entity),
+            });
+            end_turns.send(EndTurn(entity));
+            continue;
+        }
+        let cost = rules.move_cost * nav.step_cost(next);
+        if cost > points.current {
+            continue;
+        }
+        if pushing.is_block(next) {
+            if pushing.push(next, dir) {
+                *location = next;
+                points.spend(cost);
+            }
+        } else if nav.is_passable(*location, next) {
+            if pulling {
+                pushing.pull(behind, *location);
+            }
+            *location = next;
+            points.spend(cost);
+        }
+        if points.is_spent() {
+            end_turns.send(EndTurn(entity));
+        }
+    }
+}
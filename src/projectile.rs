@@ -0,0 +1,273 @@
+use crate::camera::{CameraInputConfig, Easing};
+use crate::dialogue::not_talking;
+use crate::editor::GameMode;
+use crate::health::{Damage, DamageSystem, Health};
+use crate::map::{
+    Direction, Location, MoveTween, NavGrid, Occupancy, OccupancySystem,
+    WALL_HEIGHT,
+};
+use crate::material::ViewLayer;
+use crate::player::Player;
+use crate::turn::{player_acts, EndTurn, TurnQueue};
+use bevy::prelude::*;
+use bevy_egui::EguiContext;
+use iyes_loopless::prelude::*;
+use std::collections::VecDeque;
+
+pub struct ProjectilePlugin;
+
+impl Plugin for ProjectilePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<Launch>()
+            .add_event::<ProjectileImpact>()
+            .add_exit_system(GameMode::Playing, despawn_projectiles)
+            .add_system(
+                fire_arrow
+                    .run_in_state(GameMode::Playing)
+                    .run_if(player_acts)
+                    .run_if(not_talking),
+            )
+            .add_system(launch.after(fire_arrow))
+            .add_system(
+                Projectile::advance.after(launch).after(OccupancySystem),
+            )
+            .add_system(
+                hit_on_impact
+                    .after(Projectile::advance)
+                    .before(DamageSystem),
+            );
+    }
+}
+
+/// Tiles an arrow flies before dropping
+const ARROW_RANGE: u32 = 8;
+
+/// Something flying across the map a tile at a time, stopped by walls,
+/// obstacles, and the first unit in its way
+#[derive(Component, Debug, Clone)]
+pub struct Projectile {
+    /// Tiles still to fly through, in order
+    pub path: VecDeque<Location>,
+    /// Seconds spent crossing each tile
+    pub step: f32,
+    /// Dealt to whatever it hits
+    pub damage: u32,
+    /// Whoever launched it, which it flies straight past
+    pub source: Option<Entity>,
+    /// Seconds into crossing the current tile
+    elapsed: f32,
+}
+
+impl Projectile {
+    /// Fly through each tile of `path` in turn
+    pub fn along(
+        path: impl IntoIterator<Item = Location>,
+        damage: u32,
+    ) -> Self {
+        Self {
+            path: path.into_iter().collect(),
+            step: 0.05,
+            damage,
+            source: None,
+            elapsed: 0.0,
+        }
+    }
+
+    /// Fly `range` tiles from `from` in `direction`
+    pub fn straight(
+        from: Location,
+        direction: Direction,
+        range: u32,
+        damage: u32,
+    ) -> Self {
+        let path = (1..=range as i32).scan(from, move |at, _| {
+            *at = at.neighbor(direction);
+            Some(*at)
+        });
+        Self::along(path, damage)
+    }
+
+    /// Quick and light, for bows
+    pub fn arrow(from: Location, direction: Direction) -> Self {
+        Self::straight(from, direction, ARROW_RANGE, 1)
+    }
+
+    /// Slow and heavy, for spells
+    pub fn fireball(from: Location, direction: Direction) -> Self {
+        Self {
+            step: 0.12,
+            ..Self::straight(from, direction, ARROW_RANGE / 2, 2)
+        }
+    }
+
+    /// Step each projectile into its next tile once it has spent long enough
+    /// crossing the last, stopping it against anything in the way
+    fn advance(
+        mut commands: Commands,
+        time: Res<Time>,
+        nav: Res<NavGrid>,
+        occupancy: Res<Occupancy>,
+        mut impacts: EventWriter<ProjectileImpact>,
+        mut projectiles: Query<(Entity, &mut Projectile, &mut Location)>,
+        units: Query<(), With<Health>>,
+    ) {
+        for (entity, mut projectile, mut location) in projectiles.iter_mut() {
+            projectile.elapsed += time.delta_seconds();
+            while projectile.elapsed >= projectile.step {
+                projectile.elapsed -= projectile.step;
+                let here = *location;
+
+                let next = projectile.path.pop_front();
+                let blocked = next.filter(|&next| {
+                    nav.is_walled(here, next)
+                        || !nav.has_floor(next)
+                        || nav.is_obstacle(next)
+                });
+                let hit = next.and_then(|next| {
+                    occupancy.entities_at(next).iter().copied().find(|&unit| {
+                        Some(unit) != projectile.source
+                            && units.get(unit).is_ok()
+                    })
+                });
+
+                let impact = match (next, blocked, hit) {
+                    // Out of range, or ran into a wall
+                    (None, ..) | (_, Some(_), _) => Some((here, None)),
+                    (Some(next), None, Some(hit)) => Some((next, Some(hit))),
+                    (Some(_), None, None) => None,
+                };
+                match impact {
+                    Some((at, hit)) => {
+                        impacts.send(ProjectileImpact {
+                            projectile: entity,
+                            location: at,
+                            hit,
+                            damage: projectile.damage,
+                            source: projectile.source,
+                        });
+                        commands.entity(entity).despawn_recursive();
+                        break;
+                    }
+                    None => *location = next.unwrap_or(here),
+                }
+            }
+        }
+    }
+}
+
+/// Send to put a [`Projectile`] on the map at `from`, shown as a ball of
+/// `color`
+#[derive(Debug, Clone)]
+pub struct Launch {
+    pub from: Location,
+    pub projectile: Projectile,
+    pub color: Color,
+}
+
+/// Sent when a [`Projectile`] stops, having hit a unit or not, at the last
+/// tile it reached
+#[derive(Debug, Clone, Copy)]
+pub struct ProjectileImpact {
+    /// Already despawned by the time this is read
+    pub projectile: Entity,
+    pub location: Location,
+    /// Unit which stopped it, `None` for a wall or running out of range
+    pub hit: Option<Entity>,
+    pub damage: u32,
+    pub source: Option<Entity>,
+}
+
+fn launch(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut launches: EventReader<Launch>,
+) {
+    for launch in launches.iter() {
+        let ball = commands
+            .spawn_bundle(PbrBundle {
+                mesh: meshes.add(
+                    shape::Icosphere {
+                        radius: 0.1,
+                        subdivisions: 2,
+                    }
+                    .into(),
+                ),
+                material: materials.add(StandardMaterial {
+                    base_color: launch.color,
+                    emissive: launch.color,
+                    unlit: true,
+                    ..Default::default()
+                }),
+                // Fly at about chest height
+                transform: Transform::from_xyz(0.0, WALL_HEIGHT * 0.4, 0.0),
+                ..Default::default()
+            })
+            .insert(ViewLayer::Gameplay.layers())
+            .id();
+        let step = launch.projectile.step;
+        commands
+            .spawn_bundle(TransformBundle::from_transform(
+                Transform::from_translation(launch.from.into()),
+            ))
+            .insert(launch.from)
+            .insert(launch.projectile.clone())
+            .insert(MoveTween::new(step, Easing::Linear))
+            .insert(Name::new("Projectile"))
+            .add_child(ball);
+    }
+}
+
+/// Hurt whatever a projectile hit
+fn hit_on_impact(
+    mut impacts: EventReader<ProjectileImpact>,
+    mut damage: EventWriter<Damage>,
+) {
+    for impact in impacts.iter() {
+        if let Some(target) = impact.hit {
+            damage.send(Damage {
+                target,
+                amount: impact.damage,
+                source: impact.source,
+            });
+        }
+    }
+}
+
+/// Shoot an arrow the way the player faces, which ends the player's turn
+fn fire_arrow(
+    input: Res<CameraInputConfig>,
+    keys: Res<Input<KeyCode>>,
+    turns: Res<TurnQueue>,
+    mut egui: ResMut<EguiContext>,
+    mut launches: EventWriter<Launch>,
+    mut end_turns: EventWriter<EndTurn>,
+    players: Query<(Entity, &Location, &Direction), With<Player>>,
+) {
+    if egui.ctx_mut().wants_keyboard_input() || !keys.just_pressed(input.fire) {
+        return;
+    }
+    for (entity, &from, &facing) in players.iter() {
+        if !turns.is_turn(entity) {
+            continue;
+        }
+        launches.send(Launch {
+            from,
+            projectile: Projectile {
+                source: Some(entity),
+                ..Projectile::arrow(from, facing)
+            },
+            color: Color::rgb(0.9, 0.85, 0.7),
+        });
+        end_turns.send(EndTurn(entity));
+    }
+}
+
+fn despawn_projectiles(
+    mut commands: Commands,
+    projectiles: Query<Entity, With<Projectile>>,
+) {
+    for entity in projectiles.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
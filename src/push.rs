@@ -0,0 +1,127 @@
+use crate::camera::Easing;
+use crate::health::Health;
+use crate::map::{
+    Direction, Location, MoveTween, NavGrid, Obstacle, Occupancy,
+};
+use crate::player::{Player, STEP_DURATION};
+use crate::rules::Rules;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+
+pub struct PushPlugin;
+
+impl Plugin for PushPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(Pushable::setup);
+    }
+}
+
+/// Block which can be pushed or pulled a tile at a time, placed on a prop in
+/// the map file.
+///
+/// Blocks are obstacles, so nothing walks through them and the AI paths
+/// around them.
+#[derive(Component, Debug, Clone, Copy, Default)]
+pub struct Pushable;
+
+impl Pushable {
+    /// Block the tile and slide between tiles like anything else walking
+    /// around the map
+    fn setup(
+        mut commands: Commands,
+        added: Query<(Entity, Option<&MoveTween>), Added<Pushable>>,
+    ) {
+        for (entity, tween) in added.iter() {
+            let mut entity = commands.entity(entity);
+            entity.insert(Obstacle);
+            if tween.is_none() {
+                entity.insert(MoveTween::new(STEP_DURATION, Easing::EaseOut));
+            }
+        }
+    }
+}
+
+/// Moves pushable blocks for anything walking into them
+#[derive(SystemParam)]
+pub struct Pushing<'w, 's> {
+    nav: Res<'w, NavGrid>,
+    occupancy: ResMut<'w, Occupancy>,
+    rules: Res<'w, Rules>,
+    blocks: Query<
+        'w,
+        's,
+        (Entity, &'static mut Location),
+        (With<Pushable>, Without<Player>),
+    >,
+    occupants:
+        Query<'w, 's, (), (With<Health>, Without<Pushable>, Without<Player>)>,
+}
+
+impl<'w, 's> Pushing<'w, 's> {
+    pub fn is_block(&self, location: Location) -> bool {
+        self.block_at(location).is_some()
+    }
+
+    /// Something with [`Health`] standing at `location`, other than the
+    /// player
+    pub fn occupant(&self, location: Location) -> Option<Entity> {
+        self.occupancy
+            .entities_at(location)
+            .iter()
+            .copied()
+            .find(|&entity| self.occupants.get(entity).is_ok())
+    }
+
+    fn block_at(&self, location: Location) -> Option<Entity> {
+        self.occupancy
+            .entities_at(location)
+            .iter()
+            .copied()
+            .find(|&entity| self.blocks.get(entity).is_ok())
+    }
+
+    /// Shift the row of blocks starting at `at` a tile in `dir`, if the tile
+    /// past the last block is free and the row is no longer than the rules
+    /// allow
+    pub fn push(&mut self, at: Location, dir: Direction) -> bool {
+        let mut row = Vec::new();
+        let mut next = at;
+        while let Some(block) = self.block_at(next) {
+            let ahead = next.neighbor(dir);
+            if self.nav.is_walled(next, ahead) {
+                return false;
+            }
+            row.push(block);
+            next = ahead;
+        }
+        if row.is_empty()
+            || row.len() > self.rules.push_chain as usize
+            || !self.nav.has_floor(next)
+            || self.nav.is_obstacle(next)
+            || self.occupant(next).is_some()
+        {
+            return false;
+        }
+
+        for block in row {
+            if let Ok((_, mut location)) = self.blocks.get_mut(block) {
+                *location = location.neighbor(dir);
+                self.occupancy.move_to(block, *location);
+            }
+        }
+        true
+    }
+
+    /// Drag the block at `at` onto `to`, the tile the puller is stepping off
+    pub fn pull(&mut self, at: Location, to: Location) -> bool {
+        let block = match self.block_at(at) {
+            Some(block) if !self.nav.is_walled(at, to) => block,
+            _ => return false,
+        };
+        if let Ok((_, mut location)) = self.blocks.get_mut(block) {
+            *location = to;
+            self.occupancy.move_to(block, to);
+        }
+        true
+    }
+}
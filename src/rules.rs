@@ -0,0 +1,127 @@
+use bevy::prelude::*;
+use bevy_inspector_egui::{Inspectable, RegisterInspectable};
+use iyes_loopless::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub struct RulesPlugin;
+
+impl Plugin for RulesPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_inspectable::<GlobalRules>()
+            .init_resource::<GlobalRules>()
+            .init_resource::<RuleOverrides>()
+            .init_resource::<Rules>()
+            .add_system_set(
+                ConditionSet::new()
+                    .with_system(merge_rules)
+                    .with_system(apply_ambient_light)
+                    .into(),
+            );
+    }
+}
+
+/// Gameplay constants in effect for the current map
+#[derive(Inspectable, Debug, Clone, PartialEq)]
+pub struct Rules {
+    /// How many tiles the player can see
+    pub vision_radius: u32,
+    /// Action points spent moving one tile
+    pub move_cost: u32,
+    /// Action points the player gets at the start of each turn
+    pub action_points: u32,
+    /// Brightness of the ambient light
+    pub ambient_light: f32,
+    /// Most pushable blocks in a row which can be pushed at once
+    pub push_chain: u32,
+    /// Whether units hurt others on their own team
+    pub friendly_fire: bool,
+    /// Seconds an actor waits after a full turn in real time, less for a
+    /// turn it only spent some of its action points on
+    pub turn_seconds: f32,
+    /// Seconds each round lasts in real time
+    pub round_seconds: f32,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Self {
+            vision_radius: 6,
+            move_cost: 1,
+            action_points: 4,
+            ambient_light: 0.05,
+            push_chain: 1,
+            friendly_fire: true,
+            turn_seconds: 1.0,
+            round_seconds: 2.0,
+        }
+    }
+}
+
+/// Rules used by every map unless it overrides them
+#[derive(Inspectable, Debug, Clone, Default)]
+pub struct GlobalRules(pub Rules);
+
+/// Constants a map changes from the [`GlobalRules`], configured by the
+/// `rules` section of a map
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RuleOverrides {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vision_radius: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub move_cost: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub action_points: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ambient_light: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub push_chain: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub friendly_fire: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub turn_seconds: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub round_seconds: Option<f32>,
+}
+
+impl RuleOverrides {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Apply the overrides on top of `rules`
+    pub fn merge(&self, rules: &Rules) -> Rules {
+        Rules {
+            vision_radius: self.vision_radius.unwrap_or(rules.vision_radius),
+            move_cost: self.move_cost.unwrap_or(rules.move_cost),
+            action_points: self.action_points.unwrap_or(rules.action_points),
+            ambient_light: self.ambient_light.unwrap_or(rules.ambient_light),
+            push_chain: self.push_chain.unwrap_or(rules.push_chain),
+            friendly_fire: self.friendly_fire.unwrap_or(rules.friendly_fire),
+            turn_seconds: self.turn_seconds.unwrap_or(rules.turn_seconds),
+            round_seconds: self.round_seconds.unwrap_or(rules.round_seconds),
+        }
+    }
+}
+
+/// Recompute the active rules when the global rules or map overrides change
+fn merge_rules(
+    global: Res<GlobalRules>,
+    overrides: Res<RuleOverrides>,
+    mut rules: ResMut<Rules>,
+) {
+    if !global.is_changed() && !overrides.is_changed() {
+        return;
+    }
+
+    let merged = overrides.merge(&global.0);
+    if *rules != merged {
+        *rules = merged;
+    }
+}
+
+fn apply_ambient_light(rules: Res<Rules>, mut light: ResMut<AmbientLight>) {
+    if rules.is_changed() {
+        light.brightness = rules.ambient_light;
+    }
+}
@@ -0,0 +1,338 @@
+use crate::camera::{
+    CameraInputConfig, CameraProjectionExt, CameraSettings, CameraTransition,
+    IsometricCamera, YawPitchControls,
+};
+use crate::editor::GameMode;
+use crate::highlight::Highlighted;
+use crate::map::{Location, Occupancy, Tile, TileMesh, TILE_SIZE};
+use crate::material::ViewLayer;
+use crate::team::Team;
+use bevy::prelude::*;
+use bevy::utils::HashSet;
+use bevy_egui::EguiContext;
+use iyes_loopless::prelude::*;
+
+pub struct SelectPlugin;
+
+/// Label applied to the hover and selection systems
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
+pub struct SelectSystem;
+
+impl Plugin for SelectPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<HoveredTile>()
+            .init_resource::<HoveredPoint>()
+            .init_resource::<Selection>()
+            .init_resource::<SelectionStyle>()
+            .add_event::<TileClicked>()
+            .add_event::<TileSelected>()
+            .add_system_to_stage(CoreStage::PreUpdate, hover_system)
+            .add_system_set(
+                ConditionSet::new()
+                    .label(SelectSystem)
+                    .with_system(click_system)
+                    .into(),
+            )
+            .add_system(highlight_system.after(SelectSystem))
+            .add_system(outline_hovered)
+            .add_system(frame_selection.run_in_state(GameMode::Editing));
+    }
+}
+
+/// The tile currently under the cursor, if any
+#[derive(Default, Debug)]
+pub struct HoveredTile(pub Option<Location>);
+
+/// Point on the ground plane under the cursor, if any
+#[derive(Default, Debug)]
+pub struct HoveredPoint(pub Option<Vec3>);
+
+/// Emitted when a tile is clicked, before the selection is updated
+#[derive(Debug, Clone, Copy)]
+pub struct TileClicked {
+    pub location: Location,
+    pub button: MouseButton,
+}
+
+/// Emitted for every tile added to the [`Selection`]
+#[derive(Debug, Clone, Copy)]
+pub struct TileSelected(pub Location);
+
+/// Set of selected tiles shared by editor tools and unit commands
+#[derive(Default, Debug)]
+pub struct Selection {
+    pub tiles: HashSet<Location>,
+    /// Where a shift-drag box selection started
+    drag_start: Option<Location>,
+}
+
+impl Selection {
+    pub fn contains(&self, location: Location) -> bool {
+        self.tiles.contains(&location)
+    }
+
+    pub fn clear(&mut self) {
+        self.tiles.clear();
+    }
+
+    /// The two corners of the box selection in progress, if any
+    pub fn drag_box(
+        &self,
+        hovered: &HoveredTile,
+    ) -> Option<(Location, Location)> {
+        self.drag_start.zip(hovered.0)
+    }
+}
+
+/// Find the tile under the cursor by casting a ray onto the ground plane
+fn hover_system(
+    windows: Res<Windows>,
+    cameras: Query<(&Camera, &GlobalTransform), With<IsometricCamera>>,
+    mut hovered: ResMut<HoveredTile>,
+    mut point: ResMut<HoveredPoint>,
+) {
+    let ground = cameras.get_single().ok().and_then(|(camera, transform)| {
+        camera.cursor_to_ground(transform, &windows)
+    });
+
+    if point.0 != ground {
+        point.0 = ground;
+    }
+    let tile = ground.map(Location::from_world);
+    if hovered.0 != tile {
+        hovered.0 = tile;
+    }
+}
+
+/// Turn mouse input over the hovered tile into clicks and selections
+fn click_system(
+    buttons: Res<Input<MouseButton>>,
+    keys: Res<Input<KeyCode>>,
+    hovered: Res<HoveredTile>,
+    mut egui: ResMut<EguiContext>,
+    mut selection: ResMut<Selection>,
+    mut clicked: EventWriter<TileClicked>,
+    mut selected: EventWriter<TileSelected>,
+) {
+    let shift = keys.any_pressed([KeyCode::LShift, KeyCode::RShift]);
+
+    // Finish a box selection even if the cursor left the map
+    if buttons.just_released(MouseButton::Left) {
+        if let Some(start) = selection.drag_start.take() {
+            let end = hovered.0.unwrap_or(start);
+            for y in start.y.min(end.y)..=start.y.max(end.y) {
+                for x in start.x.min(end.x)..=start.x.max(end.x) {
+                    let location = Location { x, y };
+                    if selection.tiles.insert(location) {
+                        selected.send(TileSelected(location));
+                    }
+                }
+            }
+        }
+    }
+
+    // Don't click through egui windows
+    if egui.ctx_mut().wants_pointer_input() {
+        return;
+    }
+
+    let location = match hovered.0 {
+        Some(location) => location,
+        None => return,
+    };
+
+    for &button in buttons.get_just_pressed() {
+        clicked.send(TileClicked { location, button });
+    }
+
+    if buttons.just_pressed(MouseButton::Left) {
+        if shift {
+            selection.drag_start = Some(location);
+        } else {
+            selection.clear();
+            selection.tiles.insert(location);
+            selected.send(TileSelected(location));
+        }
+    }
+}
+
+/// Room left around the tiles framed by [`frame_selection`], as a fraction
+/// of the view
+const FRAME_PADDING: f32 = 1.25;
+
+/// Move the camera over the selected tiles, or the hovered tile when
+/// nothing is selected, zoomed to fit them all in view
+#[allow(clippy::too_many_arguments)]
+fn frame_selection(
+    input: Res<CameraInputConfig>,
+    keys: Res<Input<KeyCode>>,
+    settings: Res<CameraSettings>,
+    selection: Res<Selection>,
+    hovered: Res<HoveredTile>,
+    mut egui: ResMut<EguiContext>,
+    mut transitions: EventWriter<CameraTransition>,
+    cameras: Query<
+        (&YawPitchControls, &OrthographicProjection),
+        With<IsometricCamera>,
+    >,
+) {
+    if egui.ctx_mut().wants_keyboard_input()
+        || !keys.just_pressed(input.frame_selection)
+    {
+        return;
+    }
+    let (controls, projection) = match cameras.get_single() {
+        Ok(camera) => camera,
+        Err(_) => return,
+    };
+
+    let points = selection
+        .tiles
+        .iter()
+        .copied()
+        .chain(hovered.0.filter(|_| selection.tiles.is_empty()))
+        .map(|location| {
+            let point = Vec3::from(location);
+            Vec2::new(point.x, point.z)
+        });
+    let (min, max) = match points.fold(None, |bounds, point| match bounds {
+        Some((min, max)) => Some((point.min(min), point.max(max))),
+        None => Some((point, point)),
+    }) {
+        Some(bounds) => bounds,
+        None => return,
+    };
+
+    // Tiles are centered on their location, so reach out half a tile
+    let half = Vec2::splat(TILE_SIZE * 0.5);
+    let diagonal = ((max + half) - (min - half)).length() * FRAME_PADDING;
+    // Whichever way the view turns, the diagonal fits across the view and
+    // foreshortened by the pitch from top to bottom
+    let width = (projection.right - projection.left).max(f32::EPSILON);
+    let height = (projection.top - projection.bottom).max(f32::EPSILON);
+    let fit = (diagonal / width)
+        .max(diagonal * controls.pitch.sin().abs() / height)
+        .clamp(settings.min_zoom, settings.max_zoom.max(settings.min_zoom));
+
+    let center = (min + max) * 0.5;
+    transitions.send(
+        CameraTransition::to(Vec3::new(center.x, 0.0, center.y)).with_zoom(fit),
+    );
+}
+
+/// Marker for the tile outlined because it is under the cursor
+#[derive(Component)]
+pub struct HoverOutline;
+
+/// Outline the tile under the cursor, in the color of the [`Team`] of
+/// whoever stands on it
+fn outline_hovered(
+    mut commands: Commands,
+    hovered: Res<HoveredTile>,
+    occupancy: Res<Occupancy>,
+    teams: Query<&Team>,
+    tiles: Query<(Entity, &Location, Option<&Highlighted>), With<Tile>>,
+    outlined: Query<(), With<HoverOutline>>,
+) {
+    if !hovered.is_changed() && !occupancy.is_changed() {
+        return;
+    }
+    let team = hovered.0.and_then(|at| {
+        let entities = occupancy.entities_at(at);
+        entities.iter().find_map(|&e| teams.get(e).ok())
+    });
+    let highlight = match team {
+        Some(team) => Highlighted {
+            color: team.color(),
+        },
+        None => Highlighted::default(),
+    };
+
+    for (entity, &location, shown) in tiles.iter() {
+        let hover = hovered.0 == Some(location);
+        match (hover, outlined.get(entity).is_ok()) {
+            (true, true) if shown == Some(&highlight) => {}
+            (true, _) => {
+                commands
+                    .entity(entity)
+                    .insert(HoverOutline)
+                    .insert(highlight);
+            }
+            (false, true) => {
+                commands
+                    .entity(entity)
+                    .remove::<HoverOutline>()
+                    .remove::<Highlighted>();
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Appearance of the selection highlights
+pub struct SelectionStyle {
+    pub material: Handle<StandardMaterial>,
+}
+
+impl FromWorld for SelectionStyle {
+    fn from_world(world: &mut World) -> Self {
+        Self {
+            material: world.resource_mut::<Assets<StandardMaterial>>().add(
+                StandardMaterial {
+                    base_color: Color::rgba(1.0, 0.9, 0.2, 0.35),
+                    alpha_mode: AlphaMode::Blend,
+                    unlit: true,
+                    ..Default::default()
+                },
+            ),
+        }
+    }
+}
+
+/// Marker for the overlay drawn on top of a selected tile
+#[derive(Component)]
+pub struct SelectionHighlight;
+
+/// Rebuild the highlight overlays whenever the selection changes
+fn highlight_system(
+    mut commands: Commands,
+    selection: Res<Selection>,
+    hovered: Res<HoveredTile>,
+    style: Res<SelectionStyle>,
+    mesh: Res<TileMesh>,
+    highlights: Query<Entity, With<SelectionHighlight>>,
+) {
+    let drag_box = selection.drag_box(&hovered);
+    let dragged = drag_box.is_some() && hovered.is_changed();
+    if !(selection.is_changed() || dragged) {
+        return;
+    }
+
+    for entity in highlights.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    // Preview the box selection in progress alongside the current selection
+    let mut tiles = selection.tiles.clone();
+    if let Some((start, end)) = drag_box {
+        for y in start.y.min(end.y)..=start.y.max(end.y) {
+            for x in start.x.min(end.x)..=start.x.max(end.x) {
+                tiles.insert(Location { x, y });
+            }
+        }
+    }
+
+    for location in tiles {
+        // Lift slightly off the ground to avoid z-fighting with the tile
+        let translation = Vec3::from(location) + Vec3::Y * 0.001;
+        commands
+            .spawn_bundle(PbrBundle {
+                mesh: mesh.handle.clone(),
+                material: style.material.clone(),
+                transform: Transform::from_translation(translation),
+                ..Default::default()
+            })
+            .insert(SelectionHighlight)
+            .insert(ViewLayer::Editor.layers());
+    }
+}
@@ -0,0 +1,178 @@
+use crate::ambience::MapAmbience;
+use crate::map::{Axis, Direction, Location, Prop, WallGrid, TILE_SIZE};
+use bevy::prelude::*;
+use bevy::render::mesh::{Indices, PrimitiveTopology};
+use bevy::render::render_resource::{
+    Extent3d, TextureDimension, TextureFormat,
+};
+
+pub struct ShadowPlugin;
+
+impl Plugin for ShadowPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ShadowDecals>().add_system(cast_shadows);
+    }
+}
+
+/// Marker for the translucent decals grounding walls and props
+#[derive(Component)]
+pub struct BlobShadow;
+
+/// How far a wall's shadow reaches across the floor on either side
+const WALL_SHADOW_WIDTH: f32 = TILE_SIZE * 0.5;
+
+/// Width of the blob under a prop
+const PROP_SHADOW_SIZE: f32 = TILE_SIZE * 0.7;
+
+/// Texels across the generated shadow textures
+const SHADOW_TEXTURE_SIZE: u32 = 32;
+
+/// Meshes and gradients shared by every shadow decal
+pub struct ShadowDecals {
+    /// Strip along an edge, lying on the floor
+    strip: Handle<Mesh>,
+    /// Darkest along the middle of the strip, fading out to either side
+    strip_texture: Handle<Image>,
+    blob: Handle<Mesh>,
+    /// Darkest in the middle, fading out to a circle
+    blob_texture: Handle<Image>,
+}
+
+impl FromWorld for ShadowDecals {
+    fn from_world(world: &mut World) -> Self {
+        let mut meshes = world.resource_mut::<Assets<Mesh>>();
+        // Like a wall, the strip runs along z
+        let strip = meshes.add(floor_quad(WALL_SHADOW_WIDTH * 2.0, TILE_SIZE));
+        let blob = meshes.add(floor_quad(PROP_SHADOW_SIZE, PROP_SHADOW_SIZE));
+
+        let mut images = world.resource_mut::<Assets<Image>>();
+        let strip_texture =
+            images.add(gradient(|u, _| 1.0 - (u * 2.0 - 1.0).abs()));
+        let blob_texture = images.add(gradient(|u, v| {
+            let (x, y) = (u * 2.0 - 1.0, v * 2.0 - 1.0);
+            1.0 - (x * x + y * y).sqrt().min(1.0)
+        }));
+
+        Self {
+            strip,
+            strip_texture,
+            blob,
+            blob_texture,
+        }
+    }
+}
+
+/// Quad on the ground plane, `width` along x and `length` along z
+fn floor_quad(width: f32, length: f32) -> Mesh {
+    let (x, z) = (width * 0.5, length * 0.5);
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    mesh.insert_attribute(
+        Mesh::ATTRIBUTE_POSITION,
+        vec![[-x, 0.0, -z], [x, 0.0, -z], [x, 0.0, z], [-x, 0.0, z]],
+    );
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, vec![[0.0, 1.0, 0.0]; 4]);
+    mesh.insert_attribute(
+        Mesh::ATTRIBUTE_UV_0,
+        vec![[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]],
+    );
+    mesh.set_indices(Some(Indices::U16(vec![0, 2, 1, 0, 3, 2])));
+    mesh
+}
+
+/// White texture whose alpha is `falloff` of the texel's uv, smoothed so
+/// the shadow has no hard edge
+fn gradient(falloff: impl Fn(f32, f32) -> f32) -> Image {
+    let size = SHADOW_TEXTURE_SIZE;
+    let mut data = Vec::with_capacity((size * size * 4) as usize);
+    for y in 0..size {
+        for x in 0..size {
+            let u = (x as f32 + 0.5) / size as f32;
+            let v = (y as f32 + 0.5) / size as f32;
+            let t = falloff(u, v).clamp(0.0, 1.0);
+            let alpha = t * t * (3.0 - 2.0 * t);
+            data.extend_from_slice(&[255, 255, 255, (alpha * 255.0) as u8]);
+        }
+    }
+    Image::new(
+        Extent3d {
+            width: size,
+            height: size,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+    )
+}
+
+/// Lay a soft shadow along every wall and under every prop, rebuilt
+/// whenever they move or the ambience settings change
+#[allow(clippy::too_many_arguments)]
+fn cast_shadows(
+    mut commands: Commands,
+    ambience: Res<MapAmbience>,
+    decals: Res<ShadowDecals>,
+    walls: Res<WallGrid>,
+    props: Query<&Location, With<Prop>>,
+    changed: Query<(), (With<Prop>, Changed<Location>)>,
+    removed: RemovedComponents<Prop>,
+    shadows: Query<Entity, With<BlobShadow>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let dirty = ambience.is_changed()
+        || walls.is_changed()
+        || !changed.is_empty()
+        || removed.iter().next().is_some();
+    if !dirty {
+        return;
+    }
+
+    for entity in shadows.iter() {
+        commands.entity(entity).despawn();
+    }
+    if !ambience.shadows || ambience.shadow_darkness <= 0.0 {
+        return;
+    }
+
+    let mut material = |texture: &Handle<Image>| {
+        materials.add(StandardMaterial {
+            base_color: Color::rgba(0.0, 0.0, 0.0, ambience.shadow_darkness),
+            base_color_texture: Some(texture.clone()),
+            alpha_mode: AlphaMode::Blend,
+            unlit: true,
+            ..Default::default()
+        })
+    };
+    let strip = material(&decals.strip_texture);
+    let blob = material(&decals.blob_texture);
+
+    // Just above the edge shading, which sits just above the tiles
+    let lift = Vec3::Y * 0.001;
+    for (&edge, _) in walls.iter() {
+        let direction = match edge.axis {
+            Axis::X => Direction::PositiveX,
+            Axis::Y => Direction::PositiveY,
+        };
+        commands
+            .spawn_bundle(PbrBundle {
+                mesh: decals.strip.clone(),
+                material: strip.clone(),
+                transform: Transform::from_translation(Vec3::from(edge) + lift)
+                    .with_rotation(direction.into()),
+                ..Default::default()
+            })
+            .insert(BlobShadow);
+    }
+    for &location in props.iter() {
+        commands
+            .spawn_bundle(PbrBundle {
+                mesh: decals.blob.clone(),
+                material: blob.clone(),
+                transform: Transform::from_translation(
+                    Vec3::from(location) + lift,
+                ),
+                ..Default::default()
+            })
+            .insert(BlobShadow);
+    }
+}
@@ -0,0 +1,237 @@
+use crate::ai::{spawn_enemy, EnemyKind};
+use crate::editor::GameMode;
+use crate::health::{Corpse, Health};
+use crate::interact::LeverToggled;
+use crate::map::{Location, Occupancy, OccupancySystem};
+use crate::rules::Rules;
+use crate::team::Team;
+use crate::turn::{round_started, Timing, TurnPhase};
+use bevy::prelude::*;
+use iyes_loopless::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub struct SpawnerPlugin;
+
+impl Plugin for SpawnerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<WaveCompleted>()
+            .add_enter_system(GameMode::Playing, Spawner::reset)
+            .add_enter_system(TurnPhase::PlayerInput, Spawner::tick)
+            .add_system(
+                Spawner::tick
+                    .after(OccupancySystem)
+                    .run_in_state(GameMode::Playing)
+                    .run_in_state(Timing::RealTime)
+                    .run_if(round_started),
+            )
+            .add_system(Spawner::trigger.run_in_state(GameMode::Playing))
+            .add_system(Spawner::track.run_in_state(GameMode::Playing));
+    }
+}
+
+/// Sends waves of enemies out from its tile, placed on a prop in the map file.
+///
+/// Each round at most one enemy comes out, and the next wave only starts once
+/// everything from the last is dead.
+#[derive(
+    Component, Debug, Clone, Default, PartialEq, Serialize, Deserialize,
+)]
+#[serde(default)]
+pub struct Spawner {
+    /// Sent out in order, each once
+    pub waves: Vec<Wave>,
+    /// Waits for a lever on this channel to be switched on rather than
+    /// starting with play
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channel: Option<u32>,
+    #[serde(skip)]
+    state: SpawnerState,
+}
+
+/// Group of enemies sent out together
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Wave {
+    pub enemies: Vec<WaveEnemies>,
+    /// Rounds to wait before the wave starts, counted from the end of the
+    /// last one
+    pub delay: u32,
+    /// Rounds between each enemy of the wave coming out
+    pub interval: u32,
+}
+
+impl Default for Wave {
+    fn default() -> Self {
+        Self {
+            enemies: Vec::new(),
+            delay: 0,
+            interval: 1,
+        }
+    }
+}
+
+/// `count` enemies of one kind in a [`Wave`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WaveEnemies {
+    #[serde(default)]
+    pub kind: EnemyKind,
+    #[serde(default = "WaveEnemies::default_count")]
+    pub count: u32,
+}
+
+impl WaveEnemies {
+    fn default_count() -> u32 {
+        1
+    }
+}
+
+/// Where a [`Spawner`] is in its waves
+#[derive(Debug, Clone, Default, PartialEq)]
+struct SpawnerState {
+    /// Whether play or a lever started the spawner
+    active: bool,
+    /// Index of the wave being sent out
+    wave: usize,
+    /// Rounds until the next enemy comes out
+    wait: u32,
+    /// Enemies of the current wave still to come out, the next last
+    pending: Vec<EnemyKind>,
+    /// Enemies of the current wave which are out and still alive
+    alive: Vec<Entity>,
+}
+
+impl Spawner {
+    /// Whether every wave has been sent out and beaten
+    pub fn is_finished(&self) -> bool {
+        self.state.wave >= self.waves.len()
+    }
+
+    /// Queue up the wave at `index`, if there is one
+    fn start_wave(&mut self, index: usize) {
+        self.state.wave = index;
+        self.state.alive.clear();
+        self.state.pending.clear();
+        if let Some(wave) = self.waves.get(index) {
+            for group in wave.enemies.iter().rev() {
+                self.state
+                    .pending
+                    .extend((0..group.count).map(|_| group.kind));
+            }
+            self.state.wait = wave.delay;
+        }
+    }
+
+    /// Start over from the first wave whenever play starts
+    fn reset(mut spawners: Query<&mut Spawner>) {
+        for mut spawner in spawners.iter_mut() {
+            spawner.state = SpawnerState {
+                active: spawner.channel.is_none(),
+                ..Default::default()
+            };
+            spawner.start_wave(0);
+        }
+    }
+
+    /// Start spawners waiting on a lever which was switched on
+    fn trigger(
+        mut toggled: EventReader<LeverToggled>,
+        mut spawners: Query<&mut Spawner>,
+    ) {
+        for lever in toggled.iter().filter(|lever| lever.on) {
+            for mut spawner in spawners.iter_mut() {
+                if spawner.channel == Some(lever.channel)
+                    && !spawner.state.active
+                {
+                    spawner.state.active = true;
+                }
+            }
+        }
+    }
+
+    /// Send out the next enemy of each running wave once it is due, holding
+    /// it back while something stands on the spawner
+    fn tick(
+        mut commands: Commands,
+        mut meshes: ResMut<Assets<Mesh>>,
+        mut materials: ResMut<Assets<StandardMaterial>>,
+        rules: Res<Rules>,
+        occupancy: Res<Occupancy>,
+        mut spawners: Query<(&mut Spawner, &Location)>,
+        units: Query<(), With<Health>>,
+    ) {
+        for (mut spawner, &location) in spawners.iter_mut() {
+            if !spawner.state.active || spawner.state.pending.is_empty() {
+                continue;
+            }
+            if spawner.state.wait > 0 {
+                spawner.state.wait -= 1;
+                continue;
+            }
+            let entities = occupancy.entities_at(location);
+            if entities.iter().any(|&e| units.get(e).is_ok()) {
+                continue;
+            }
+
+            let kind = match spawner.state.pending.pop() {
+                Some(kind) => kind,
+                None => continue,
+            };
+            let enemy = spawn_enemy(
+                &mut commands,
+                &mut meshes,
+                &mut materials,
+                &rules,
+                location,
+                kind,
+                Team::Enemy,
+            );
+            spawner.state.alive.push(enemy);
+            let interval = spawner
+                .waves
+                .get(spawner.state.wave)
+                .map_or(1, |wave| wave.interval);
+            spawner.state.wait = interval.saturating_sub(1);
+        }
+    }
+
+    /// Forget enemies which died, and move on to the next wave once the
+    /// whole of the current one is dead
+    fn track(
+        mut completed: EventWriter<WaveCompleted>,
+        mut spawners: Query<(Entity, &mut Spawner)>,
+        living: Query<(), (With<Health>, Without<Corpse>)>,
+    ) {
+        for (entity, mut spawner) in spawners.iter_mut() {
+            let state = &spawner.state;
+            let dead = state.alive.iter().any(|&e| living.get(e).is_err());
+            let cleared = state.active
+                && !spawner.is_finished()
+                && state.pending.is_empty()
+                && state.alive.iter().all(|&e| living.get(e).is_err());
+            if !dead && !cleared {
+                continue;
+            }
+
+            spawner.state.alive.retain(|&e| living.get(e).is_ok());
+            if cleared {
+                let wave = spawner.state.wave;
+                spawner.start_wave(wave + 1);
+                completed.send(WaveCompleted {
+                    spawner: entity,
+                    wave,
+                    last: spawner.is_finished(),
+                });
+            }
+        }
+    }
+}
+
+/// Sent when every enemy of a [`Spawner`]'s wave is dead
+#[derive(Debug, Clone, Copy)]
+pub struct WaveCompleted {
+    pub spawner: Entity,
+    /// Index of the wave in the spawner's waves
+    pub wave: usize,
+    /// Whether it was the spawner's final wave
+    pub last: bool,
+}
@@ -0,0 +1,396 @@
+use crate::console::{Console, ConsoleCommand};
+use crate::health::{DamageSystem, Died, Health};
+use crate::item::{Inventory, ItemCatalog, Items};
+use crate::map::FlashTint;
+use crate::player::Player;
+use crate::turn::TurnStarted;
+use bevy::prelude::*;
+use bevy_inspector_egui::{Inspectable, RegisterInspectable};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::ops::{Add, AddAssign, Mul};
+
+pub struct StatsPlugin;
+
+impl Plugin for StatsPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_inspectable::<LevelCurve>()
+            .init_resource::<LevelCurve>()
+            .add_event::<ExperienceGained>()
+            .add_event::<LeveledUp>()
+            .add_system(StatusEffects::tick)
+            .add_system(experience_on_kill.after(DamageSystem))
+            .add_system(xp_command)
+            .add_system(
+                Experience::system
+                    .after(experience_on_kill)
+                    .after(xp_command),
+            )
+            .add_system(Stats::system.after(Experience::system))
+            .add_system(announce_level_ups.after(Experience::system));
+    }
+}
+
+/// Tint a unit flashes as it goes up a level
+const LEVEL_UP_FLASH: Color = Color::rgba(1.0, 0.85, 0.3, 0.8);
+
+/// Numbers which make a unit better or worse in a fight, stacked up from its
+/// base, its level, what it carries, and what it is affected by
+#[derive(
+    Inspectable,
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    Serialize,
+    Deserialize,
+)]
+#[serde(default)]
+pub struct StatBlock {
+    /// Added to the damage of every hit dealt
+    pub attack: i32,
+    /// Taken off the damage of every hit taken, which always does at least 1
+    pub defense: i32,
+    /// Added to the initiative of an actor, acting earlier in its phase
+    pub speed: i32,
+}
+
+impl Add for StatBlock {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            attack: self.attack + other.attack,
+            defense: self.defense + other.defense,
+            speed: self.speed + other.speed,
+        }
+    }
+}
+
+impl AddAssign for StatBlock {
+    fn add_assign(&mut self, other: Self) {
+        *self = *self + other;
+    }
+}
+
+impl Mul<i32> for StatBlock {
+    type Output = Self;
+
+    fn mul(self, times: i32) -> Self {
+        Self {
+            attack: self.attack * times,
+            defense: self.defense * times,
+            speed: self.speed * times,
+        }
+    }
+}
+
+impl StatBlock {
+    /// Each stat picked uniformly from `-self..=self`, for jittering the
+    /// stats of a unit
+    pub fn jitter(&self, rng: &mut impl Rng) -> Self {
+        let mut roll = |range: i32| {
+            let range = range.abs();
+            rng.gen_range(-range..=range)
+        };
+        Self {
+            attack: roll(self.attack),
+            defense: roll(self.defense),
+            speed: roll(self.speed),
+        }
+    }
+}
+
+/// Added to the base stats of the unit spawned from a map marker, rolled
+/// from the marker's randomization when the map is spawned
+#[derive(Component, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StatJitter(pub StatBlock);
+
+/// A unit's [`StatBlock`], with every modifier on top of its `base`
+#[derive(Component, Debug, Clone, Copy, Default, PartialEq)]
+pub struct Stats {
+    pub base: StatBlock,
+    /// Base plus every modifier, kept up to date by [`Stats::system`]
+    total: StatBlock,
+}
+
+impl Stats {
+    pub fn new(base: StatBlock) -> Self {
+        Self { base, total: base }
+    }
+
+    pub fn total(&self) -> StatBlock {
+        self.total
+    }
+
+    /// Add up the stats of every unit from its base, level, the items it
+    /// carries, and its status effects
+    fn system(
+        curve: Res<LevelCurve>,
+        items: Res<Items>,
+        catalogs: Res<Assets<ItemCatalog>>,
+        mut units: Query<(
+            &mut Stats,
+            Option<&Experience>,
+            Option<&Inventory>,
+            Option<&StatusEffects>,
+        )>,
+    ) {
+        let catalog = catalogs.get(&items.catalog);
+        for (mut stats, experience, inventory, effects) in units.iter_mut() {
+            let mut total = stats.base;
+            if let Some(experience) = experience {
+                total +=
+                    curve.per_level * experience.level.saturating_sub(1) as i32;
+            }
+            // Each item counts once however many are carried
+            let mut carried: Vec<&str> = inventory
+                .iter()
+                .flat_map(|inventory| inventory.slots.iter())
+                .map(|stack| stack.item.as_str())
+                .collect();
+            carried.sort_unstable();
+            carried.dedup();
+            for item in carried {
+                if let Some(definition) =
+                    catalog.and_then(|catalog| catalog.items.get(item))
+                {
+                    total += definition.stats;
+                }
+            }
+            for effect in effects.iter().flat_map(|effects| effects.0.iter()) {
+                total += effect.stats;
+            }
+
+            if stats.total != total {
+                stats.total = total;
+            }
+        }
+    }
+}
+
+/// Damage a hit of `amount` does once the attacker's attack and the target's
+/// defense are taken into account, never less than 1
+pub fn scale_damage(amount: u32, attack: i32, defense: i32) -> u32 {
+    if amount == 0 {
+        return 0;
+    }
+    let scaled = amount as i64 + attack as i64 - defense as i64;
+    scaled.clamp(1, u32::MAX as i64) as u32
+}
+
+/// Changes a unit's [`Stats`] for a number of turns, or for good
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatusEffect {
+    /// Effects with the same name replace each other rather than stack
+    pub name: String,
+    pub stats: StatBlock,
+    /// Turns of the unit's left before the effect wears off, `None` to last
+    pub turns: Option<u32>,
+}
+
+/// Every [`StatusEffect`] on a unit
+#[derive(Component, Debug, Clone, Default)]
+pub struct StatusEffects(pub Vec<StatusEffect>);
+
+impl StatusEffects {
+    /// Put `effect` on, replacing any effect of the same name
+    pub fn apply(&mut self, effect: StatusEffect) {
+        self.0.retain(|other| other.name != effect.name);
+        self.0.push(effect);
+    }
+
+    /// Count down the effects of each unit whose turn starts, taking off any
+    /// which wore off
+    fn tick(
+        mut started: EventReader<TurnStarted>,
+        mut units: Query<&mut StatusEffects>,
+    ) {
+        for &TurnStarted { entity, .. } in started.iter() {
+            let mut effects = match units.get_mut(entity) {
+                Ok(effects) => effects,
+                Err(_) => continue,
+            };
+            if effects.0.iter().all(|effect| effect.turns.is_none()) {
+                continue;
+            }
+            for effect in effects.0.iter_mut() {
+                if let Some(turns) = &mut effect.turns {
+                    *turns = turns.saturating_sub(1);
+                }
+            }
+            effects.0.retain(|effect| effect.turns != Some(0));
+        }
+    }
+}
+
+/// How much experience each level takes, and what it is worth
+#[derive(Inspectable, Debug, Clone, PartialEq)]
+pub struct LevelCurve {
+    /// Experience from the first level to the second
+    pub base: u32,
+    /// How steeply each level costs more than the last, 1 for the same
+    /// amount every level and 2 for quadratic growth
+    pub exponent: f32,
+    /// Highest level a unit can reach
+    pub max_level: u32,
+    /// Added to the stats of a unit for every level past the first
+    pub per_level: StatBlock,
+}
+
+impl Default for LevelCurve {
+    fn default() -> Self {
+        Self {
+            base: 10,
+            exponent: 1.5,
+            max_level: 20,
+            per_level: StatBlock {
+                attack: 1,
+                defense: 1,
+                speed: 0,
+            },
+        }
+    }
+}
+
+impl LevelCurve {
+    /// Experience taken to get from `level` to the next
+    pub fn to_next(&self, level: u32) -> u32 {
+        let cost = self.base as f32 * (level.max(1) as f32).powf(self.exponent);
+        (cost.round() as u32).max(1)
+    }
+}
+
+/// Experience a unit has earned towards its next level
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Experience {
+    pub level: u32,
+    /// Earned since reaching the current level
+    pub xp: u32,
+}
+
+impl Default for Experience {
+    fn default() -> Self {
+        Self { level: 1, xp: 0 }
+    }
+}
+
+impl Experience {
+    /// Put gained experience towards the next level, going up as many levels
+    /// as it pays for
+    fn system(
+        curve: Res<LevelCurve>,
+        mut gained: EventReader<ExperienceGained>,
+        mut leveled: EventWriter<LeveledUp>,
+        mut units: Query<&mut Experience>,
+    ) {
+        for gain in gained.iter() {
+            let mut experience = match units.get_mut(gain.entity) {
+                Ok(experience) => experience,
+                Err(_) => continue,
+            };
+            if experience.level >= curve.max_level {
+                continue;
+            }
+            experience.xp += gain.amount;
+            while experience.level < curve.max_level
+                && experience.xp >= curve.to_next(experience.level)
+            {
+                experience.xp -= curve.to_next(experience.level);
+                experience.level += 1;
+                leveled.send(LeveledUp {
+                    entity: gain.entity,
+                    level: experience.level,
+                });
+            }
+            if experience.level >= curve.max_level {
+                experience.xp = 0;
+            }
+        }
+    }
+}
+
+/// Send to give a unit with [`Experience`] some more
+#[derive(Debug, Clone, Copy)]
+pub struct ExperienceGained {
+    pub entity: Entity,
+    pub amount: u32,
+}
+
+/// Sent for every level a unit goes up, for the UI and effects
+#[derive(Debug, Clone, Copy)]
+pub struct LeveledUp {
+    pub entity: Entity,
+    /// Level reached
+    pub level: u32,
+}
+
+/// Flash whoever went up a level, and tell the player about their own
+fn announce_level_ups(
+    mut commands: Commands,
+    mut console: ResMut<Console>,
+    mut leveled: EventReader<LeveledUp>,
+    players: Query<(), With<Player>>,
+) {
+    for &LeveledUp { entity, level } in leveled.iter() {
+        commands
+            .entity(entity)
+            .insert(FlashTint::new(LEVEL_UP_FLASH, 0.6));
+        if players.get(entity).is_ok() {
+            console.print(format!("Reached level {}", level));
+        }
+    }
+}
+
+/// Killing something is worth experience equal to the hit points it had
+fn experience_on_kill(
+    mut died: EventReader<Died>,
+    mut gained: EventWriter<ExperienceGained>,
+    health: Query<&Health>,
+    learners: Query<(), With<Experience>>,
+) {
+    for &Died { entity, killer } in died.iter() {
+        let killer = match killer {
+            Some(killer) if killer != entity => killer,
+            _ => continue,
+        };
+        if let (Ok(health), Ok(())) = (health.get(entity), learners.get(killer))
+        {
+            gained.send(ExperienceGained {
+                entity: killer,
+                amount: health.max,
+            });
+        }
+    }
+}
+
+/// `xp <amount>` gives the player experience
+fn xp_command(
+    mut events: EventReader<ConsoleCommand>,
+    mut console: ResMut<Console>,
+    mut gained: EventWriter<ExperienceGained>,
+    players: Query<Entity, (With<Player>, With<Experience>)>,
+) {
+    for event in events.iter().filter(|event| event.name == "xp") {
+        let amount = match event.args.as_slice() {
+            [amount] => amount.parse::<u32>(),
+            _ => {
+                console.print("usage: xp <amount>");
+                continue;
+            }
+        };
+        let amount = match amount {
+            Ok(amount) => amount,
+            Err(err) => {
+                console.print(format!("{}", err));
+                continue;
+            }
+        };
+        match players.get_single() {
+            Ok(entity) => gained.send(ExperienceGained { entity, amount }),
+            Err(_) => console.print("No player to give experience to"),
+        }
+    }
+}
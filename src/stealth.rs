@@ -0,0 +1,237 @@
+use crate::fog::{FogOfWar, TileVisibility};
+use crate::fov::{FieldOfView, VisionCone};
+use crate::health::{Corpse, Damage, Health};
+use crate::map::{Location, Occupancy, TileMesh};
+use crate::material::ViewLayer;
+use crate::team::{Hostility, Team};
+use crate::turn::TurnStarted;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+pub struct StealthPlugin;
+
+impl Plugin for StealthPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ConeStyle>()
+            .add_event::<AlertChanged>()
+            .add_system(Alert::system.label(AlertSystem))
+            .add_system(alert_on_hit.label(AlertSystem))
+            .add_system(draw_cones.after(AlertSystem));
+    }
+}
+
+/// Systems which raise and lower [`Alert`] levels, the AI acts after them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, SystemLabel)]
+pub struct AlertSystem;
+
+/// How sure an enemy is that the player is around
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum AlertLevel {
+    /// Going about its patrol
+    Unaware,
+    /// Caught a glimpse, and turns to look
+    Suspicious,
+    /// Hunting down what it saw
+    Alerted,
+}
+
+/// Noticing a unit on a hostile [`Team`] in the [`FieldOfView`], sharpened a
+/// level each turn one stays in sight and dulled once they are lost for long
+/// enough
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Alert {
+    pub level: AlertLevel,
+    /// Turns an alerted enemy keeps hunting after losing sight of its target
+    pub memory: u32,
+    /// Turns since anything hostile was last seen
+    unseen: u32,
+}
+
+impl Default for Alert {
+    fn default() -> Self {
+        Self {
+            level: AlertLevel::Unaware,
+            memory: 3,
+            unseen: 0,
+        }
+    }
+}
+
+impl Alert {
+    /// Whether the enemy acts on having seen the player
+    pub fn is_alerted(&self) -> bool {
+        self.level == AlertLevel::Alerted
+    }
+
+    /// Look for anything hostile at the start of each of the enemy's turns
+    fn system(
+        occupancy: Res<Occupancy>,
+        hostility: Res<Hostility>,
+        mut started: EventReader<TurnStarted>,
+        mut changed: EventWriter<AlertChanged>,
+        units: Query<(Entity, &Team), (With<Health>, Without<Corpse>)>,
+        mut watchers: Query<(&mut Alert, &FieldOfView, &Team)>,
+    ) {
+        for &TurnStarted { entity, .. } in started.iter() {
+            let (mut alert, fov, &team) = match watchers.get_mut(entity) {
+                Ok(watcher) => watcher,
+                Err(_) => continue,
+            };
+            let seen = units
+                .iter()
+                .filter(|&(_, &other)| hostility.is_hostile(team, other))
+                .filter_map(|(other, _)| occupancy.location_of(other))
+                .any(|at| fov.can_see(at));
+            if seen {
+                alert.unseen = 0;
+            } else {
+                alert.unseen += 1;
+            }
+
+            let level = match (alert.level, seen) {
+                (AlertLevel::Unaware, true) => AlertLevel::Suspicious,
+                (_, true) => AlertLevel::Alerted,
+                (AlertLevel::Alerted, false)
+                    if alert.unseen <= alert.memory =>
+                {
+                    AlertLevel::Alerted
+                }
+                (AlertLevel::Alerted, false) => AlertLevel::Suspicious,
+                (_, false) => AlertLevel::Unaware,
+            };
+            if level != alert.level {
+                alert.level = level;
+                changed.send(AlertChanged { entity, level });
+            }
+        }
+    }
+}
+
+/// Sent when an [`Alert`] is raised or lowered
+#[derive(Debug, Clone, Copy)]
+pub struct AlertChanged {
+    pub entity: Entity,
+    pub level: AlertLevel,
+}
+
+/// Being hurt by anything hostile gives it away, however it snuck up
+fn alert_on_hit(
+    hostility: Res<Hostility>,
+    mut damage: EventReader<Damage>,
+    mut changed: EventWriter<AlertChanged>,
+    teams: Query<&Team>,
+    mut alerts: Query<&mut Alert>,
+) {
+    for hit in damage.iter() {
+        let teams = hit
+            .source
+            .and_then(|source| teams.get(source).ok())
+            .zip(teams.get(hit.target).ok());
+        if !matches!(teams, Some((&a, &b)) if hostility.is_hostile(a, b)) {
+            continue;
+        }
+        if let Ok(mut alert) = alerts.get_mut(hit.target) {
+            alert.unseen = 0;
+            if !alert.is_alerted() {
+                alert.level = AlertLevel::Alerted;
+                changed.send(AlertChanged {
+                    entity: hit.target,
+                    level: AlertLevel::Alerted,
+                });
+            }
+        }
+    }
+}
+
+/// Materials the vision cones are drawn with, one for each [`AlertLevel`]
+pub struct ConeStyle {
+    pub unaware: Handle<StandardMaterial>,
+    pub suspicious: Handle<StandardMaterial>,
+    pub alerted: Handle<StandardMaterial>,
+}
+
+impl ConeStyle {
+    fn material(&self, level: AlertLevel) -> Handle<StandardMaterial> {
+        match level {
+            AlertLevel::Unaware => self.unaware.clone(),
+            AlertLevel::Suspicious => self.suspicious.clone(),
+            AlertLevel::Alerted => self.alerted.clone(),
+        }
+    }
+}
+
+impl FromWorld for ConeStyle {
+    fn from_world(world: &mut World) -> Self {
+        let mut materials = world.resource_mut::<Assets<StandardMaterial>>();
+        let mut add = |color: Color| {
+            materials.add(StandardMaterial {
+                base_color: color,
+                alpha_mode: AlphaMode::Blend,
+                unlit: true,
+                ..Default::default()
+            })
+        };
+        Self {
+            unaware: add(Color::rgba(1.0, 1.0, 0.6, 0.15)),
+            suspicious: add(Color::rgba(1.0, 0.7, 0.2, 0.25)),
+            alerted: add(Color::rgba(1.0, 0.2, 0.2, 0.3)),
+        }
+    }
+}
+
+/// Marker for the overlay drawn on a tile inside a vision cone
+#[derive(Component)]
+pub struct ConeOverlay;
+
+/// Shade the tiles the enemies' vision cones cover, where the player can see
+/// them, in the color of the most alert enemy looking at each
+#[allow(clippy::too_many_arguments)]
+fn draw_cones(
+    mut commands: Commands,
+    fog: Res<FogOfWar>,
+    style: Res<ConeStyle>,
+    mesh: Res<TileMesh>,
+    cones: Query<(&FieldOfView, Option<&Alert>), With<VisionCone>>,
+    changed: Query<
+        (),
+        (With<VisionCone>, Or<(Changed<FieldOfView>, Changed<Alert>)>),
+    >,
+    removed: RemovedComponents<FieldOfView>,
+    overlays: Query<Entity, With<ConeOverlay>>,
+) {
+    let stale = fog.is_changed()
+        || !changed.is_empty()
+        || removed.iter().next().is_some();
+    if !stale {
+        return;
+    }
+    for entity in overlays.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    let mut covered: HashMap<Location, AlertLevel> = HashMap::default();
+    for (fov, alert) in cones.iter() {
+        let level = alert.map_or(AlertLevel::Unaware, |alert| alert.level);
+        for &location in fov.visible() {
+            if fog.get(location) != TileVisibility::Visible {
+                continue;
+            }
+            let shown = covered.entry(location).or_insert(level);
+            *shown = (*shown).max(level);
+        }
+    }
+
+    for (location, level) in covered {
+        // Lift slightly off the ground to avoid z-fighting with the tile
+        let translation = Vec3::from(location) + Vec3::Y * 0.001;
+        commands
+            .spawn_bundle(PbrBundle {
+                mesh: mesh.handle.clone(),
+                material: style.material(level),
+                transform: Transform::from_translation(translation),
+                ..Default::default()
+            })
+            .insert(ConeOverlay)
+            .insert(ViewLayer::Gameplay.layers());
+    }
+}
@@ -0,0 +1,100 @@
+use crate::turn::TurnPhase;
+use bevy::prelude::*;
+use bevy_inspector_egui::Inspectable;
+use serde::{Deserialize, Serialize};
+
+pub struct TeamPlugin;
+
+impl Plugin for TeamPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Hostility>();
+    }
+}
+
+/// Side a unit fights on, which decides who it attacks and when it takes its
+/// turn
+#[derive(
+    Component,
+    Inspectable,
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    Hash,
+    Serialize,
+    Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Team {
+    Player,
+    #[default]
+    Enemy,
+    /// Fights the enemies as well as the player, for three-way fights
+    Rival,
+    /// Keeps to itself unless a map says otherwise
+    Neutral,
+}
+
+impl Team {
+    pub fn is_enemy(&self) -> bool {
+        *self == Team::Enemy
+    }
+
+    /// Only the player's team acts while waiting on input, everyone else
+    /// acts on the enemies' turns
+    pub fn acts_in(self, phase: TurnPhase) -> bool {
+        match phase {
+            TurnPhase::PlayerInput => self == Team::Player,
+            TurnPhase::EnemyTurns => self != Team::Player,
+            TurnPhase::Resolution => false,
+        }
+    }
+
+    /// Shown on whatever belongs to the team, like the hover outline
+    pub fn color(self) -> Color {
+        match self {
+            Team::Player => Color::rgb(0.3, 0.7, 1.0),
+            Team::Enemy => Color::rgb(1.0, 0.25, 0.2),
+            Team::Rival => Color::rgb(0.75, 0.3, 1.0),
+            Team::Neutral => Color::rgb(0.8, 0.8, 0.8),
+        }
+    }
+}
+
+/// Which teams attack each other on sight, configured by the `hostility`
+/// section of a map.
+///
+/// Hostility goes both ways, and a team is never hostile to itself.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Hostility {
+    pub hostile: Vec<[Team; 2]>,
+}
+
+impl Default for Hostility {
+    fn default() -> Self {
+        Self {
+            hostile: vec![
+                [Team::Player, Team::Enemy],
+                [Team::Player, Team::Rival],
+                [Team::Enemy, Team::Rival],
+            ],
+        }
+    }
+}
+
+impl Hostility {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub fn is_hostile(&self, a: Team, b: Team) -> bool {
+        a != b
+            && self
+                .hostile
+                .iter()
+                .any(|&pair| pair == [a, b] || pair == [b, a])
+    }
+}
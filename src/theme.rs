@@ -0,0 +1,227 @@
+use crate::ambience::{MapAmbience, MapEmissive};
+use crate::map::{Edge, MapSprites, SpriteIndex};
+use crate::material::SpriteSheet;
+use bevy::asset::{AssetLoader, LoadContext, LoadedAsset};
+use bevy::prelude::*;
+use bevy::reflect::TypeUuid;
+use bevy::utils::{BoxedFuture, HashMap};
+use bevy_inspector_egui::{Inspectable, RegisterInspectable};
+use iyes_loopless::prelude::*;
+use serde::Deserialize;
+
+pub struct ThemePlugin;
+
+impl Plugin for ThemePlugin {
+    fn build(&self, app: &mut App) {
+        app.register_inspectable::<SpriteKind>()
+            .add_asset::<Theme>()
+            .init_asset_loader::<ThemeLoader>()
+            .init_resource::<ActiveTheme>()
+            .add_system_set(
+                ConditionSet::new()
+                    .with_system(cycle_theme)
+                    .with_system(apply_theme)
+                    .into(),
+            );
+    }
+}
+
+/// Logical name of the sprite drawn on a tile or wall, like `"floor"`.
+///
+/// The active [`Theme`] decides which [`SpriteIndex`] each kind is drawn
+/// with, so the same map can be reskinned without touching its tiles.
+#[derive(
+    Component, Inspectable, Clone, Debug, PartialEq, Eq, Hash, Default,
+)]
+pub struct SpriteKind(pub String);
+
+impl SpriteKind {
+    pub fn new(kind: impl Into<String>) -> Self {
+        Self(kind.into())
+    }
+}
+
+/// Skin for the map which maps sprite kinds to indices in its sprite sheets
+#[derive(Debug, Clone, Deserialize, TypeUuid)]
+#[uuid = "4b1e1cf6-5d0c-4b8e-9a39-4a4de2f4d6a1"]
+pub struct Theme {
+    pub name: String,
+    pub tiles: ThemeSheet,
+    pub walls: ThemeSheet,
+    /// Sprite kinds in the tile sheet which can be placed as props
+    #[serde(default)]
+    pub props: Vec<String>,
+}
+
+/// A sprite sheet and the names of the sprites in it
+#[derive(Debug, Clone, Deserialize)]
+pub struct ThemeSheet {
+    /// Asset path of the sheet texture
+    pub texture: String,
+    /// Asset path of a normal map laid out like the texture, used by lit
+    /// sprites
+    #[serde(default)]
+    pub normals: Option<String>,
+    /// Asset path of a texture laid out like the texture whose bright pixels
+    /// glow through tint, fog, and darkness, unless the map has its own in
+    /// its [`MapEmissive`]
+    #[serde(default)]
+    pub emissive: Option<String>,
+    pub columns: u32,
+    pub rows: u32,
+    /// Texels trimmed from every side of each sprite, half a texel stops
+    /// neighbouring sprites from bleeding in at tile borders
+    #[serde(default)]
+    pub inset: f32,
+    pub sprites: HashMap<String, u32>,
+}
+
+impl ThemeSheet {
+    /// The sheet, glowing with `emissive` in place of the theme's own
+    pub fn sheet(
+        &self,
+        asset_server: &AssetServer,
+        emissive: Option<&str>,
+    ) -> SpriteSheet {
+        let sheet = SpriteSheet::new(
+            asset_server.load(self.texture.as_str()),
+            self.columns,
+            self.rows,
+        )
+        .with_inset(self.inset);
+        let sheet = match &self.normals {
+            Some(normals) => {
+                sheet.with_normals(asset_server.load(normals.as_str()))
+            }
+            None => sheet,
+        };
+        match emissive.or(self.emissive.as_deref()) {
+            Some(emissive) => sheet.with_emissive(asset_server.load(emissive)),
+            None => sheet,
+        }
+    }
+}
+
+impl Theme {
+    /// Sprite used for a kind on a tile or a wall
+    pub fn index(&self, kind: &SpriteKind, is_wall: bool) -> Option<u32> {
+        let sheet = if is_wall { &self.walls } else { &self.tiles };
+        sheet.sprites.get(&kind.0).copied()
+    }
+}
+
+/// Load themes from `.theme.yaml` files
+#[derive(Default)]
+pub struct ThemeLoader;
+
+impl AssetLoader for ThemeLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let theme: Theme = serde_yaml::from_slice(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(theme));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["theme.yaml"]
+    }
+}
+
+/// The theme the map is currently drawn with, and every theme available
+pub struct ActiveTheme {
+    pub handle: Handle<Theme>,
+    pub available: Vec<Handle<Theme>>,
+}
+
+impl FromWorld for ActiveTheme {
+    fn from_world(world: &mut World) -> Self {
+        let asset_server = world.resource::<AssetServer>();
+        let available = asset_server
+            .load_folder("themes")
+            .map(|handles| {
+                handles.into_iter().map(|handle| handle.typed()).collect()
+            })
+            .unwrap_or_else(|err| {
+                warn!("Unable to load themes: {:?}", err);
+                Vec::new()
+            });
+        Self {
+            handle: asset_server.load("themes/dungeon.theme.yaml"),
+            available,
+        }
+    }
+}
+
+/// Key which switches to the next available theme
+const CYCLE_THEME: KeyCode = KeyCode::F6;
+
+fn cycle_theme(keys: Res<Input<KeyCode>>, mut active: ResMut<ActiveTheme>) {
+    if !keys.just_pressed(CYCLE_THEME) || active.available.is_empty() {
+        return;
+    }
+
+    let next = active
+        .available
+        .iter()
+        .position(|handle| *handle == active.handle)
+        .map_or(0, |i| (i + 1) % active.available.len());
+    active.handle = active.available[next].clone();
+}
+
+/// Swap the map's sprite sheets and resolve sprite kinds whenever the theme
+/// is switched or edited, the map's glow changes, or new kinds are placed
+#[allow(clippy::too_many_arguments)]
+fn apply_theme(
+    mut commands: Commands,
+    active: Res<ActiveTheme>,
+    mut events: EventReader<AssetEvent<Theme>>,
+    themes: Res<Assets<Theme>>,
+    asset_server: Res<AssetServer>,
+    ambience: Res<MapAmbience>,
+    mut applied: Local<MapEmissive>,
+    mut sprites: ResMut<MapSprites>,
+    all: Query<(Entity, &SpriteKind, Option<&Edge>)>,
+    changed: Query<(Entity, &SpriteKind, Option<&Edge>), Changed<SpriteKind>>,
+) {
+    let reloaded = events.iter().any(|event| match event {
+        AssetEvent::Created { handle } | AssetEvent::Modified { handle } => {
+            *handle == active.handle
+        }
+        AssetEvent::Removed { .. } => false,
+    });
+
+    let theme = match themes.get(&active.handle) {
+        Some(theme) => theme,
+        None => return,
+    };
+
+    let reglow = *applied != ambience.emissive;
+    let retheme = reloaded || active.is_changed() || reglow;
+    if retheme {
+        info!("Applying theme {:?}", theme.name);
+        *applied = ambience.emissive.clone();
+        let (tiles, walls) = (&applied.tiles, &applied.walls);
+        sprites.tiles = theme.tiles.sheet(&asset_server, tiles.as_deref());
+        sprites.walls = theme.walls.sheet(&asset_server, walls.as_deref());
+    }
+
+    // Every sprite is redrawn from the new sheets
+    let entities: Vec<_> = if retheme {
+        all.iter().collect()
+    } else {
+        changed.iter().collect()
+    };
+    for (entity, kind, edge) in entities {
+        match theme.index(kind, edge.is_some()) {
+            Some(index) => {
+                commands.entity(entity).insert(SpriteIndex(index));
+            }
+            None => warn!("Theme {:?} has no sprite {:?}", theme.name, kind.0),
+        }
+    }
+}
@@ -0,0 +1,269 @@
+use crate::dialogue::Conversation;
+use crate::editor::GameMode;
+use crate::map::MoveTween;
+use crate::projectile::Projectile;
+use crate::stats::Stats;
+use crate::team::Team;
+use bevy::prelude::*;
+use bevy::utils::HashSet;
+use iyes_loopless::prelude::*;
+use std::collections::VecDeque;
+
+mod realtime;
+
+pub use realtime::{round_started, Timing};
+
+pub struct TurnPlugin;
+
+impl Plugin for TurnPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TurnQueue>()
+            .add_event::<EndTurn>()
+            .add_event::<TurnStarted>()
+            .add_event::<TurnEnded>()
+            .add_loopless_state(TurnPhase::PlayerInput)
+            .add_plugin(realtime::RealTimePlugin)
+            .add_enter_system(GameMode::Playing, TurnQueue::reset)
+            .add_enter_system(TurnPhase::PlayerInput, TurnQueue::next_round)
+            .add_enter_system(TurnPhase::EnemyTurns, TurnQueue::clear)
+            .add_system(ActionPoints::refill)
+            .add_system(
+                TurnQueue::system
+                    .run_in_state(GameMode::Playing)
+                    .run_in_state(Timing::TurnBased)
+                    .run_not_in_state(TurnPhase::Resolution),
+            )
+            .add_system(
+                resolve
+                    .run_in_state(GameMode::Playing)
+                    .run_in_state(Timing::TurnBased)
+                    .run_in_state(TurnPhase::Resolution),
+            );
+    }
+}
+
+/// Step of a round, systems which only make sense during one of them should
+/// `run_in_state` it, or use [`player_acts`] and [`enemies_act`] to also run
+/// in real time.
+///
+/// Each round the players act, everything they did plays out, and then the
+/// enemies act in turn. In real time the phase stays on `PlayerInput`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TurnPhase {
+    /// Waiting on the player to act
+    PlayerInput,
+    /// Letting moves and effects finish before anything else acts
+    Resolution,
+    /// Enemies act one at a time
+    EnemyTurns,
+}
+
+/// Run condition for the player's actions, during the player's phase of a
+/// round or at any time in real time
+pub fn player_acts(
+    timing: Res<CurrentState<Timing>>,
+    phase: Res<CurrentState<TurnPhase>>,
+) -> bool {
+    timing.0 == Timing::RealTime || phase.0 == TurnPhase::PlayerInput
+}
+
+/// Run condition for the enemies' actions, during the enemies' phase of a
+/// round or at any time in real time
+pub fn enemies_act(
+    timing: Res<CurrentState<Timing>>,
+    phase: Res<CurrentState<TurnPhase>>,
+) -> bool {
+    timing.0 == Timing::RealTime || phase.0 == TurnPhase::EnemyTurns
+}
+
+/// Something which takes turns, in the phase its [`Team`] acts in
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct Actor {
+    /// Actors with higher initiative act earlier in their phase, along with
+    /// the speed from their [`Stats`]
+    pub initiative: i32,
+}
+
+/// Budget an [`Actor`] spends on moving during its turn, topped back up to
+/// `max` whenever its turn starts
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ActionPoints {
+    pub max: u32,
+    pub current: u32,
+}
+
+impl ActionPoints {
+    pub fn new(max: u32) -> Self {
+        Self { max, current: max }
+    }
+
+    /// Take `cost` out of the points left, if there are enough
+    pub fn spend(&mut self, cost: u32) -> bool {
+        if cost > self.current {
+            return false;
+        }
+        self.current -= cost;
+        true
+    }
+
+    pub fn is_spent(&self) -> bool {
+        self.current == 0
+    }
+
+    fn refill(
+        mut started: EventReader<TurnStarted>,
+        mut query: Query<&mut ActionPoints>,
+    ) {
+        for &TurnStarted { entity, .. } in started.iter() {
+            if let Ok(mut points) = query.get_mut(entity) {
+                if points.current != points.max {
+                    points.current = points.max;
+                }
+            }
+        }
+    }
+}
+
+/// Send to end the turn of an actor, once it has acted
+#[derive(Debug, Clone, Copy)]
+pub struct EndTurn(pub Entity);
+
+#[derive(Debug, Clone, Copy)]
+pub struct TurnStarted {
+    pub entity: Entity,
+    pub round: u32,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct TurnEnded {
+    pub entity: Entity,
+    pub round: u32,
+}
+
+/// Actors still to act in the current phase, and whose turn it is
+#[derive(Debug, Default)]
+pub struct TurnQueue {
+    /// Rounds started since play began
+    pub round: u32,
+    current: Option<Entity>,
+    /// Actors whose turns are going at the same time in real time
+    acting: HashSet<Entity>,
+    waiting: VecDeque<Entity>,
+    /// Whether the actors of this phase have been queued yet
+    filled: bool,
+}
+
+impl TurnQueue {
+    /// Actor whose turn it is
+    pub fn current(&self) -> Option<Entity> {
+        self.current
+    }
+
+    pub fn is_turn(&self, entity: Entity) -> bool {
+        self.current == Some(entity) || self.acting.contains(&entity)
+    }
+
+    /// Start over from the first round whenever play starts
+    fn reset(mut commands: Commands, mut queue: ResMut<TurnQueue>) {
+        *queue = TurnQueue::default();
+        commands.insert_resource(NextState(TurnPhase::PlayerInput));
+    }
+
+    fn next_round(mut queue: ResMut<TurnQueue>) {
+        queue.round += 1;
+        queue.clear_phase();
+    }
+
+    fn clear(mut queue: ResMut<TurnQueue>) {
+        queue.clear_phase();
+    }
+
+    fn clear_phase(&mut self) {
+        self.current = None;
+        self.acting.clear();
+        self.waiting.clear();
+        self.filled = false;
+    }
+
+    /// End turns as actors finish them and hand the next actor its turn,
+    /// moving on once everyone in the phase has acted, but not while a
+    /// conversation is going
+    #[allow(clippy::too_many_arguments)]
+    fn system(
+        mut commands: Commands,
+        phase: Res<CurrentState<TurnPhase>>,
+        conversation: Res<Conversation>,
+        mut queue: ResMut<TurnQueue>,
+        mut end_turns: EventReader<EndTurn>,
+        mut started: EventWriter<TurnStarted>,
+        mut ended: EventWriter<TurnEnded>,
+        actors: Query<(Entity, &Actor, &Team, Option<&Stats>)>,
+    ) {
+        let phase = phase.0;
+        let round = queue.round;
+
+        if !queue.filled {
+            let mut acting: Vec<(Entity, i32)> = actors
+                .iter()
+                .filter(|(_, _, team, _)| team.acts_in(phase))
+                .map(|(entity, actor, _, stats)| {
+                    let speed = stats.map(|stats| stats.total().speed);
+                    (entity, actor.initiative + speed.unwrap_or(0))
+                })
+                .collect();
+            // Nothing happens until there is a player to wait on, like while
+            // the player is still being spawned
+            if acting.is_empty() && phase == TurnPhase::PlayerInput {
+                return;
+            }
+            acting.sort_by_key(|&(entity, initiative)| (-initiative, entity));
+            queue.waiting = acting.into_iter().map(|(e, _)| e).collect();
+            queue.filled = true;
+        }
+
+        for EndTurn(entity) in end_turns.iter() {
+            if queue.is_turn(*entity) {
+                ended.send(TurnEnded {
+                    entity: *entity,
+                    round,
+                });
+                queue.current = None;
+            }
+        }
+
+        // Actors despawned mid turn lose it
+        if let Some(entity) = queue.current {
+            if actors.get(entity).is_err() {
+                queue.current = None;
+            }
+        }
+        if queue.current.is_some() || conversation.is_active() {
+            return;
+        }
+
+        while let Some(entity) = queue.waiting.pop_front() {
+            if actors.get(entity).is_ok() {
+                queue.current = Some(entity);
+                started.send(TurnStarted { entity, round });
+                return;
+            }
+        }
+
+        let next = match phase {
+            TurnPhase::PlayerInput => TurnPhase::Resolution,
+            _ => TurnPhase::PlayerInput,
+        };
+        commands.insert_resource(NextState(next));
+    }
+}
+
+/// Wait for every move and projectile to finish before the enemies act
+fn resolve(
+    mut commands: Commands,
+    tweens: Query<&MoveTween>,
+    projectiles: Query<(), With<Projectile>>,
+) {
+    if projectiles.is_empty() && tweens.iter().all(|tween| !tween.is_moving()) {
+        commands.insert_resource(NextState(TurnPhase::EnemyTurns));
+    }
+}
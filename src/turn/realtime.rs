@@ -0,0 +1,261 @@
+use super::{
+    ActionPoints, Actor, EndTurn, TurnEnded, TurnPhase, TurnQueue, TurnStarted,
+};
+use crate::console::{Console, ConsoleCommand};
+use crate::dialogue::Conversation;
+use crate::editor::GameMode;
+use crate::map::MoveTween;
+use crate::movement::WalkPath;
+use crate::rules::Rules;
+use crate::stats::Stats;
+use bevy::prelude::*;
+use iyes_loopless::prelude::*;
+
+pub struct RealTimePlugin;
+
+impl Plugin for RealTimePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RoundClock>()
+            .add_loopless_state(Timing::TurnBased)
+            .add_enter_system(GameMode::Playing, RoundClock::reset)
+            .add_enter_system(Timing::RealTime, start)
+            .add_exit_system(Timing::RealTime, stop)
+            .add_system(toggle_timing)
+            .add_system(timing_command)
+            .add_system(
+                schedule
+                    .run_in_state(GameMode::Playing)
+                    .run_in_state(Timing::RealTime),
+            );
+    }
+}
+
+/// Key which switches between taking turns and real time
+const TOGGLE_TIMING: KeyCode = KeyCode::F7;
+
+/// How much shorter each point of speed makes a cooldown
+const SPEED_HASTE: f32 = 0.15;
+
+/// Whether actors take turns round by round or whenever their [`Cooldown`]
+/// is up.
+///
+/// Both go through the same [`TurnStarted`] and [`EndTurn`] events, so
+/// movement and combat work the same either way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Timing {
+    TurnBased,
+    /// Every actor acts as soon as it is ready, the player included, without
+    /// waiting on anyone else
+    RealTime,
+}
+
+impl Timing {
+    fn toggled(self) -> Self {
+        match self {
+            Timing::TurnBased => Timing::RealTime,
+            Timing::RealTime => Timing::TurnBased,
+        }
+    }
+}
+
+/// Seconds until an actor's next turn in real time
+#[derive(Component, Debug, Clone, Copy, Default, PartialEq)]
+pub struct Cooldown {
+    pub remaining: f32,
+}
+
+impl Cooldown {
+    /// Wait after a turn which spent `points`, shorter for a turn which only
+    /// spent some of them and for faster actors
+    fn after_turn(
+        rules: &Rules,
+        points: Option<&ActionPoints>,
+        stats: Option<&Stats>,
+    ) -> Self {
+        let spent = match points {
+            Some(points) if points.max > 0 && points.current < points.max => {
+                (points.max - points.current) as f32 / points.max as f32
+            }
+            _ => 1.0,
+        };
+        let speed = stats.map(|stats| stats.total().speed).unwrap_or(0);
+        let haste = (1.0 - SPEED_HASTE * speed as f32).clamp(0.25, 2.0);
+        Self {
+            remaining: rules.turn_seconds * spent * haste,
+        }
+    }
+}
+
+/// Time left in the current round in real time, where rounds still pass for
+/// hazards and spawners
+#[derive(Debug, Default)]
+pub struct RoundClock {
+    remaining: f32,
+    /// Whether a round started this frame
+    started: bool,
+}
+
+impl RoundClock {
+    fn reset(rules: Res<Rules>, mut clock: ResMut<RoundClock>) {
+        clock.remaining = rules.round_seconds;
+        clock.started = false;
+    }
+}
+
+/// Run condition for systems which happen once a round in real time
+pub fn round_started(clock: Res<RoundClock>) -> bool {
+    clock.started
+}
+
+/// Drop the turns of the phase and let everyone act as soon as they can
+fn start(
+    mut commands: Commands,
+    rules: Res<Rules>,
+    mut queue: ResMut<TurnQueue>,
+    mut clock: ResMut<RoundClock>,
+    mut cooldowns: Query<&mut Cooldown>,
+) {
+    queue.clear_phase();
+    commands.insert_resource(NextState(TurnPhase::PlayerInput));
+    clock.remaining = rules.round_seconds;
+    for mut cooldown in cooldowns.iter_mut() {
+        cooldown.remaining = 0.0;
+    }
+}
+
+/// Drop the turns going on, starting the round over with the player
+fn stop(mut commands: Commands, mut queue: ResMut<TurnQueue>) {
+    queue.clear_phase();
+    commands.insert_resource(NextState(TurnPhase::PlayerInput));
+}
+
+fn toggle_timing(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    timing: Res<CurrentState<Timing>>,
+) {
+    if keys.just_pressed(TOGGLE_TIMING) {
+        let next = timing.0.toggled();
+        info!("Switching to {:?}", next);
+        commands.insert_resource(NextState(next));
+    }
+}
+
+/// End the turns actors are done with and start the turn of the next actor
+/// whose cooldown is up, holding everything while a conversation is going.
+///
+/// An actor's turn ends once it sends [`EndTurn`], or once it has spent some
+/// of its [`ActionPoints`] and finished moving, so moving a tile at a time
+/// only waits out part of a cooldown.
+#[allow(clippy::too_many_arguments)]
+fn schedule(
+    mut commands: Commands,
+    time: Res<Time>,
+    rules: Res<Rules>,
+    conversation: Res<Conversation>,
+    mut queue: ResMut<TurnQueue>,
+    mut clock: ResMut<RoundClock>,
+    mut end_turns: EventReader<EndTurn>,
+    mut started: EventWriter<TurnStarted>,
+    mut ended: EventWriter<TurnEnded>,
+    mut actors: Query<(
+        Entity,
+        &Actor,
+        Option<&mut Cooldown>,
+        Option<&ActionPoints>,
+        Option<&Stats>,
+        Option<&MoveTween>,
+        Option<&WalkPath>,
+    )>,
+) {
+    clock.started = false;
+    if conversation.is_active() {
+        return;
+    }
+    let delta = time.delta_seconds();
+    clock.remaining -= delta;
+    if clock.remaining <= 0.0 {
+        clock.remaining += rules.round_seconds.max(delta);
+        clock.started = true;
+        queue.round += 1;
+    }
+    let round = queue.round;
+
+    // Actors despawned mid turn lose it
+    queue.acting.retain(|&entity| actors.get(entity).is_ok());
+    let mut ending: Vec<Entity> =
+        end_turns.iter().map(|&EndTurn(entity)| entity).collect();
+    ending.extend(queue.acting.iter().copied().filter(|&entity| {
+        match actors.get(entity) {
+            Ok((_, _, _, points, _, tween, walk)) => {
+                let spent = matches!(points, Some(p) if p.current < p.max);
+                let moving = matches!(tween, Some(t) if t.is_moving());
+                spent && !moving && walk.is_none()
+            }
+            Err(_) => false,
+        }
+    }));
+    for entity in ending {
+        if !queue.acting.remove(&entity) {
+            continue;
+        }
+        ended.send(TurnEnded { entity, round });
+        if let Ok((_, _, cooldown, points, stats, ..)) = actors.get_mut(entity)
+        {
+            let next = Cooldown::after_turn(&rules, points, stats);
+            match cooldown {
+                Some(mut cooldown) => *cooldown = next,
+                None => {
+                    commands.entity(entity).insert(next);
+                }
+            }
+        }
+    }
+
+    let mut ready: Vec<(Entity, i32)> = Vec::new();
+    for (entity, actor, cooldown, _, stats, ..) in actors.iter_mut() {
+        if let Some(mut cooldown) = cooldown {
+            if cooldown.remaining > 0.0 {
+                cooldown.remaining -= delta;
+                continue;
+            }
+        }
+        if !queue.acting.contains(&entity) {
+            let speed = stats.map(|stats| stats.total().speed).unwrap_or(0);
+            ready.push((entity, actor.initiative + speed));
+        }
+    }
+    // Only one turn starts a frame, so actors see where the last one moved
+    let next = ready
+        .into_iter()
+        .min_by_key(|&(entity, initiative)| (-initiative, entity));
+    if let Some((entity, _)) = next {
+        queue.acting.insert(entity);
+        started.send(TurnStarted { entity, round });
+    }
+}
+
+/// `timing [turns|realtime]` shows or switches how actors take their turns
+fn timing_command(
+    mut commands: Commands,
+    mut events: EventReader<ConsoleCommand>,
+    mut console: ResMut<Console>,
+    timing: Res<CurrentState<Timing>>,
+) {
+    for event in events.iter().filter(|event| event.name == "timing") {
+        let next = match event.args.first().map(String::as_str) {
+            None => {
+                console.print(format!("Timing is {:?}", timing.0));
+                continue;
+            }
+            Some("turns") => Timing::TurnBased,
+            Some("realtime") => Timing::RealTime,
+            Some(_) => {
+                console.print("usage: timing [turns|realtime]");
+                continue;
+            }
+        };
+        commands.insert_resource(NextState(next));
+        console.print(format!("Switching to {:?}", next));
+    }
+}